@@ -21,7 +21,7 @@ mod encryption {
     fn vic(bencher: Bencher) {
         let c = VicCipher::new("89", "741776", "IDREAMOFJEANNIEWITHT", "77651").unwrap();
         let src = PLAIN.as_bytes();
-        let mut dst = vec![0u8; src.len() * 3];
+        let mut dst = vec![0u8; c.encrypt_len(src)];
         bencher.bench_local(|| {
             c.encrypt(&mut dst, src);
         });
@@ -31,7 +31,7 @@ mod encryption {
     fn caesar(bencher: Bencher) {
         let c = CaesarCipher::new(3);
         let src = PLAIN.as_bytes();
-        let mut dst = vec![0u8; src.len()];
+        let mut dst = vec![0u8; c.encrypt_len(src)];
         bencher.bench_local(|| {
             c.encrypt(&mut dst, src);
         });
@@ -41,7 +41,7 @@ mod encryption {
     fn square(bencher: Bencher) {
         let c = SquareCipher::new("ARABESQUE", "012345").unwrap();
         let src = PLAIN.as_bytes();
-        let mut dst = vec![0u8; src.len() * 2];
+        let mut dst = vec![0u8; c.encrypt_len(src)];
         bencher.bench_local(|| {
             c.encrypt(&mut dst, src);
         });
@@ -51,7 +51,7 @@ mod encryption {
     fn transposition(bencher: Bencher) {
         let c = Transposition::new("SUBWAY").unwrap();
         let src = PLAIN.as_bytes();
-        let mut dst = vec![0u8; src.len()];
+        let mut dst = vec![0u8; c.encrypt_len(src)];
         bencher.bench_local(|| {
             c.encrypt(&mut dst, src);
         });
@@ -61,7 +61,7 @@ mod encryption {
     fn irregular_transposition(bencher: Bencher) {
         let c = IrregularTransposition::new("SUBWAY").unwrap();
         let src = PLAIN.as_bytes();
-        let mut dst = vec![0u8; src.len()];
+        let mut dst = vec![0u8; c.encrypt_len(src)];
         bencher.bench_local(|| {
             c.encrypt(&mut dst, src);
         });
@@ -71,7 +71,7 @@ mod encryption {
     fn chaocipher(bencher: Bencher) {
         let c = Chaocipher::new(KEY_PLAIN, KEY_CIPHER).unwrap();
         let src = PLAIN.as_bytes();
-        let mut dst = vec![0u8; src.len()];
+        let mut dst = vec![0u8; c.encrypt_len(src)];
         bencher.bench_local(|| {
             c.encrypt(&mut dst, src);
         });
@@ -81,7 +81,7 @@ mod encryption {
     fn playfair(bencher: Bencher) {
         let c = PlayfairCipher::new("ARABESQUE");
         let src = PLAIN.as_bytes();
-        let mut dst = vec![0u8; src.len() + 1];
+        let mut dst = vec![0u8; c.encrypt_len(src)];
         bencher.bench_local(|| {
             c.encrypt(&mut dst, src);
         });
@@ -91,7 +91,7 @@ mod encryption {
     fn adfgvx(bencher: Bencher) {
         let c = ADFGVX::new("ARABESQUE", "SUBWAY").unwrap();
         let src = PLAIN.as_bytes();
-        let mut dst = vec![0u8; src.len() * 2];
+        let mut dst = vec![0u8; c.encrypt_len(src)];
         bencher.bench_local(|| {
             c.encrypt(&mut dst, src);
         });
@@ -101,7 +101,7 @@ mod encryption {
     fn straddling(bencher: Bencher) {
         let c = StraddlingCheckerboard::new("ARABESQUE", "37").unwrap();
         let src = PLAIN.as_bytes();
-        let mut dst = vec![0u8; src.len() * 2];
+        let mut dst = vec![0u8; c.encrypt_len(src)];
         bencher.bench_local(|| {
             c.encrypt(&mut dst, src);
         });
@@ -111,7 +111,7 @@ mod encryption {
     fn nihilist(bencher: Bencher) {
         let c = Nihilist::new("ARABESQUE", "SUBWAY", "37").unwrap();
         let src = PLAIN.as_bytes();
-        let mut dst = vec![0u8; src.len() * 2];
+        let mut dst = vec![0u8; c.encrypt_len(src)];
         bencher.bench_local(|| {
             c.encrypt(&mut dst, src);
         });
@@ -122,7 +122,7 @@ mod encryption {
         let c = Wheatstone::new(b'M', "CIPHER", "MACHINE").unwrap();
         let fixpt = helpers::fix_double(PLAIN, 'Q');
         let src = fixpt.as_bytes();
-        let mut dst = vec![0u8; src.len()];
+        let mut dst = vec![0u8; c.encrypt_len(src)];
         bencher.bench_local(|| {
             c.encrypt(&mut dst, src);
         });
@@ -137,13 +137,11 @@ mod decryption {
     fn vic(bencher: Bencher) {
         let c = VicCipher::new("89", "741776", "IDREAMOFJEANNIEWITHT", "77651").unwrap();
         let src = PLAIN.as_bytes();
-        let mut ct = vec![0u8; src.len() * 3];
+        let mut ct = vec![0u8; c.encrypt_len(src)];
         c.encrypt(&mut ct, src);
-        let ct_len = ct.iter().position(|&x| x == 0).unwrap_or(ct.len());
-        let ct_trimmed = &ct[..ct_len];
-        let mut dst = vec![0u8; src.len()];
+        let mut dst = vec![0u8; c.decrypt_len(&ct)];
         bencher.bench_local(|| {
-            c.decrypt(&mut dst, ct_trimmed);
+            c.decrypt(&mut dst, &ct);
         });
     }
 
@@ -151,9 +149,9 @@ mod decryption {
     fn caesar(bencher: Bencher) {
         let c = CaesarCipher::new(3);
         let src = PLAIN.as_bytes();
-        let mut ct = vec![0u8; src.len()];
+        let mut ct = vec![0u8; c.encrypt_len(src)];
         c.encrypt(&mut ct, src);
-        let mut dst = vec![0u8; src.len()];
+        let mut dst = vec![0u8; c.decrypt_len(&ct)];
         bencher.bench_local(|| {
             c.decrypt(&mut dst, &ct);
         });
@@ -163,9 +161,9 @@ mod decryption {
     fn square(bencher: Bencher) {
         let c = SquareCipher::new("ARABESQUE", "012345").unwrap();
         let src = PLAIN.as_bytes();
-        let mut ct = vec![0u8; src.len() * 2];
+        let mut ct = vec![0u8; c.encrypt_len(src)];
         c.encrypt(&mut ct, src);
-        let mut dst = vec![0u8; src.len()];
+        let mut dst = vec![0u8; c.decrypt_len(&ct)];
         bencher.bench_local(|| {
             c.decrypt(&mut dst, &ct);
         });
@@ -175,9 +173,9 @@ mod decryption {
     fn transposition(bencher: Bencher) {
         let c = Transposition::new("SUBWAY").unwrap();
         let src = PLAIN.as_bytes();
-        let mut ct = vec![0u8; src.len()];
+        let mut ct = vec![0u8; c.encrypt_len(src)];
         c.encrypt(&mut ct, src);
-        let mut dst = vec![0u8; src.len()];
+        let mut dst = vec![0u8; c.decrypt_len(&ct)];
         bencher.bench_local(|| {
             c.decrypt(&mut dst, &ct);
         });
@@ -187,9 +185,9 @@ mod decryption {
     fn irregular_transposition(bencher: Bencher) {
         let c = IrregularTransposition::new("SUBWAY").unwrap();
         let src = PLAIN.as_bytes();
-        let mut ct = vec![0u8; src.len()];
+        let mut ct = vec![0u8; c.encrypt_len(src)];
         c.encrypt(&mut ct, src);
-        let mut dst = vec![0u8; src.len()];
+        let mut dst = vec![0u8; c.decrypt_len(&ct)];
         bencher.bench_local(|| {
             c.decrypt(&mut dst, &ct);
         });
@@ -199,9 +197,9 @@ mod decryption {
     fn chaocipher(bencher: Bencher) {
         let c = Chaocipher::new(KEY_PLAIN, KEY_CIPHER).unwrap();
         let src = PLAIN.as_bytes();
-        let mut ct = vec![0u8; src.len()];
+        let mut ct = vec![0u8; c.encrypt_len(src)];
         c.encrypt(&mut ct, src);
-        let mut dst = vec![0u8; src.len()];
+        let mut dst = vec![0u8; c.decrypt_len(&ct)];
         bencher.bench_local(|| {
             c.decrypt(&mut dst, &ct);
         });
@@ -211,9 +209,9 @@ mod decryption {
     fn playfair(bencher: Bencher) {
         let c = PlayfairCipher::new("ARABESQUE");
         let src = PLAIN.as_bytes();
-        let mut ct = vec![0u8; src.len() + (src.len() % 2)];
+        let mut ct = vec![0u8; c.encrypt_len(src)];
         c.encrypt(&mut ct, src);
-        let mut dst = vec![0u8; ct.len()];
+        let mut dst = vec![0u8; c.decrypt_len(&ct)];
         bencher.bench_local(|| {
             c.decrypt(&mut dst, &ct);
         });
@@ -223,9 +221,9 @@ mod decryption {
     fn adfgvx(bencher: Bencher) {
         let c = ADFGVX::new("ARABESQUE", "SUBWAY").unwrap();
         let src = PLAIN.as_bytes();
-        let mut ct = vec![0u8; src.len() * 2];
+        let mut ct = vec![0u8; c.encrypt_len(src)];
         c.encrypt(&mut ct, src);
-        let mut dst = vec![0u8; src.len()];
+        let mut dst = vec![0u8; c.decrypt_len(&ct)];
         bencher.bench_local(|| {
             c.decrypt(&mut dst, &ct);
         });
@@ -235,9 +233,9 @@ mod decryption {
     fn straddling(bencher: Bencher) {
         let c = StraddlingCheckerboard::new("ARABESQUE", "37").unwrap();
         let src = PLAIN.as_bytes();
-        let mut ct = vec![0u8; src.len() * 2];
+        let mut ct = vec![0u8; c.encrypt_len(src)];
         c.encrypt(&mut ct, src);
-        let mut dst = vec![0u8; ct.len()];
+        let mut dst = vec![0u8; c.decrypt_len(&ct)];
         bencher.bench_local(|| {
             c.decrypt(&mut dst, &ct);
         });
@@ -247,9 +245,9 @@ mod decryption {
     fn nihilist(bencher: Bencher) {
         let c = Nihilist::new("ARABESQUE", "SUBWAY", "37").unwrap();
         let src = PLAIN.as_bytes();
-        let mut ct = vec![0u8; src.len() * 2];
+        let mut ct = vec![0u8; c.encrypt_len(src)];
         c.encrypt(&mut ct, src);
-        let mut dst = vec![0u8; ct.len()];
+        let mut dst = vec![0u8; c.decrypt_len(&ct)];
         bencher.bench_local(|| {
             c.decrypt(&mut dst, &ct);
         });
@@ -260,11 +258,76 @@ mod decryption {
         let c = Wheatstone::new(b'M', "CIPHER", "MACHINE").unwrap();
         let fixpt = helpers::fix_double(PLAIN, 'Q');
         let src = fixpt.as_bytes();
-        let mut ct = vec![0u8; src.len()];
+        let mut ct = vec![0u8; c.encrypt_len(src)];
         c.encrypt(&mut ct, src);
-        let mut dst = vec![0u8; src.len()];
+        let mut dst = vec![0u8; c.decrypt_len(&ct)];
         bencher.bench_local(|| {
             c.decrypt(&mut dst, &ct);
         });
     }
 }
+
+#[divan::bench_group]
+mod rekeying {
+    use super::*;
+    use old_crypto_rs::Rekey;
+
+    const KEYS: [&str; 4] = ["ARABESQUE", "PORTABLE", "SUBWAY", "PLAYFAIREXAMPLE"];
+
+    #[divan::bench]
+    fn playfair_new(bencher: Bencher) {
+        bencher.bench_local(|| {
+            for key in KEYS {
+                divan::black_box(PlayfairCipher::new(key));
+            }
+        });
+    }
+
+    #[divan::bench]
+    fn playfair_rekey(bencher: Bencher) {
+        let mut c = PlayfairCipher::new("A");
+        bencher.bench_local(|| {
+            for key in KEYS {
+                c.rekey(key).unwrap();
+            }
+        });
+    }
+
+    #[divan::bench]
+    fn square_new(bencher: Bencher) {
+        bencher.bench_local(|| {
+            for key in KEYS {
+                divan::black_box(SquareCipher::new(key, "ADFGVX").unwrap());
+            }
+        });
+    }
+
+    #[divan::bench]
+    fn square_rekey(bencher: Bencher) {
+        let mut c = SquareCipher::new("A", "ADFGVX").unwrap();
+        bencher.bench_local(|| {
+            for key in KEYS {
+                c.rekey(key).unwrap();
+            }
+        });
+    }
+
+    #[divan::bench]
+    fn transposition_new(bencher: Bencher) {
+        bencher.bench_local(|| {
+            for key in KEYS {
+                divan::black_box(Transposition::new(key).unwrap());
+            }
+        });
+    }
+
+    #[divan::bench]
+    fn transposition_rekey(bencher: Bencher) {
+        let mut c = Transposition::new("A").unwrap();
+        bencher.bench_local(|| {
+            for key in KEYS {
+                c.rekey(key).unwrap();
+            }
+        });
+    }
+}
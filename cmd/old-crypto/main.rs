@@ -8,10 +8,8 @@ use ratatui::{
     prelude::*,
     widgets::{Block, Borders, Clear, List, ListItem, ListState, Paragraph, Wrap},
 };
-use old_crypto_rs::{
-    Block as CipherBlock, ADFGVX, CaesarCipher, Chaocipher, Nihilist, NullCipher, PlayfairCipher,
-    Solitaire, SquareCipher, StraddlingCheckerboard, Transposition, VicCipher, Wheatstone,
-};
+use old_crypto_rs::registry::{self, CipherEntry, Params};
+use old_crypto_rs::{BlockExt, CipherError};
 
 enum InputMode {
     Normal,
@@ -38,7 +36,7 @@ struct App {
     key4: String,
     result: String,
     
-    ciphers: Vec<&'static str>,
+    ciphers: &'static [CipherEntry],
     cipher_list_state: ListState,
     selected_cipher_index: usize,
 }
@@ -56,153 +54,39 @@ impl App {
             key3: String::new(),
             key4: String::new(),
             result: String::new(),
-            ciphers: vec![
-                "Caesar",
-                "Playfair",
-                "Chaocipher",
-                "ADFGVX",
-                "Solitaire",
-                "Null",
-                "Square",
-                "Transposition",
-                "Straddling",
-                "Nihilist",
-                "VIC",
-                "Wheatstone",
-                "Sigaba",
-            ],
+            ciphers: registry::ciphers(),
             cipher_list_state,
             selected_cipher_index: 0,
         }
     }
 
     fn run_cipher(&mut self) {
-        let cipher_name = self.ciphers[self.selected_cipher_index];
-        let src = self.cleartext.as_bytes();
+        let entry = &self.ciphers[self.selected_cipher_index];
 
-        match cipher_name {
-            "Caesar" => {
-                if let Ok(shift) = self.key1.parse::<i32>() {
-                    let cipher = CaesarCipher::new(shift);
-                    let mut d = vec![0u8; src.len()];
-                    cipher.encrypt(&mut d, src);
-                    self.result = String::from_utf8_lossy(&d).to_string();
-                } else {
-                    self.result = "Invalid key (must be integer)".to_string();
-                }
-            }
-            "Playfair" => {
-                let cipher = PlayfairCipher::new(&self.key1);
-                let mut d = vec![0u8; src.len() + 1]; // +1 for possible padding
-                let n = cipher.encrypt(&mut d, src);
-                self.result = String::from_utf8_lossy(&d[..n]).to_string();
-            }
-            "Chaocipher" => {
-                match Chaocipher::new(&self.key1, &self.key2) {
-                    Ok(cipher) => {
-                        let mut d = vec![0u8; src.len()];
-                        cipher.encrypt(&mut d, src);
-                        self.result = String::from_utf8_lossy(&d).to_string();
-                    }
-                    Err(e) => {
-                        self.result = format!("Error: {}", e);
-                    }
-                }
-            }
-            "ADFGVX" => {
-                match ADFGVX::new(&self.key1, &self.key2) {
-                    Ok(cipher) => {
-                        let mut d = vec![0u8; src.len() * 4]; // ADFGVX doubles length then might pad
-                        let n = cipher.encrypt(&mut d, src);
-                        self.result = String::from_utf8_lossy(&d[..n]).to_string();
-                    }
-                    Err(e) => {
-                        self.result = format!("Error: {}", e);
-                    }
-                }
-            }
-            "Solitaire" => {
-                let cipher = Solitaire::new_with_passphrase(&self.key1);
-                let mut d = vec![0u8; src.len() + 5]; // Padding to block size
-                let n = cipher.encrypt(&mut d, src);
-                self.result = String::from_utf8_lossy(&d[..n]).to_string();
-            }
-            "Null" => {
-                let cipher = NullCipher::new();
-                let mut d = vec![0u8; src.len()];
-                cipher.encrypt(&mut d, src);
-                self.result = String::from_utf8_lossy(&d).to_string();
-            }
-            "Square" => {
-                match SquareCipher::new(&self.key1, &self.key2) {
-                    Ok(cipher) => {
-                        let mut d = vec![0u8; src.len() * 2];
-                        let n = cipher.encrypt(&mut d, src);
-                        self.result = String::from_utf8_lossy(&d[..n]).to_string();
-                    }
-                    Err(e) => self.result = format!("Error: {}", e),
-                }
-            }
-            "Transposition" => {
-                match Transposition::new(&self.key1) {
-                    Ok(cipher) => {
-                        let mut d = vec![0u8; src.len() + cipher.block_size()];
-                        let n = cipher.encrypt(&mut d, src);
-                        self.result = String::from_utf8_lossy(&d[..n]).to_string();
-                    }
-                    Err(e) => self.result = format!("Error: {}", e),
-                }
-            }
-            "Straddling" => {
-                match StraddlingCheckerboard::new(&self.key1, &self.key2) {
-                    Ok(cipher) => {
-                        let mut d = vec![0u8; src.len() * 3];
-                        let n = cipher.encrypt(&mut d, src);
-                        self.result = String::from_utf8_lossy(&d[..n]).to_string();
-                    }
-                    Err(e) => self.result = format!("Error: {}", e),
-                }
-            }
-            "Nihilist" => {
-                match Nihilist::new(&self.key1, &self.key2, &self.key3) {
-                    Ok(cipher) => {
-                        let mut d = vec![0u8; src.len() * 3];
-                        let n = cipher.encrypt(&mut d, src);
-                        self.result = String::from_utf8_lossy(&d[..n]).to_string();
-                    }
-                    Err(e) => self.result = format!("Error: {}", e),
-                }
-            }
-            "VIC" => {
-                match VicCipher::new(&self.key1, &self.key2, &self.key3, &self.key4) {
-                    Ok(cipher) => {
-                        let mut d = vec![0u8; src.len() * 4];
-                        let n = cipher.encrypt(&mut d, src);
-                        self.result = String::from_utf8_lossy(&d[..n]).to_string();
-                    }
-                    Err(e) => self.result = format!("Error: {}", e),
-                }
-            }
-            "Wheatstone" => {
-                let start = self.key1.as_bytes().first().cloned().unwrap_or(b'M');
-                match Wheatstone::new(start, &self.key2, &self.key3) {
-                    Ok(cipher) => {
-                        let mut d = vec![0u8; src.len() * 2];
-                        let n = cipher.encrypt(&mut d, src);
-                        self.result = String::from_utf8_lossy(&d[..n]).to_string();
-                    }
-                    Err(e) => self.result = format!("Error: {}", e),
-                }
-            }
-            "Sigaba" => {
-                self.result = "Sigaba requires complex keying, not fully supported in TUI yet"
-                    .to_string();
-            }
-            _ => self.result = "Not implemented in TUI yet".to_string(),
-        }
+        // Key fields are given to the parameters in order, empty ones are left out
+        let values = [&self.key1, &self.key2, &self.key3, &self.key4];
+        let params: Params = entry
+            .params
+            .iter()
+            .zip(values)
+            .filter(|(_, v)| !v.is_empty())
+            .map(|(p, v)| (p.name.to_string(), v.clone()))
+            .collect();
+
+        // The cleartext is put in the form the cipher expects first
+        self.result = render(
+            entry
+                .build(&params)
+                .and_then(|c| c.encrypt_str(&c.normalizer().normalize(&self.cleartext))),
+        );
     }
 }
 
+/// Formats the outcome of an encryption for the result pane.
+fn render(res: Result<String, CipherError>) -> String {
+    res.unwrap_or_else(|e| format!("Error: {}", e))
+}
+
 fn main() -> io::Result<()> {
     enable_raw_mode()?;
     stdout().execute(EnterAlternateScreen)?;
@@ -228,134 +112,116 @@ where
     loop {
         terminal.draw(|f| ui(f, app))?;
 
-        if let Event::Key(key) = event::read()? {
-            if key.kind == KeyEventKind::Press {
-                match app.input_mode {
-                    InputMode::Normal => match key.code {
-                        KeyCode::Char('q') => return Ok(()),
-                        KeyCode::Char('e') => {
-                            app.input_mode = InputMode::Editing;
-                        }
-                        KeyCode::Char('c') => {
-                            app.input_mode = InputMode::SelectingCipher;
-                        }
-                        KeyCode::Tab => {
-                            app.focused_field = match app.focused_field {
-                                FocusedField::Cleartext => FocusedField::Key1,
-                                FocusedField::Key1 => FocusedField::Key2,
-                                FocusedField::Key2 => FocusedField::Key3,
-                                FocusedField::Key3 => FocusedField::Key4,
-                                FocusedField::Key4 => FocusedField::Cleartext,
-                            };
-                        }
-                        KeyCode::Enter => {
-                            app.run_cipher();
-                        }
-                        _ => {}
-                    },
-                    InputMode::SelectingCipher => match key.code {
-                        KeyCode::Esc => {
-                            app.input_mode = InputMode::Normal;
-                        }
-                        KeyCode::Up => {
-                            let i = match app.cipher_list_state.selected() {
-                                Some(i) => {
-                                    if i == 0 {
-                                        app.ciphers.len() - 1
-                                    } else {
-                                        i - 1
-                                    }
-                                }
-                                None => 0,
-                            };
-                            app.cipher_list_state.select(Some(i));
-                            app.selected_cipher_index = i;
-                        }
-                        KeyCode::Down => {
-                            let i = match app.cipher_list_state.selected() {
-                                Some(i) => {
-                                    if i >= app.ciphers.len() - 1 {
-                                        0
-                                    } else {
-                                        i + 1
-                                    }
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+        {
+            match app.input_mode {
+                InputMode::Normal => match key.code {
+                    KeyCode::Char('q') => return Ok(()),
+                    KeyCode::Char('e') => {
+                        app.input_mode = InputMode::Editing;
+                    }
+                    KeyCode::Char('c') => {
+                        app.input_mode = InputMode::SelectingCipher;
+                    }
+                    KeyCode::Tab => {
+                        app.focused_field = match app.focused_field {
+                            FocusedField::Cleartext => FocusedField::Key1,
+                            FocusedField::Key1 => FocusedField::Key2,
+                            FocusedField::Key2 => FocusedField::Key3,
+                            FocusedField::Key3 => FocusedField::Key4,
+                            FocusedField::Key4 => FocusedField::Cleartext,
+                        };
+                    }
+                    KeyCode::Enter => {
+                        app.run_cipher();
+                    }
+                    _ => {}
+                },
+                InputMode::SelectingCipher => match key.code {
+                    KeyCode::Esc => {
+                        app.input_mode = InputMode::Normal;
+                    }
+                    KeyCode::Up => {
+                        let i = match app.cipher_list_state.selected() {
+                            Some(i) => {
+                                if i == 0 {
+                                    app.ciphers.len() - 1
+                                } else {
+                                    i - 1
                                 }
-                                None => 0,
-                            };
-                            app.cipher_list_state.select(Some(i));
-                            app.selected_cipher_index = i;
-                        }
-                        KeyCode::Enter => {
-                            app.input_mode = InputMode::Normal;
-                        }
-                        _ => {}
-                    },
-                    InputMode::Editing => match key.code {
-                        KeyCode::Esc => {
-                            app.input_mode = InputMode::Normal;
-                        }
-                        KeyCode::Char(c) => {
-                            match app.focused_field {
-                                FocusedField::Cleartext => app.cleartext.push(c),
-                                FocusedField::Key1 => app.key1.push(c),
-                                FocusedField::Key2 => app.key2.push(c),
-                                FocusedField::Key3 => app.key3.push(c),
-                                FocusedField::Key4 => app.key4.push(c),
                             }
-                        }
-                        KeyCode::Backspace => {
-                            match app.focused_field {
-                                FocusedField::Cleartext => { app.cleartext.pop(); }
-                                FocusedField::Key1 => { app.key1.pop(); }
-                                FocusedField::Key2 => { app.key2.pop(); }
-                                FocusedField::Key3 => { app.key3.pop(); }
-                                FocusedField::Key4 => { app.key4.pop(); }
+                            None => 0,
+                        };
+                        app.cipher_list_state.select(Some(i));
+                        app.selected_cipher_index = i;
+                    }
+                    KeyCode::Down => {
+                        let i = match app.cipher_list_state.selected() {
+                            Some(i) => {
+                                if i >= app.ciphers.len() - 1 {
+                                    0
+                                } else {
+                                    i + 1
+                                }
                             }
+                            None => 0,
+                        };
+                        app.cipher_list_state.select(Some(i));
+                        app.selected_cipher_index = i;
+                    }
+                    KeyCode::Enter => {
+                        app.input_mode = InputMode::Normal;
+                    }
+                    _ => {}
+                },
+                InputMode::Editing => match key.code {
+                    KeyCode::Esc => {
+                        app.input_mode = InputMode::Normal;
+                    }
+                    KeyCode::Char(c) => {
+                        match app.focused_field {
+                            FocusedField::Cleartext => app.cleartext.push(c),
+                            FocusedField::Key1 => app.key1.push(c),
+                            FocusedField::Key2 => app.key2.push(c),
+                            FocusedField::Key3 => app.key3.push(c),
+                            FocusedField::Key4 => app.key4.push(c),
                         }
-                        KeyCode::Enter => {
-                            app.run_cipher();
+                    }
+                    KeyCode::Backspace => {
+                        match app.focused_field {
+                            FocusedField::Cleartext => { app.cleartext.pop(); }
+                            FocusedField::Key1 => { app.key1.pop(); }
+                            FocusedField::Key2 => { app.key2.pop(); }
+                            FocusedField::Key3 => { app.key3.pop(); }
+                            FocusedField::Key4 => { app.key4.pop(); }
                         }
-                        _ => {}
-                    },
-                }
+                    }
+                    KeyCode::Enter => {
+                        app.run_cipher();
+                    }
+                    _ => {}
+                },
             }
         }
     }
 }
 
 fn ui(f: &mut Frame, app: &mut App) {
-    let cipher_name = app.ciphers[app.selected_cipher_index];
-    
-    // Determine which key fields to show and their labels
-    let key_configs = match cipher_name {
-        "Caesar" => vec![("Shift (integer)", &app.key1)],
-        "Playfair" => vec![("Key", &app.key1)],
-        "Chaocipher" => vec![("Plain Alphabet", &app.key1), ("Cipher Alphabet", &app.key2)],
-        "ADFGVX" => vec![("Square Key", &app.key1), ("Transposition Key", &app.key2)],
-        "Solitaire" => vec![("Passphrase", &app.key1)],
-        "Null" => vec![],
-        "Square" => vec![("Key", &app.key1), ("Characters (6)", &app.key2)],
-        "Transposition" => vec![("Key", &app.key1)],
-        "Straddling" => vec![("Key", &app.key1), ("Blank Positions (2)", &app.key2)],
-        "Nihilist" => vec![
-            ("Checkerboard Key", &app.key1),
-            ("Transposition Key", &app.key2),
-            ("Blank Positions (2)", &app.key3),
-        ],
-        "VIC" => vec![
-            ("Personal Code", &app.key1),
-            ("Date/Index", &app.key2),
-            ("Phrase", &app.key3),
-            ("Key Message", &app.key4),
-        ],
-        "Wheatstone" => vec![
-            ("Start Character", &app.key1),
-            ("Plain Key", &app.key2),
-            ("Cipher Key", &app.key3),
-        ],
-        "Sigaba" => vec![("Key (Not fully supported)", &app.key1)],
-        _ => vec![("Key 1", &app.key1), ("Key 2", &app.key2)],
-    };
+    let entry = &app.ciphers[app.selected_cipher_index];
+    let cipher_name = format!("{} ({})", entry.display_name, entry.era);
+
+    // One key field per parameter, labelled from its schema
+    let labels: Vec<String> = entry
+        .params
+        .iter()
+        .map(|p| match p.optional {
+            true => format!("{} ({}, optional)", p.label, p.kind),
+            false => format!("{} ({})", p.label, p.kind),
+        })
+        .collect();
+    let values = [&app.key1, &app.key2, &app.key3, &app.key4];
+    let key_configs: Vec<(&str, &String)> = labels.iter().map(String::as_str).zip(values).collect();
 
     let mut constraints = vec![
         Constraint::Length(3), // Cipher
@@ -456,7 +322,7 @@ fn ui(f: &mut Frame, app: &mut App) {
     if let InputMode::SelectingCipher = app.input_mode {
         let area = centered_rect(60, 40, f.area());
         f.render_widget(Clear, area);
-        let items: Vec<ListItem> = app.ciphers.iter().map(|i| ListItem::new(*i)).collect();
+        let items: Vec<ListItem> = app.ciphers.iter().map(|e| ListItem::new(e.name)).collect();
         let list = List::new(items)
             .block(Block::default().title("Select Cipher").borders(Borders::ALL))
             .highlight_style(Style::default().add_modifier(Modifier::BOLD).fg(Color::Yellow))
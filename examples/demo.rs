@@ -1,4 +1,4 @@
-use old_crypto_rs::{ADFGVX, Block, CaesarCipher, Chaocipher, Nihilist, PlayfairCipher, Solitaire, SquareCipher, StraddlingCheckerboard, Transposition, VicCipher, Wheatstone, helpers, IrregularTransposition};
+use old_crypto_rs::{Block, BlockExt, helpers, registry};
 
 const KEY_PLAIN: &str = "PTLNBQDEOYSFAVZKGJRIHWXUMC";
 const KEY_CIPHER: &str = "HXUCZVAMDSLKPEFJRIGTWOBNYQ";
@@ -7,114 +7,47 @@ const PLAIN: &str = "CETOOTESTCHIFFREAVECADFGVXETLESCLESMASTODONETSOCIALX";
 struct Cph {
     name: String,
     c: Box<dyn Block>,
-    size: usize,
 }
 
 fn main() {
-    let mut allciphers: Vec<Cph> = Vec::new();
-
-    allciphers.push(Cph {
-        name: "Caesar".to_string(),
-        c: Box::new(CaesarCipher::new(3)),
-        size: PLAIN.len(),
-    });
-
-    allciphers.push(Cph {
-        name: "Square".to_string(),
-        c: Box::new(SquareCipher::new("ARABESQUE", "012345").unwrap()),
-        size: PLAIN.len() * 2,
-    });
-
-    allciphers.push(Cph {
-        name: "Transp".to_string(),
-        c: Box::new(Transposition::new("SUBWAY").unwrap()),
-        size: PLAIN.len(),
-    });
-
-    allciphers.push(Cph {
-        name: "Irr. Transp.".to_string(),
-        c: Box::new(IrregularTransposition::new("SUBWAY").unwrap()),
-        size: PLAIN.len(),
-    });
-
-    allciphers.push(Cph {
-        name: "Chaocipher".to_string(),
-        c: Box::new(Chaocipher::new(KEY_PLAIN, KEY_CIPHER).unwrap()),
-        size: PLAIN.len(),
-    });
-
-    allciphers.push(Cph {
-        name: "Playfair".to_string(),
-        c: Box::new(PlayfairCipher::new("ARABESQUE")),
-        size: PLAIN.len(),
-    });
-
-    allciphers.push(Cph {
-        name: "ADFGVX".to_string(),
-        c: Box::new(ADFGVX::new("ARABESQUE", "SUBWAY").unwrap()),
-        size: PLAIN.len() * 2,
-    });
-
-    allciphers.push(Cph {
-        name: "ADFGVX2".to_string(),
-        c: Box::new(ADFGVX::new("MASTODON", "SOCIAL").unwrap()),
-        size: PLAIN.len() * 2,
-    });
-
-    allciphers.push(Cph {
-        name: "Straddling".to_string(),
-        c: Box::new(StraddlingCheckerboard::new("ARABESQUE", "37").unwrap()),
-        size: PLAIN.len() * 2,
-    });
-
-    allciphers.push(Cph {
-        name: "Nihilist".to_string(),
-        c: Box::new(Nihilist::new("ARABESQUE", "SUBWAY", "37").unwrap()),
-        size: PLAIN.len() * 2,
-    });
-
-    allciphers.push(Cph {
-        name: "Wheatstone".to_string(),
-        c: Box::new(Wheatstone::new(b'M', "CIPHER", "MACHINE").unwrap()),
-        size: PLAIN.len(),
-    });
-
-    allciphers.push(Cph {
-        name: "VIC".to_string(),
-        c: Box::new(VicCipher::new("89", "741776", "IDREAMOFJEANNIEWITHT", "77651").unwrap()),
-        size: PLAIN.len() * 2,
-    });
-
-    allciphers.push(Cph {
-        name: "Solitaire".to_string(),
-        c: Box::new(Solitaire::new_unkeyed()),
-        size: PLAIN.len(),
-    });
+    let specs = [
+        ("Caesar", "caesar:key=3".to_string()),
+        ("Square", "square:key=ARABESQUE,chrs=012345".to_string()),
+        ("Transp", "transposition:key=SUBWAY".to_string()),
+        ("Irr. Transp.", "irregular:key=SUBWAY".to_string()),
+        ("Chaocipher", format!("chaocipher:pkey={},ckey={}", KEY_PLAIN, KEY_CIPHER)),
+        ("Playfair", "playfair:key=ARABESQUE".to_string()),
+        ("ADFGVX", "adfgvx:key1=ARABESQUE,key2=SUBWAY".to_string()),
+        ("ADFGVX2", "adfgvx:key1=MASTODON,key2=SOCIAL".to_string()),
+        ("Straddling", "straddling:key=ARABESQUE,chrs=37".to_string()),
+        ("Nihilist", "nihilist:key1=ARABESQUE,key2=SUBWAY,chrs=37".to_string()),
+        ("Wheatstone", "wheatstone:start=M,pkey=CIPHER,ckey=MACHINE".to_string()),
+        ("VIC", "vic:persn=89,ind=741776,phrase=IDREAMOFJEANNIEWITHT,imsg=77651".to_string()),
+        ("Solitaire", "solitaire".to_string()),
+    ];
+
+    let allciphers: Vec<Cph> = specs
+        .iter()
+        .map(|(name, spec)| Cph {
+            name: name.to_string(),
+            c: registry::from_spec(spec).unwrap(),
+        })
+        .collect();
 
     println!("==> Plain = \n{}", PLAIN);
 
     for cp in allciphers {
-        let fixpt: String;
-        let mut dst: Vec<u8>;
-        let mut dst1: Vec<u8>;
-
-        if cp.name == "Wheatstone" {
-            fixpt = helpers::fix_double(PLAIN, 'Q');
-            dst = vec![0u8; fixpt.len()];
-            dst1 = vec![0u8; fixpt.len()];
+        let fixpt = if cp.name == "Wheatstone" {
+            helpers::fix_double(PLAIN, 'Q')
         } else {
-            fixpt = PLAIN.to_string();
-            dst = vec![0u8; cp.size];
-            dst1 = vec![0u8; PLAIN.len()];
-        }
+            PLAIN.to_string()
+        };
 
-        let n = cp.c.encrypt(&mut dst, fixpt.as_bytes());
+        let ct = cp.c.encrypt_str(&fixpt).unwrap();
         println!("==> {}", cp.name);
-        println!("{}", helpers::by_n(&String::from_utf8_lossy(&dst[..n]), 5));
-
-        let n1 = cp.c.decrypt(&mut dst1, &dst[..n]);
+        println!("{}", helpers::by_n(&ct, 5));
 
-        let nplain = String::from_utf8_lossy(&dst1[..n1]);
+        let nplain = cp.c.decrypt_str(&ct).unwrap();
         if nplain == fixpt {
             println!("decrypt ok\n");
         } else {
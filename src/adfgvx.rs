@@ -21,10 +21,10 @@
 //! cipher.encrypt(&mut ciphertext, plaintext);
 //! ```
 //!
-use crate::Block;
+use crate::{Alphabet, Block, CipherError, Grid, Normalizer, Rng, Tracer};
 use crate::square::SquareCipher;
 use crate::transposition::Transposition;
-use std::cell::RefCell;
+use crate::trace::NoTrace;
 
 /// ADFGVX cipher combining Polybius square substitution with columnar transposition.
 ///
@@ -35,7 +35,6 @@ use std::cell::RefCell;
 pub struct ADFGVX {
     sqr: SquareCipher,
     transp: Transposition,
-    buf: RefCell<Vec<u8>>,
 }
 
 impl ADFGVX {
@@ -48,8 +47,8 @@ impl ADFGVX {
     ///
     /// # Returns
     ///
-    /// Returns `Ok(ADFGVX)` if both keys are valid, or `Err(String)` with an error message
-    /// if either key is invalid (e.g., empty or contains invalid characters).
+    /// Returns `Ok(ADFGVX)` if both keys are valid, or a `CipherError` naming `key1` or
+    /// `key2` if either key is invalid (e.g., empty).
     ///
     /// # Examples
     ///
@@ -65,15 +64,65 @@ impl ADFGVX {
     /// - The keys contain invalid characters
     /// - The Polybius square or transposition cipher cannot be initialized
     ///
-    pub fn new(key1: &str, key2: &str) -> Result<Self, String> {
-        let sqr = SquareCipher::new(key1, "ADFGVX")?;
-        let transp = Transposition::new(key2)?;
+    pub fn new(key1: &str, key2: &str) -> Result<Self, CipherError> {
+        Self::with_alphabet(key1, key2, &Alphabet::base36())
+    }
+
+    /// Creates an ADFGVX cipher whose square holds `alphabet` instead of the 26 letters
+    /// and 10 digits, e.g. the 25 letters without J, in which case only the first five
+    /// coordinates (ADFGV) are used.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`ADFGVX::new`], plus `BadAlphabetLength` naming `alphabet` if it has
+    /// more than 36 symbols.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use old_crypto_rs::{Alphabet, BlockExt, ADFGVX};
+    ///
+    /// let a = Alphabet::latin().without("J").unwrap();
+    /// let cipher = ADFGVX::with_alphabet("PORTABLE", "SUBWAY", &a).unwrap();
+    /// let ct = cipher.encrypt_str("ATTACKATDAWN").unwrap();
+    /// assert!(ct.bytes().all(|b| b"ADFGV".contains(&b)));
+    /// ```
+    ///
+    pub fn with_alphabet(key1: &str, key2: &str, alphabet: &Alphabet) -> Result<Self, CipherError> {
+        if alphabet.len() > 36 {
+            return Err(CipherError::BadAlphabetLength { param: "alphabet", expected: 36, got: alphabet.len() });
+        }
+        let sqr = SquareCipher::with_alphabet(key1, "ADFGVX", alphabet).map_err(|e| match e.param() {
+            "alphabet" => e,
+            _ => e.for_param("key1"),
+        })?;
+        let transp = Transposition::new(key2).map_err(|e| e.for_param("key2"))?;
 
-        Ok(ADFGVX {
-            sqr,
-            transp,
-            buf: RefCell::new(Vec::new()),
-        })
+        Ok(ADFGVX { sqr, transp })
+    }
+
+    /// Creates an ADFGVX cipher with a random square and a transposition key of `len`
+    /// random letters.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EmptyKey` naming `key2` if `len` is 0.
+    ///
+    pub fn random(rng: &mut Rng, len: usize) -> Result<Self, CipherError> {
+        let a = Alphabet::base36();
+        Self::new(&rng.permutation(&a), &rng.word(&Alphabet::latin(), len))
+    }
+
+    /// Returns the Polybius square, labelled with the ADFGVX coordinates.
+    ///
+    pub fn square_grid(&self) -> Grid {
+        self.sqr.grid()
+    }
+
+    /// Returns the numbered columns of the transposition.
+    ///
+    pub fn transposition_grid(&self) -> Grid {
+        self.transp.grid()
     }
 }
 
@@ -92,6 +141,40 @@ impl Block for ADFGVX {
         self.transp.block_size()
     }
 
+    fn alphabet(&self) -> Option<Alphabet> {
+        self.sqr.alphabet()
+    }
+
+    /// The Polybius square holds the alphabet, the 26 letters and the 10 digits by default.
+    ///
+    fn normalizer(&self) -> Normalizer {
+        self.sqr.normalizer()
+    }
+
+    /// The square doubles the length of the plaintext, the transposition keeps it.
+    ///
+    fn encrypt_len(&self, src: &[u8]) -> usize {
+        self.sqr.encrypt_len(src)
+    }
+
+    /// The transposition keeps the length, the square halves it.
+    ///
+    fn decrypt_len(&self, src: &[u8]) -> usize {
+        self.sqr.decrypt_len(src)
+    }
+
+    fn max_encrypt_len(&self, len: usize) -> usize {
+        self.sqr.max_encrypt_len(len)
+    }
+
+    fn max_decrypt_len(&self, len: usize) -> usize {
+        self.sqr.max_decrypt_len(len)
+    }
+
+    fn encrypt_chars(&self, chars: usize) -> Option<usize> {
+        self.sqr.encrypt_chars(chars)
+    }
+
     /// Encrypts plaintext using the ADFGVX cipher.
     ///
     /// The encryption is performed in two stages:
@@ -108,14 +191,21 @@ impl Block for ADFGVX {
     ///
     /// The number of bytes written to `dst`.
     ///
-    fn encrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        let mut buf = self.buf.borrow_mut();
-        let needed = 2 * src.len();
-        if buf.len() < needed {
-            buf.resize(needed, 0);
-        }
-        let n = self.sqr.encrypt(&mut buf[..needed], src);
-        self.transp.encrypt(dst, &buf[..n])
+    /// # Errors
+    ///
+    /// Returns `CipherError::InvalidChar` for a character that is not in the square and
+    /// `CipherError::BufferTooSmall` if `dst` is shorter than 2 * src.len().
+    ///
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.try_encrypt_traced(dst, src, &mut NoTrace)
+    }
+
+    /// Reports the square, then the transposition.
+    ///
+    fn try_encrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        let mut buf = vec![0u8; self.sqr.encrypt_len(src)];
+        let n = self.sqr.try_encrypt_traced(&mut buf, src, tracer)?;
+        self.transp.try_encrypt_traced(dst, &buf[..n], tracer)
     }
 
     /// Decrypts ciphertext using the ADFGVX cipher.
@@ -134,20 +224,29 @@ impl Block for ADFGVX {
     ///
     /// The number of bytes written to `dst`.
     ///
-    fn decrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        let mut buf = self.buf.borrow_mut();
-        let needed = src.len();
-        if buf.len() < needed {
-            buf.resize(needed, 0);
-        }
-        let n = self.transp.decrypt(&mut buf[..needed], src);
-        self.sqr.decrypt(dst, &buf[..n])
+    /// # Errors
+    ///
+    /// Returns `CipherError::OddLength` or `CipherError::UnknownBigram` if the transposed
+    /// text is not a valid sequence of ADFGVX bigrams (the position of an unknown bigram
+    /// refers to the transposed text), and `CipherError::BufferTooSmall` if `dst` is too small.
+    ///
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.try_decrypt_traced(dst, src, &mut NoTrace)
+    }
+
+    /// Reports the transposition, then the square.
+    ///
+    fn try_decrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        let mut buf = vec![0u8; src.len()];
+        let n = self.transp.try_decrypt_traced(&mut buf, src, tracer)?;
+        self.sqr.try_decrypt_traced(dst, &buf[..n], tracer)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::BlockExt;
 
     #[test]
     fn test_new_cipher() {
@@ -158,6 +257,34 @@ mod tests {
     fn test_new_cipher_bad_keys() {
         assert!(ADFGVX::new("PORTABLE", "").is_err());
         assert!(ADFGVX::new("", "SUBWAY").is_err());
+        assert_eq!(ADFGVX::new("PORTABLE", "").err(), Some(CipherError::EmptyKey("key2")));
+    }
+
+    #[test]
+    fn test_adfgvx_with_alphabet() {
+        let a = Alphabet::latin().without("J").unwrap();
+        let c = ADFGVX::with_alphabet("PORTABLE", "SUBWAY", &a).unwrap();
+        assert_eq!(c.normalizer(), Normalizer::letters().substitute('J', "I"));
+        let mut ct = vec![0u8; 24];
+        let n = c.encrypt(&mut ct, b"ATTACKATDAWN");
+        let mut pt = vec![0u8; 12];
+        c.decrypt(&mut pt, &ct[..n]);
+        assert_eq!(pt, b"ATTACKATDAWN");
+
+        let ru = Alphabet::new("АБВГДЕЁЖЗИЙКЛМНОПРСТУФХЦЧШЩЪЫЬЭЮЯ.,-").unwrap();
+        let c = ADFGVX::with_alphabet("ПАРОЛЬ", "SUBWAY", &ru).unwrap();
+        let ct = c.encrypt_to_vec("АТАКА.НА.РАССВЕТЕ".as_bytes()).unwrap();
+        assert_eq!(c.decrypt_to_vec(&ct).unwrap(), "АТАКА.НА.РАССВЕТЕ".as_bytes());
+
+        let big = Alphabet::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789/").unwrap();
+        assert_eq!(
+            ADFGVX::with_alphabet("PORTABLE", "SUBWAY", &big).err(),
+            Some(CipherError::BadAlphabetLength { param: "alphabet", expected: 36, got: 37 })
+        );
+        assert_eq!(
+            ADFGVX::with_alphabet("", "SUBWAY", &a).err(),
+            Some(CipherError::EmptyKey("key1"))
+        );
     }
 
     #[test]
@@ -166,6 +293,13 @@ mod tests {
         assert_eq!(c.block_size(), 6);
     }
 
+    #[test]
+    fn test_adfgvx_lengths() {
+        let c = ADFGVX::new("PORTABLE", "SUBWAY").unwrap();
+        assert_eq!(c.encrypt_len(b"ATTACKATDAWN"), 24);
+        assert_eq!(c.decrypt_len(b"AFDFADAGAAAAVVVVGFGVGGGX"), 12);
+    }
+
     #[test]
     fn test_adfgvx_encrypt() {
         let c = ADFGVX::new("PORTABLE", "SUBWAY").unwrap();
@@ -185,4 +319,15 @@ mod tests {
         c.decrypt(&mut dst, ct.as_bytes());
         assert_eq!(String::from_utf8_lossy(&dst), pt);
     }
+
+    #[test]
+    fn test_adfgvx_grids() {
+        let c = ADFGVX::new("PORTABLE", "SUBWAY").unwrap();
+        let g = c.square_grid();
+        assert_eq!(g.columns(), ["A", "D", "F", "G", "V", "X"]);
+        assert_eq!(g.rows().map(|(label, _)| label).collect::<String>(), "ADFGVX");
+        assert_eq!(g.cell(0, 0), Some("P"));
+        assert_eq!(g.cell(5, 5), Some("9"));
+        assert_eq!(c.transposition_grid().to_string(), "S U B W A Y\n3 4 2 5 1 6\n");
+    }
 }
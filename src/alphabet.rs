@@ -0,0 +1,451 @@
+//! Alphabets the ciphers work on.
+//!
+//! Most pen-and-paper ciphers are defined over the 26 uppercase letters, but nothing
+//! in them depends on it: Caesar works as well over A-Z0-9, Chaocipher over 36
+//! symbols and Playfair over a 6×6 square.  An [`Alphabet`] is an ordered set of
+//! distinct symbols with the operations the ciphers need: membership, indexing,
+//! keyed mixing and key validation.
+//!
+//! # Example
+//!
+//! ```
+//! use old_crypto_rs::{Alphabet, BlockExt, CaesarCipher};
+//!
+//! let a = Alphabet::base36();
+//! assert_eq!(a.len(), 36);
+//! assert_eq!(a.index('0'), Some(26));
+//! assert_eq!(a.keyed("PORTABLE").to_string(), "PORTABLECDFGHIJKMNQSUVWXYZ0123456789");
+//!
+//! let c = CaesarCipher::with_alphabet(3, &a).unwrap();
+//! assert_eq!(c.encrypt_str("HAL9000").unwrap(), "KDOC333");
+//! ```
+//!
+use crate::{CipherError, Digits, Normalizer, Punctuation};
+use crate::helpers;
+use std::fmt;
+use std::str::FromStr;
+
+/// Marker for ASCII characters that are not part of the alphabet.
+const NONE: u16 = u16::MAX;
+
+/// An ordered set of distinct symbols.
+///
+/// Lookups of ASCII symbols go through a table, the others are searched for.
+///
+#[derive(Clone, PartialEq, Eq)]
+pub struct Alphabet {
+    symbols: Vec<char>,
+    ascii: [u16; 128],
+}
+
+impl Alphabet {
+    /// Creates an alphabet from its symbols, in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EmptyKey` if `symbols` is empty and `DuplicateSymbol` if a symbol is
+    /// there twice.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use old_crypto_rs::{Alphabet, CipherError};
+    ///
+    /// let a = Alphabet::new("ABCDEF").unwrap();
+    /// assert_eq!(a.len(), 6);
+    /// assert_eq!(
+    ///     Alphabet::new("ABCA").err(),
+    ///     Some(CipherError::DuplicateSymbol { param: "alphabet", symbol: 'A' })
+    /// );
+    /// ```
+    ///
+    pub fn new(symbols: &str) -> Result<Self, CipherError> {
+        if symbols.is_empty() {
+            return Err(CipherError::EmptyKey("alphabet"));
+        }
+        let mut a = Alphabet { symbols: Vec::new(), ascii: [NONE; 128] };
+        for ch in symbols.chars() {
+            if a.contains(ch) {
+                return Err(CipherError::DuplicateSymbol { param: "alphabet", symbol: ch });
+            }
+            if ch.is_ascii() {
+                a.ascii[ch as usize] = a.symbols.len() as u16;
+            }
+            a.symbols.push(ch);
+        }
+        Ok(a)
+    }
+
+    /// The 26 uppercase letters.
+    ///
+    pub fn latin() -> Self {
+        Self::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ").unwrap()
+    }
+
+    /// The 26 uppercase letters followed by the 10 digits.
+    ///
+    pub fn base36() -> Self {
+        Self::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789").unwrap()
+    }
+
+    /// Returns the same alphabet without the symbols of `removed`, e.g. the 25 letters
+    /// of the Playfair square with `Alphabet::latin().without("J")`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EmptyKey` if no symbol is left.
+    ///
+    pub fn without(&self, removed: &str) -> Result<Self, CipherError> {
+        let rest: String = self.symbols.iter().filter(|&&c| !removed.contains(c)).collect();
+        Self::new(&rest)
+    }
+
+    /// Returns the number of symbols.
+    ///
+    pub fn len(&self) -> usize {
+        self.symbols.len()
+    }
+
+    /// Returns `true` if there is no symbol, which can not happen for a valid alphabet.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.symbols.is_empty()
+    }
+
+    /// Returns the symbols, in order.
+    ///
+    pub fn symbols(&self) -> &[char] {
+        &self.symbols
+    }
+
+    /// Returns `true` if `ch` is one of the symbols.
+    ///
+    pub fn contains(&self, ch: char) -> bool {
+        self.index(ch).is_some()
+    }
+
+    /// Returns the position of `ch` in the alphabet.
+    ///
+    pub fn index(&self, ch: char) -> Option<usize> {
+        if ch.is_ascii() {
+            match self.ascii[ch as usize] {
+                NONE => None,
+                i => Some(i as usize),
+            }
+        } else {
+            self.symbols.iter().position(|&c| c == ch)
+        }
+    }
+
+    /// Returns the symbol at position `i`.
+    ///
+    pub fn get(&self, i: usize) -> Option<char> {
+        self.symbols.get(i).copied()
+    }
+
+    /// Returns `true` if every symbol is ASCII.
+    ///
+    pub fn is_ascii(&self) -> bool {
+        self.symbols.iter().all(char::is_ascii)
+    }
+
+    /// Returns the number of bytes of the widest symbol once encoded as UTF-8, 1 for
+    /// an ASCII alphabet.
+    ///
+    pub fn max_utf8_len(&self) -> usize {
+        self.symbols.iter().map(|c| c.len_utf8()).max().unwrap_or(1)
+    }
+
+    /// Returns the mixed alphabet made of `key` followed by the other symbols, as
+    /// produced by [`helpers::condense`].  Key characters that are not in the
+    /// alphabet are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use old_crypto_rs::Alphabet;
+    ///
+    /// let a = Alphabet::latin().keyed("ARABESQUE");
+    /// assert_eq!(a.to_string(), "ARBESQUCDFGHIJKLMNOPTVWXYZ");
+    /// ```
+    ///
+    pub fn keyed(&self, key: &str) -> Self {
+        self.restrict(&helpers::condense(&format!("{}{}", key, self)))
+    }
+
+    /// Returns the alphabet mixed by [`helpers::shuffle`], the columnar transposition
+    /// of [`keyed`](Self::keyed) under `key`.  Key characters that are not in the
+    /// alphabet are ignored.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use old_crypto_rs::Alphabet;
+    ///
+    /// let a = Alphabet::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ/-").unwrap();
+    /// assert_eq!(a.shuffled("ARABESQUE").to_string(), "ACKVRDLWBFMXEGNYSHOZQIP/UJT-");
+    /// ```
+    ///
+    pub fn shuffled(&self, key: &str) -> Self {
+        self.restrict(&helpers::shuffle(key, &self.to_string()))
+    }
+
+    /// Keeps the characters of `mixed` that are symbols, `mixed` holding each of
+    /// them exactly once.
+    ///
+    fn restrict(&self, mixed: &str) -> Self {
+        let symbols: String = mixed.chars().filter(|&c| self.contains(c)).collect();
+        Self::new(&symbols).unwrap()
+    }
+
+    /// Turns this alphabet into `base` keyed by `key`, as [`keyed`](Self::keyed)
+    /// does, reusing its storage.
+    ///
+    pub(crate) fn set_keyed(&mut self, base: &Alphabet, key: &str) {
+        self.set_symbols(key.chars().chain(base.symbols.iter().copied()).filter(|&c| base.contains(c)));
+    }
+
+    /// Turns this alphabet into `base` mixed by `key`, as [`shuffled`](Self::shuffled)
+    /// does, reusing its storage.
+    ///
+    pub(crate) fn set_shuffled(&mut self, base: &Alphabet, key: &str) {
+        self.set_symbols(helpers::shuffle(key, &base.to_string()).chars().filter(|&c| base.contains(c)));
+    }
+
+    /// Replaces the symbols by the characters of `symbols`, those already there
+    /// being skipped.
+    ///
+    fn set_symbols(&mut self, symbols: impl Iterator<Item = char>) {
+        for &ch in &self.symbols {
+            if ch.is_ascii() {
+                self.ascii[ch as usize] = NONE;
+            }
+        }
+        self.symbols.clear();
+        for ch in symbols {
+            if self.contains(ch) {
+                continue;
+            }
+            if ch.is_ascii() {
+                self.ascii[ch as usize] = self.symbols.len() as u16;
+            }
+            self.symbols.push(ch);
+        }
+    }
+
+    /// Checks that every character of `key` is a symbol.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UnknownSymbol` for the first one which is not, tagged with `param`.
+    ///
+    pub fn check_key(&self, param: &'static str, key: &str) -> Result<(), CipherError> {
+        match key.chars().find(|&c| !self.contains(c)) {
+            Some(symbol) => Err(CipherError::UnknownSymbol { param, symbol }),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that `key` is a permutation of the alphabet, as the Chaocipher keys.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BadAlphabetLength` if `key` does not have as many characters as the
+    /// alphabet, `UnknownSymbol` for a character which is not a symbol and
+    /// `DuplicateSymbol` for a symbol given twice, all tagged with `param`.
+    ///
+    pub fn check_permutation(&self, param: &'static str, key: &str) -> Result<(), CipherError> {
+        let got = key.chars().count();
+        if got != self.len() {
+            return Err(CipherError::BadAlphabetLength { param, expected: self.len(), got });
+        }
+        let mut seen = vec![false; self.len()];
+        for symbol in key.chars() {
+            let i = self.index(symbol).ok_or(CipherError::UnknownSymbol { param, symbol })?;
+            if seen[i] {
+                return Err(CipherError::DuplicateSymbol { param, symbol });
+            }
+            seen[i] = true;
+        }
+        Ok(())
+    }
+
+    /// Checks that the ciphers working on bytes can use the alphabet.
+    ///
+    pub(crate) fn check_ascii(&self) -> Result<(), CipherError> {
+        match self.symbols.iter().find(|c| !c.is_ascii()) {
+            Some(&symbol) => Err(CipherError::UnknownSymbol { param: "alphabet", symbol }),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns the normalization profile matching the alphabet: letters only, or
+    /// letters and digits if all the digits are symbols.  Text is uppercased unless
+    /// the alphabet has lowercase letters.
+    ///
+    /// Punctuation and digits are spelled out in English, so an alphabet without
+    /// Latin letters drops them instead.  Punctuation signs which are symbols of the
+    /// alphabet are kept.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use old_crypto_rs::Alphabet;
+    ///
+    /// let a = Alphabet::new("АБВГДЕЁЖЗИЙКЛМНОПРСТУФХЦЧШЩЪЫЬЭЮЯ").unwrap();
+    /// assert_eq!(a.normalizer().normalize("Ёжик, 2 шт."), "ЁЖИКШТ");
+    /// ```
+    ///
+    pub fn normalizer(&self) -> Normalizer {
+        let digits = ('0'..='9').all(|d| self.contains(d));
+        let mut n = if digits {
+            Normalizer::alphanumeric()
+        } else {
+            Normalizer::letters()
+        };
+        if !self.symbols.iter().any(char::is_ascii_alphabetic) {
+            n = n
+                .punctuation(Punctuation::Drop)
+                .digits(if digits { Digits::Keep } else { Digits::Drop });
+        }
+        let punctuation: String = self
+            .symbols
+            .iter()
+            .filter(|c| !c.is_alphanumeric() && !c.is_whitespace())
+            .collect();
+        n.uppercase(!self.symbols.iter().any(|c| c.is_lowercase()))
+            .keep_symbols(&punctuation)
+    }
+}
+
+impl fmt::Display for Alphabet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.symbols.iter().try_for_each(|c| write!(f, "{}", c))
+    }
+}
+
+impl fmt::Debug for Alphabet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Alphabet({:?})", self.to_string())
+    }
+}
+
+impl FromStr for Alphabet {
+    type Err = CipherError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    #[test]
+    fn test_alphabet_new() {
+        let a = Alphabet::latin();
+        assert_eq!(a.len(), 26);
+        assert!(!a.is_empty());
+        assert!(a.is_ascii());
+        assert_eq!(a.index('A'), Some(0));
+        assert_eq!(a.index('Z'), Some(25));
+        assert_eq!(a.index('a'), None);
+        assert_eq!(a.index('é'), None);
+        assert_eq!(a.get(2), Some('C'));
+        assert_eq!(a.get(26), None);
+        assert_eq!(format!("{:?}", Alphabet::new("AB").unwrap()), "Alphabet(\"AB\")");
+    }
+
+    #[test]
+    fn test_alphabet_non_ascii() {
+        let a: Alphabet = "ΑΒΓΔ".parse().unwrap();
+        assert_eq!(a.len(), 4);
+        assert_eq!(a.index('Γ'), Some(2));
+        assert!(!a.contains('A'));
+        assert!(!a.is_ascii());
+        assert_eq!(a.max_utf8_len(), 2);
+        assert_eq!(Alphabet::latin().max_utf8_len(), 1);
+        assert_eq!(a.check_ascii(), Err(CipherError::UnknownSymbol { param: "alphabet", symbol: 'Α' }));
+    }
+
+    #[rstest]
+    #[case("", CipherError::EmptyKey("alphabet"))]
+    #[case("ABCB", CipherError::DuplicateSymbol { param: "alphabet", symbol: 'B' })]
+    #[case("ЖЖ", CipherError::DuplicateSymbol { param: "alphabet", symbol: 'Ж' })]
+    fn test_alphabet_errors(#[case] symbols: &str, #[case] err: CipherError) {
+        assert_eq!(Alphabet::new(symbols), Err(err));
+    }
+
+    #[test]
+    fn test_alphabet_without() {
+        let a = Alphabet::latin().without("J").unwrap();
+        assert_eq!(a.len(), 25);
+        assert_eq!(a.to_string(), "ABCDEFGHIKLMNOPQRSTUVWXYZ");
+        assert_eq!(Alphabet::new("AB").unwrap().without("BA"), Err(CipherError::EmptyKey("alphabet")));
+    }
+
+    #[rstest]
+    #[case("PLAYFAIREXAMPLE", "PLAYFIREXMBCDGHKNOQSTUVWZ")]
+    #[case("playfair", "ABCDEFGHIKLMNOPQRSTUVWXYZ")]
+    #[case("JAVA", "AVBCDEFGHIKLMNOPQRSTUWXYZ")]
+    #[case("ΚΛΕΙΔΙ", "ABCDEFGHIKLMNOPQRSTUVWXYZ")]
+    fn test_alphabet_keyed(#[case] key: &str, #[case] mixed: &str) {
+        let a = Alphabet::latin().without("J").unwrap();
+        assert_eq!(a.keyed(key).to_string(), mixed);
+
+        // Rekeying in place gives the same alphabet, lookups included
+        let mut b = Alphabet::new("ΚΛΕΙΔZYX").unwrap();
+        b.set_keyed(&a, key);
+        assert_eq!(b, a.keyed(key));
+    }
+
+    #[test]
+    fn test_alphabet_shuffled() {
+        let a = Alphabet::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ/-").unwrap();
+        assert_eq!(a.shuffled("SUBWAY").to_string(), "SCIOXUDJPZBEKQ/WFLR-AGMTYHNV");
+        assert_eq!(a.shuffled("").to_string(), a.to_string());
+
+        let mut b = a.clone();
+        b.set_shuffled(&a, "SUBWAY");
+        assert_eq!(b, a.shuffled("SUBWAY"));
+    }
+
+    #[rstest]
+    #[case("PTLNBQDEOYSFAVZKGJRIHWXUMC", Ok(()))]
+    #[case("AB", Err(CipherError::BadAlphabetLength { param: "pkey", expected: 26, got: 2 }))]
+    #[case("PTLNBQDEOYSFAVZKGJRIHWXUM1", Err(CipherError::UnknownSymbol { param: "pkey", symbol: '1' }))]
+    #[case("PTLNBQDEOYSFAVZKGJRIHWXUMP", Err(CipherError::DuplicateSymbol { param: "pkey", symbol: 'P' }))]
+    fn test_alphabet_check_permutation(#[case] key: &str, #[case] res: Result<(), CipherError>) {
+        assert_eq!(Alphabet::latin().check_permutation("pkey", key), res);
+    }
+
+    #[test]
+    fn test_alphabet_check_key() {
+        let a = Alphabet::latin();
+        assert_eq!(a.check_key("key", "ARABESQUE"), Ok(()));
+        assert_eq!(a.check_key("key", "Arabesque"), Err(CipherError::UnknownSymbol { param: "key", symbol: 'r' }));
+    }
+
+    #[test]
+    fn test_alphabet_normalizer() {
+        assert_eq!(Alphabet::latin().normalizer(), Normalizer::letters());
+        assert_eq!(Alphabet::base36().normalizer(), Normalizer::alphanumeric());
+        assert_eq!(Alphabet::new("abc").unwrap().normalizer(), Normalizer::letters().uppercase(false));
+        assert_eq!(
+            Alphabet::new("ΑΒΓΔ0123456789").unwrap().normalizer(),
+            Normalizer::alphanumeric().punctuation(Punctuation::Drop)
+        );
+    }
+
+    #[test]
+    fn test_alphabet_normalizer_symbols() {
+        let board = Alphabet::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ/-").unwrap();
+        assert_eq!(board.normalizer().normalize("Re-run 1/2, now!"), "RE-RUNONE/TWOCOMMANOWEXCLAM");
+
+        let ru = Alphabet::new("АБВГДЕЁЖЗИЙКЛМНОПРСТУФХЦЧШЩЪЫЬЭЮЯ.,-").unwrap();
+        assert_eq!(ru.normalizer().normalize("Ёжик, 2 шт.!"), "ЁЖИК,ШТ.");
+    }
+}
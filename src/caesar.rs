@@ -22,21 +22,25 @@
 //! assert_eq!(&decrypted, plaintext);
 //! ```
 //! 
-use crate::Block;
+use crate::{Alphabet, Block, CipherError, Normalizer, Rng};
+use crate::error::check_dst;
+use crate::utf8::{self, Output};
 
 /// A Caesar cipher implementation.
 ///
-/// This struct maintains the shift key for the uppercase English alphabet (A-Z).
-/// Characters not in the alphabet are left unchanged.
+/// This struct maintains the shift key over an alphabet, the uppercase English
+/// alphabet (A-Z) by default.  Characters not in the alphabet are left unchanged.
 ///
 /// # Fields
 ///
-/// * `enc` - Encryption lookup table mapping A-Z (0-25) to ciphertext
-/// * `dec` - Decryption lookup table mapping A-Z (0-25) to plaintext
+/// * `alphabet` - The symbols being shifted
+/// * `enc` - Encryption lookup table mapping each alphabet index to ciphertext
+/// * `dec` - Decryption lookup table mapping each alphabet index to plaintext
 ///
 pub struct CaesarCipher {
-    enc: [u8; 26],
-    dec: [u8; 26],
+    alphabet: Alphabet,
+    enc: Vec<char>,
+    dec: Vec<char>,
 }
 
 impl CaesarCipher {
@@ -61,15 +65,86 @@ impl CaesarCipher {
     /// ```
     ///
     pub fn new(key: i32) -> Self {
-        let mut enc = [0u8; 26];
-        let mut dec = [0u8; 26];
-        let shift = (key % 26 + 26) % 26 as i32;
-        for i in 0..26 {
-            let e = (i as i32 + shift) % 26;
-            enc[i] = (e as u8) + b'A';
-            dec[e as usize] = (i as u8) + b'A';
+        Self::with_alphabet(key, &Alphabet::latin()).unwrap()
+    }
+
+    /// Creates a Caesar cipher shifting the symbols of `alphabet`.
+    ///
+    /// # Arguments
+    ///
+    /// * `key` - The shift value, taken modulo the alphabet length
+    /// * `alphabet` - The symbols to shift, anything else is left unchanged
+    ///
+    /// # Errors
+    ///
+    /// None for now, the `Result` is there for consistency with the other ciphers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use old_crypto_rs::{Alphabet, BlockExt, CaesarCipher};
+    ///
+    /// let cipher = CaesarCipher::with_alphabet(1, &Alphabet::base36()).unwrap();
+    /// assert_eq!(cipher.encrypt_str("Z9").unwrap(), "0A");
+    ///
+    /// let a = Alphabet::new("АБВГДЕЁЖЗИЙКЛМНОПРСТУФХЦЧШЩЪЫЬЭЮЯ").unwrap();
+    /// let cipher = CaesarCipher::with_alphabet(3, &a).unwrap();
+    /// assert_eq!(cipher.encrypt_str("ЦЕЗАРЬ").unwrap(), "ЩЗКГУЯ");
+    /// ```
+    ///
+    pub fn with_alphabet(key: i32, alphabet: &Alphabet) -> Result<Self, CipherError> {
+        let n = alphabet.len();
+        let symbols = alphabet.symbols();
+        let mut enc = vec!['\0'; n];
+        let mut dec = vec!['\0'; n];
+        let shift = key.rem_euclid(n as i32) as usize;
+        for (i, e) in enc.iter_mut().enumerate() {
+            let j = (i + shift) % n;
+            *e = symbols[j];
+            dec[j] = symbols[i];
+        }
+        Ok(CaesarCipher { alphabet: alphabet.clone(), enc, dec })
+    }
+
+    /// Creates a Caesar cipher with a random shift (never 0) over A-Z.
+    ///
+    pub fn random(rng: &mut Rng) -> Self {
+        Self::new(1 + rng.below(25) as i32)
+    }
+
+    /// Maps `src` into `dst` through `table`, leaving what is not in the alphabet.
+    ///
+    /// An ASCII alphabet is shifted byte by byte, so any input goes through: the bytes
+    /// of multi-byte UTF-8 sequences are never ASCII and are copied unchanged.
+    ///
+    fn process(&self, table: &[char], dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        if self.alphabet.is_ascii() {
+            check_dst(dst, src.len())?;
+            for (i, &ch) in src.iter().enumerate() {
+                dst[i] = match self.alphabet.index(ch as char) {
+                    Some(j) if ch.is_ascii() => table[j] as u8,
+                    _ => ch,
+                };
+            }
+            return Ok(src.len());
+        }
+        let mut out = Output::new(dst);
+        for ch in utf8::decode(src)?.chars() {
+            out.push(self.alphabet.index(ch).map_or(ch, |j| table[j]));
+        }
+        out.finish()
+    }
+
+    /// Returns the length of `src` once mapped through `table`.
+    ///
+    fn process_len(&self, table: &[char], src: &[u8]) -> usize {
+        if self.alphabet.is_ascii() {
+            return src.len();
+        }
+        match utf8::decode(src) {
+            Ok(s) => s.chars().map(|ch| self.alphabet.index(ch).map_or(ch, |j| table[j]).len_utf8()).sum(),
+            Err(_) => src.len(),
         }
-        CaesarCipher { enc, dec }
     }
 }
 
@@ -81,10 +156,44 @@ impl Block for CaesarCipher {
         1
     }
 
+    fn alphabet(&self) -> Option<Alphabet> {
+        Some(self.alphabet.clone())
+    }
+
+    /// Only the alphabet is shifted, everything else is passed through.
+    ///
+    fn normalizer(&self) -> Normalizer {
+        let upper = !self.alphabet.symbols().iter().any(|c| c.is_lowercase());
+        Normalizer::new().uppercase(upper).strip_accents(true)
+    }
+
+    /// Symbols of a non-ASCII alphabet may not have the same UTF-8 length as their
+    /// image, otherwise this is `src.len()`.
+    ///
+    fn encrypt_len(&self, src: &[u8]) -> usize {
+        self.process_len(&self.enc, src)
+    }
+
+    /// Same as `encrypt_len`, with the decryption table.
+    ///
+    fn decrypt_len(&self, src: &[u8]) -> usize {
+        self.process_len(&self.dec, src)
+    }
+
+    /// Every character becomes at most as wide as the widest symbol.
+    ///
+    fn max_encrypt_len(&self, len: usize) -> usize {
+        len * self.alphabet.max_utf8_len()
+    }
+
+    fn max_decrypt_len(&self, len: usize) -> usize {
+        len * self.alphabet.max_utf8_len()
+    }
+
     /// Encrypts the source data into the destination buffer.
     ///
     /// Each byte in the source is shifted by the key value. Characters
-    /// not in the alphabet (A-Z) are copied unchanged.
+    /// not in the alphabet are copied unchanged.
     ///
     /// # Arguments
     ///
@@ -93,23 +202,22 @@ impl Block for CaesarCipher {
     ///
     /// # Returns
     ///
-    /// The number of bytes written to the destination buffer (equal to `src.len()`).
+    /// The number of bytes written to the destination buffer (equal to `src.len()` for
+    /// an ASCII alphabet).
     ///
-    fn encrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        for (i, &ch) in src.iter().enumerate() {
-            if ch.is_ascii_uppercase() {
-                dst[i] = self.enc[(ch - b'A') as usize];
-            } else {
-                dst[i] = ch;
-            }
-        }
-        src.len()
+    /// # Errors
+    ///
+    /// Returns `CipherError::BufferTooSmall` if `dst` is shorter than `encrypt_len`, and
+    /// `CipherError::InvalidUtf8` if the alphabet is not ASCII and `src` is not UTF-8.
+    ///
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.process(&self.enc, dst, src)
     }
 
     /// Decrypts the source data into the destination buffer.
     ///
     /// Each byte in the source is shifted back by the key value. Characters
-    /// not in the alphabet (A-Z) are copied unchanged.
+    /// not in the alphabet are copied unchanged.
     ///
     /// # Arguments
     ///
@@ -118,23 +226,23 @@ impl Block for CaesarCipher {
     ///
     /// # Returns
     ///
-    /// The number of bytes written to the destination buffer (equal to `src.len()`).
+    /// The number of bytes written to the destination buffer (equal to `src.len()` for
+    /// an ASCII alphabet).
+    ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::BufferTooSmall` if `dst` is shorter than `decrypt_len`, and
+    /// `CipherError::InvalidUtf8` if the alphabet is not ASCII and `src` is not UTF-8.
     /// 
-    fn decrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        for (i, &ch) in src.iter().enumerate() {
-            if ch.is_ascii_uppercase() {
-                dst[i] = self.dec[(ch - b'A') as usize];
-            } else {
-                dst[i] = ch;
-            }
-        }
-        src.len()
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.process(&self.dec, dst, src)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::BlockExt;
 
     use rstest::rstest;
 
@@ -150,8 +258,8 @@ mod tests {
     #[test]
     fn test_internal_mapping() {
         let c = CaesarCipher::new(3);
-        assert_eq!(c.enc[(b'A' - b'A') as usize], b'D');
-        assert_eq!(c.dec[(b'D' - b'A') as usize], b'A');
+        assert_eq!(c.enc[(b'A' - b'A') as usize], 'D');
+        assert_eq!(c.dec[(b'D' - b'A') as usize], 'A');
     }
 
     #[rstest]
@@ -177,4 +285,33 @@ mod tests {
         c.decrypt(&mut plain, cipher);
         assert_eq!(plain, pt.as_bytes());
     }
+
+    #[rstest]
+    #[case("ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789", 3, "HAL 9000", "KDO C333")]
+    #[case("ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789", -1, "A0", "9Z")]
+    #[case("abcdefghijklmnopqrstuvwxyz", 13, "Hello", "Hryyb")]
+    fn test_caesar_with_alphabet(#[case] alphabet: &str, #[case] key: i32, #[case] pt: &str, #[case] ct: &str) {
+        let c = CaesarCipher::with_alphabet(key, &alphabet.parse().unwrap()).unwrap();
+        assert_eq!(c.encrypt_str(pt).unwrap(), ct);
+        assert_eq!(c.decrypt_str(ct).unwrap(), pt);
+    }
+
+    #[test]
+    fn test_caesar_non_ascii() {
+        let a = Alphabet::new("АБВГДЕЁЖЗИЙКЛМНОПРСТУФХЦЧШЩЪЫЬЭЮЯ").unwrap();
+        let c = CaesarCipher::with_alphabet(3, &a).unwrap();
+        assert_eq!(c.encrypt_str("ЯЗЫК 33").unwrap(), "ВКЮН 33");
+        assert_eq!(c.decrypt_str("ВКЮН 33").unwrap(), "ЯЗЫК 33");
+        assert_eq!(c.encrypt_len("ЯЗЫК 33".as_bytes()), "ВКЮН 33".len());
+        assert_eq!(c.try_encrypt(&mut [0u8; 8], b"\xd0"), Err(CipherError::InvalidUtf8 { pos: 0 }));
+
+        // Symbols of different widths
+        let c = CaesarCipher::with_alphabet(1, &Alphabet::new("AΩ").unwrap()).unwrap();
+        assert_eq!(c.encrypt_len(b"AAA"), 6);
+        assert_eq!(c.encrypt_str("AΩA").unwrap(), "ΩAΩ");
+
+        // Latin alphabet: UTF-8 goes through byte by byte
+        let c = CaesarCipher::new(3);
+        assert_eq!(c.encrypt_str("ABÇ").unwrap(), "DEÇ");
+    }
 }
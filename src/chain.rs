@@ -0,0 +1,348 @@
+//! Super-encipherment of any sequence of ciphers.
+//!
+//! `ADFGVX`, `Nihilist` and the VIC cipher are all a substitution followed by one or
+//! more transpositions.  [`Chain`] builds such product ciphers out of any `Block`:
+//! each stage encrypts the output of the previous one and decryption runs the stages
+//! in reverse order.  Intermediate buffers are sized with each stage's
+//! [`Block::encrypt_len`]/[`Block::decrypt_len`], so stages that expand the text
+//! (Square, Straddling…) need no special care; the length of the whole chain is an
+//! upper bound from [`Block::max_encrypt_len`], so sizing the output does not run the
+//! stages.
+//!
+//! # Example
+//!
+//! ```
+//! use old_crypto_rs::{BlockExt, Chain, SquareCipher, Transposition};
+//!
+//! // This is ADFGVX
+//! let c = Chain::new()
+//!     .then(SquareCipher::new("PORTABLE", "ADFGVX").unwrap())
+//!     .then(Transposition::new("SUBWAY").unwrap());
+//! assert_eq!(c.encrypt_str("ATTACKATDAWN").unwrap(), "AFDFADAGAAAAVVVVGFGVGGGX");
+//! assert_eq!(c.decrypt_str("AFDFADAGAAAAVVVVGFGVGGGX").unwrap(), "ATTACKATDAWN");
+//! ```
+//!
+use crate::{Alphabet, Block, BlockExt, CipherError, Normalizer, Tracer};
+use crate::error::check_dst;
+use crate::trace::{self, NoTrace};
+
+/// A product cipher made of several stages.
+///
+/// An empty chain copies its input, like `NullCipher`.
+///
+#[derive(Default)]
+pub struct Chain {
+    stages: Vec<Box<dyn Block + Send + Sync>>,
+}
+
+impl Chain {
+    /// Creates an empty chain.
+    ///
+    pub fn new() -> Self {
+        Chain { stages: Vec::new() }
+    }
+
+    /// Adds `c` as the last stage and returns the chain.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use old_crypto_rs::{CaesarCipher, Chain, Transposition};
+    ///
+    /// let c = Chain::new()
+    ///     .then(CaesarCipher::new(3))
+    ///     .then(Transposition::new("SUBWAY").unwrap());
+    /// assert_eq!(c.len(), 2);
+    /// ```
+    ///
+    pub fn then<C: Block + Send + Sync + 'static>(mut self, c: C) -> Self {
+        self.stages.push(Box::new(c));
+        self
+    }
+
+    /// Adds an already boxed cipher as the last stage.
+    ///
+    pub fn push(&mut self, c: Box<dyn Block + Send + Sync>) {
+        self.stages.push(c);
+    }
+
+    /// Returns the number of stages.
+    ///
+    pub fn len(&self) -> usize {
+        self.stages.len()
+    }
+
+    /// Returns `true` if the chain has no stage.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.stages.is_empty()
+    }
+
+    /// Runs `src` through the stages, each one in its own buffer.
+    ///
+    fn run<'a>(
+        mut stages: impl Iterator<Item = &'a Box<dyn Block + Send + Sync>>,
+        src: &[u8],
+        encrypt: bool,
+        tracer: &mut dyn Tracer,
+    ) -> Result<Vec<u8>, CipherError> {
+        stages.try_fold(src.to_vec(), |buf, c| {
+            if encrypt {
+                c.encrypt_traced(&buf, tracer)
+            } else {
+                c.decrypt_traced(&buf, tracer)
+            }
+        })
+    }
+
+    /// Copies `src` into `dst`, for the empty chain.
+    ///
+    fn copy(dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        check_dst(dst, src.len())?;
+        dst[..src.len()].copy_from_slice(src);
+        Ok(src.len())
+    }
+}
+
+impl From<Vec<Box<dyn Block + Send + Sync>>> for Chain {
+    fn from(stages: Vec<Box<dyn Block + Send + Sync>>) -> Self {
+        Chain { stages }
+    }
+}
+
+impl Block for Chain {
+    /// The block size is the one of the last stage, the one shaping the output.
+    ///
+    fn block_size(&self) -> usize {
+        self.stages.last().map_or(1, |c| c.block_size())
+    }
+
+    /// The plaintext goes into the first stage, so this is its profile, restricted
+    /// by the later stages on what the earlier ones pass unchanged: spaces,
+    /// punctuation or digits that the first stage keeps but a later one rejects are
+    /// handled as the later one wants.
+    ///
+    /// The plaintext goes into the first stage.
+    ///
+    fn alphabet(&self) -> Option<Alphabet> {
+        self.stages.first().and_then(|c| c.alphabet())
+    }
+
+    /// The letters are changed by the stages, so a later stage with a smaller
+    /// alphabet (a 25-letter square after a Caesar shift) can still fail.
+    ///
+    fn normalizer(&self) -> Normalizer {
+        match self.stages.split_first() {
+            None => Normalizer::new(),
+            Some((first, rest)) => rest.iter().fold(first.normalizer(), |n, c| n.then(&c.normalizer())),
+        }
+    }
+
+    /// The length depends on the intermediate texts, which are not computed here: the
+    /// first stage gives the exact length of its output, and the later ones an upper
+    /// bound from it.
+    ///
+    fn encrypt_len(&self, src: &[u8]) -> usize {
+        match self.stages.split_first() {
+            None => src.len(),
+            Some((first, rest)) => rest.iter().fold(first.encrypt_len(src), |n, c| c.max_encrypt_len(n)),
+        }
+    }
+
+    /// Same as `encrypt_len`, starting from the last stage.
+    ///
+    fn decrypt_len(&self, src: &[u8]) -> usize {
+        match self.stages.split_last() {
+            None => src.len(),
+            Some((last, rest)) => rest.iter().rev().fold(last.decrypt_len(src), |n, c| c.max_decrypt_len(n)),
+        }
+    }
+
+    fn max_encrypt_len(&self, len: usize) -> usize {
+        self.stages.iter().fold(len, |n, c| c.max_encrypt_len(n))
+    }
+
+    fn max_decrypt_len(&self, len: usize) -> usize {
+        self.stages.iter().rev().fold(len, |n, c| c.max_decrypt_len(n))
+    }
+
+    fn encrypt_chars(&self, chars: usize) -> Option<usize> {
+        self.stages.iter().try_fold(chars, |n, c| c.encrypt_chars(n))
+    }
+
+    fn whole_message(&self) -> bool {
+        self.stages.iter().any(|c| c.whole_message())
+    }
+
+    /// Encrypts `src` with every stage in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error reported by a stage.
+    ///
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.try_encrypt_traced(dst, src, &mut NoTrace)
+    }
+
+    /// Decrypts `src` with every stage in reverse order.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error reported by a stage.
+    ///
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.try_decrypt_traced(dst, src, &mut NoTrace)
+    }
+
+    /// Reports every stage in order, the empty chain being a stage of its own.
+    ///
+    fn try_encrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        match self.stages.split_last() {
+            None => {
+                let res = Self::copy(dst, src);
+                trace::report(tracer, "Chain", src, dst, res)
+            }
+            Some((last, rest)) => {
+                let buf = Self::run(rest.iter(), src, true, tracer)?;
+                last.try_encrypt_traced(dst, &buf, tracer)
+            }
+        }
+    }
+
+    /// Reports every stage in reverse order.
+    ///
+    fn try_decrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        match self.stages.split_first() {
+            None => {
+                let res = Self::copy(dst, src);
+                trace::report(tracer, "Chain", src, dst, res)
+            }
+            Some((first, rest)) => {
+                let buf = Self::run(rest.iter().rev(), src, false, tracer)?;
+                first.try_decrypt_traced(dst, &buf, tracer)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        ADFGVX, CaesarCipher, IrregularTransposition, Nihilist, PlayfairCipher, SquareCipher,
+        StraddlingCheckerboard, Transposition,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_chain_empty() {
+        let c = Chain::new();
+        assert!(c.is_empty());
+        assert_eq!(c.block_size(), 1);
+        assert_eq!(c.encrypt_str("HELLO").unwrap(), "HELLO");
+        assert_eq!(c.decrypt_str("HELLO").unwrap(), "HELLO");
+    }
+
+    #[test]
+    fn test_chain_adfgvx() {
+        let c = Chain::new()
+            .then(SquareCipher::new("PORTABLE", "ADFGVX").unwrap())
+            .then(Transposition::new("SUBWAY").unwrap());
+        let adfgvx = ADFGVX::new("PORTABLE", "SUBWAY").unwrap();
+        let pt = b"ATTACKATDAWN";
+
+        assert_eq!(c.block_size(), adfgvx.block_size());
+        assert_eq!(c.encrypt_len(pt), 24);
+        assert_eq!(c.encrypt_to_vec(pt).unwrap(), adfgvx.encrypt_to_vec(pt).unwrap());
+        assert_eq!(c.decrypt_len(b"AFDFADAGAAAAVVVVGFGVGGGX"), 12);
+    }
+
+    #[test]
+    fn test_chain_nihilist() {
+        let c = Chain::new()
+            .then(StraddlingCheckerboard::new("ARABESQUE", "37").unwrap())
+            .then(Transposition::new("SUBWAY").unwrap());
+        let nihilist = Nihilist::new("ARABESQUE", "SUBWAY", "37").unwrap();
+        let pt = "IFYOUCANREADTHIS";
+
+        let ct = c.encrypt_str(pt).unwrap();
+        assert_eq!(ct, nihilist.encrypt_str(pt).unwrap());
+        assert_eq!(c.encrypt_len(pt.as_bytes()), ct.len());
+        assert_eq!(c.decrypt_str(&ct).unwrap(), pt);
+    }
+
+    static CALLS: AtomicUsize = AtomicUsize::new(0);
+
+    /// Copies its input, counting the calls to `try_encrypt` in `CALLS`.
+    struct Counted;
+
+    impl Block for Counted {
+        fn block_size(&self) -> usize {
+            1
+        }
+
+        fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+            CALLS.fetch_add(1, Ordering::Relaxed);
+            Chain::copy(dst, src)
+        }
+
+        fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+            Chain::copy(dst, src)
+        }
+    }
+
+    #[test]
+    fn test_chain_encrypt_len() {
+        let c = Chain::new()
+            .then(Counted)
+            .then(CaesarCipher::new(3))
+            .then(StraddlingCheckerboard::new("ARABESQUE", "37").unwrap())
+            .then(Transposition::new("SUBWAY").unwrap());
+        let pt = b"ATTACKAT1030";
+
+        let ct = c.encrypt_to_vec(pt).unwrap();
+        assert_eq!(CALLS.load(Ordering::Relaxed), 1);
+        assert!(c.encrypt_len(pt) >= ct.len());
+        assert!(c.decrypt_len(&ct) >= pt.len());
+        assert_eq!(c.decrypt_to_vec(&ct).unwrap(), pt);
+    }
+
+    #[test]
+    fn test_chain_roundtrip() {
+        let c: Chain = vec![
+            Box::new(PlayfairCipher::new("PLAYFAIREXAMPLE")) as Box<dyn Block + Send + Sync>,
+            Box::new(IrregularTransposition::new("SUBWAY").unwrap()),
+            Box::new(CaesarCipher::new(3)),
+        ]
+        .into();
+        let pt = "HIDETHEGOLDINTHETREXESTUMP";
+        let ct = c.encrypt_str(pt).unwrap();
+        assert_ne!(ct, pt);
+        assert_eq!(c.decrypt_str(&ct).unwrap(), pt);
+    }
+
+    #[test]
+    fn test_chain_normalizer() {
+        let c = Chain::new()
+            .then(CaesarCipher::new(3))
+            .then(SquareCipher::new("ARABESQUE", "012345").unwrap());
+        let pt = c.normalizer().normalize("Attack at 10, dawn!");
+        assert_eq!(pt, "ATTACKAT10COMMADAWNEXCLAM");
+        let ct = c.encrypt_str(&pt).unwrap();
+        assert_eq!(c.decrypt_str(&ct).unwrap(), pt);
+        assert!(c.encrypt_str("ATTACK AT DAWN").is_err());
+    }
+
+    #[test]
+    fn test_chain_errors() {
+        let c = Chain::new()
+            .then(PlayfairCipher::new("PLAYFAIREXAMPLE"))
+            .then(Transposition::new("SUBWAY").unwrap());
+        assert_eq!(c.encrypt_str("HIJACK"), Err(CipherError::InvalidChar { ch: 'J', pos: 2 }));
+        let mut dst = [0u8; 2];
+        assert_eq!(
+            c.try_encrypt(&mut dst, b"HIDE"),
+            Err(CipherError::BufferTooSmall { needed: 4, got: 2 })
+        );
+    }
+}
@@ -21,39 +21,63 @@
 //! cipher.encrypt(&mut ciphertext, plaintext);
 //! ```
 //!
-use crate::Block;
-use std::cell::RefCell;
+use crate::{Alphabet, Block, CipherError, Normalizer, Rekey, Rng, StreamCipher, Tracer};
+use crate::error::check_dst;
+use crate::trace;
+use std::fmt;
 
-const ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
 const ZENITH: usize = 0;
-const NADIR: usize = 13;
 
 /// A Chaocipher instance with two permutation alphabets.
 ///
-/// The `Chaocipher` struct maintains two 26-character alphabet keys (plaintext and cipher keys)
-/// and an internal state that is updated after each character encryption/decryption.
-/// The state is kept in a `RefCell` to allow interior mutability during const operations.
+/// The `Chaocipher` struct maintains two alphabet keys (plaintext and cipher keys), permutations
+/// of the 26 letters by default, from which a fresh working state is derived for every message, so the cipher itself
+/// never changes and can be shared between threads.
 ///
 pub struct Chaocipher {
+    /// The symbols both keys are permutations of
+    alphabet: Alphabet,
     /// The plaintext alphabet key (right alphabet)
     pkey: String,
     /// The cipher alphabet key (left alphabet)
     ckey: String,
-    /// Internal mutable state containing the working alphabets
-    state: RefCell<ChaocipherState>,
 }
 
 /// Internal state of the Chaocipher algorithm.
 ///
 /// Contains the two working alphabets that are permuted after each character operation.
+/// It is also the state used by the [`StreamCipher`] methods.
 ///
-struct ChaocipherState {
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChaocipherState {
     /// The plaintext working alphabet (right alphabet)
     pw: Vec<u8>,
     /// The cipher working alphabet (left alphabet)
     cw: Vec<u8>,
 }
 
+/// Prints the plaintext (right) alphabet, then the cipher (left) one.
+///
+impl fmt::Display for ChaocipherState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", String::from_utf8_lossy(&self.pw), String::from_utf8_lossy(&self.cw))
+    }
+}
+
+impl ChaocipherState {
+    /// Returns the current plaintext (right) alphabet.
+    ///
+    pub fn plain_alphabet(&self) -> &[u8] {
+        &self.pw
+    }
+
+    /// Returns the current cipher (left) alphabet.
+    ///
+    pub fn cipher_alphabet(&self) -> &[u8] {
+        &self.cw
+    }
+}
+
 impl Chaocipher {
     /// Creates a new Chaocipher instance with the provided keys.
     ///
@@ -66,8 +90,18 @@ impl Chaocipher {
     ///
     /// # Returns
     ///
-    /// Returns `Ok(Chaocipher)` if the keys are valid, or `Err(String)` if either key
-    /// has an incorrect length.
+    /// Returns `Ok(Chaocipher)` if the keys are valid, or a `CipherError` otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if either key:
+    /// - does not have exactly 26 characters (`BadAlphabetLength`)
+    /// - contains a character outside A-Z (`UnknownSymbol`)
+    /// - contains the same letter twice (`DuplicateSymbol`)
+    ///
+    /// Up to 0.3.0 only the length of the keys was checked: keys with lowercase
+    /// letters, other symbols or a repeated letter, which are not alphabets the
+    /// cipher can permute, are now rejected.
     ///
     /// # Example
     ///
@@ -80,21 +114,53 @@ impl Chaocipher {
     /// ).unwrap();
     /// ```
     ///
-    pub fn new(pkey: &str, ckey: &str) -> Result<Self, String> {
-        if pkey.len() != ALPHABET.len() || ckey.len() != ALPHABET.len() {
-            return Err("bad alphabet length".to_string());
-        }
+    pub fn new(pkey: &str, ckey: &str) -> Result<Self, CipherError> {
+        Self::with_alphabet(pkey, ckey, &Alphabet::latin())
+    }
+
+    /// Creates a Chaocipher whose keys are permutations of `alphabet`.
+    ///
+    /// The nadir is at the middle of the alphabets, position 13 for 26 letters.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Chaocipher::new`] with `alphabet` instead of A-Z, plus
+    /// `UnknownSymbol` if the alphabet has a non-ASCII symbol.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use old_crypto_rs::{Alphabet, BlockExt, Chaocipher};
+    ///
+    /// let a = Alphabet::base36();
+    /// let cipher = Chaocipher::with_alphabet(
+    ///     &a.keyed("PLAINTEXT").to_string(),
+    ///     &a.keyed("CIPHER").to_string(),
+    ///     &a,
+    /// ).unwrap();
+    /// let ct = cipher.encrypt_str("AGENT007").unwrap();
+    /// assert_eq!(cipher.decrypt_str(&ct).unwrap(), "AGENT007");
+    /// ```
+    ///
+    pub fn with_alphabet(pkey: &str, ckey: &str, alphabet: &Alphabet) -> Result<Self, CipherError> {
+        alphabet.check_ascii()?;
+        alphabet.check_permutation("pkey", pkey)?;
+        alphabet.check_permutation("ckey", ckey)?;
 
         Ok(Chaocipher {
+            alphabet: alphabet.clone(),
             pkey: pkey.to_string(),
             ckey: ckey.to_string(),
-            state: RefCell::new(ChaocipherState {
-                pw: pkey.as_bytes().to_vec(),
-                cw: ckey.as_bytes().to_vec(),
-            }),
         })
     }
 
+    /// Creates a Chaocipher with two random permutations of A-Z as keys.
+    ///
+    pub fn random(rng: &mut Rng) -> Self {
+        let a = Alphabet::latin();
+        Self::new(&rng.permutation(&a), &rng.permutation(&a)).unwrap()
+    }
+
     /// Performs a left circular shift on an alphabet by n positions.
     ///
     /// # Arguments
@@ -124,17 +190,19 @@ impl Chaocipher {
     /// * `idx` - The position of the character that was just processed
     ///
     fn advance(state: &mut ChaocipherState, idx: usize) {
+        let nadir = state.cw.len() / 2;
+
         // First we shift the left alphabet (cw)
         Self::lshift_n(&mut state.cw, idx);
         let l = state.cw[ZENITH + 1];
-        state.cw[ZENITH + 1..NADIR + 1].rotate_left(1);
-        state.cw[NADIR] = l;
+        state.cw[ZENITH + 1..nadir + 1].rotate_left(1);
+        state.cw[nadir] = l;
 
         // Then we shift the right alphabet (pw)
         Self::lshift_n(&mut state.pw, idx + 1);
         let l = state.pw[ZENITH + 2];
-        state.pw[ZENITH + 2..NADIR + 1].rotate_left(1);
-        state.pw[NADIR] = l;
+        state.pw[ZENITH + 2..nadir + 1].rotate_left(1);
+        state.pw[nadir] = l;
     }
 
     /// Encodes or decodes a single character.
@@ -145,6 +213,7 @@ impl Chaocipher {
     ///
     /// # Arguments
     ///
+    /// * `state` - The current cipher state, advanced after the character
     /// * `is_encrypt` - If true, encrypts; if false, decrypts
     /// * `ch` - The character to process
     ///
@@ -152,8 +221,7 @@ impl Chaocipher {
     ///
     /// The encrypted or decrypted character
     ///
-    fn encode_both(&self, is_encrypt: bool, ch: u8) -> u8 {
-        let mut state = self.state.borrow_mut();
+    fn encode_both(state: &mut ChaocipherState, is_encrypt: bool, ch: u8) -> u8 {
         let idx = if is_encrypt {
             state.pw.iter().position(|&x| x == ch).unwrap_or(0)
         } else {
@@ -166,19 +234,23 @@ impl Chaocipher {
             state.pw[idx]
         };
 
-        Self::advance(&mut state, idx);
+        Self::advance(state, idx);
         pt
     }
 
-    /// Resets the cipher state to the initial key configuration.
+    /// Encodes or decodes `src` into `dst` from the given state.
     ///
-    /// This method restores both working alphabets to their original key values,
-    /// allowing the cipher to be reused for multiple messages.
+    /// The whole input is checked before the state is touched.
     ///
-    fn reset(&self) {
-        let mut state = self.state.borrow_mut();
-        state.pw = self.pkey.as_bytes().to_vec();
-        state.cw = self.ckey.as_bytes().to_vec();
+    fn process(&self, state: &mut ChaocipherState, is_encrypt: bool, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        check_dst(dst, src.len())?;
+        if let Some(pos) = src.iter().position(|&ch| !self.alphabet.contains(ch as char)) {
+            return Err(CipherError::InvalidChar { ch: src[pos] as char, pos });
+        }
+        for (i, &ch) in src.iter().enumerate() {
+            dst[i] = Self::encode_both(state, is_encrypt, ch);
+        }
+        Ok(src.len())
     }
 }
 
@@ -191,10 +263,19 @@ impl Block for Chaocipher {
         1
     }
 
+    fn alphabet(&self) -> Option<Alphabet> {
+        Some(self.alphabet.clone())
+    }
+
+    /// Both alphabets have only the symbols of the alphabet, the 26 letters by default.
+    ///
+    fn normalizer(&self) -> Normalizer {
+        self.alphabet.normalizer()
+    }
+
     /// Encrypts the source data into the destination buffer.
     ///
-    /// The cipher state is reset before encryption begins, ensuring that each encryption
-    /// operation starts from the initial key configuration.
+    /// Every call starts from the initial key configuration.
     ///
     /// # Arguments
     ///
@@ -205,6 +286,11 @@ impl Block for Chaocipher {
     ///
     /// The number of bytes encrypted (equal to the length of `src`)
     ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::InvalidChar` for anything outside the alphabet and
+    /// `CipherError::BufferTooSmall` if `dst` is shorter than `src`.
+    ///
     /// # Example
     ///
     /// ```
@@ -220,18 +306,13 @@ impl Block for Chaocipher {
     /// cipher.encrypt(&mut ciphertext, plaintext);
     /// ```
     ///
-    fn encrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        self.reset();
-        for (i, &ch) in src.iter().enumerate() {
-            dst[i] = self.encode_both(true, ch);
-        }
-        src.len()
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.encrypt_stream(&mut self.start(), dst, src)
     }
 
     /// Decrypts the source data into the destination buffer.
     ///
-    /// The cipher state is reset before decryption begins, ensuring that each decryption
-    /// operation starts from the initial key configuration.
+    /// Every call starts from the initial key configuration.
     ///
     /// # Arguments
     ///
@@ -242,6 +323,11 @@ impl Block for Chaocipher {
     ///
     /// The number of bytes decrypted (equal to the length of `src`)
     ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::InvalidChar` for anything outside the alphabet and
+    /// `CipherError::BufferTooSmall` if `dst` is shorter than `src`.
+    ///
     /// # Example
     ///
     /// ```
@@ -257,18 +343,78 @@ impl Block for Chaocipher {
     /// cipher.decrypt(&mut plaintext, ciphertext);
     /// ```
     ///
-    fn decrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        self.reset();
-        for (i, &ch) in src.iter().enumerate() {
-            dst[i] = self.encode_both(false, ch);
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.decrypt_stream(&mut self.start(), dst, src)
+    }
+
+    /// Reports the state of the machine after every character.
+    ///
+    fn try_encrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        trace::trace_stream(self, dst, src, tracer, true)
+    }
+
+    /// Reports the state of the machine after every character.
+    ///
+    fn try_decrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        trace::trace_stream(self, dst, src, tracer, false)
+    }
+}
+
+impl StreamCipher for Chaocipher {
+    type State = ChaocipherState;
+
+    /// Returns both working alphabets set to their original key values.
+    ///
+    fn start(&self) -> ChaocipherState {
+        ChaocipherState {
+            pw: self.pkey.as_bytes().to_vec(),
+            cw: self.ckey.as_bytes().to_vec(),
         }
-        src.len()
+    }
+
+    /// Reads both working alphabets, which must be permutations of the alphabet of
+    /// the cipher (`BadAlphabetLength`, `UnknownSymbol` or `DuplicateSymbol`
+    /// otherwise).
+    ///
+    fn restore(&self, saved: &str) -> Result<ChaocipherState, CipherError> {
+        match saved.split_whitespace().collect::<Vec<_>>()[..] {
+            [pw, cw] => {
+                self.alphabet.check_permutation("state", pw)?;
+                self.alphabet.check_permutation("state", cw)?;
+                Ok(ChaocipherState { pw: pw.as_bytes().to_vec(), cw: cw.as_bytes().to_vec() })
+            }
+            _ => Err(CipherError::InvalidParameter { param: "state", value: saved.to_string() }),
+        }
+    }
+
+    fn encrypt_stream(&self, state: &mut ChaocipherState, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.process(state, true, dst, src)
+    }
+
+    fn decrypt_stream(&self, state: &mut ChaocipherState, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.process(state, false, dst, src)
+    }
+}
+
+/// The key is the pair of plaintext and cipher keys, permutations of the same
+/// alphabet as before.
+///
+impl Rekey<[&str; 2]> for Chaocipher {
+    fn rekey(&mut self, [pkey, ckey]: &[&str; 2]) -> Result<(), CipherError> {
+        self.alphabet.check_permutation("pkey", pkey)?;
+        self.alphabet.check_permutation("ckey", ckey)?;
+        self.pkey.clear();
+        self.pkey.push_str(pkey);
+        self.ckey.clear();
+        self.ckey.push_str(ckey);
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::BlockExt;
 
     use rstest::rstest;
 
@@ -278,6 +424,7 @@ mod tests {
     const LCIPHER_TXT: &str = "TLMAGOONSKJBJYBQVGDQCDUNWNMZPLOYCWPCWKWQRBOYADSLQBKYCDGXJOLONKTTLRUZZJQGJBQNRQHQRREUIYIDHZOMVWZMVYUFQOGSNNUVYTJGQPSQTBRWFHLTCLVVBPMYYQVC";
     const KEY_PLAIN: &str = "PTLNBQDEOYSFAVZKGJRIHWXUMC";
     const KEY_CIPHER: &str = "HXUCZVAMDSLKPEFJRIGTWOBNYQ";
+    const ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
 
     #[test]
     fn test_new_cipher() {
@@ -290,6 +437,30 @@ mod tests {
         assert!(Chaocipher::new("AB", "CD").is_err());
     }
 
+    #[rstest]
+    #[case("AB", KEY_CIPHER, CipherError::BadAlphabetLength { param: "pkey", expected: 26, got: 2 })]
+    #[case(KEY_PLAIN, "AACDEFGHIJKLMNOPQRSTUVWXYZ", CipherError::DuplicateSymbol { param: "ckey", symbol: 'A' })]
+    #[case("aBCDEFGHIJKLMNOPQRSTUVWXYZ", KEY_CIPHER, CipherError::UnknownSymbol { param: "pkey", symbol: 'a' })]
+    fn test_new_cipher_errors(#[case] pkey: &str, #[case] ckey: &str, #[case] err: CipherError) {
+        assert_eq!(Chaocipher::new(pkey, ckey).err(), Some(err));
+    }
+
+    #[test]
+    fn test_chaocipher_with_alphabet() {
+        let a = Alphabet::base36();
+        let c = Chaocipher::with_alphabet(&a.keyed("PLAINTEXT").to_string(), &a.keyed("CIPHER").to_string(), &a).unwrap();
+        let ct = c.encrypt_str("AGENT007").unwrap();
+        assert_ne!(ct, "AGENT007");
+        assert_eq!(c.decrypt_str(&ct).unwrap(), "AGENT007");
+        assert_eq!(c.normalizer(), Normalizer::alphanumeric());
+
+        // Same keys, different alphabet
+        let latin = Chaocipher::new(KEY_PLAIN, KEY_CIPHER).unwrap();
+        let err = Chaocipher::with_alphabet(KEY_PLAIN, KEY_CIPHER, &a).err();
+        assert_eq!(err, Some(CipherError::BadAlphabetLength { param: "pkey", expected: 36, got: 26 }));
+        assert_eq!(latin.encrypt_str(PLAIN_TXT).unwrap(), CIPHER_TXT);
+    }
+
     #[rstest]
     #[case(PLAIN_TXT, CIPHER_TXT)]
     #[case(LPLAIN_TXT, LCIPHER_TXT)]
@@ -312,6 +483,63 @@ mod tests {
         assert_eq!(dst, pt.as_bytes());
     }
 
+    #[test]
+    fn test_chaocipher_stream() {
+        let c = Chaocipher::new(KEY_PLAIN, KEY_CIPHER).unwrap();
+        let src = LPLAIN_TXT.as_bytes();
+        let mut dst = vec![0u8; src.len()];
+        let mut state = c.start();
+        for (i, chunk) in src.chunks(7).enumerate() {
+            c.encrypt_stream(&mut state, &mut dst[i * 7..], chunk).unwrap();
+        }
+        assert_eq!(dst, LCIPHER_TXT.as_bytes());
+
+        let mut state = c.start();
+        let mut pt = vec![0u8; 10];
+        c.decrypt_stream(&mut state, &mut pt, &LCIPHER_TXT.as_bytes()[..10]).unwrap();
+        let saved = state.clone();
+        assert_eq!(saved.plain_alphabet().len(), 26);
+        let mut rest = vec![0u8; 5];
+        c.decrypt_stream(&mut state, &mut rest, &LCIPHER_TXT.as_bytes()[10..15]).unwrap();
+        let mut state = saved;
+        let mut again = vec![0u8; 5];
+        c.decrypt_stream(&mut state, &mut again, &LCIPHER_TXT.as_bytes()[10..15]).unwrap();
+        assert_eq!(rest, again);
+        assert_eq!(again, &LPLAIN_TXT.as_bytes()[10..15]);
+    }
+
+    #[test]
+    fn test_chaocipher_restore() {
+        let c = Chaocipher::new(KEY_PLAIN, KEY_CIPHER).unwrap();
+        let mut state = c.start();
+        let mut ct = vec![0u8; PLAIN_TXT.len()];
+        c.encrypt_stream(&mut state, &mut ct[..10], &PLAIN_TXT.as_bytes()[..10]).unwrap();
+        let saved = state.to_string();
+
+        let mut state = c.restore(&saved).unwrap();
+        assert_eq!(state.to_string(), saved);
+        c.encrypt_stream(&mut state, &mut ct[10..], &PLAIN_TXT.as_bytes()[10..]).unwrap();
+        assert_eq!(ct, CIPHER_TXT.as_bytes());
+    }
+
+    #[rstest]
+    #[case(KEY_PLAIN, CipherError::InvalidParameter { param: "state", value: KEY_PLAIN.to_string() })]
+    #[case("PTLNBQDEOYSFAVZKGJRIHWXUMC ABC", CipherError::BadAlphabetLength { param: "state", expected: 26, got: 3 })]
+    #[case("PTLNBQDEOYSFAVZKGJRIHWXUMC PTLNBQDEOYSFAVZKGJRIHWXUMM", CipherError::DuplicateSymbol { param: "state", symbol: 'M' })]
+    fn test_chaocipher_restore_errors(#[case] saved: &str, #[case] err: CipherError) {
+        let c = Chaocipher::new(KEY_PLAIN, KEY_CIPHER).unwrap();
+        assert_eq!(c.restore(saved).err(), Some(err));
+    }
+
+    #[test]
+    fn test_chaocipher_try_errors() {
+        let c = Chaocipher::new(KEY_PLAIN, KEY_CIPHER).unwrap();
+        let mut dst = vec![0u8; 8];
+        assert_eq!(c.try_encrypt(&mut dst, b"WELL DONE"), Err(CipherError::BufferTooSmall { needed: 9, got: 8 }));
+        assert_eq!(c.try_encrypt(&mut dst, b"WELL DON"), Err(CipherError::InvalidChar { ch: ' ', pos: 4 }));
+        assert_eq!(c.try_decrypt(&mut dst, b"oahq"), Err(CipherError::InvalidChar { ch: 'o', pos: 0 }));
+    }
+
     #[rstest]
     #[case('A', 12, "PFJRIGTWOBNYQEHXUCZVAMDSLK", "VZGJRIHWXUMCPKTLNBQDEOYSFA")]
     #[case('W', 21, "ONYQHXUCZVAMDBSLKPEFJRIGTW", "XUCPTLNBQDEOYMSFAVZKGJRIHW")]
@@ -320,11 +548,22 @@ mod tests {
         let idx = KEY_PLAIN.find(c_find).unwrap();
         assert_eq!(idx, expected_idx);
 
-        {
-            let mut state = c.state.borrow_mut();
-            Chaocipher::advance(&mut state, idx);
-        }
-        assert_eq!(String::from_utf8_lossy(&c.state.borrow().cw), expected_cw);
-        assert_eq!(String::from_utf8_lossy(&c.state.borrow().pw), expected_pw);
+        let mut state = c.start();
+        Chaocipher::advance(&mut state, idx);
+        assert_eq!(String::from_utf8_lossy(&state.cw), expected_cw);
+        assert_eq!(String::from_utf8_lossy(&state.pw), expected_pw);
+    }
+
+    #[test]
+    fn test_chaocipher_rekey() {
+        let mut c = Chaocipher::new(KEY_CIPHER, KEY_PLAIN).unwrap();
+        c.rekey(&[KEY_PLAIN, KEY_CIPHER]).unwrap();
+        assert_eq!(c.encrypt_str(PLAIN_TXT).unwrap(), CIPHER_TXT);
+
+        assert_eq!(
+            c.rekey(&[KEY_PLAIN, "AB"]),
+            Err(CipherError::BadAlphabetLength { param: "ckey", expected: 26, got: 2 })
+        );
+        assert_eq!(c.encrypt_str(PLAIN_TXT).unwrap(), CIPHER_TXT);
     }
 }
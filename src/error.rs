@@ -0,0 +1,239 @@
+//! Error type shared by every cipher in the crate.
+//!
+//! All constructors validate their key material and report problems through
+//! [`CipherError`] rather than a free-form message, so callers can match on
+//! the kind of error and on the parameter that caused it.
+//!
+//! The same type is returned by [`Block::try_encrypt`](crate::Block::try_encrypt) and
+//! [`Block::try_decrypt`](crate::Block::try_decrypt) when the input can not be processed
+//! or the destination buffer is too small.
+//!
+//! # Example
+//!
+//! ```
+//! use old_crypto_rs::{Chaocipher, CipherError};
+//!
+//! match Chaocipher::new("ABC", "HXUCZVAMDSLKPEFJRIGTWOBNYQ") {
+//!     Err(CipherError::BadAlphabetLength { param, expected, got }) => {
+//!         assert_eq!(param, "pkey");
+//!         assert_eq!(expected, 26);
+//!         assert_eq!(got, 3);
+//!     }
+//!     _ => unreachable!(),
+//! }
+//! ```
+//!
+use std::fmt;
+
+/// Errors returned when building or using a cipher.
+///
+/// Each variant carries the name of the offending parameter (as documented in
+/// the constructor's `# Arguments` section) and, where it makes sense, the
+/// offending value.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CipherError {
+    /// A key or parameter that must not be empty was empty.
+    EmptyKey(&'static str),
+    /// An alphabet (or a key that must be a full alphabet) has the wrong number of symbols.
+    BadAlphabetLength {
+        param: &'static str,
+        expected: usize,
+        got: usize,
+    },
+    /// A symbol appears more than once where all symbols must be distinct.
+    DuplicateSymbol { param: &'static str, symbol: char },
+    /// A symbol is not part of the alphabet it is expected to come from.
+    UnknownSymbol { param: &'static str, symbol: char },
+    /// A character that must be a decimal digit is not one.
+    InvalidDigit { param: &'static str, ch: char },
+    /// A key is shorter than the procedure requires.
+    KeyTooShort {
+        param: &'static str,
+        min: usize,
+        got: usize,
+    },
+    /// The destination buffer can not hold the whole output.
+    BufferTooSmall { needed: usize, got: usize },
+    /// A character of the input is not handled by the cipher.
+    InvalidChar { ch: char, pos: usize },
+    /// The input must have an even length (bigram ciphers).
+    OddLength { len: usize },
+    /// A pair of characters of the input does not map to anything.
+    UnknownBigram { bigram: [char; 2], pos: usize },
+    /// The input of a cipher working on characters is not valid UTF-8.
+    InvalidUtf8 { pos: usize },
+    /// A plaintext ends with the fill letter, which would be taken for padding.
+    EndsWithFill { fill: char },
+    /// The padding at the end of a decrypted text is not a valid one.
+    BadPadding,
+    /// No cipher of that name in the [registry](crate::registry).
+    UnknownCipher(String),
+    /// A required parameter was not given to the [registry](crate::registry).
+    MissingParameter(&'static str),
+    /// A parameter given to the [registry](crate::registry) is not taken by the cipher.
+    UnknownParameter(String),
+    /// A parameter value could not be parsed (e.g. a non-numeric Caesar shift).
+    InvalidParameter { param: &'static str, value: String },
+    /// A spec string is not of the `name:param=value,…` form.
+    BadSpec(String),
+    /// An error found at a given line (starting at 1) of a [key file](crate::keyfile).
+    AtLine { line: usize, error: Box<CipherError> },
+}
+
+impl CipherError {
+    /// Returns the name of the parameter this error refers to.
+    ///
+    /// Errors raised while encrypting or decrypting refer to the `src` or `dst`
+    /// argument of [`Block::try_encrypt`](crate::Block::try_encrypt).
+    ///
+    pub fn param(&self) -> &'static str {
+        match self {
+            CipherError::EmptyKey(param) => param,
+            CipherError::BadAlphabetLength { param, .. }
+            | CipherError::DuplicateSymbol { param, .. }
+            | CipherError::UnknownSymbol { param, .. }
+            | CipherError::InvalidDigit { param, .. }
+            | CipherError::KeyTooShort { param, .. }
+            | CipherError::InvalidParameter { param, .. } => param,
+            CipherError::MissingParameter(param) => param,
+            CipherError::BufferTooSmall { .. } => "dst",
+            CipherError::InvalidChar { .. }
+            | CipherError::OddLength { .. }
+            | CipherError::UnknownBigram { .. }
+            | CipherError::InvalidUtf8 { .. }
+            | CipherError::EndsWithFill { .. }
+            | CipherError::BadPadding => "src",
+            CipherError::UnknownCipher(_) => "name",
+            CipherError::UnknownParameter(_) => "params",
+            CipherError::BadSpec(_) => "spec",
+            CipherError::AtLine { error, .. } => error.param(),
+        }
+    }
+
+    /// Renames the parameter carried by the error.
+    ///
+    /// Composite ciphers (ADFGVX, Nihilist, VIC) use this to report their own
+    /// argument names instead of the ones of the cipher they delegate to.
+    ///
+    pub(crate) fn for_param(self, name: &'static str) -> Self {
+        match self {
+            CipherError::EmptyKey(_) => CipherError::EmptyKey(name),
+            CipherError::BadAlphabetLength { expected, got, .. } => {
+                CipherError::BadAlphabetLength { param: name, expected, got }
+            }
+            CipherError::DuplicateSymbol { symbol, .. } => {
+                CipherError::DuplicateSymbol { param: name, symbol }
+            }
+            CipherError::UnknownSymbol { symbol, .. } => {
+                CipherError::UnknownSymbol { param: name, symbol }
+            }
+            CipherError::InvalidDigit { ch, .. } => CipherError::InvalidDigit { param: name, ch },
+            CipherError::KeyTooShort { min, got, .. } => {
+                CipherError::KeyTooShort { param: name, min, got }
+            }
+            e => e,
+        }
+    }
+}
+
+impl fmt::Display for CipherError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CipherError::EmptyKey(param) => write!(f, "{} can not be empty", param),
+            CipherError::BadAlphabetLength { param, expected, got } => {
+                write!(f, "bad alphabet length for {}: expected {}, got {}", param, expected, got)
+            }
+            CipherError::DuplicateSymbol { param, symbol } => {
+                write!(f, "duplicate symbol '{}' in {}", symbol, param)
+            }
+            CipherError::UnknownSymbol { param, symbol } => {
+                write!(f, "unknown symbol '{}' in {}", symbol, param)
+            }
+            CipherError::InvalidDigit { param, ch } => {
+                write!(f, "invalid digit '{}' in {}", ch, param)
+            }
+            CipherError::KeyTooShort { param, min, got } => {
+                write!(f, "{} too short: need at least {}, got {}", param, min, got)
+            }
+            CipherError::BufferTooSmall { needed, got } => {
+                write!(f, "destination buffer too small: need {}, got {}", needed, got)
+            }
+            CipherError::InvalidChar { ch, pos } => {
+                write!(f, "invalid character '{}' at position {}", ch.escape_default(), pos)
+            }
+            CipherError::OddLength { len } => write!(f, "odd number of elements: {}", len),
+            CipherError::UnknownBigram { bigram, pos } => {
+                write!(f, "unknown bigram '{}{}' at position {}", bigram[0], bigram[1], pos)
+            }
+            CipherError::InvalidUtf8 { pos } => write!(f, "invalid UTF-8 at position {}", pos),
+            CipherError::EndsWithFill { fill } => write!(f, "text ends with the fill letter '{}'", fill),
+            CipherError::BadPadding => write!(f, "invalid padding"),
+            CipherError::UnknownCipher(name) => write!(f, "unknown cipher '{}'", name),
+            CipherError::MissingParameter(param) => write!(f, "missing parameter {}", param),
+            CipherError::UnknownParameter(param) => write!(f, "unknown parameter {}", param),
+            CipherError::InvalidParameter { param, value } => {
+                write!(f, "invalid value '{}' for {}", value, param)
+            }
+            CipherError::BadSpec(item) => write!(f, "bad cipher spec near '{}'", item),
+            CipherError::AtLine { line, error } => write!(f, "line {}: {}", line, error),
+        }
+    }
+}
+
+impl std::error::Error for CipherError {}
+
+/// Checks that `dst` can hold `needed` bytes of output.
+///
+pub(crate) fn check_dst(dst: &[u8], needed: usize) -> Result<(), CipherError> {
+    if dst.len() < needed {
+        return Err(CipherError::BufferTooSmall { needed, got: dst.len() });
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(CipherError::EmptyKey("key").to_string(), "key can not be empty");
+        assert_eq!(
+            CipherError::BadAlphabetLength { param: "pkey", expected: 26, got: 2 }.to_string(),
+            "bad alphabet length for pkey: expected 26, got 2"
+        );
+        assert_eq!(
+            CipherError::InvalidDigit { param: "ind", ch: 'X' }.to_string(),
+            "invalid digit 'X' in ind"
+        );
+        assert_eq!(
+            CipherError::InvalidChar { ch: 'J', pos: 3 }.to_string(),
+            "invalid character 'J' at position 3"
+        );
+        assert_eq!(CipherError::OddLength { len: 3 }.to_string(), "odd number of elements: 3");
+        assert_eq!(CipherError::InvalidUtf8 { pos: 5 }.to_string(), "invalid UTF-8 at position 5");
+        assert_eq!(CipherError::MissingParameter("key2").to_string(), "missing parameter key2");
+        assert_eq!(
+            CipherError::AtLine { line: 4, error: Box::new(CipherError::EmptyKey("pkey")) }.to_string(),
+            "line 4: pkey can not be empty"
+        );
+        assert_eq!(
+            CipherError::InvalidParameter { param: "key", value: "three".to_string() }.to_string(),
+            "invalid value 'three' for key"
+        );
+    }
+
+    #[test]
+    fn test_for_param() {
+        let e = CipherError::EmptyKey("key").for_param("key2");
+        assert_eq!(e, CipherError::EmptyKey("key2"));
+        assert_eq!(e.param(), "key2");
+
+        let e = CipherError::KeyTooShort { param: "chrs", min: 2, got: 0 }.for_param("persn");
+        assert_eq!(e.param(), "persn");
+
+        let e = CipherError::BufferTooSmall { needed: 4, got: 2 }.for_param("key1");
+        assert_eq!(e.param(), "dst");
+    }
+}
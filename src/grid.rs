@@ -0,0 +1,130 @@
+//! Key schedules laid out as on paper.
+//!
+//! The ciphers built on a table (the Playfair matrix, the Polybius square of ADFGVX,
+//! the straddling checkerboard, the numbered columns of a transposition) can return
+//! it as a [`Grid`], so an operator can check it against the one drawn by hand.  A
+//! grid prints as a table, with its column and row labels if it has some.
+//!
+//! # Example
+//!
+//! ```
+//! use old_crypto_rs::SquareCipher;
+//!
+//! let c = SquareCipher::new("PORTABLE", "ADFGVX").unwrap();
+//! assert_eq!(
+//!     c.grid().to_string(),
+//!     "  A D F G V X
+//! A P O R T A B
+//! D L E C D F G
+//! F H I J K M N
+//! G Q S U V W X
+//! V Y Z 0 1 2 3
+//! X 4 5 6 7 8 9
+//! "
+//! );
+//! ```
+//!
+use std::fmt;
+
+/// A table of cells, with optional column and row labels.
+///
+/// Cells are strings so that they can hold numbers; an empty cell is a blank one.
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Grid {
+    columns: Vec<String>,
+    rows: Vec<(String, Vec<String>)>,
+}
+
+impl Grid {
+    /// Creates an empty grid with the given column labels, none if `columns` is empty.
+    ///
+    pub fn new<S: ToString>(columns: &[S]) -> Self {
+        Grid { columns: columns.iter().map(S::to_string).collect(), rows: Vec::new() }
+    }
+
+    /// Appends a row, with an empty `label` if it has none.
+    ///
+    pub fn push_row<S: ToString>(&mut self, label: &str, cells: &[S]) {
+        self.rows.push((label.to_string(), cells.iter().map(S::to_string).collect()));
+    }
+
+    /// Returns the column labels.
+    ///
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+
+    /// Returns the label and cells of each row.
+    ///
+    pub fn rows(&self) -> impl Iterator<Item = (&str, &[String])> {
+        self.rows.iter().map(|(label, cells)| (label.as_str(), cells.as_slice()))
+    }
+
+    /// Returns the cell at row `r` and column `c`, `None` if out of the grid.
+    ///
+    pub fn cell(&self, r: usize, c: usize) -> Option<&str> {
+        self.rows.get(r)?.1.get(c).map(String::as_str)
+    }
+}
+
+/// Cells are right-aligned in columns separated by a space, trailing blanks are
+/// trimmed.
+///
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let ncols = self.rows.iter().map(|(_, cells)| cells.len()).chain([self.columns.len()]).max().unwrap_or(0);
+        let mut widths = vec![1; ncols];
+        for cells in self.rows.iter().map(|(_, cells)| cells).chain([&self.columns]) {
+            for (w, cell) in widths.iter_mut().zip(cells) {
+                *w = (*w).max(cell.chars().count());
+            }
+        }
+        let label_width = self.rows.iter().map(|(label, _)| label.chars().count()).max().unwrap_or(0);
+
+        let mut line = |label: &str, cells: &[String]| {
+            let mut out = String::new();
+            if label_width > 0 {
+                out.push_str(&format!("{:>w$} ", label, w = label_width));
+            }
+            for (i, w) in widths.iter().enumerate() {
+                let cell = cells.get(i).map_or("", String::as_str);
+                out.push_str(&format!("{:>w$} ", cell, w = w));
+            }
+            writeln!(f, "{}", out.trim_end())
+        };
+        if !self.columns.is_empty() {
+            line("", &self.columns)?;
+        }
+        for (label, cells) in &self.rows {
+            line(label, cells)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid() {
+        let mut g = Grid::new(&['A', 'B']);
+        g.push_row("1", &["X", "Y"]);
+        g.push_row("", &["10", ""]);
+        assert_eq!(g.columns(), ["A", "B"]);
+        assert_eq!(g.cell(1, 0), Some("10"));
+        assert_eq!(g.cell(2, 0), None);
+        assert_eq!(g.rows().count(), 2);
+        assert_eq!(g.to_string(), "   A B\n1  X Y\n  10\n");
+    }
+
+    #[test]
+    fn test_grid_no_labels() {
+        let mut g = Grid::new::<char>(&[]);
+        g.push_row("", &['P', 'L']);
+        g.push_row("", &['A', 'Y']);
+        assert_eq!(g.to_string(), "P L\nA Y\n");
+        assert_eq!(Grid::default().to_string(), "");
+    }
+}
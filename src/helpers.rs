@@ -69,15 +69,27 @@ pub fn condense(str: &str) -> String {
 /// Efficiently removes all duplicate characters from a string, returning a new String.
 ///
 /// This implementation uses a bitset for ASCII characters to achieve O(n) time complexity
-/// and minimal overhead.
-/// 
+/// and minimal overhead, other characters go through a `HashSet`.
+///
+/// ```
+/// use old_crypto_rs::helpers::condense_str;
+///
+/// assert_eq!(condense_str("ARABESQUE"), "ARBESQU");
+/// assert_eq!(condense_str("ШИФРОВАНИЕ"), "ШИФРОВАНЕ");
+/// ```
+///
 pub fn condense_str(s: &str) -> String {
-    let mut seen_ascii = [false; 256];
+    let mut seen_ascii = [false; 128];
+    let mut seen = HashSet::new();
     let mut res = String::with_capacity(s.len());
 
     for c in s.chars() {
-        if !seen_ascii[c as usize] {
-            seen_ascii[c as usize] = true;
+        let first = if c.is_ascii() {
+            !std::mem::replace(&mut seen_ascii[c as usize], true)
+        } else {
+            seen.insert(c)
+        };
+        if first {
             res.push(c);
         }
     }
@@ -254,42 +266,36 @@ pub fn expand(src: &[u8]) -> Vec<u8> {
 /// Space complexity: O(n + m) for the working vector and result string
 /// 
 pub fn shuffle(key: &str, alphabet: &str) -> String {
-    let mut word = Vec::with_capacity(key.len() + alphabet.len());
-    let mut seen = [false; 256];
-    let mut length = 0;
+    let mut word: Vec<char> = Vec::with_capacity(key.len() + alphabet.len());
+    let mut seen = HashSet::new();
     for c in key.chars() {
-        if (c as usize) < 256 && !seen[c as usize] {
-            seen[c as usize] = true;
-            word.push(c as u8);
-            length += 1;
+        if seen.insert(c) {
+            word.push(c);
         }
     }
+    let length = word.len();
     if length == 0 {
         return alphabet.to_string();
     }
 
     for c in alphabet.chars() {
-        if (c as usize) < 256 && !seen[c as usize] {
-            seen[c as usize] = true;
-            word.push(c as u8);
+        if seen.insert(c) {
+            word.push(c);
         }
     }
 
-    let mut height = alphabet.len() / length;
-    if alphabet.len() % length != 0 {
-        height += 1;
-    }
+    let height = alphabet.chars().count().div_ceil(length);
 
     let mut res = String::with_capacity(word.len());
     for i in (0..length).rev() {
         for j in 0..=height {
             if word.len() <= height.saturating_sub(1) {
-                res.push_str(std::str::from_utf8(&word).unwrap());
+                res.extend(word);
                 return res;
             } else {
                 if i * j < word.len() {
                     let c = word.remove(i * j);
-                    res.push(c as char);
+                    res.push(c);
                 }
             }
         }
@@ -297,12 +303,21 @@ pub fn shuffle(key: &str, alphabet: &str) -> String {
     res
 }
 
+/// Returns the rank of each character of `key` in alphabetical order, ties being
+/// broken by position, as used to number the columns of a transposition.
+///
+/// ```
+/// use old_crypto_rs::helpers::to_numeric;
+///
+/// assert_eq!(to_numeric("SUBWAY"), vec![2, 3, 1, 4, 0, 5]);
+/// assert_eq!(to_numeric("ΚΛΕΙΔΙ"), vec![4, 5, 1, 2, 0, 3]);
+/// ```
+///
 pub fn to_numeric(key: &str) -> Vec<u8> {
-    let letters = key.as_bytes();
-    let mut indexed: Vec<(usize, u8)> = letters.iter().enumerate().map(|(i, &b)| (i, b)).collect();
-    indexed.sort_by_key(|&(_, b)| b);
+    let mut indexed: Vec<(usize, char)> = key.chars().enumerate().collect();
+    indexed.sort_by_key(|&(_, c)| c);
 
-    let mut ar = vec![0u8; letters.len()];
+    let mut ar = vec![0u8; indexed.len()];
     for (rank, (original_idx, _)) in indexed.into_iter().enumerate() {
         ar[original_idx] = rank as u8;
     }
@@ -311,13 +326,11 @@ pub fn to_numeric(key: &str) -> Vec<u8> {
 
 pub fn by_n(ct: &str, n: usize) -> String {
     let mut out = String::new();
-    let mut count = 0;
-    for ch in ct.chars() {
+    for (count, ch) in ct.chars().enumerate() {
         if count > 0 && count % n == 0 {
             out.push(' ');
         }
         out.push(ch);
-        count += 1;
     }
     out
 }
@@ -339,14 +352,16 @@ pub fn output_as_block(input: &str) -> String {
 }
 
 /// Replace all instance of NN with NQN
+///
+/// A doubled `fill` is split with `X` instead (`QQ` → `QXQ`), or with `Q` if `fill`
+/// is `X`, so that the output has no doubled letter left.
 pub fn fix_double(str: &str, fill: char) -> String {
+    let other = if fill == 'X' { 'Q' } else { 'X' };
     let mut fixed = String::new();
     let mut prev = None;
     for ch in str.chars() {
-        if let Some(p) = prev {
-            if ch == p {
-                fixed.push(fill);
-            }
+        if prev == Some(ch) {
+            fixed.push(if ch == fill { other } else { fill });
         }
         fixed.push(ch);
         prev = Some(ch);
@@ -412,6 +427,14 @@ mod tests {
         assert_eq!(res, "SCIOXUDJPZBEKQ/WFLR-AGMTYHNV");
     }
 
+    #[test]
+    fn test_non_latin() {
+        assert_eq!(condense_str("ΑΛΦΑΒΗΤΟ"), "ΑΛΦΒΗΤΟ");
+        assert_eq!(condense("ΑΛΦΑΒΗΤΟ"), condense_str("ΑΛΦΑΒΗΤΟ"));
+        assert_eq!(shuffle("ВЕК", "АБВГДЕЖЗ"), "ВАДЕБЖКГЗ");
+        assert_eq!(shuffle("CFX", "ABCDEFGH"), "CAEFBGXDH");
+    }
+
     #[test]
     fn test_to_numeric() {
         let test_data = [
@@ -455,9 +478,11 @@ mod tests {
             ("ABCDEF", "ABCDEF"),
             ("AABCDE", "AQABCDE"),
             ("AAAAA", "AQAQAQAQA"),
+            ("AQQA", "AQXQA"),
         ];
         for (in_str, out_str) in test_data {
             assert_eq!(fix_double(in_str, 'Q'), out_str);
         }
+        assert_eq!(fix_double("AXXA", 'X'), "AXQXA");
     }
 }
@@ -0,0 +1,715 @@
+//! Encrypting files and pipes.
+//!
+//! The [`Block`] methods work on a whole message in memory.  The adapters of this
+//! module wrap a [`std::io::Write`] or [`std::io::Read`] instead, so that a large
+//! file or standard input goes through a cipher a piece at a time:
+//!
+//! - [`CipherWriter`] and [`CipherReader`] cut the text into chunks of a whole number
+//!   of blocks ([`Block::block_size`]) and run each of them through the cipher.  The
+//!   last, partial, chunk gets the [`Padding`] of the adapter, which is removed again
+//!   on decryption.
+//! - [`StreamWriter`] and [`StreamReader`] drive a [`StreamCipher`] (Chaocipher,
+//!   Wheatstone, Solitaire, SIGABA), whose state carries over from one piece to the
+//!   next: the output is the same as for the whole text at once.
+//!
+//! Each chunk is a message of its own.  This makes no difference for ciphers working
+//! block by block (Caesar, Playfair…) but transpositions and the product ciphers
+//! built on them mix the symbols of a chunk only, so the same chunk size must be used
+//! to decrypt.  The ciphertext is then cut after what the cipher writes for a chunk
+//! ([`Block::encrypt_chars`]), twice as many symbols for Square or ADFGVX.  A cipher
+//! writing a variable number of them (straddling checkerboard, VIC) ends every chunk
+//! but the last with a newline, where the decrypting side cuts it.  A cipher adding
+//! something once per message ([`Block::whole_message`]), like the keygroup of a
+//! [`VicMessage`](crate::VicMessage), gets the whole text as a single chunk.
+//!
+//! Errors of the cipher come back as `InvalidData` errors wrapping the
+//! [`CipherError`], with positions in the chunk.
+//!
+//! # Example
+//!
+//! ```
+//! use old_crypto_rs::{CipherReader, CipherWriter, Padding, PlayfairCipher};
+//! use std::io::{self, Read, Write};
+//!
+//! let mut w = CipherWriter::encrypt(Vec::new(), PlayfairCipher::new("PLAYFAIREXAMPLE"))
+//!     .with_padding(Padding::Fill('X'))
+//!     .unwrap();
+//! w.write_all(b"HIDETHE").unwrap();
+//! w.write_all(b"GOLD").unwrap();
+//! let ct = w.finish().unwrap();
+//! assert_eq!(ct, b"BMODZBXDNAGE");
+//!
+//! let mut r = CipherReader::decrypt(&ct[..], PlayfairCipher::new("PLAYFAIREXAMPLE"))
+//!     .with_padding(Padding::Fill('X'))
+//!     .unwrap();
+//! let mut pt = String::new();
+//! r.read_to_string(&mut pt).unwrap();
+//! assert_eq!(pt, "HIDETHEGOLD");
+//! ```
+//!
+use crate::{Block, BlockExt, CipherError, Padded, Padding, StreamCipher};
+use std::io::{self, Read, Write};
+
+/// Number of characters in a chunk by default, rounded up to a whole number of blocks.
+const CHUNK: usize = 4096;
+
+/// Size of the reads from the wrapped reader.
+const READ_SIZE: usize = 8192;
+
+/// Wraps a cipher error for the `std::io` traits.
+///
+fn io_error(e: CipherError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e)
+}
+
+/// Returns the length in bytes of the first `n` characters of `buf`, if there is more
+/// after them.
+///
+fn split_chars(buf: &[u8], n: usize) -> Option<usize> {
+    buf.iter()
+        .enumerate()
+        .filter(|&(_, &b)| b & 0xc0 != 0x80)
+        .map(|(i, _)| i)
+        .nth(n)
+}
+
+/// Returns the least common multiple of `a` and `b`.
+///
+fn lcm(a: usize, b: usize) -> usize {
+    let (mut x, mut y) = (a, b);
+    while y != 0 {
+        (x, y) = (y, x % y);
+    }
+    a / x * b
+}
+
+/// Returns the length of `buf` without the character it may end in the middle of.
+///
+/// Invalid UTF-8 is kept, for the cipher to report it.
+///
+fn complete(buf: &[u8]) -> usize {
+    match std::str::from_utf8(buf) {
+        Err(e) if e.error_len().is_none() => e.valid_up_to(),
+        _ => buf.len(),
+    }
+}
+
+/// Cuts the text into chunks and runs them through the cipher.
+///
+struct Chunker<C> {
+    cipher: Padded<C>,
+    encrypt: bool,
+    chars: usize,
+}
+
+impl<C: Block> Chunker<C> {
+    fn new(cipher: C, encrypt: bool) -> Self {
+        // No padding never fails
+        let cipher = Padded::new(cipher, Padding::None).unwrap_or_else(|_| unreachable!());
+        Chunker { cipher, encrypt, chars: CHUNK }
+    }
+
+    fn set_padding(&mut self, padding: Padding) -> Result<(), CipherError> {
+        self.cipher.set_padding(padding)
+    }
+
+    /// Returns the number of characters in a chunk of plaintext, a whole number of
+    /// blocks and of rows of the padding.
+    ///
+    fn plain_chars(&self) -> usize {
+        let m = lcm(self.cipher.multiple(), self.cipher.cipher().block_size().max(1));
+        self.chars.div_ceil(m).max(1) * m
+    }
+
+    /// Returns the number of characters the cipher writes for a chunk, `None` if
+    /// chunks are ended by a newline.
+    ///
+    fn encrypted_chars(&self) -> Option<usize> {
+        self.cipher.cipher().encrypt_chars(self.plain_chars())
+    }
+
+    /// Returns the length of the next chunk of `buf` and of what it takes in `buf`
+    /// with its newline, if it is not the last one.
+    ///
+    fn next(&self, buf: &[u8]) -> Option<(usize, usize)> {
+        if self.cipher.cipher().whole_message() {
+            return None;
+        }
+        let chars = match (self.encrypt, self.encrypted_chars()) {
+            (true, _) => self.plain_chars(),
+            (false, Some(chars)) => chars,
+            (false, None) => return buf.iter().position(|&b| b == b'\n').map(|n| (n, n + 1)),
+        };
+        split_chars(buf, chars).map(|n| (n, n))
+    }
+
+    /// Encrypts a chunk which is not the last one, with its newline if needed.
+    ///
+    fn encrypt_chunk(&self, src: &[u8]) -> Result<Vec<u8>, CipherError> {
+        let mut out = self.cipher.cipher().encrypt_to_vec(src)?;
+        if self.encrypted_chars().is_none() {
+            out.push(b'\n');
+        }
+        Ok(out)
+    }
+
+    /// Runs a chunk through the cipher, the last one with its padding.
+    ///
+    /// Nulls add a whole block to a text filling its chunk: that block is encrypted
+    /// on its own, as the next chunk, for the decrypting side to find it in the last
+    /// one.
+    ///
+    fn run(&self, src: &[u8], last: bool) -> Result<Vec<u8>, CipherError> {
+        match (self.encrypt, last) {
+            (true, true) => {
+                let padded = self.cipher.pad(src)?;
+                match self.next(&padded) {
+                    Some((n, _)) => {
+                        let mut out = self.encrypt_chunk(&padded[..n])?;
+                        out.extend(self.cipher.cipher().encrypt_to_vec(&padded[n..])?);
+                        Ok(out)
+                    }
+                    None => self.cipher.encrypt_to_vec(src),
+                }
+            }
+            (true, false) => self.encrypt_chunk(src),
+            (false, true) => self.cipher.decrypt_to_vec(src),
+            (false, false) => self.cipher.cipher().decrypt_to_vec(src),
+        }
+    }
+}
+
+/// A writer running what it is given through a cipher, a chunk at a time.
+///
+/// The last chunk is only written by [`CipherWriter::finish`], or when the writer is
+/// dropped, in which case errors are ignored.
+///
+pub struct CipherWriter<W: Write, C: Block> {
+    inner: Option<W>,
+    chunker: Chunker<C>,
+    buf: Vec<u8>,
+}
+
+impl<W: Write, C: Block> CipherWriter<W, C> {
+    /// Creates a writer encrypting into `inner`.
+    ///
+    pub fn encrypt(inner: W, cipher: C) -> Self {
+        CipherWriter { inner: Some(inner), chunker: Chunker::new(cipher, true), buf: Vec::new() }
+    }
+
+    /// Creates a writer decrypting into `inner`.
+    ///
+    pub fn decrypt(inner: W, cipher: C) -> Self {
+        CipherWriter { inner: Some(inner), chunker: Chunker::new(cipher, false), buf: Vec::new() }
+    }
+
+    /// Sets the padding of the last chunk, none by default.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Padded::new`].
+    ///
+    pub fn with_padding(mut self, padding: Padding) -> Result<Self, CipherError> {
+        self.chunker.set_padding(padding)?;
+        Ok(self)
+    }
+
+    /// Sets the number of characters in a chunk, rounded up to a whole number of
+    /// blocks and of rows of a [`Padding::Rectangle`].
+    ///
+    pub fn with_chunk(mut self, chars: usize) -> Self {
+        self.chunker.chars = chars;
+        self
+    }
+
+    /// Returns the wrapped writer.
+    ///
+    pub fn get_ref(&self) -> Option<&W> {
+        self.inner.as_ref()
+    }
+
+    /// Writes the last chunk, with its padding, and returns the wrapped writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the cipher, as `InvalidData`, or of the wrapped writer.
+    ///
+    pub fn finish(mut self) -> io::Result<W> {
+        let res = self.write_last();
+        let inner = self.inner.take();
+        res.map(|_| inner.unwrap_or_else(|| unreachable!()))
+    }
+
+    fn write_last(&mut self) -> io::Result<()> {
+        let Some(inner) = self.inner.as_mut() else {
+            return Ok(());
+        };
+        let out = self.chunker.run(&std::mem::take(&mut self.buf), true).map_err(io_error)?;
+        inner.write_all(&out)?;
+        inner.flush()
+    }
+}
+
+impl<W: Write, C: Block> Write for CipherWriter<W, C> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        let Some(inner) = self.inner.as_mut() else {
+            return Err(io::Error::other("writer already finished"));
+        };
+        self.buf.extend_from_slice(data);
+        while let Some((n, taken)) = self.chunker.next(&self.buf) {
+            let out = self.chunker.run(&self.buf[..n], false).map_err(io_error)?;
+            inner.write_all(&out)?;
+            self.buf.drain(..taken);
+        }
+        Ok(data.len())
+    }
+
+    /// Flushes the wrapped writer.  The current chunk stays in the buffer: it can not
+    /// be written before it is complete.
+    ///
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.as_mut().map_or(Ok(()), Write::flush)
+    }
+}
+
+impl<W: Write, C: Block> Drop for CipherWriter<W, C> {
+    fn drop(&mut self) {
+        let _ = self.write_last();
+    }
+}
+
+/// A reader running what it reads through a cipher, a chunk at a time.
+///
+pub struct CipherReader<R: Read, C: Block> {
+    inner: R,
+    chunker: Chunker<C>,
+    buf: Vec<u8>,
+    out: Vec<u8>,
+    pos: usize,
+    eof: bool,
+    done: bool,
+}
+
+impl<R: Read, C: Block> CipherReader<R, C> {
+    /// Creates a reader encrypting what it reads from `inner`.
+    ///
+    pub fn encrypt(inner: R, cipher: C) -> Self {
+        Self::new(inner, Chunker::new(cipher, true))
+    }
+
+    /// Creates a reader decrypting what it reads from `inner`.
+    ///
+    pub fn decrypt(inner: R, cipher: C) -> Self {
+        Self::new(inner, Chunker::new(cipher, false))
+    }
+
+    fn new(inner: R, chunker: Chunker<C>) -> Self {
+        CipherReader { inner, chunker, buf: Vec::new(), out: Vec::new(), pos: 0, eof: false, done: false }
+    }
+
+    /// Sets the padding of the last chunk, none by default.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Padded::new`].
+    ///
+    pub fn with_padding(mut self, padding: Padding) -> Result<Self, CipherError> {
+        self.chunker.set_padding(padding)?;
+        Ok(self)
+    }
+
+    /// Sets the number of characters in a chunk, rounded up to a whole number of
+    /// blocks and of rows of a [`Padding::Rectangle`].
+    ///
+    pub fn with_chunk(mut self, chars: usize) -> Self {
+        self.chunker.chars = chars;
+        self
+    }
+
+    /// Returns the wrapped reader.
+    ///
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    /// Runs the next chunk through the cipher, returns `false` once the last one is
+    /// done.
+    ///
+    fn fill(&mut self) -> io::Result<bool> {
+        loop {
+            if let Some((n, taken)) = self.chunker.next(&self.buf) {
+                self.out = self.chunker.run(&self.buf[..n], false).map_err(io_error)?;
+                self.buf.drain(..taken);
+                break;
+            }
+            if self.eof {
+                if self.done {
+                    return Ok(false);
+                }
+                self.done = true;
+                self.out = self.chunker.run(&std::mem::take(&mut self.buf), true).map_err(io_error)?;
+                break;
+            }
+            let mut tmp = [0u8; READ_SIZE];
+            match self.inner.read(&mut tmp)? {
+                0 => self.eof = true,
+                n => self.buf.extend_from_slice(&tmp[..n]),
+            }
+        }
+        self.pos = 0;
+        Ok(true)
+    }
+}
+
+impl<R: Read, C: Block> Read for CipherReader<R, C> {
+    fn read(&mut self, dst: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.out.len() {
+            if !self.fill()? {
+                return Ok(0);
+            }
+        }
+        let n = dst.len().min(self.out.len() - self.pos);
+        dst[..n].copy_from_slice(&self.out[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+/// Runs `src` through a stream cipher from `state`.
+///
+fn run_stream<C: StreamCipher>(c: &C, state: &mut C::State, src: &[u8], encrypt: bool) -> Result<Vec<u8>, CipherError> {
+    let n = if encrypt { c.encrypt_len(src) } else { c.decrypt_len(src) };
+    let mut dst = vec![0u8; n];
+    let n = if encrypt {
+        c.encrypt_stream(state, &mut dst, src)?
+    } else {
+        c.decrypt_stream(state, &mut dst, src)?
+    };
+    dst.truncate(n);
+    Ok(dst)
+}
+
+/// A writer running what it is given through a stream cipher as it comes.
+///
+/// Only a character cut in the middle is kept for the next write.
+///
+pub struct StreamWriter<W: Write, C: StreamCipher> {
+    inner: W,
+    cipher: C,
+    state: C::State,
+    encrypt: bool,
+    buf: Vec<u8>,
+}
+
+impl<W: Write, C: StreamCipher> StreamWriter<W, C> {
+    /// Creates a writer encrypting into `inner`, from the initial state of `cipher`.
+    ///
+    pub fn encrypt(inner: W, cipher: C) -> Self {
+        let state = cipher.start();
+        StreamWriter { inner, cipher, state, encrypt: true, buf: Vec::new() }
+    }
+
+    /// Creates a writer decrypting into `inner`, from the initial state of `cipher`.
+    ///
+    pub fn decrypt(inner: W, cipher: C) -> Self {
+        let state = cipher.start();
+        StreamWriter { inner, cipher, state, encrypt: false, buf: Vec::new() }
+    }
+
+    /// Returns the current state of the cipher, e.g. to resume later.
+    ///
+    pub fn state(&self) -> &C::State {
+        &self.state
+    }
+
+    /// Returns the wrapped writer.
+    ///
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    /// Flushes and returns the wrapped writer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidData` if the text ends in the middle of a character, and the
+    /// errors of the wrapped writer.
+    ///
+    pub fn finish(mut self) -> io::Result<W> {
+        if !self.buf.is_empty() {
+            run_stream(&self.cipher, &mut self.state, &self.buf, self.encrypt).map_err(io_error)?;
+        }
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+}
+
+impl<W: Write, C: StreamCipher> Write for StreamWriter<W, C> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(data);
+        let n = complete(&self.buf);
+        let out = run_stream(&self.cipher, &mut self.state, &self.buf[..n], self.encrypt).map_err(io_error)?;
+        self.inner.write_all(&out)?;
+        self.buf.drain(..n);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A reader running what it reads through a stream cipher as it comes.
+///
+pub struct StreamReader<R: Read, C: StreamCipher> {
+    inner: R,
+    cipher: C,
+    state: C::State,
+    encrypt: bool,
+    buf: Vec<u8>,
+    out: Vec<u8>,
+    pos: usize,
+}
+
+impl<R: Read, C: StreamCipher> StreamReader<R, C> {
+    /// Creates a reader encrypting what it reads from `inner`.
+    ///
+    pub fn encrypt(inner: R, cipher: C) -> Self {
+        let state = cipher.start();
+        StreamReader { inner, cipher, state, encrypt: true, buf: Vec::new(), out: Vec::new(), pos: 0 }
+    }
+
+    /// Creates a reader decrypting what it reads from `inner`.
+    ///
+    pub fn decrypt(inner: R, cipher: C) -> Self {
+        let state = cipher.start();
+        StreamReader { inner, cipher, state, encrypt: false, buf: Vec::new(), out: Vec::new(), pos: 0 }
+    }
+
+    /// Returns the current state of the cipher.
+    ///
+    pub fn state(&self) -> &C::State {
+        &self.state
+    }
+
+    /// Returns the wrapped reader.
+    ///
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+}
+
+impl<R: Read, C: StreamCipher> Read for StreamReader<R, C> {
+    fn read(&mut self, dst: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.out.len() {
+            let mut tmp = [0u8; READ_SIZE];
+            let read = self.inner.read(&mut tmp)?;
+            self.buf.extend_from_slice(&tmp[..read]);
+            // At the end, what is left goes through, for the cipher to report it
+            let n = if read == 0 { self.buf.len() } else { complete(&self.buf) };
+            if read == 0 && n == 0 {
+                return Ok(0);
+            }
+            self.out = run_stream(&self.cipher, &mut self.state, &self.buf[..n], self.encrypt).map_err(io_error)?;
+            self.buf.drain(..n);
+            self.pos = 0;
+        }
+        let n = dst.len().min(self.out.len() - self.pos);
+        dst[..n].copy_from_slice(&self.out[self.pos..self.pos + n]);
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{registry, CaesarCipher, Chaocipher, PlayfairCipher, Transposition, VicMessage};
+
+    use rstest::rstest;
+    use std::io::Cursor;
+
+    const TEXT: &str = "ATTACKATDAWNRETREATATNOONHOLDTHEBRIDGE";
+
+    fn chaocipher() -> Chaocipher {
+        Chaocipher::new("PTLNBQDEOYSFAVZKGJRIHWXUMC", "HXUCZVAMDSLKPEFJRIGTWOBNYQ").unwrap()
+    }
+
+    fn write_all<W: Write>(mut w: W, text: &[u8], piece: usize) -> W {
+        for p in text.chunks(piece) {
+            w.write_all(p).unwrap();
+        }
+        w
+    }
+
+    #[rstest]
+    #[case(1, 1)]
+    #[case(3, 5)]
+    #[case(10, 4096)]
+    fn test_cipher_writer_caesar(#[case] chunk: usize, #[case] piece: usize) {
+        let c = CaesarCipher::new(3);
+        let w = write_all(CipherWriter::encrypt(Vec::new(), CaesarCipher::new(3)).with_chunk(chunk), TEXT.as_bytes(), piece);
+        assert_eq!(String::from_utf8(w.finish().unwrap()).unwrap(), c.encrypt_str(TEXT).unwrap());
+    }
+
+    #[test]
+    fn test_cipher_writer_utf8() {
+        let c = CaesarCipher::with_alphabet(3, &crate::Alphabet::new("АБВГДЕЖЗИЙКЛМНОПРСТУФХЦЧШЩЪЫЬЭЮЯ").unwrap()).unwrap();
+        let ct = c.encrypt_str("ПРИВЕТМИР").unwrap();
+        let w = write_all(CipherWriter::encrypt(Vec::new(), c).with_chunk(2), "ПРИВЕТМИР".as_bytes(), 1);
+        assert_eq!(String::from_utf8(w.finish().unwrap()).unwrap(), ct);
+    }
+
+    #[rstest]
+    #[case(Padding::Fill('X'), 2)]
+    #[case(Padding::Fill('X'), 7)]
+    #[case(Padding::Nulls, 1000)]
+    #[case(Padding::Rectangle { width: 5, fill: 'Q' }, 1)]
+    #[case(Padding::Rectangle { width: 5, fill: 'Q' }, 5)]
+    #[case(Padding::Rectangle { width: 5, fill: 'Q' }, 12)]
+    fn test_cipher_round_trip(#[case] padding: Padding, #[case] chunk: usize) {
+        let text = format!("{}A", TEXT);
+        let w = CipherWriter::encrypt(Vec::new(), PlayfairCipher::new("PLAYFAIREXAMPLE")).with_padding(padding).unwrap();
+        let ct = write_all(w.with_chunk(chunk), text.as_bytes(), 3).finish().unwrap();
+        assert_eq!(ct.len() % 2, 0);
+
+        let mut r = CipherReader::decrypt(Cursor::new(ct), PlayfairCipher::new("PLAYFAIREXAMPLE"))
+            .with_padding(padding)
+            .unwrap()
+            .with_chunk(chunk);
+        let mut pt = String::new();
+        r.read_to_string(&mut pt).unwrap();
+        assert_eq!(pt, text);
+    }
+
+    #[test]
+    fn test_cipher_reader_transposition() {
+        let text = TEXT.repeat(300);
+        let mut r = CipherReader::encrypt(text.as_bytes(), Transposition::new("SUBWAY").unwrap())
+            .with_padding(Padding::Nulls)
+            .unwrap();
+        let mut ct = Vec::new();
+        io::copy(&mut r, &mut ct).unwrap();
+        assert_eq!(ct.len() % 6, 0);
+
+        let mut w = CipherWriter::decrypt(Vec::new(), Transposition::new("SUBWAY").unwrap())
+            .with_padding(Padding::Nulls)
+            .unwrap();
+        io::copy(&mut &ct[..], &mut w).unwrap();
+        assert_eq!(w.finish().unwrap(), text.as_bytes());
+
+        // A last chunk filled by the text gets its nulls in a chunk of their own
+        let mut r = CipherReader::encrypt(&b"ATTACKATDAWN"[..], Transposition::new("SUBWAY").unwrap())
+            .with_padding(Padding::Nulls)
+            .unwrap()
+            .with_chunk(6);
+        let mut ct = Vec::new();
+        io::copy(&mut r, &mut ct).unwrap();
+        assert_eq!(ct.len(), 18);
+
+        let mut r = CipherReader::decrypt(&ct[..], Transposition::new("SUBWAY").unwrap())
+            .with_padding(Padding::Nulls)
+            .unwrap()
+            .with_chunk(6);
+        let mut pt = String::new();
+        r.read_to_string(&mut pt).unwrap();
+        assert_eq!(pt, "ATTACKATDAWN");
+    }
+
+    #[rstest]
+    #[case("square:key=PORTABLE,chrs=ADFGVX", "ATTACKATDAWN")]
+    #[case("adfgvx:key1=PORTABLE,key2=SUBWAY", "ATTACKATDAWN")]
+    #[case("straddling:key=ARABESQUE,chrs=89", "BACKATTWO")]
+    #[case("nihilist:key1=ARABESQUE,key2=SUBWAY,chrs=37", "BACKATTWO")]
+    #[case("vic:persn=89,ind=741776,phrase=IDREAMOFJEANNIEWITHT,imsg=77651", "BACKATTWO")]
+    fn test_cipher_round_trip_length(#[case] spec: &str, #[case] text: &str, #[values(Padding::None, Padding::Nulls)] padding: Padding) {
+        let text = text.repeat(999);
+        let ct = write_all(
+            CipherWriter::encrypt(Vec::new(), registry::from_spec(spec).unwrap()).with_padding(padding).unwrap(),
+            text.as_bytes(),
+            1000,
+        )
+        .finish()
+        .unwrap();
+
+        let mut r = CipherReader::decrypt(Cursor::new(ct), registry::from_spec(spec).unwrap())
+            .with_padding(padding)
+            .unwrap();
+        let mut pt = String::new();
+        r.read_to_string(&mut pt).unwrap();
+        assert_eq!(pt, text);
+    }
+
+    #[test]
+    fn test_cipher_vic_message() {
+        // The nulls and keygroup go in once, the text being a single message
+        let m = || VicMessage::new(6, "391945", "IDREAMOFJEANNIEWITHT", "77651").unwrap();
+        let text = TEXT.repeat(50);
+        let ct = write_all(CipherWriter::encrypt(Vec::new(), m()).with_chunk(10), text.as_bytes(), 7).finish().unwrap();
+        assert_eq!(ct, m().encrypt_to_vec(text.as_bytes()).unwrap());
+
+        let mut r = CipherReader::decrypt(Cursor::new(ct), m()).with_chunk(10);
+        let mut pt = String::new();
+        r.read_to_string(&mut pt).unwrap();
+        assert!(pt.starts_with(&text) && pt[text.len()..].chars().all(|c| c == 'A'), "{}", pt);
+    }
+
+    #[test]
+    fn test_cipher_writer_drop() {
+        let mut out = Vec::new();
+        {
+            let mut w = CipherWriter::encrypt(&mut out, CaesarCipher::new(3));
+            w.write_all(b"HELLO").unwrap();
+        }
+        assert_eq!(out, b"KHOOR");
+    }
+
+    #[test]
+    fn test_cipher_errors() {
+        // Positions are in the chunk
+        let mut w = CipherWriter::encrypt(Vec::new(), PlayfairCipher::new("PLAYFAIREXAMPLE")).with_chunk(2);
+        let e = w.write_all(b"ABCD-FGH").unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(e.into_inner().unwrap().downcast::<CipherError>().unwrap().as_ref(), &CipherError::InvalidChar { ch: '-', pos: 0 });
+
+        let e = CipherWriter::encrypt(Vec::new(), PlayfairCipher::new("PLAYFAIREXAMPLE"))
+            .with_padding(Padding::Fill('X'))
+            .unwrap();
+        let e = write_all(e, b"HELLX", 5).finish().unwrap_err();
+        assert_eq!(e.to_string(), "text ends with the fill letter 'X'");
+
+        assert!(CipherReader::decrypt(&b""[..], CaesarCipher::new(3))
+            .with_padding(Padding::Rectangle { width: 0, fill: 'X' })
+            .is_err());
+    }
+
+    #[rstest]
+    #[case(1)]
+    #[case(3)]
+    #[case(100)]
+    fn test_stream_writer(#[case] piece: usize) {
+        let c = chaocipher();
+        let w = write_all(StreamWriter::encrypt(Vec::new(), chaocipher()), TEXT.as_bytes(), piece);
+        assert_eq!(w.state().to_string(), {
+            let mut s = c.start();
+            c.encrypt_stream(&mut s, &mut [0u8; TEXT.len()], TEXT.as_bytes()).unwrap();
+            s.to_string()
+        });
+        let ct = w.finish().unwrap();
+        assert_eq!(String::from_utf8(ct.clone()).unwrap(), c.encrypt_str(TEXT).unwrap());
+
+        let mut r = StreamReader::decrypt(Cursor::new(ct), chaocipher());
+        let mut pt = String::new();
+        r.read_to_string(&mut pt).unwrap();
+        assert_eq!(pt, TEXT);
+    }
+
+    #[test]
+    fn test_stream_errors() {
+        let w = write_all(StreamWriter::encrypt(Vec::new(), chaocipher()), &"ABÉ".as_bytes()[..3], 1);
+        assert_eq!(w.finish().unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        let mut r = StreamReader::encrypt(&b"AB C"[..], chaocipher());
+        assert_eq!(r.read_to_end(&mut Vec::new()).unwrap_err().kind(), io::ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,407 @@
+//! Key files.
+//!
+//! Key material (Chaocipher alphabets, VIC phrase and indicators, SIGABA rotor
+//! settings, a Solitaire deck…) can be saved in a plain-text file instead of being
+//! typed again every session.  The format is a small subset of TOML, one section per
+//! cipher:
+//!
+//! ```text
+//! # Key of the day
+//! [chaocipher]
+//! pkey = PTLNBQDEOYSFAVZKGJRIHWXUMC
+//! ckey = HXUCZVAMDSLKPEFJRIGTWOBNYQ
+//! ```
+//!
+//! - `[name]` starts the section of the cipher called `name` in the
+//!   [registry](crate::registry); several sections build a [`Chain`] of the ciphers,
+//!   in order.
+//! - `param = value` gives a parameter of the current section, named and written as
+//!   for [`registry::build`]: a Caesar shift is `key = 3`, SIGABA rotors are
+//!   `cipher = 01234`, `cipher_pos = AAAAA`, `cipher_rev = 00000` and so on for the
+//!   control and index banks, a Solitaire deck is the 54 card numbers separated by
+//!   spaces.
+//! - Values are trimmed.  A value between double quotes keeps its spaces, with `\"`,
+//!   `\\` and `\n` standing for a quote, a backslash and a newline.
+//! - Empty lines and lines starting with `#` are ignored.
+//!
+//! Errors found while loading a file are reported with the line they come from, as
+//! `CipherError::AtLine`.
+//!
+//! # Example
+//!
+//! ```
+//! use old_crypto_rs::{keyfile, BlockExt, CipherError};
+//! use old_crypto_rs::keyfile::KeyFile;
+//!
+//! let text = "\
+//! [square]
+//! key = PORTABLE
+//! chrs = ADFGVX
+//!
+//! [transposition]
+//! key = SUBWAY
+//! ";
+//! let c = keyfile::load(text).unwrap();
+//! assert_eq!(c.encrypt_str("ATTACKATDAWN").unwrap(), "AFDFADAGAAAAVVVVGFGVGGGX");
+//!
+//! // Writing the key file back gives the same text
+//! assert_eq!(text.parse::<KeyFile>().unwrap().to_string(), text);
+//!
+//! assert_eq!(
+//!     keyfile::load("[adfgvx]\nkey1 = PORTABLE\nkey2 =\n").err(),
+//!     Some(CipherError::AtLine { line: 3, error: Box::new(CipherError::EmptyKey("key2")) })
+//! );
+//! ```
+//!
+use crate::registry::{self, Params};
+use crate::{Block, Chain, CipherError};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// One cipher of a key file.
+///
+#[derive(Clone, Debug)]
+struct Section {
+    name: String,
+    params: Params,
+    /// Line of the `[name]` header, 0 if the section was not read from a file.
+    line: usize,
+    /// Line of each parameter.
+    lines: BTreeMap<String, usize>,
+}
+
+impl Section {
+    /// Tags `e` with the line of the parameter it names, or the one of the header.
+    ///
+    fn at_line(&self, e: CipherError) -> CipherError {
+        let param = match &e {
+            CipherError::UnknownParameter(p) => p.as_str(),
+            e => e.param(),
+        };
+        match self.lines.get(param).copied().unwrap_or(self.line) {
+            0 => e,
+            line => CipherError::AtLine { line, error: Box::new(e) },
+        }
+    }
+}
+
+/// The content of a key file: the ciphers to chain and their parameters.
+///
+#[derive(Clone, Debug, Default)]
+pub struct KeyFile {
+    sections: Vec<Section>,
+}
+
+impl KeyFile {
+    /// Creates an empty key file.
+    ///
+    pub fn new() -> Self {
+        KeyFile { sections: Vec::new() }
+    }
+
+    /// Creates the key file of a registry spec string, one section per cipher.
+    ///
+    /// # Errors
+    ///
+    /// See [`registry::parse_spec`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use old_crypto_rs::keyfile::KeyFile;
+    ///
+    /// let kf = KeyFile::from_spec("vic:persn=89,ind=741776,phrase=IDREAMOFJEANNIEWITHT,imsg=77651").unwrap();
+    /// assert_eq!(
+    ///     kf.to_string(),
+    ///     "[vic]\npersn = 89\nind = 741776\nphrase = IDREAMOFJEANNIEWITHT\nimsg = 77651\n"
+    /// );
+    /// ```
+    ///
+    pub fn from_spec(spec: &str) -> Result<Self, CipherError> {
+        let mut kf = Self::new();
+        for part in spec.split('+') {
+            let (name, params) = registry::parse_spec(part)?;
+            kf.push(&name, params);
+        }
+        Ok(kf)
+    }
+
+    /// Adds the cipher called `name` as the last section.
+    ///
+    pub fn push(&mut self, name: &str, params: Params) {
+        self.sections.push(Section { name: name.to_string(), params, line: 0, lines: BTreeMap::new() });
+    }
+
+    /// Returns the name and parameters of each section, in order.
+    ///
+    pub fn sections(&self) -> impl Iterator<Item = (&str, &Params)> {
+        self.sections.iter().map(|s| (s.name.as_str(), &s.params))
+    }
+
+    /// Returns the number of sections.
+    ///
+    pub fn len(&self) -> usize {
+        self.sections.len()
+    }
+
+    /// Returns `true` if there is no section.
+    ///
+    pub fn is_empty(&self) -> bool {
+        self.sections.is_empty()
+    }
+
+    /// Reads a key file.
+    ///
+    /// Parameters are only checked by [`KeyFile::build`], cipher names are checked
+    /// here.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::AtLine` with `BadSpec` for a line which is neither a
+    /// header nor a `param = value` pair (or a pair before the first header, or a
+    /// parameter given twice), and with `UnknownCipher` for a header naming no cipher.
+    ///
+    pub fn parse(text: &str) -> Result<Self, CipherError> {
+        let mut kf = Self::new();
+        for (i, raw) in text.lines().enumerate() {
+            let line = i + 1;
+            let at = |error| CipherError::AtLine { line, error: Box::new(error) };
+            let trimmed = raw.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            if let Some(header) = trimmed.strip_prefix('[') {
+                let name = header.strip_suffix(']').ok_or_else(|| at(CipherError::BadSpec(trimmed.to_string())))?;
+                let entry = registry::find(name.trim()).ok_or_else(|| at(CipherError::UnknownCipher(name.trim().to_string())))?;
+                kf.sections.push(Section { name: entry.name.to_string(), params: Params::new(), line, lines: BTreeMap::new() });
+                continue;
+            }
+
+            let bad = || at(CipherError::BadSpec(trimmed.to_string()));
+            let (k, v) = trimmed.split_once('=').ok_or_else(bad)?;
+            let (k, value) = (k.trim(), unquote(v.trim()).ok_or_else(bad)?);
+            let section = kf.sections.last_mut().ok_or_else(bad)?;
+            if k.is_empty() || section.params.insert(k.to_string(), value).is_some() {
+                return Err(bad());
+            }
+            section.lines.insert(k.to_string(), line);
+        }
+        Ok(kf)
+    }
+
+    /// Builds the cipher of the key file, a [`Chain`] if there are several sections.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UnknownCipher` with an empty name if there is no section, otherwise
+    /// the first error of [`registry::build`], tagged with the line of the parameter
+    /// (or of the section header) if the key file was read with [`KeyFile::parse`].
+    ///
+    pub fn build(&self) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+        let mut ciphers = Vec::with_capacity(self.sections.len());
+        for s in &self.sections {
+            ciphers.push(registry::build(&s.name, &s.params).map_err(|e| s.at_line(e))?);
+        }
+        match ciphers.len() {
+            0 => Err(CipherError::UnknownCipher(String::new())),
+            1 => Ok(ciphers.remove(0)),
+            _ => Ok(Box::new(Chain::from(ciphers))),
+        }
+    }
+}
+
+impl FromStr for KeyFile {
+    type Err = CipherError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+/// Writes the key file, sections separated by an empty line and parameters in the
+/// order the registry lists them.
+///
+impl fmt::Display for KeyFile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, s) in self.sections.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            writeln!(f, "[{}]", s.name)?;
+            let order = registry::find(&s.name).map_or(&[][..], |e| e.params);
+            let mut params: Vec<_> = s.params.iter().collect();
+            params.sort_by_key(|(k, _)| order.iter().position(|p| p.name == *k).unwrap_or(order.len()));
+            for (k, v) in params {
+                writeln!(f, "{} = {}", k, quote(v))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Reads a key file and builds its cipher.
+///
+/// # Errors
+///
+/// See [`KeyFile::parse`] and [`KeyFile::build`].
+///
+pub fn load(text: &str) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+    KeyFile::parse(text)?.build()
+}
+
+/// Returns `value` as written in a key file, quoted if trimming would change it.
+///
+fn quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value.trim() == value
+        && !value.starts_with('"')
+        && !value.contains('\n');
+    if plain {
+        return value.to_string();
+    }
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Returns the value of a (trimmed) `value` field, `None` for a bad quoted string.
+///
+fn unquote(value: &str) -> Option<String> {
+    let Some(inner) = value.strip_prefix('"') else {
+        return Some(value.to_string());
+    };
+    let mut out = String::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => return chars.as_str().is_empty().then_some(out),
+            '\\' => out.push(match chars.next()? {
+                '"' => '"',
+                '\\' => '\\',
+                'n' => '\n',
+                _ => return None,
+            }),
+            c => out.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockExt, Solitaire};
+
+    use rstest::rstest;
+
+    #[test]
+    fn test_keyfile_parse() {
+        let text = "# comment\n\n[Chaocipher]\n  pkey = PTLNBQDEOYSFAVZKGJRIHWXUMC \nckey=HXUCZVAMDSLKPEFJRIGTWOBNYQ\n";
+        let kf = KeyFile::parse(text).unwrap();
+        assert_eq!(kf.len(), 1);
+        let (name, params) = kf.sections().next().unwrap();
+        assert_eq!(name, "chaocipher");
+        assert_eq!(params["pkey"], "PTLNBQDEOYSFAVZKGJRIHWXUMC");
+        assert_eq!(kf.build().unwrap().encrypt_str("WELLDONE").unwrap(), "OAHQHCNY");
+    }
+
+    #[rstest]
+    #[case("key = 3\n", 1, CipherError::BadSpec("key = 3".to_string()))]
+    #[case("[caesar\nkey = 3\n", 1, CipherError::BadSpec("[caesar".to_string()))]
+    #[case("# Enigma\n[enigma]\n", 2, CipherError::UnknownCipher("enigma".to_string()))]
+    #[case("[caesar]\nkey 3\n", 2, CipherError::BadSpec("key 3".to_string()))]
+    #[case("[caesar]\nkey = 3\nkey = 4\n", 3, CipherError::BadSpec("key = 4".to_string()))]
+    #[case("[caesar]\nkey = \"3\n", 2, CipherError::BadSpec("key = \"3".to_string()))]
+    #[case("[caesar]\nkey = \"3\" 4\n", 2, CipherError::BadSpec("key = \"3\" 4".to_string()))]
+    fn test_keyfile_parse_errors(#[case] text: &str, #[case] line: usize, #[case] err: CipherError) {
+        assert_eq!(KeyFile::parse(text).err(), Some(CipherError::AtLine { line, error: Box::new(err) }));
+    }
+
+    #[rstest]
+    #[case("[caesar]\n\n# shift\nkey = three\n", 4, CipherError::InvalidParameter { param: "key", value: "three".to_string() })]
+    #[case("[caesar]\nkey = 3\nshift = 4\n", 3, CipherError::UnknownParameter("shift".to_string()))]
+    #[case("[null]\n[adfgvx]\nkey1 = PORTABLE\n", 2, CipherError::MissingParameter("key2"))]
+    #[case("[nihilist]\nkey1 = ARABESQUE\nkey2 = SUBWAY\nchrs = 3\n", 4, CipherError::KeyTooShort { param: "chrs", min: 2, got: 1 })]
+    fn test_keyfile_build_errors(#[case] text: &str, #[case] line: usize, #[case] err: CipherError) {
+        assert_eq!(load(text).err(), Some(CipherError::AtLine { line, error: Box::new(err) }));
+    }
+
+    #[test]
+    fn test_keyfile_empty() {
+        assert!(KeyFile::parse("# nothing\n").unwrap().is_empty());
+        assert_eq!(load("").err(), Some(CipherError::UnknownCipher(String::new())));
+        let mut kf = KeyFile::new();
+        kf.push("caesar", Params::new());
+        assert_eq!(kf.build().err(), Some(CipherError::MissingParameter("key")));
+    }
+
+    #[rstest]
+    #[case("plain", "plain")]
+    #[case("", "\"\"")]
+    #[case(" ARABESQUE", "\" ARABESQUE\"")]
+    #[case("\"quoted\"", "\"\\\"quoted\\\"\"")]
+    #[case("A\\B\nC", "\"A\\\\B\\nC\"")]
+    fn test_keyfile_quote(#[case] value: &str, #[case] written: &str) {
+        assert_eq!(quote(value), written);
+        assert_eq!(unquote(written).as_deref(), Some(value));
+    }
+
+    #[test]
+    fn test_keyfile_roundtrip() {
+        let deck: Vec<String> = Solitaire::new_with_passphrase("CRYPTONOMICON")
+            .deck()
+            .iter()
+            .map(u8::to_string)
+            .collect();
+        let mut kf = KeyFile::from_spec("playfair:key=PLAYFAIREXAMPLE+irregular:key=SUBWAY").unwrap();
+        kf.push("solitaire", Params::from([("deck".to_string(), deck.join(" "))]));
+        kf.push("wheatstone", Params::from([
+            ("start".to_string(), "M".to_string()),
+            ("pkey".to_string(), " CIPHER ".to_string()),
+            ("ckey".to_string(), "MACHINE".to_string()),
+        ]));
+
+        let text = kf.to_string();
+        let read = KeyFile::parse(&text).unwrap();
+        assert_eq!(read.to_string(), text);
+        assert!(read.sections().eq(kf.sections()));
+        assert!(text.contains("pkey = \" CIPHER \"\n"));
+    }
+
+    #[test]
+    fn test_keyfile_chain() {
+        let c = load("[playfair]\nkey = PLAYFAIREXAMPLE\n\n[caesar]\nkey = 3\n").unwrap();
+        let ct = c.encrypt_str("HIDETHEGOLD").unwrap();
+        assert_eq!(c.decrypt_str(&ct).unwrap(), "HIDETHEGOLDX");
+    }
+
+    #[cfg(feature = "sigaba")]
+    #[test]
+    fn test_keyfile_sigaba() {
+        let text = "[sigaba]\ncipher = 01234\ncipher_pos = ABCDE\ncipher_rev = 01000\n\
+                    control = 56789\ncontrol_pos = AAAAA\ncontrol_rev = 00000\n\
+                    index = 01234\nindex_pos = AAAAA\nindex_rev = 00001\n";
+        let kf = KeyFile::parse(text).unwrap();
+        let c = kf.build().unwrap();
+        let ct = c.encrypt_str("HELLOWORLD").unwrap();
+        assert_eq!(c.decrypt_str(&ct).unwrap(), "HELLOWORLD");
+        assert_eq!(
+            load(&text.replace("index = 01234", "index = 01239")).err(),
+            Some(CipherError::AtLine {
+                line: 8,
+                error: Box::new(CipherError::InvalidParameter { param: "index", value: "01239".to_string() }),
+            })
+        );
+    }
+}
@@ -0,0 +1,287 @@
+//! Keeping the layout of a text through encryption.
+//!
+//! Ciphers work on the bare symbols of their alphabet: most reject lowercase letters,
+//! spaces and punctuation, and the [`Normalizer`] of a cipher removes them.  For a
+//! letter or a classroom handout it is nicer to keep them.  [`KeepLayout`] wraps any
+//! cipher: only the symbols the cipher handles go through it, everything else stays
+//! in place, and each letter keeps its case.  Decryption works the same way on the
+//! ciphertext.
+//!
+//! The letters go through the normalizer of the cipher one at a time, so accents are
+//! removed and letter substitutions (Playfair `J` → `I`) applied: those are not
+//! restored.  Neither is the case of a letter whose place is taken by a digit in the
+//! ciphertext, which has no case.  A cipher writing more symbols than it reads
+//! (Playfair padding) has the extra ones appended at the end, one writing fewer leaves
+//! the last letters out.
+//!
+//! # Example
+//!
+//! ```
+//! use old_crypto_rs::{BlockExt, Chaocipher, KeepLayout};
+//!
+//! let c = KeepLayout::new(
+//!     Chaocipher::new("PTLNBQDEOYSFAVZKGJRIHWXUMC", "HXUCZVAMDSLKPEFJRIGTWOBNYQ").unwrap(),
+//! );
+//! let ct = c.encrypt_str("Well done is better than well said.").unwrap();
+//! assert_eq!(ct, "Oahq hcny nx tszjrr hjby hqks oujy.");
+//! assert_eq!(c.decrypt_str(&ct).unwrap(), "Well done is better than well said.");
+//! ```
+//!
+use crate::{Alphabet, Block, CipherError, Normalizer, Tracer};
+use crate::error::check_dst;
+use crate::trace::{self, NoTrace};
+use crate::utf8;
+
+/// A cipher keeping the case, spacing and punctuation of the text.
+///
+#[derive(Debug)]
+pub struct KeepLayout<C> {
+    cipher: C,
+}
+
+impl<C: Block> KeepLayout<C> {
+    /// Wraps `cipher`.
+    ///
+    pub fn new(cipher: C) -> Self {
+        KeepLayout { cipher }
+    }
+
+    /// Returns the wrapped cipher.
+    ///
+    pub fn cipher(&self) -> &C {
+        &self.cipher
+    }
+
+    /// Returns the symbols of `text` going through the cipher, in the form it
+    /// expects, with the offset of each of them in `text` and whether it was
+    /// uppercased on the way.
+    ///
+    fn extract(&self, text: &str) -> (String, Vec<(usize, bool)>) {
+        let n = self.cipher.normalizer();
+        let mut symbols = String::new();
+        let mut slots = Vec::new();
+        for (i, ch) in text.char_indices() {
+            if let Some(s) = symbol(&n, ch) {
+                symbols.push(s);
+                slots.push((i, ch.is_lowercase() && !s.is_lowercase()));
+            }
+        }
+        (symbols, slots)
+    }
+
+    /// Runs the cipher over the symbols of `src` and puts its output back in place.
+    ///
+    fn run(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer, encrypt: bool) -> Result<usize, CipherError> {
+        let text = utf8::decode(src)?;
+        let (symbols, slots) = self.extract(text);
+        let res = if encrypt {
+            let mut buf = vec![0u8; self.cipher.encrypt_len(symbols.as_bytes())];
+            self.cipher.try_encrypt_traced(&mut buf, symbols.as_bytes(), tracer).map(|n| (buf, n))
+        } else {
+            let mut buf = vec![0u8; self.cipher.decrypt_len(symbols.as_bytes())];
+            self.cipher.try_decrypt_traced(&mut buf, symbols.as_bytes(), tracer).map(|n| (buf, n))
+        };
+        let (buf, n) = res.map_err(|e| in_text(e, &symbols, &slots))?;
+
+        let out = insert(text, &slots, &String::from_utf8_lossy(&buf[..n]));
+        let res = check_dst(dst, out.len()).map(|_| {
+            dst[..out.len()].copy_from_slice(out.as_bytes());
+            out.len()
+        });
+        trace::report(tracer, "KeepLayout", src, dst, res)
+    }
+}
+
+/// Returns the form of `ch` in the cipher, if it is a letter or a digit the cipher
+/// handles.
+///
+fn symbol(n: &Normalizer, ch: char) -> Option<char> {
+    let mut s = n.normalize(ch.encode_utf8(&mut [0; 4]));
+    let c = s.pop()?;
+    (s.is_empty() && c.is_alphanumeric()).then_some(c)
+}
+
+/// Replaces the characters of `text` at `slots` by the ones of `out`, lowercased where
+/// the cipher got uppercased letters.  Slots left over are removed, characters left
+/// over appended.
+///
+/// Ciphers keeping the case move it with the letters instead.
+///
+fn insert(text: &str, slots: &[(usize, bool)], out: &str) -> String {
+    let mut res = String::with_capacity(text.len() + out.len());
+    let mut out = out.chars();
+    let mut last = 0;
+    for &(i, lower) in slots {
+        res.push_str(&text[last..i]);
+        let orig = text[i..].chars().next().unwrap_or_default();
+        match out.next() {
+            Some(c) if lower => res.extend(c.to_lowercase()),
+            Some(c) => res.push(c),
+            None => {}
+        }
+        last = i + orig.len_utf8();
+    }
+    res.push_str(&text[last..]);
+    res.extend(out);
+    res
+}
+
+/// Moves the position of an error from the symbols given to the cipher to the text.
+///
+fn in_text(e: CipherError, symbols: &str, slots: &[(usize, bool)]) -> CipherError {
+    let at = |pos: usize| symbols.get(..pos).and_then(|s| slots.get(s.chars().count())).map_or(pos, |&(i, _)| i);
+    match e {
+        CipherError::InvalidChar { ch, pos } => CipherError::InvalidChar { ch, pos: at(pos) },
+        CipherError::UnknownBigram { bigram, pos } => CipherError::UnknownBigram { bigram, pos: at(pos) },
+        e => e,
+    }
+}
+
+impl<C: Block> Block for KeepLayout<C> {
+    fn block_size(&self) -> usize {
+        self.cipher.block_size()
+    }
+
+    fn alphabet(&self) -> Option<Alphabet> {
+        self.cipher.alphabet()
+    }
+
+    /// The text is taken as it is.
+    ///
+    fn normalizer(&self) -> Normalizer {
+        Normalizer::new()
+    }
+
+    /// The text around the symbols plus what the cipher writes for them.  Changing the
+    /// case may change the width of a character, so this is doubled.
+    ///
+    fn encrypt_len(&self, src: &[u8]) -> usize {
+        let (symbols, _) = self.extract(&String::from_utf8_lossy(src));
+        src.len() + 2 * self.cipher.encrypt_len(symbols.as_bytes())
+    }
+
+    /// Same as `encrypt_len`, with the decryption of the cipher.
+    ///
+    fn decrypt_len(&self, src: &[u8]) -> usize {
+        let (symbols, _) = self.extract(&String::from_utf8_lossy(src));
+        src.len() + 2 * self.cipher.decrypt_len(symbols.as_bytes())
+    }
+
+    fn max_encrypt_len(&self, len: usize) -> usize {
+        len + 2 * self.cipher.max_encrypt_len(len)
+    }
+
+    fn max_decrypt_len(&self, len: usize) -> usize {
+        len + 2 * self.cipher.max_decrypt_len(len)
+    }
+
+    /// Only known if the cipher keeps the length, the symbols being mixed with the
+    /// rest.
+    ///
+    fn encrypt_chars(&self, chars: usize) -> Option<usize> {
+        self.cipher.encrypt_chars(chars).filter(|&n| n == chars)
+    }
+
+    fn whole_message(&self) -> bool {
+        self.cipher.whole_message()
+    }
+
+    /// Encrypts the letters and digits of `src` handled by the cipher, leaving the
+    /// rest in place.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidUtf8` if `src` is not UTF-8, and any error of the cipher, with
+    /// positions in `src`.
+    ///
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.run(dst, src, &mut NoTrace, true)
+    }
+
+    /// Decrypts the letters and digits of `src` handled by the cipher, leaving the
+    /// rest in place.
+    ///
+    /// # Errors
+    ///
+    /// Same as `try_encrypt`.
+    ///
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.run(dst, src, &mut NoTrace, false)
+    }
+
+    /// Reports the stages of the cipher on the bare symbols, then the text with the
+    /// layout put back.
+    ///
+    fn try_encrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        self.run(dst, src, tracer, true)
+    }
+
+    fn try_decrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        self.run(dst, src, tracer, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockExt, CaesarCipher, Padded, Padding, PlayfairCipher, SquareCipher, Trace, Transposition};
+
+    use rstest::rstest;
+
+    #[rstest]
+    #[case("Attack at dawn!", "Dwwdfn dw gdzq!")]
+    #[case("Rendez-vous à 10h, OK?", "Uhqghc-yrxv d 10k, RN?")]
+    #[case("...", "...")]
+    #[case("", "")]
+    fn test_keep_layout_caesar(#[case] pt: &str, #[case] ct: &str) {
+        let c = KeepLayout::new(CaesarCipher::new(3));
+        assert_eq!(c.encrypt_str(pt).unwrap(), ct);
+        assert_eq!(c.decrypt_str(ct).unwrap(), pt.replace('à', "a"));
+    }
+
+    #[test]
+    fn test_keep_layout_transposition() {
+        let c = KeepLayout::new(Transposition::new("SUBWAY").unwrap());
+        let ct = c.encrypt_str("Attack at dawn, now!").unwrap();
+        assert_eq!(ct, "Cwtdwa an ttoa, akn!");
+        assert_eq!(c.decrypt_str(&ct).unwrap(), "Attack at dawn, now!");
+    }
+
+    #[test]
+    fn test_keep_layout_playfair() {
+        let c = KeepLayout::new(Padded::new(PlayfairCipher::new("PLAYFAIREXAMPLE"), Padding::Fill('X')).unwrap());
+        let ct = c.encrypt_str("Hide the gold!").unwrap();
+        assert_eq!(ct, "Bmod zbx dnag!E");
+        assert_eq!(c.decrypt_str(&ct).unwrap(), "Hide the gold!");
+
+        // Without padding, the filler comes back at the end
+        let c = KeepLayout::new(PlayfairCipher::new("PLAYFAIREXAMPLE"));
+        assert_eq!(c.decrypt_str(&c.encrypt_str("Hide the gold!").unwrap()).unwrap(), "Hide the gold!X");
+    }
+
+    #[test]
+    fn test_keep_layout_expanding() {
+        // Each letter becomes two: the extra ones go at the end
+        let c = KeepLayout::new(SquareCipher::new("PORTABLE", "ADFGVX").unwrap());
+        let ct = c.encrypt_str("At dawn").unwrap();
+        assert_eq!(ct, "Av agdgAVGVFX");
+    }
+
+    #[test]
+    fn test_keep_layout_errors() {
+        let c = KeepLayout::new(PlayfairCipher::with_alphabet("BAD", &crate::Alphabet::new("ABCD").unwrap()).unwrap());
+        assert_eq!(c.encrypt_str("ab, cE"), Err(CipherError::InvalidChar { ch: 'E', pos: 5 }));
+        assert_eq!(c.try_encrypt(&mut [0u8; 2], b"ab, cd"), Err(CipherError::BufferTooSmall { needed: 6, got: 2 }));
+    }
+
+    #[test]
+    fn test_keep_layout_traced() {
+        let c = KeepLayout::new(CaesarCipher::new(3));
+        let mut trace = Trace::new();
+        c.encrypt_traced(b"Attack at dawn!", &mut trace).unwrap();
+        assert_eq!(
+            trace.to_string(),
+            "CaesarCipher: ATTACKATDAWN -> DWWDFNDWGDZQ\nKeepLayout: Attack at dawn! -> Dwwdfn dw gdzq!\n"
+        );
+    }
+}
@@ -1,4 +1,20 @@
 pub mod helpers;
+pub mod registry;
+pub mod keyfile;
+mod error;
+mod alphabet;
+mod utf8;
+mod stream;
+mod rekey;
+mod chain;
+mod padding;
+mod layout;
+mod overlay;
+mod io;
+mod normalize;
+mod grid;
+mod trace;
+mod rng;
 mod null;
 mod caesar;
 mod playfair;
@@ -14,25 +30,438 @@ mod wheatstone;
 mod sigaba;
 mod solitaire;
 
+pub use error::CipherError;
+pub use alphabet::Alphabet;
+pub use stream::StreamCipher;
+pub use rekey::Rekey;
+pub use chain::Chain;
+pub use padding::{Padded, Padding};
+pub use layout::KeepLayout;
+pub use overlay::DigitOverlay;
+pub use io::{CipherReader, CipherWriter, StreamReader, StreamWriter};
+pub use normalize::{Digits, Normalizer, Punctuation};
+pub use rng::Rng;
+pub use grid::Grid;
+pub use trace::{Trace, TraceEntry, Tracer};
 pub use null::NullCipher;
 pub use caesar::CaesarCipher;
-pub use playfair::PlayfairCipher;
-pub use chaocipher::Chaocipher;
+pub use playfair::{PlayfairCipher, PlayfairSquare};
+pub use chaocipher::{Chaocipher, ChaocipherState};
 pub use square::SquareCipher;
 pub use transposition::Transposition;
 pub use transposition::IrregularTransposition;
 pub use adfgvx::ADFGVX;
-pub use straddling::StraddlingCheckerboard;
+pub use straddling::{DigitEscape, StraddlingCheckerboard};
 pub use nihilist::Nihilist;
-pub use vic::VicCipher;
-pub use wheatstone::Wheatstone;
+pub use vic::{VicCipher, VicMessage, VicWorksheet, VIC_BOARD};
+pub use wheatstone::{Wheatstone, WheatstoneState};
 #[cfg(feature = "sigaba")]
-pub use sigaba::Sigaba;
-pub use solitaire::Solitaire;
+pub use sigaba::{Sigaba, SigabaState};
+pub use solitaire::{Solitaire, SolitaireState};
 
 
+/// Common interface of all ciphers.
+///
+/// Implementors provide the fallible `try_encrypt`/`try_decrypt` pair; `encrypt` and
+/// `decrypt` are thin wrappers that panic with the error message.
+///
 pub trait Block {
     fn block_size(&self) -> usize;
-    fn encrypt(&self, dst: &mut [u8], src: &[u8]) -> usize;
-    fn decrypt(&self, dst: &mut [u8], src: &[u8]) -> usize;
+
+    /// Returns the number of bytes `encrypt` writes for `src`.
+    ///
+    /// The value is either exact or an upper bound, so it can always be used to size
+    /// the destination buffer.  The default is `src.len()`.
+    ///
+    fn encrypt_len(&self, src: &[u8]) -> usize {
+        src.len()
+    }
+
+    /// Returns the number of bytes `decrypt` writes for `src`.
+    ///
+    /// The value is either exact or an upper bound, so it can always be used to size
+    /// the destination buffer.  The default is `src.len()`.
+    ///
+    fn decrypt_len(&self, src: &[u8]) -> usize {
+        src.len()
+    }
+
+    /// Returns an upper bound of `encrypt_len` for any `src` of `len` bytes.
+    ///
+    /// A [`Chain`] sizes its buffers with it, without running its stages.  The default
+    /// is `len`, a cipher overriding `encrypt_len` overrides this one too.
+    ///
+    fn max_encrypt_len(&self, len: usize) -> usize {
+        len
+    }
+
+    /// Returns an upper bound of `decrypt_len` for any `src` of `len` bytes.
+    ///
+    /// The default is `len`, a cipher overriding `decrypt_len` overrides this one too.
+    ///
+    fn max_decrypt_len(&self, len: usize) -> usize {
+        len
+    }
+
+    /// Returns the number of characters `encrypt` writes for any text of `chars`
+    /// characters, a whole number of blocks, or `None` if it depends on the text.
+    ///
+    /// The [`io`](crate::io) adapters use it to cut the ciphertext where the
+    /// plaintext was cut.  The default is `Some(chars)`, a cipher changing the length
+    /// overrides it.
+    ///
+    fn encrypt_chars(&self, chars: usize) -> Option<usize> {
+        Some(chars)
+    }
+
+    /// Returns whether `encrypt` adds something once per message, like padding or a
+    /// keygroup, so that the text can not be cut into messages of its own.
+    ///
+    /// The [`io`](crate::io) adapters then encrypt and decrypt the whole text in one
+    /// piece.  The default is `false`.
+    ///
+    fn whole_message(&self) -> bool {
+        false
+    }
+
+    /// Returns the symbols of the plaintext, or `None` for a cipher taking any text.
+    ///
+    /// [`Padded`] draws its nulls from them.  The default is `None`.
+    ///
+    fn alphabet(&self) -> Option<Alphabet> {
+        None
+    }
+
+    /// Returns the normalization profile the plaintext should go through first.
+    ///
+    /// The default leaves the text untouched, for ciphers accepting anything.
+    ///
+    fn normalizer(&self) -> Normalizer {
+        Normalizer::new()
+    }
+
+    /// Encrypts `src` into `dst` and returns the number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `CipherError` if `src` contains something the cipher can not handle
+    /// or if `dst` is too small.  The content of `dst` is unspecified in that case.
+    ///
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError>;
+
+    /// Decrypts `src` into `dst` and returns the number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `CipherError` if `src` is not a valid ciphertext for the cipher
+    /// or if `dst` is too small.  The content of `dst` is unspecified in that case.
+    ///
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError>;
+
+    /// Same as [`Block::try_encrypt`], reporting what happens to `tracer`.
+    ///
+    /// The default reports the whole cipher as a single stage.  Product ciphers
+    /// report each of their stages instead, and machines their state after every
+    /// character.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Block::try_encrypt`].  The failing stage is reported with its error.
+    ///
+    fn try_encrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        let res = self.try_encrypt(dst, src);
+        trace::report(tracer, trace::stage_name::<Self>(), src, dst, res)
+    }
+
+    /// Same as [`Block::try_decrypt`], reporting what happens to `tracer`.
+    ///
+    /// See [`Block::try_encrypt_traced`].
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Block::try_decrypt`].  The failing stage is reported with its error.
+    ///
+    fn try_decrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        let res = self.try_decrypt(dst, src);
+        trace::report(tracer, trace::stage_name::<Self>(), src, dst, res)
+    }
+
+    /// Encrypts `src` into `dst` and returns the number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics on any error reported by [`Block::try_encrypt`].
+    ///
+    fn encrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
+        match self.try_encrypt(dst, src) {
+            Ok(n) => n,
+            Err(e) => panic!("{}", e),
+        }
+    }
+
+    /// Decrypts `src` into `dst` and returns the number of bytes written.
+    ///
+    /// # Panics
+    ///
+    /// Panics on any error reported by [`Block::try_decrypt`].
+    ///
+    fn decrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
+        match self.try_decrypt(dst, src) {
+            Ok(n) => n,
+            Err(e) => panic!("{}", e),
+        }
+    }
+}
+
+/// A boxed cipher is a cipher, so wrappers like [`Padded`] also take the ones built
+/// by the [registry](crate::registry).
+///
+impl<T: Block + ?Sized> Block for Box<T> {
+    fn block_size(&self) -> usize {
+        (**self).block_size()
+    }
+
+    fn encrypt_len(&self, src: &[u8]) -> usize {
+        (**self).encrypt_len(src)
+    }
+
+    fn decrypt_len(&self, src: &[u8]) -> usize {
+        (**self).decrypt_len(src)
+    }
+
+    fn max_encrypt_len(&self, len: usize) -> usize {
+        (**self).max_encrypt_len(len)
+    }
+
+    fn max_decrypt_len(&self, len: usize) -> usize {
+        (**self).max_decrypt_len(len)
+    }
+
+    fn encrypt_chars(&self, chars: usize) -> Option<usize> {
+        (**self).encrypt_chars(chars)
+    }
+
+    fn whole_message(&self) -> bool {
+        (**self).whole_message()
+    }
+
+    fn alphabet(&self) -> Option<Alphabet> {
+        (**self).alphabet()
+    }
+
+    fn normalizer(&self) -> Normalizer {
+        (**self).normalizer()
+    }
+
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        (**self).try_encrypt(dst, src)
+    }
+
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        (**self).try_decrypt(dst, src)
+    }
+
+    fn try_encrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        (**self).try_encrypt_traced(dst, src, tracer)
+    }
+
+    fn try_decrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        (**self).try_decrypt_traced(dst, src, tracer)
+    }
+}
+
+/// Convenience methods returning owned output, available on every [`Block`].
+///
+/// The destination buffer is sized with [`Block::encrypt_len`] / [`Block::decrypt_len`]
+/// and truncated to what the cipher actually wrote.
+///
+/// # Example
+///
+/// ```
+/// use old_crypto_rs::{Block, BlockExt, CaesarCipher};
+///
+/// let c: Box<dyn Block> = Box::new(CaesarCipher::new(3));
+/// assert_eq!(c.encrypt_str("HELLO").unwrap(), "KHOOR");
+/// assert_eq!(c.decrypt_to_vec(b"KHOOR").unwrap(), b"HELLO");
+/// ```
+///
+pub trait BlockExt: Block {
+    /// Encrypts `src` into a freshly allocated vector.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error reported by [`Block::try_encrypt`].
+    ///
+    fn encrypt_to_vec(&self, src: &[u8]) -> Result<Vec<u8>, CipherError> {
+        let mut dst = vec![0u8; self.encrypt_len(src)];
+        let n = self.try_encrypt(&mut dst, src)?;
+        dst.truncate(n);
+        Ok(dst)
+    }
+
+    /// Decrypts `src` into a freshly allocated vector.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error reported by [`Block::try_decrypt`].
+    ///
+    fn decrypt_to_vec(&self, src: &[u8]) -> Result<Vec<u8>, CipherError> {
+        let mut dst = vec![0u8; self.decrypt_len(src)];
+        let n = self.try_decrypt(&mut dst, src)?;
+        dst.truncate(n);
+        Ok(dst)
+    }
+
+    /// Encrypts `src` into a freshly allocated vector, reporting the stages to
+    /// `tracer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error reported by [`Block::try_encrypt_traced`].
+    ///
+    fn encrypt_traced(&self, src: &[u8], tracer: &mut dyn Tracer) -> Result<Vec<u8>, CipherError> {
+        let mut dst = vec![0u8; self.encrypt_len(src)];
+        let n = self.try_encrypt_traced(&mut dst, src, tracer)?;
+        dst.truncate(n);
+        Ok(dst)
+    }
+
+    /// Decrypts `src` into a freshly allocated vector, reporting the stages to
+    /// `tracer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error reported by [`Block::try_decrypt_traced`].
+    ///
+    fn decrypt_traced(&self, src: &[u8], tracer: &mut dyn Tracer) -> Result<Vec<u8>, CipherError> {
+        let mut dst = vec![0u8; self.decrypt_len(src)];
+        let n = self.try_decrypt_traced(&mut dst, src, tracer)?;
+        dst.truncate(n);
+        Ok(dst)
+    }
+
+    /// Encrypts a string and returns the ciphertext as a `String`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error reported by [`Block::try_encrypt`].
+    ///
+    fn encrypt_str(&self, src: &str) -> Result<String, CipherError> {
+        let dst = self.encrypt_to_vec(src.as_bytes())?;
+        Ok(String::from_utf8_lossy(&dst).into_owned())
+    }
+
+    /// Decrypts a string and returns the plaintext as a `String`.
+    ///
+    /// # Errors
+    ///
+    /// Returns any error reported by [`Block::try_decrypt`].
+    ///
+    fn decrypt_str(&self, src: &str) -> Result<String, CipherError> {
+        let dst = self.decrypt_to_vec(src.as_bytes())?;
+        Ok(String::from_utf8_lossy(&dst).into_owned())
+    }
+}
+
+impl<T: Block + ?Sized> BlockExt for T {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_ext_roundtrip() {
+        let c = ADFGVX::new("PORTABLE", "SUBWAY").unwrap();
+        let ct = c.encrypt_str("ATTACKATDAWN").unwrap();
+        assert_eq!(ct, "AFDFADAGAAAAVVVVGFGVGGGX");
+        assert_eq!(c.decrypt_str(&ct).unwrap(), "ATTACKATDAWN");
+    }
+
+    #[test]
+    fn test_block_ext_dyn() {
+        let c: Box<dyn Block> = Box::new(StraddlingCheckerboard::new("ARABESQUE", "89").unwrap());
+        assert_eq!(c.encrypt_to_vec(b"ATTACKAT2AM").unwrap(), b"0770808107972297088");
+        assert_eq!(c.decrypt_to_vec(b"0770808107972297088").unwrap(), b"ATTACKAT2AM");
+    }
+
+    #[test]
+    fn test_block_ext_error() {
+        let c = PlayfairCipher::new("PLAYFAIREXAMPLE");
+        assert_eq!(c.encrypt_str("JOKE"), Err(CipherError::InvalidChar { ch: 'J', pos: 0 }));
+    }
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    fn assert_send_sync_val<T: Send + Sync + ?Sized>(_: &T) {}
+
+    #[test]
+    fn test_normalizer_profiles() {
+        let text = "Rendez-vous à l'hôtel Jéna, chambre 12.";
+        for e in registry::ciphers() {
+            let spec = match e.name {
+                "null" => "null",
+                "caesar" => "caesar:key=3",
+                "playfair" => "playfair:key=ARABESQUE,padding=fill X",
+                "chaocipher" => "chaocipher:pkey=PTLNBQDEOYSFAVZKGJRIHWXUMC,ckey=HXUCZVAMDSLKPEFJRIGTWOBNYQ",
+                "square" => "square:key=ARABESQUE,chrs=012345",
+                "transposition" => "transposition:key=SUBWAY",
+                "irregular" => "irregular:key=SUBWAY",
+                "adfgvx" => "adfgvx:key1=ARABESQUE,key2=SUBWAY",
+                "straddling" => "straddling:key=ARABESQUE,chrs=37",
+                "nihilist" => "nihilist:key1=ARABESQUE,key2=SUBWAY,chrs=37",
+                "vic" => "vic:persn=89,ind=741776,phrase=IDREAMOFJEANNIEWITHT,imsg=77651",
+                "wheatstone" => "wheatstone:start=M,pkey=CIPHER,ckey=MACHINE",
+                "solitaire" => "solitaire",
+                "sigaba" => "sigaba:cipher=01234,control=01234,index=01234",
+                _ => unreachable!(),
+            };
+            let c = registry::from_spec(spec).unwrap();
+            let pt = c.normalizer().normalize(text);
+            let ct = c.encrypt_str(&pt).unwrap_or_else(|err| panic!("{}: {}", e.name, err));
+            assert_eq!(c.decrypt_str(&ct).unwrap(), pt, "{}", e.name);
+        }
+    }
+
+    #[test]
+    fn test_ciphers_send_sync() {
+        assert_send_sync::<NullCipher>();
+        assert_send_sync::<CaesarCipher>();
+        assert_send_sync::<PlayfairCipher>();
+        assert_send_sync::<Chaocipher>();
+        assert_send_sync::<SquareCipher>();
+        assert_send_sync::<Transposition>();
+        assert_send_sync::<IrregularTransposition>();
+        assert_send_sync::<ADFGVX>();
+        assert_send_sync::<StraddlingCheckerboard>();
+        assert_send_sync::<Nihilist>();
+        assert_send_sync::<VicCipher>();
+        assert_send_sync::<VicMessage>();
+        assert_send_sync::<DigitOverlay<StraddlingCheckerboard>>();
+        assert_send_sync::<Wheatstone>();
+        #[cfg(feature = "sigaba")]
+        assert_send_sync::<Sigaba>();
+        assert_send_sync::<Solitaire>();
+        assert_send_sync::<Chain>();
+        assert_send_sync_val(&registry::from_spec("caesar:key=3").unwrap());
+        assert_send_sync_val(&registry::build("null", &registry::Params::new()).unwrap());
+        assert_send_sync_val(&keyfile::load("[caesar]\nkey = 3\n").unwrap());
+    }
+
+    #[test]
+    fn test_spec_sent_to_thread() {
+        let spec = "transposition:key=SUBWAY+caesar:key=3";
+        let c = registry::from_spec(spec).unwrap();
+        let ct = std::thread::spawn(move || c.encrypt_str("ATTACKATDAWN").unwrap()).join().unwrap();
+        assert_eq!(registry::from_spec(spec).unwrap().decrypt_str(&ct).unwrap(), "ATTACKATDAWN");
+    }
+
+    #[test]
+    fn test_shared_between_threads() {
+        let c = Chaocipher::new("PTLNBQDEOYSFAVZKGJRIHWXUMC", "HXUCZVAMDSLKPEFJRIGTWOBNYQ").unwrap();
+        std::thread::scope(|s| {
+            for _ in 0..4 {
+                s.spawn(|| assert_eq!(c.encrypt_str("WELLDONE").unwrap(), "OAHQHCNY"));
+            }
+        });
+    }
 }
@@ -16,9 +16,10 @@
 //! cipher.encrypt(&mut ciphertext, plaintext);
 //! ```
 
-use crate::Block;
-use crate::straddling::StraddlingCheckerboard;
+use crate::{Alphabet, Block, CipherError, Grid, Normalizer, Rng, Tracer};
+use crate::straddling::{StraddlingCheckerboard, ALPHABET_TXT};
 use crate::transposition::Transposition;
+use crate::trace::NoTrace;
 
 /// Nihilist cipher combining straddling checkerboard and transposition.
 ///
@@ -41,12 +42,12 @@ impl Nihilist {
     ///
     /// * `key1` - The key for the straddling checkerboard (e.g., "ARABESQUE")
     /// * `key2` - The key for the transposition cipher (e.g., "SUBWAY")
-    /// * `chrs` - Two characters defining the blank positions in the checkerboard (e.g., "37")
+    /// * `chrs` - The digits defining the blank positions in the checkerboard (e.g., "37")
     ///
     /// # Returns
     ///
-    /// Returns `Ok(Nihilist)` if both keys are valid, or `Err(String)` with an error message
-    /// if either key is invalid or empty.
+    /// Returns `Ok(Nihilist)` if both keys are valid, or a `CipherError` naming `key1`,
+    /// `key2` or `chrs` if one of them is invalid or empty.
     ///
     /// # Examples
     ///
@@ -61,16 +62,93 @@ impl Nihilist {
     /// Returns an error if:
     /// - `key1` is empty or invalid for the straddling checkerboard
     /// - `key2` is empty or invalid for the transposition cipher
-    /// - `chrs` is not exactly two characters or contains invalid positions
-    pub fn new(key1: &str, key2: &str, chrs: &str) -> Result<Self, String> {
-        let sc = StraddlingCheckerboard::new(key1, chrs)?;
-        let transp = Transposition::new(key2)?;
+    /// - `chrs` has fewer than two characters, a digit twice or something other than digits
+    pub fn new(key1: &str, key2: &str, chrs: &str) -> Result<Self, CipherError> {
+        Self::with_alphabet(key1, key2, chrs, &Alphabet::new(ALPHABET_TXT)?)
+    }
+
+    /// Creates a Nihilist cipher whose checkerboard holds `alphabet` instead of the
+    /// default A-Z plus '/' and '-'.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Nihilist::new`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use old_crypto_rs::{Alphabet, BlockExt, Nihilist};
+    ///
+    /// let a = Alphabet::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ./").unwrap();
+    /// let cipher = Nihilist::with_alphabet("ARABESQUE", "SUBWAY", "37", &a).unwrap();
+    /// let ct = cipher.encrypt_str("STOP.AT.10").unwrap();
+    /// assert_eq!(cipher.decrypt_str(&ct).unwrap(), "STOP.AT.10");
+    /// ```
+    ///
+    pub fn with_alphabet(key1: &str, key2: &str, chrs: &str, alphabet: &Alphabet) -> Result<Self, CipherError> {
+        Self::new_with_freq(key1, key2, chrs, "ESANTIRU", alphabet)
+    }
+
+    /// Creates a Nihilist cipher over `alphabet`, the symbols of `freq` getting the
+    /// single-digit codes of the checkerboard.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Nihilist::new`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use old_crypto_rs::{Alphabet, BlockExt, Nihilist};
+    ///
+    /// let a = Alphabet::new("АБВГДЕЁЖЗИЙКЛМНОПРСТУФХЦЧШЩЪЫЬЭЮЯ./").unwrap();
+    /// let cipher = Nihilist::new_with_freq("СНЕГОПАД", "ЗИМА", "257", "ОЕАИНТС", &a).unwrap();
+    /// let ct = cipher.encrypt_str("ЯВКАПРОВАЛЕНА").unwrap();
+    /// assert_eq!(cipher.decrypt_str(&ct).unwrap(), "ЯВКАПРОВАЛЕНА");
+    /// ```
+    ///
+    pub fn new_with_freq(
+        key1: &str,
+        key2: &str,
+        chrs: &str,
+        freq: &str,
+        alphabet: &Alphabet,
+    ) -> Result<Self, CipherError> {
+        let sc = StraddlingCheckerboard::new_with_freq(key1, chrs, freq, alphabet).map_err(|e| match e.param() {
+            "key" => e.for_param("key1"),
+            _ => e,
+        })?;
+        let transp = Transposition::new(key2).map_err(|e| e.for_param("key2"))?;
 
         Ok(Nihilist {
             sc,
             transp,
         })
     }
+
+    /// Creates a Nihilist cipher with a random checkerboard and a transposition key of
+    /// `len` random letters.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EmptyKey` naming `key2` if `len` is 0.
+    ///
+    pub fn random(rng: &mut Rng, len: usize) -> Result<Self, CipherError> {
+        let a = Alphabet::latin();
+        Self::new(&rng.permutation(&a), &rng.word(&a, len), &rng.distinct_digits(2))
+    }
+
+    /// Returns the straddling checkerboard, see [`StraddlingCheckerboard::grid`].
+    ///
+    pub fn checkerboard_grid(&self) -> Grid {
+        self.sc.grid()
+    }
+
+    /// Returns the numbered columns of the transposition.
+    ///
+    pub fn transposition_grid(&self) -> Grid {
+        self.transp.grid()
+    }
 }
 
 impl Block for Nihilist {
@@ -86,6 +164,40 @@ impl Block for Nihilist {
         self.transp.block_size()
     }
 
+    fn alphabet(&self) -> Option<Alphabet> {
+        self.sc.alphabet()
+    }
+
+    /// The checkerboard takes its alphabet and digits.
+    ///
+    fn normalizer(&self) -> Normalizer {
+        self.sc.normalizer()
+    }
+
+    /// The output has as many digits as the checkerboard produces.
+    ///
+    fn encrypt_len(&self, src: &[u8]) -> usize {
+        self.sc.encrypt_len(src)
+    }
+
+    /// The transposition keeps the length, so this is the one of the checkerboard.
+    ///
+    fn decrypt_len(&self, src: &[u8]) -> usize {
+        self.sc.decrypt_len(src)
+    }
+
+    fn max_encrypt_len(&self, len: usize) -> usize {
+        self.sc.max_encrypt_len(len)
+    }
+
+    fn max_decrypt_len(&self, len: usize) -> usize {
+        self.sc.max_decrypt_len(len)
+    }
+
+    fn encrypt_chars(&self, chars: usize) -> Option<usize> {
+        self.sc.encrypt_chars(chars)
+    }
+
     /// Encrypts the source data into the destination buffer.
     ///
     /// The encryption process:
@@ -101,10 +213,21 @@ impl Block for Nihilist {
     ///
     /// The number of bytes written to `dst`.
     ///
-    fn encrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        let mut buf = vec![0u8; 2 * src.len()];
-        let n = self.sc.encrypt(&mut buf, src);
-        self.transp.encrypt(dst, &buf[..n])
+    /// # Errors
+    ///
+    /// Returns `CipherError::InvalidChar` for a character the checkerboard can not
+    /// encode and `CipherError::BufferTooSmall` if `dst` can not hold the whole output.
+    ///
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.try_encrypt_traced(dst, src, &mut NoTrace)
+    }
+
+    /// Reports the checkerboard, then the transposition.
+    ///
+    fn try_encrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        let mut buf = vec![0u8; self.sc.encrypt_len(src)];
+        let n = self.sc.try_encrypt_traced(&mut buf, src, tracer)?;
+        self.transp.try_encrypt_traced(dst, &buf[..n], tracer)
     }
 
     /// Decrypts the source ciphertext into the destination buffer.
@@ -121,17 +244,28 @@ impl Block for Nihilist {
     /// # Returns
     ///
     /// The number of bytes written to `dst`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::BufferTooSmall` if `dst` can not hold the whole output.
     /// 
-    fn decrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.try_decrypt_traced(dst, src, &mut NoTrace)
+    }
+
+    /// Reports the transposition, then the checkerboard.
+    ///
+    fn try_decrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
         let mut buf = vec![0u8; src.len()];
-        let n = self.transp.decrypt(&mut buf, src);
-        self.sc.decrypt(dst, &buf[..n])
+        let n = self.transp.try_decrypt_traced(&mut buf, src, tracer)?;
+        self.sc.try_decrypt_traced(dst, &buf[..n], tracer)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::BlockExt;
 
     #[test]
     fn test_new_cipher() {
@@ -143,6 +277,9 @@ mod tests {
     fn test_new_cipher_bad_keys() {
         assert!(Nihilist::new("PORTABLE", "", "89").is_err());
         assert!(Nihilist::new("", "SUBWAY", "62").is_err());
+        assert_eq!(Nihilist::new("", "SUBWAY", "62").err(), Some(CipherError::EmptyKey("key1")));
+        assert_eq!(Nihilist::new("PORTABLE", "", "89").err(), Some(CipherError::EmptyKey("key2")));
+        assert_eq!(Nihilist::new("PORTABLE", "SUBWAY", "8").err().map(|e| e.param()), Some("chrs"));
     }
 
     #[test]
@@ -164,4 +301,43 @@ mod tests {
         c.decrypt(&mut dst, ct.as_bytes());
         assert_eq!(String::from_utf8_lossy(&dst).trim_matches('\0'), pt);
     }
+
+    #[test]
+    fn test_nihilist_greek() {
+        let a = Alphabet::new("ΑΒΓΔΕΖΗΘΙΚΛΜΝΞΟΠΡΣΤΥΦΧΨΩ./").unwrap();
+        let c = Nihilist::new_with_freq("ΑΘΗΝΑ", "ΣΠΑΡΤΗ", "25", "ΑΟΙΕΤΣΝΗ", &a).unwrap();
+        assert_eq!(c.block_size(), 6);
+
+        let pt = "ΣΥΝΑΝΤΗΣΗ.ΣΤΙΣ.1905";
+        let ct = c.encrypt_str(pt).unwrap();
+        assert!(ct.bytes().all(|b| b.is_ascii_digit()));
+        assert_eq!(c.encrypt_len(pt.as_bytes()), ct.len());
+        assert!(c.decrypt_len(ct.as_bytes()) >= pt.len());
+        assert_eq!(c.decrypt_str(&ct).unwrap(), pt);
+        assert_eq!(c.normalizer().normalize("Συναντηση στις 10:30!"), "ΣΥΝΑΝΤΗΣΗΣΤΙΣ1030");
+    }
+
+    #[test]
+    fn test_nihilist_cyrillic() {
+        let a = Alphabet::new("АБВГДЕЁЖЗИЙКЛМНОПРСТУФХЦЧШЩЪЫЬЭЮЯ./").unwrap();
+        let c = Nihilist::new_with_freq("СНЕГОПАД", "ЗИМА", "257", "ОЕАИНТС", &a).unwrap();
+        assert_eq!(c.block_size(), 4);
+
+        let pt = "ВСТРЕЧА.СЪЕЗД.В.1905";
+        let ct = c.encrypt_str(pt).unwrap();
+        assert!(ct.bytes().all(|b| b.is_ascii_digit()));
+        assert_eq!(c.encrypt_len(pt.as_bytes()), ct.len());
+        assert!(c.decrypt_len(ct.as_bytes()) >= pt.len());
+        assert_eq!(c.decrypt_str(&ct).unwrap(), pt);
+        assert_eq!(c.normalizer().normalize("Встреча в 10:30!"), "ВСТРЕЧАВ1030");
+    }
+
+    #[test]
+    fn test_nihilist_grids() {
+        let c = Nihilist::new("ARABESQUE", "SUBWAY", "89").unwrap();
+        let g = c.checkerboard_grid();
+        assert_eq!(g.cell(0, 8), Some(""));
+        assert_eq!(g.rows().map(|(label, _)| label).collect::<Vec<_>>(), ["", "8", "9"]);
+        assert_eq!(c.transposition_grid().cell(0, 4), Some("1"));
+    }
 }
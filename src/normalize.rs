@@ -0,0 +1,435 @@
+//! Plaintext normalization.
+//!
+//! Pen-and-paper ciphers work on a small alphabet, usually the 26 uppercase letters,
+//! sometimes with the digits.  Real-world text has lowercase letters, accents,
+//! punctuation and figures, which each cipher handles in its own way (or not at all).
+//! A [`Normalizer`] turns such text into something the cipher accepts, the way a
+//! cipher clerk would before starting the encipherment.
+//!
+//! Every cipher advertises the profile it needs through [`Block::normalizer`], so
+//! frontends can apply it without knowing the cipher.
+//!
+//! # Example
+//!
+//! ```
+//! use old_crypto_rs::{Block, Normalizer, PlayfairCipher};
+//!
+//! let n = Normalizer::letters().substitute('J', "I");
+//! assert_eq!(n.normalize("Jérôme arrive à 10h."), "IEROMEARRIVEAONEZEROHSTOP");
+//!
+//! // This is the profile of Playfair
+//! let c = PlayfairCipher::new("PLAYFAIREXAMPLE");
+//! assert_eq!(c.normalizer(), n);
+//! ```
+//!
+//! [`Block::normalizer`]: crate::Block::normalizer
+//!
+use crate::helpers;
+
+/// What to do with punctuation (anything that is neither a letter, a digit nor a space).
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Punctuation {
+    /// Leave it alone.
+    Keep,
+    /// Remove it.
+    Drop,
+    /// Replace the usual signs by a code word (`.` is `STOP`, `?` is `QUERY`…) and
+    /// remove the others.
+    SpellOut,
+}
+
+/// What to do with the digits.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Digits {
+    /// Leave them alone.
+    Keep,
+    /// Remove them.
+    Drop,
+    /// Replace each digit by its name (`1` is `ONE`…).
+    SpellOut,
+    /// Replace each digit by a letter (`1` is `A` … `9` is `I`, `0` is `J`) and put
+    /// every run of digits between two copies of the given marker.
+    Escape(char),
+}
+
+/// Code words used by [`Punctuation::SpellOut`].
+const PUNCTUATION_WORDS: &[(char, &str)] = &[
+    ('.', "STOP"),
+    ('?', "QUERY"),
+    (',', "COMMA"),
+    (':', "COLON"),
+    (';', "SEMICOLON"),
+    ('!', "EXCLAM"),
+    ('-', "DASH"),
+    ('"', "QUOTE"),
+    ('(', "PAREN"),
+    (')', "PAREN"),
+];
+
+/// Names used by [`Digits::SpellOut`].
+const DIGIT_WORDS: [&str; 10] = [
+    "ZERO", "ONE", "TWO", "THREE", "FOUR", "FIVE", "SIX", "SEVEN", "EIGHT", "NINE",
+];
+
+/// Returns the unaccented form of a Latin or Greek letter, if it has one.
+///
+fn fold_accent(ch: char) -> Option<&'static str> {
+    let s = match ch {
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Ā' | 'Ă' | 'Ą' => "A",
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' => "a",
+        'Æ' => "AE",
+        'æ' => "ae",
+        'Ç' | 'Ć' | 'Č' => "C",
+        'ç' | 'ć' | 'č' => "c",
+        'Ð' | 'Ď' => "D",
+        'ð' | 'ď' => "d",
+        'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ė' | 'Ę' | 'Ě' => "E",
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ė' | 'ę' | 'ě' => "e",
+        'Ğ' => "G",
+        'ğ' => "g",
+        'Ì' | 'Í' | 'Î' | 'Ï' | 'Ī' | 'İ' => "I",
+        'ì' | 'í' | 'î' | 'ï' | 'ī' | 'ı' => "i",
+        'Ł' => "L",
+        'ł' => "l",
+        'Ñ' | 'Ń' | 'Ň' => "N",
+        'ñ' | 'ń' | 'ň' => "n",
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ō' | 'Ő' => "O",
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ő' => "o",
+        'Œ' => "OE",
+        'œ' => "oe",
+        'Ř' => "R",
+        'ř' => "r",
+        'Ś' | 'Š' | 'Ş' => "S",
+        'ś' | 'š' | 'ş' => "s",
+        'ß' => "ss",
+        'ẞ' => "SS",
+        'Ť' | 'Ţ' => "T",
+        'ť' | 'ţ' => "t",
+        'Þ' => "TH",
+        'þ' => "th",
+        'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ū' | 'Ů' | 'Ű' => "U",
+        'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' | 'ű' => "u",
+        'Ý' | 'Ÿ' => "Y",
+        'ý' | 'ÿ' => "y",
+        'Ź' | 'Ż' | 'Ž' => "Z",
+        'ź' | 'ż' | 'ž' => "z",
+        'Ά' => "Α",
+        'ά' => "α",
+        'Έ' => "Ε",
+        'έ' => "ε",
+        'Ή' => "Η",
+        'ή' => "η",
+        'Ί' | 'Ϊ' => "Ι",
+        'ί' | 'ϊ' | 'ΐ' => "ι",
+        'Ό' => "Ο",
+        'ό' => "ο",
+        'Ύ' | 'Ϋ' => "Υ",
+        'ύ' | 'ϋ' | 'ΰ' => "υ",
+        'Ώ' => "Ω",
+        'ώ' => "ω",
+        _ => return None,
+    };
+    Some(s)
+}
+
+/// A configurable plaintext normalizer.
+///
+/// Each character goes through these steps, in order:
+/// 1. accent stripping (`é` → `e`, `ß` → `ss`)
+/// 2. uppercasing
+/// 3. letter substitutions (`J` → `I`, `W` → `VV`…)
+/// 4. punctuation kept as a symbol of the cipher, then digits, spaces and
+///    punctuation policies
+///
+/// Doubled letters are then split with a filler if asked to (Wheatstone).
+///
+/// `Normalizer::new()` changes nothing; the other constructors are the common
+/// profiles.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Normalizer {
+    uppercase: bool,
+    strip_accents: bool,
+    substitutions: Vec<(char, String)>,
+    punctuation: Punctuation,
+    digits: Digits,
+    keep_spaces: bool,
+    split_doubles: Option<char>,
+    symbols: Vec<char>,
+}
+
+impl Normalizer {
+    /// Creates a normalizer leaving the text untouched.
+    ///
+    pub fn new() -> Self {
+        Normalizer {
+            uppercase: false,
+            strip_accents: false,
+            substitutions: Vec::new(),
+            punctuation: Punctuation::Keep,
+            digits: Digits::Keep,
+            keep_spaces: true,
+            split_doubles: None,
+            symbols: Vec::new(),
+        }
+    }
+
+    /// Profile for ciphers working on the 26 letters only: uppercase, no accents, no
+    /// spaces, punctuation and digits spelled out.
+    ///
+    pub fn letters() -> Self {
+        Normalizer {
+            uppercase: true,
+            strip_accents: true,
+            substitutions: Vec::new(),
+            punctuation: Punctuation::SpellOut,
+            digits: Digits::SpellOut,
+            keep_spaces: false,
+            split_doubles: None,
+            symbols: Vec::new(),
+        }
+    }
+
+    /// Same as [`Normalizer::letters`] but the digits are kept.
+    ///
+    pub fn alphanumeric() -> Self {
+        Self::letters().digits(Digits::Keep)
+    }
+
+    /// Enables or disables uppercasing.
+    ///
+    pub fn uppercase(mut self, yes: bool) -> Self {
+        self.uppercase = yes;
+        self
+    }
+
+    /// Enables or disables accent stripping.
+    ///
+    pub fn strip_accents(mut self, yes: bool) -> Self {
+        self.strip_accents = yes;
+        self
+    }
+
+    /// Replaces `from` by `to` (after uppercasing), e.g. `J` by `I` for Playfair.
+    ///
+    /// A later substitution of the same letter replaces the earlier one.
+    ///
+    pub fn substitute(mut self, from: char, to: &str) -> Self {
+        self.substitutions.retain(|(c, _)| *c != from);
+        self.substitutions.push((from, to.to_string()));
+        self
+    }
+
+    /// Sets the punctuation policy.
+    ///
+    pub fn punctuation(mut self, p: Punctuation) -> Self {
+        self.punctuation = p;
+        self
+    }
+
+    /// Sets the digits policy.
+    ///
+    pub fn digits(mut self, d: Digits) -> Self {
+        self.digits = d;
+        self
+    }
+
+    /// Keeps or removes whitespace.
+    ///
+    pub fn keep_spaces(mut self, yes: bool) -> Self {
+        self.keep_spaces = yes;
+        self
+    }
+
+    /// Inserts `filler` between two identical characters (`LL` → `LQL`), or not if `None`.
+    ///
+    /// A doubled filler is split with `X`, or `Q` if the filler is `X`.
+    ///
+    pub fn split_doubles(mut self, filler: Option<char>) -> Self {
+        self.split_doubles = filler;
+        self
+    }
+
+    /// Keeps the punctuation signs of `symbols` whatever the punctuation policy, for
+    /// those the cipher has in its alphabet.
+    ///
+    pub fn keep_symbols(mut self, symbols: &str) -> Self {
+        for c in symbols.chars() {
+            if !self.symbols.contains(&c) {
+                self.symbols.push(c);
+            }
+        }
+        self
+    }
+
+    /// Adds the restrictions of `later`, the profile of a cipher fed with the output
+    /// of the one this profile is for, on what that first cipher leaves alone.
+    ///
+    /// Spaces, punctuation and digits kept by `self` get the policy of `later`, and
+    /// uppercasing or accent stripping is done if either asks for it.  Substitutions,
+    /// kept symbols and the splitting of doubled letters are not added: they are about
+    /// the symbols of the first cipher, which it changes.
+    ///
+    pub(crate) fn then(mut self, later: &Normalizer) -> Self {
+        self.uppercase |= later.uppercase;
+        self.strip_accents |= later.strip_accents;
+        self.keep_spaces &= later.keep_spaces;
+        if self.punctuation == Punctuation::Keep {
+            self.punctuation = later.punctuation;
+        }
+        if self.digits == Digits::Keep {
+            self.digits = later.digits;
+        }
+        self
+    }
+
+    /// Normalizes `text`.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use old_crypto_rs::{Digits, Normalizer};
+    ///
+    /// let n = Normalizer::letters().digits(Digits::Escape('Q'));
+    /// assert_eq!(n.normalize("Straße 12"), "STRASSEQABQ");
+    /// ```
+    ///
+    pub fn normalize(&self, text: &str) -> String {
+        let mut out = String::with_capacity(text.len());
+        let mut in_digits = false;
+
+        for ch in text.chars() {
+            let folded = if self.strip_accents { fold_accent(ch) } else { None };
+            let mut chars: Vec<char> = match folded {
+                Some(s) => s.chars().collect(),
+                None => vec![ch],
+            };
+            if self.uppercase {
+                chars = chars.into_iter().flat_map(char::to_uppercase).collect();
+            }
+
+            for c in chars {
+                if let Digits::Escape(marker) = self.digits
+                    && in_digits
+                    && !c.is_ascii_digit()
+                {
+                    out.push(marker);
+                    in_digits = false;
+                }
+                self.push(&mut out, c, &mut in_digits);
+            }
+        }
+        if let (Digits::Escape(marker), true) = (self.digits, in_digits) {
+            out.push(marker);
+        }
+        match self.split_doubles {
+            Some(filler) => helpers::fix_double(&out, filler),
+            None => out,
+        }
+    }
+
+    /// Appends the normalized form of a single (already folded and uppercased) char.
+    ///
+    fn push(&self, out: &mut String, c: char, in_digits: &mut bool) {
+        if let Some((_, to)) = self.substitutions.iter().find(|(from, _)| *from == c) {
+            out.push_str(to);
+        } else if c.is_alphabetic() {
+            out.push(c);
+        } else if c.is_ascii_digit() {
+            let d = c as usize - '0' as usize;
+            match self.digits {
+                Digits::Keep => out.push(c),
+                Digits::Drop => (),
+                Digits::SpellOut => out.push_str(DIGIT_WORDS[d]),
+                Digits::Escape(marker) => {
+                    if !*in_digits {
+                        out.push(marker);
+                        *in_digits = true;
+                    }
+                    out.push((b'A' + ((d + 9) % 10) as u8) as char);
+                }
+            }
+        } else if c.is_whitespace() {
+            if self.keep_spaces {
+                out.push(c);
+            }
+        } else if self.symbols.contains(&c) {
+            out.push(c);
+        } else {
+            match self.punctuation {
+                Punctuation::Keep => out.push(c),
+                Punctuation::Drop => (),
+                Punctuation::SpellOut => {
+                    if let Some((_, w)) = PUNCTUATION_WORDS.iter().find(|(p, _)| *p == c) {
+                        out.push_str(w);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Default for Normalizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rstest::rstest;
+
+    #[test]
+    fn test_normalizer_identity() {
+        let s = "Hello, World! 42 é";
+        assert_eq!(Normalizer::new().normalize(s), s);
+    }
+
+    #[rstest]
+    #[case("Ça coûte 5€ à l'œil", "CACOUTEFIVEALOEIL")]
+    #[case("Grüße aus Köln.", "GRUSSEAUSKOLNSTOP")]
+    #[case("Where? Here, now!", "WHEREQUERYHERECOMMANOWEXCLAM")]
+    #[case("1945", "ONENINEFOURFIVE")]
+    #[case("Ερμής", "ΕΡΜΗΣ")]
+    fn test_normalizer_letters(#[case] input: &str, #[case] expected: &str) {
+        assert_eq!(Normalizer::letters().normalize(input), expected);
+    }
+
+    #[rstest]
+    #[case(Digits::Keep, "AT2AM", "AT2AM")]
+    #[case(Digits::Drop, "AT2AM", "ATAM")]
+    #[case(Digits::SpellOut, "AT2AM", "ATTWOAM")]
+    #[case(Digits::Escape('Q'), "AT 20 AM", "ATQBJQAM")]
+    #[case(Digits::Escape('Q'), "1905", "QAIJEQ")]
+    fn test_normalizer_digits(#[case] d: Digits, #[case] input: &str, #[case] expected: &str) {
+        assert_eq!(Normalizer::letters().digits(d).normalize(input), expected);
+    }
+
+    #[test]
+    fn test_normalizer_then() {
+        let n = Normalizer::new().uppercase(true).then(&Normalizer::letters().substitute('J', "I"));
+        assert_eq!(n, Normalizer::letters());
+        let n = Normalizer::letters().digits(Digits::Keep).then(&Normalizer::new().digits(Digits::Drop));
+        assert_eq!(n, Normalizer::alphanumeric().digits(Digits::Drop));
+        assert_eq!(Normalizer::letters().then(&Normalizer::alphanumeric()), Normalizer::letters());
+    }
+
+    #[test]
+    fn test_normalizer_options() {
+        let n = Normalizer::new()
+            .uppercase(true)
+            .punctuation(Punctuation::Drop)
+            .substitute('W', "VV")
+            .substitute('J', "X")
+            .substitute('J', "I");
+        assert_eq!(n.normalize("Jewel, wow."), "IEVVEL VVOVV");
+        assert_eq!(n.clone().keep_spaces(false).normalize("a b"), "AB");
+        assert_eq!(Normalizer::new().strip_accents(true).normalize("Ærø"), "AEro");
+        assert_eq!(Normalizer::letters().split_doubles(Some('Q')).normalize("Hello"), "HELQLO");
+        assert_eq!(Normalizer::letters().keep_symbols(".-").normalize("Re-run, now."), "RE-RUNCOMMANOW.");
+    }
+}
@@ -1,4 +1,5 @@
-use crate::Block;
+use crate::{Block, CipherError};
+use crate::error::check_dst;
 
 /// A basic, do-nothing, cipher.
 pub struct NullCipher;
@@ -10,6 +11,12 @@ impl NullCipher {
     }
 }
 
+impl Default for NullCipher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Block for NullCipher {
     /// BlockSize is part of the interface
     fn block_size(&self) -> usize {
@@ -17,15 +24,17 @@ impl Block for NullCipher {
     }
 
     /// Encrypt is part of the interface
-    fn encrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        check_dst(dst, src.len())?;
         dst[..src.len()].copy_from_slice(src);
-        src.len()
+        Ok(src.len())
     }
 
     /// Decrypt is part of the interface
-    fn decrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        check_dst(dst, src.len())?;
         dst[..src.len()].copy_from_slice(src);
-        src.len()
+        Ok(src.len())
     }
 }
 
@@ -75,4 +84,14 @@ mod tests {
             assert_eq!(plain, pt.as_bytes());
         }
     }
+
+    #[test]
+    fn test_null_cipher_short_buffer() {
+        let c = NullCipher::new();
+        let mut dst = [0u8; 3];
+        assert_eq!(
+            c.try_encrypt(&mut dst, b"ABCDE"),
+            Err(CipherError::BufferTooSmall { needed: 5, got: 3 })
+        );
+    }
 }
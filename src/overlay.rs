@@ -0,0 +1,316 @@
+//! Adding a numeric key to a digit ciphertext.
+//!
+//! The checkerboard ciphers (straddling checkerboard, Nihilist, VIC) write digits.
+//! Soviet practice was to add a numeric key to them, digit by digit without carrying
+//! (mod 10), before sending: [`DigitOverlay`] wraps any cipher writing digits and does
+//! this on its output, subtracting the key again before decryption.
+//!
+//! The key is either repeated, extended by chain addition as in the VIC procedure
+//! (each new digit is the sum of the two digits as far back as the length of the
+//! primer), or an external pad used once, as long as the ciphertext.
+//!
+//! # Example
+//!
+//! ```
+//! use old_crypto_rs::{BlockExt, DigitOverlay, StraddlingCheckerboard};
+//!
+//! let c = DigitOverlay::repeating(StraddlingCheckerboard::new("ARABESQUE", "89").unwrap(), "1984").unwrap();
+//! // 07708081 + 19841984
+//! assert_eq!(c.encrypt_str("ATTACK").unwrap(), "16549965");
+//! assert_eq!(c.decrypt_str("16549965").unwrap(), "ATTACK");
+//! ```
+//!
+use crate::{Alphabet, Block, CipherError, Normalizer, Tracer};
+use crate::error::check_dst;
+use crate::trace::{self, NoTrace};
+use crate::vic::chainadd_extend;
+
+/// How the key is stretched over the ciphertext.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stream {
+    Repeat,
+    Chain,
+    Pad,
+}
+
+/// A cipher whose digits get a numeric key added without carrying.
+///
+/// Characters of the ciphertext other than digits are left alone and take no digit of
+/// the key.
+///
+#[derive(Debug)]
+pub struct DigitOverlay<C> {
+    cipher: C,
+    key: Vec<u8>,
+    stream: Stream,
+    subtract: bool,
+}
+
+impl<C: Block> DigitOverlay<C> {
+    /// Wraps `cipher`, adding `key` repeated as many times as needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EmptyKey` if `key` is empty and `InvalidDigit` if it holds something
+    /// other than digits.
+    ///
+    pub fn repeating(cipher: C, key: &str) -> Result<Self, CipherError> {
+        Self::new(cipher, key, Stream::Repeat)
+    }
+
+    /// Wraps `cipher`, adding `primer` followed by its extension by chain addition.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`DigitOverlay::repeating`], and `KeyTooShort` if `primer` has a single
+    /// digit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use old_crypto_rs::{BlockExt, DigitOverlay, NullCipher};
+    ///
+    /// // Key 77651 43165 74712…
+    /// let c = DigitOverlay::chained(NullCipher::new(), "77651").unwrap();
+    /// assert_eq!(c.encrypt_str("000000000000").unwrap(), "776514316574");
+    /// ```
+    ///
+    pub fn chained(cipher: C, primer: &str) -> Result<Self, CipherError> {
+        if primer.len() == 1 && primer.as_bytes()[0].is_ascii_digit() {
+            return Err(CipherError::KeyTooShort { param: "key", min: 2, got: 1 });
+        }
+        Self::new(cipher, primer, Stream::Chain)
+    }
+
+    /// Wraps `cipher`, adding the digits of a one-time pad.  Encrypting or decrypting
+    /// a text with more digits than the pad fails.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`DigitOverlay::repeating`].
+    ///
+    pub fn pad(cipher: C, pad: &str) -> Result<Self, CipherError> {
+        Self::new(cipher, pad, Stream::Pad)
+    }
+
+    fn new(cipher: C, key: &str, stream: Stream) -> Result<Self, CipherError> {
+        if key.is_empty() {
+            return Err(CipherError::EmptyKey("key"));
+        }
+        if let Some(ch) = key.chars().find(|c| !c.is_ascii_digit()) {
+            return Err(CipherError::InvalidDigit { param: "key", ch });
+        }
+        let key = key.bytes().map(|b| b - b'0').collect();
+        Ok(DigitOverlay { cipher, key, stream, subtract: false })
+    }
+
+    /// Subtracts the key on encryption, and adds it on decryption, instead of the
+    /// other way round.
+    ///
+    pub fn subtracting(mut self) -> Self {
+        self.subtract = true;
+        self
+    }
+
+    /// Returns the wrapped cipher.
+    ///
+    pub fn cipher(&self) -> &C {
+        &self.cipher
+    }
+
+    /// Returns the first `n` digits of the key.
+    ///
+    fn keystream(&self, n: usize) -> Result<Vec<u8>, CipherError> {
+        match self.stream {
+            Stream::Repeat => Ok(self.key.iter().copied().cycle().take(n).collect()),
+            Stream::Chain => {
+                let mut k = chainadd_extend(&self.key, n.saturating_sub(self.key.len()));
+                k.truncate(n);
+                Ok(k)
+            }
+            Stream::Pad if n > self.key.len() => Err(CipherError::KeyTooShort { param: "key", min: n, got: self.key.len() }),
+            Stream::Pad => Ok(self.key[..n].to_vec()),
+        }
+    }
+
+    /// Adds the key to the digits of `text`, or subtracts it.
+    ///
+    fn overlay(&self, text: &mut [u8], add: bool) -> Result<(), CipherError> {
+        let key = self.keystream(text.iter().filter(|b| b.is_ascii_digit()).count())?;
+        let digits = text.iter_mut().filter(|b| b.is_ascii_digit());
+        for (d, k) in digits.zip(key) {
+            let k = if add { k } else { 10 - k };
+            *d = b'0' + (*d - b'0' + k) % 10;
+        }
+        Ok(())
+    }
+}
+
+impl<C: Block> Block for DigitOverlay<C> {
+    fn block_size(&self) -> usize {
+        self.cipher.block_size()
+    }
+
+    fn alphabet(&self) -> Option<Alphabet> {
+        self.cipher.alphabet()
+    }
+
+    fn normalizer(&self) -> Normalizer {
+        self.cipher.normalizer()
+    }
+
+    fn encrypt_len(&self, src: &[u8]) -> usize {
+        self.cipher.encrypt_len(src)
+    }
+
+    fn decrypt_len(&self, src: &[u8]) -> usize {
+        self.cipher.decrypt_len(src)
+    }
+
+    fn max_encrypt_len(&self, len: usize) -> usize {
+        self.cipher.max_encrypt_len(len)
+    }
+
+    fn max_decrypt_len(&self, len: usize) -> usize {
+        self.cipher.max_decrypt_len(len)
+    }
+
+    fn encrypt_chars(&self, chars: usize) -> Option<usize> {
+        self.cipher.encrypt_chars(chars)
+    }
+
+    fn whole_message(&self) -> bool {
+        self.cipher.whole_message()
+    }
+
+    /// Encrypts `src`, then adds the key to the digits.
+    ///
+    /// # Errors
+    ///
+    /// Returns `KeyTooShort` if a pad has fewer digits than the ciphertext, and any
+    /// error of the cipher.
+    ///
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.try_encrypt_traced(dst, src, &mut NoTrace)
+    }
+
+    /// Subtracts the key from the digits of `src`, then decrypts it.
+    ///
+    /// # Errors
+    ///
+    /// Same as `try_encrypt`.
+    ///
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.try_decrypt_traced(dst, src, &mut NoTrace)
+    }
+
+    /// Reports the key as a stage of its own, after the ones of the cipher.
+    ///
+    fn try_encrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        check_dst(dst, self.cipher.encrypt_len(src))?;
+        let n = self.cipher.try_encrypt_traced(dst, src, tracer)?;
+        let ct = dst[..n].to_vec();
+        let res = self.overlay(&mut dst[..n], !self.subtract).map(|_| n);
+        trace::report(tracer, "DigitOverlay", &ct, dst, res)
+    }
+
+    /// Reports the removal of the key as a stage of its own, before the ones of the
+    /// cipher.
+    ///
+    fn try_decrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        let mut ct = src.to_vec();
+        let res = self.overlay(&mut ct, self.subtract);
+        tracer.stage("DigitOverlay", src, res.as_ref().map(|_| &ct[..]));
+        res?;
+        self.cipher.try_decrypt_traced(dst, &ct, tracer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockExt, Nihilist, NullCipher, StraddlingCheckerboard, Trace, VicCipher};
+
+    use rstest::rstest;
+
+    fn checkerboard() -> StraddlingCheckerboard {
+        StraddlingCheckerboard::new("ARABESQUE", "89").unwrap()
+    }
+
+    #[rstest]
+    #[case("1984", false, "ATTACKAT2AM", "1654996516713171176")]
+    #[case("1984", true, "ATTACKAT2AM", "9896710798131313990")]
+    #[case("0", false, "ATTACK", "07708081")]
+    fn test_repeating(#[case] key: &str, #[case] subtract: bool, #[case] pt: &str, #[case] ct: &str) {
+        let c = DigitOverlay::repeating(checkerboard(), key).unwrap();
+        let c = if subtract { c.subtracting() } else { c };
+        assert_eq!(c.encrypt_str(pt).unwrap(), ct);
+        assert_eq!(c.decrypt_str(ct).unwrap(), pt);
+    }
+
+    #[test]
+    fn test_chained() {
+        let c = DigitOverlay::chained(NullCipher::new(), "77651").unwrap();
+        assert_eq!(c.keystream(20).unwrap(), [7, 7, 6, 5, 1, 4, 3, 1, 6, 5, 7, 4, 7, 1, 2, 1, 1, 8, 3, 3]);
+        assert_eq!(c.keystream(3).unwrap(), [7, 7, 6]);
+
+        let c = DigitOverlay::chained(checkerboard(), "77651").unwrap();
+        let ct = c.encrypt_str("ATTACKATDAWN").unwrap();
+        assert_eq!(c.decrypt_str(&ct).unwrap(), "ATTACKATDAWN");
+    }
+
+    #[test]
+    fn test_pad() {
+        let c = DigitOverlay::pad(checkerboard(), "31415926").unwrap();
+        assert_eq!(c.encrypt_str("ATTACK").unwrap(), "38113907");
+        assert_eq!(c.decrypt_str("38113907").unwrap(), "ATTACK");
+        assert_eq!(c.encrypt_str("ATTACKS"), Err(CipherError::KeyTooShort { param: "key", min: 9, got: 8 }));
+        assert_eq!(c.decrypt_str("381119074"), Err(CipherError::KeyTooShort { param: "key", min: 9, got: 8 }));
+    }
+
+    #[test]
+    fn test_other_ciphers() {
+        let c = DigitOverlay::chained(Nihilist::new("ARABESQUE", "SUBWAY", "37").unwrap(), "314").unwrap();
+        assert_eq!(c.decrypt_str(&c.encrypt_str("ATTACKATDAWN").unwrap()).unwrap(), "ATTACKATDAWN");
+
+        let c = DigitOverlay::repeating(VicCipher::new("89", "741776", "IDREAMOFJEANNIEWITHT", "77651").unwrap(), "5")
+            .unwrap()
+            .subtracting();
+        assert_eq!(c.decrypt_str(&c.encrypt_str("ATTACKATDAWN").unwrap()).unwrap(), "ATTACKATDAWN");
+
+        // Other characters keep their place and take no key digit
+        let c = DigitOverlay::repeating(NullCipher::new(), "12").unwrap();
+        assert_eq!(c.encrypt_str("00 00-0").unwrap(), "12 12-1");
+    }
+
+    #[rstest]
+    #[case("", CipherError::EmptyKey("key"))]
+    #[case("12A", CipherError::InvalidDigit { param: "key", ch: 'A' })]
+    fn test_errors(#[case] key: &str, #[case] err: CipherError) {
+        assert_eq!(DigitOverlay::repeating(checkerboard(), key).err(), Some(err.clone()));
+        assert_eq!(DigitOverlay::pad(checkerboard(), key).err(), Some(err.clone()));
+        assert_eq!(DigitOverlay::chained(checkerboard(), key).err(), Some(err));
+        assert_eq!(
+            DigitOverlay::chained(checkerboard(), "7").err(),
+            Some(CipherError::KeyTooShort { param: "key", min: 2, got: 1 })
+        );
+    }
+
+    #[test]
+    fn test_traced() {
+        let c = DigitOverlay::repeating(checkerboard(), "1984").unwrap();
+        let mut trace = Trace::new();
+        c.encrypt_traced(b"ATTACK", &mut trace).unwrap();
+        assert_eq!(
+            trace.to_string(),
+            "StraddlingCheckerboard: ATTACK -> 07708081\nDigitOverlay: 07708081 -> 16549965\n"
+        );
+        let mut trace = Trace::new();
+        c.decrypt_traced(b"16549965", &mut trace).unwrap();
+        assert_eq!(
+            trace.to_string(),
+            "DigitOverlay: 16549965 -> 07708081\nStraddlingCheckerboard: 07708081 -> ATTACK\n"
+        );
+    }
+}
@@ -0,0 +1,452 @@
+//! Padding the plaintext, and removing it on decryption.
+//!
+//! Some ciphers need a whole number of blocks: Playfair works on digraphs and appends
+//! a filler to an odd message itself, a columnar transposition reads an incomplete
+//! rectangle differently from a complete one.  Neither can tell the padding apart
+//! from the message when decrypting.  [`Padded`] wraps any cipher with a [`Padding`]
+//! policy, applied before encryption and stripped after decryption, so that the
+//! plaintext comes back exactly as it was given.
+//!
+//! # Example
+//!
+//! ```
+//! use old_crypto_rs::{BlockExt, Padded, Padding, PlayfairCipher, Transposition};
+//!
+//! let c = Padded::new(PlayfairCipher::new("PLAYFAIREXAMPLE"), Padding::Fill('X')).unwrap();
+//! let ct = c.encrypt_str("HIDETHEGOLD").unwrap();
+//! assert_eq!(ct, "BMODZBXDNAGE");
+//! assert_eq!(c.decrypt_str(&ct).unwrap(), "HIDETHEGOLD");
+//!
+//! // Complete the rectangle of the transposition with random letters
+//! let c = Padded::new(Transposition::new("SUBWAY").unwrap(), Padding::Nulls).unwrap();
+//! let ct = c.encrypt_str("ATTACKATDAWN").unwrap();
+//! assert_eq!(ct.len(), 18);
+//! assert_eq!(c.decrypt_str(&ct).unwrap(), "ATTACKATDAWN");
+//! ```
+//!
+use crate::{Alphabet, Block, CipherError, Normalizer, Rng, Tracer};
+use crate::error::check_dst;
+use crate::trace::{self, NoTrace};
+use crate::utf8;
+use std::fmt;
+use std::str::FromStr;
+
+/// How the plaintext is completed to a whole number of blocks.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Padding {
+    /// Nothing is added or removed, the cipher gets the text as is.
+    None,
+    /// The fill letter is appended up to a whole number of blocks of the cipher (its
+    /// [`Block::block_size`]) and trailing fill letters are removed on decryption.
+    ///
+    /// A message ending with the fill letter can not be told apart from its padding,
+    /// so it is rejected when padding has to be added.  One filling its last block
+    /// is encrypted as is, but loses up to a block less one of its trailing fill
+    /// letters on decryption.
+    Fill(char),
+    /// Random symbols are appended: at least one, up to a whole number of blocks of
+    /// the cipher.  The last one is the symbol of the alphabet whose position is the
+    /// number of symbols added (`A` for one, `B` for two…), so they can be removed.
+    Nulls,
+    /// Like `Fill`, up to a multiple of `width` symbols instead of the block size,
+    /// e.g. to complete the rectangle of the transposition of a product cipher or to
+    /// send groups of five.
+    Rectangle { width: usize, fill: char },
+}
+
+/// The form used by the `padding` parameter of the [registry](crate::registry):
+/// `none`, `fill X`, `nulls` or `rectangle 5 X`.
+///
+impl fmt::Display for Padding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Padding::None => write!(f, "none"),
+            Padding::Fill(fill) => write!(f, "fill {}", fill),
+            Padding::Nulls => write!(f, "nulls"),
+            Padding::Rectangle { width, fill } => write!(f, "rectangle {} {}", width, fill),
+        }
+    }
+}
+
+impl FromStr for Padding {
+    type Err = CipherError;
+
+    /// Parses the form written by `Display`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidParameter` for the `padding` parameter if `s` is not one of the
+    /// four forms.
+    ///
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || CipherError::InvalidParameter { param: "padding", value: s.to_string() };
+        let fill = |f: &str| {
+            let mut chars = f.chars();
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) => Ok(ch),
+                _ => Err(invalid()),
+            }
+        };
+        match s.split_whitespace().collect::<Vec<_>>()[..] {
+            ["none"] => Ok(Padding::None),
+            ["fill", f] => Ok(Padding::Fill(fill(f)?)),
+            ["nulls"] => Ok(Padding::Nulls),
+            ["rectangle", width, f] => Ok(Padding::Rectangle {
+                width: width.parse().map_err(|_| invalid())?,
+                fill: fill(f)?,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// A cipher with a padding policy.
+///
+#[derive(Debug)]
+pub struct Padded<C> {
+    cipher: C,
+    padding: Padding,
+    alphabet: Alphabet,
+}
+
+impl<C: Block> Padded<C> {
+    /// Wraps `cipher` with `padding`, nulls being drawn from the alphabet of the cipher
+    /// ([`Block::alphabet`]), the latin one for a cipher taking any text.
+    ///
+    /// # Errors
+    ///
+    /// See [`Padded::with_alphabet`].
+    ///
+    pub fn new(cipher: C, padding: Padding) -> Result<Self, CipherError> {
+        let alphabet = cipher.alphabet().unwrap_or_else(Alphabet::latin);
+        Self::with_alphabet(cipher, padding, &alphabet)
+    }
+
+    /// Wraps `cipher` with `padding`, nulls being drawn from `alphabet`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EmptyKey` for a `Rectangle` of width 0, and `KeyTooShort` for
+    /// `alphabet` if it has fewer symbols than the block size of the cipher with
+    /// `Nulls`, as the last null could not tell how many were added.
+    ///
+    pub fn with_alphabet(cipher: C, padding: Padding, alphabet: &Alphabet) -> Result<Self, CipherError> {
+        check(&cipher, padding, alphabet)?;
+        Ok(Padded { cipher, padding, alphabet: alphabet.clone() })
+    }
+
+    /// Returns the wrapped cipher.
+    ///
+    pub fn cipher(&self) -> &C {
+        &self.cipher
+    }
+
+    /// Returns the padding policy.
+    ///
+    pub fn padding(&self) -> Padding {
+        self.padding
+    }
+
+    /// Replaces the padding, keeping the alphabet.
+    ///
+    pub(crate) fn set_padding(&mut self, padding: Padding) -> Result<(), CipherError> {
+        check(&self.cipher, padding, &self.alphabet)?;
+        self.padding = padding;
+        Ok(())
+    }
+
+    /// Returns the length the plaintext is padded to a multiple of.
+    ///
+    pub(crate) fn multiple(&self) -> usize {
+        match self.padding {
+            Padding::None | Padding::Fill(_) | Padding::Nulls => self.cipher.block_size().max(1),
+            Padding::Rectangle { width, .. } => width,
+        }
+    }
+
+    /// Returns `src` followed by its padding.
+    ///
+    pub(crate) fn pad(&self, src: &[u8]) -> Result<Vec<u8>, CipherError> {
+        let text = utf8::decode(src)?;
+        let (len, m) = (text.chars().count(), self.multiple());
+        let mut out = text.to_string();
+        match self.padding {
+            Padding::None => {}
+            Padding::Fill(fill) | Padding::Rectangle { fill, .. } => {
+                let n = (m - len % m) % m;
+                if n > 0 && text.ends_with(fill) {
+                    return Err(CipherError::EndsWithFill { fill });
+                }
+                out.extend(std::iter::repeat_n(fill, n));
+            }
+            Padding::Nulls => {
+                let n = m - len % m;
+                let symbols = self.alphabet.symbols();
+                let mut rng = Rng::new(fnv1a(src));
+                out.extend((1..n).map(|_| symbols[rng.below(symbols.len())]));
+                out.push(symbols[n - 1]);
+            }
+        }
+        Ok(out.into_bytes())
+    }
+
+    /// Returns the length of the plaintext in `text` once its padding is removed.
+    ///
+    fn unpad(&self, text: &[u8]) -> Result<usize, CipherError> {
+        let m = self.multiple();
+        let count = match self.padding {
+            Padding::None => 0,
+            Padding::Fill(fill) | Padding::Rectangle { fill, .. } => {
+                utf8::decode(text)?.chars().rev().take(m - 1).take_while(|&c| c == fill).count()
+            }
+            Padding::Nulls => {
+                let last = utf8::decode(text)?.chars().next_back().ok_or(CipherError::BadPadding)?;
+                let n = self.alphabet.index(last).ok_or(CipherError::BadPadding)? + 1;
+                if n > m || n > utf8::char_count(text) {
+                    return Err(CipherError::BadPadding);
+                }
+                n
+            }
+        };
+        let text = utf8::decode(text)?;
+        Ok(text.char_indices().rev().take(count).last().map_or(text.len(), |(i, _)| i))
+    }
+}
+
+/// Checks that `padding` can be used with `cipher` and `alphabet`.
+///
+fn check<C: Block>(cipher: &C, padding: Padding, alphabet: &Alphabet) -> Result<(), CipherError> {
+    match padding {
+        Padding::Rectangle { width: 0, .. } => Err(CipherError::EmptyKey("width")),
+        Padding::Nulls if alphabet.len() < cipher.block_size() => {
+            Err(CipherError::KeyTooShort { param: "alphabet", min: cipher.block_size(), got: alphabet.len() })
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Hashes `src` (FNV-1a), so that the nulls depend on the message but the same message
+/// always gets the same ones.
+///
+fn fnv1a(src: &[u8]) -> u64 {
+    src.iter().fold(0xcbf2_9ce4_8422_2325, |h, &b| (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3))
+}
+
+impl<C: Block> Block for Padded<C> {
+    fn block_size(&self) -> usize {
+        self.cipher.block_size()
+    }
+
+    /// The length of the padded plaintext once encrypted.
+    ///
+    fn encrypt_len(&self, src: &[u8]) -> usize {
+        self.pad(src).map_or(src.len(), |p| self.cipher.encrypt_len(&p))
+    }
+
+    fn decrypt_len(&self, src: &[u8]) -> usize {
+        self.cipher.decrypt_len(src)
+    }
+
+    /// The padding adds at most `multiple()` characters.
+    ///
+    fn max_encrypt_len(&self, len: usize) -> usize {
+        self.cipher.max_encrypt_len(len + 4 * self.multiple())
+    }
+
+    fn max_decrypt_len(&self, len: usize) -> usize {
+        self.cipher.max_decrypt_len(len)
+    }
+
+    fn encrypt_chars(&self, chars: usize) -> Option<usize> {
+        let m = self.multiple();
+        let pad = match self.padding {
+            Padding::None => 0,
+            Padding::Fill(_) | Padding::Rectangle { .. } => (m - chars % m) % m,
+            Padding::Nulls => m - chars % m,
+        };
+        self.cipher.encrypt_chars(chars + pad)
+    }
+
+    /// The padding goes at the end of each message.
+    ///
+    fn whole_message(&self) -> bool {
+        self.padding != Padding::None || self.cipher.whole_message()
+    }
+
+    fn alphabet(&self) -> Option<Alphabet> {
+        self.cipher.alphabet()
+    }
+
+    fn normalizer(&self) -> Normalizer {
+        self.cipher.normalizer()
+    }
+
+    /// Pads `src`, then encrypts it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EndsWithFill` if `src` ends with the fill letter and needs padding, and
+    /// any error of the cipher.
+    ///
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.try_encrypt_traced(dst, src, &mut NoTrace)
+    }
+
+    /// Decrypts `src`, then removes the padding.
+    ///
+    /// # Errors
+    ///
+    /// Returns `BadPadding` if the last null does not tell a possible number of nulls,
+    /// and any error of the cipher.
+    ///
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.try_decrypt_traced(dst, src, &mut NoTrace)
+    }
+
+    /// Reports the padding as a stage of its own, before the ones of the cipher.
+    ///
+    fn try_encrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        let padded = match self.pad(src) {
+            Ok(p) => p,
+            Err(e) => return trace::report(tracer, "Padding", src, dst, Err(e)),
+        };
+        tracer.stage("Padding", src, Ok(&padded));
+        check_dst(dst, self.cipher.encrypt_len(&padded))?;
+        self.cipher.try_encrypt_traced(dst, &padded, tracer)
+    }
+
+    /// Reports the removal of the padding as a stage of its own, after the ones of the
+    /// cipher.
+    ///
+    fn try_decrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        let n = self.cipher.try_decrypt_traced(dst, src, tracer)?;
+        let res = self.unpad(&dst[..n]);
+        tracer.stage("Padding", &dst[..n], res.as_ref().map(|&len| &dst[..len]));
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BlockExt, CaesarCipher, PlayfairCipher, Solitaire, Trace, Transposition};
+
+    use rstest::rstest;
+
+    #[rstest]
+    #[case(Padding::None, "none")]
+    #[case(Padding::Fill('X'), "fill X")]
+    #[case(Padding::Nulls, "nulls")]
+    #[case(Padding::Rectangle { width: 5, fill: 'Q' }, "rectangle 5 Q")]
+    fn test_padding_str(#[case] padding: Padding, #[case] s: &str) {
+        assert_eq!(padding.to_string(), s);
+        assert_eq!(s.parse::<Padding>(), Ok(padding));
+    }
+
+    #[rstest]
+    #[case("fill")]
+    #[case("fill XY")]
+    #[case("rectangle X 5")]
+    #[case("random")]
+    fn test_padding_str_errors(#[case] s: &str) {
+        assert_eq!(s.parse::<Padding>(), Err(CipherError::InvalidParameter { param: "padding", value: s.to_string() }));
+    }
+
+    #[rstest]
+    #[case(Padding::None, "ATTACKATDAWN", "ATTACKATDAWN")]
+    #[case(Padding::Fill('X'), "RETREAT", "RETREATXXXXX")]
+    #[case(Padding::Fill('X'), "ATTACKATDAWNNOW", "ATTACKATDAWNNOWXXX")]
+    #[case(Padding::Fill('X'), "ATTACK", "ATTACK")]
+    #[case(Padding::Rectangle { width: 5, fill: 'Q' }, "ATTACKATDAWN", "ATTACKATDAWNQQQ")]
+    fn test_padded_transposition(#[case] padding: Padding, #[case] pt: &str, #[case] padded: &str) {
+        let t = Transposition::new("SUBWAY").unwrap();
+        let c = Padded::new(Transposition::new("SUBWAY").unwrap(), padding).unwrap();
+        let ct = c.encrypt_str(pt).unwrap();
+        assert_eq!(ct, t.encrypt_str(padded).unwrap());
+        assert_eq!(c.decrypt_str(&ct).unwrap(), pt);
+    }
+
+    #[test]
+    fn test_padded_nulls() {
+        let c = Padded::new(Transposition::new("SUBWAY").unwrap(), Padding::Nulls).unwrap();
+        for pt in ["A", "ATTACK", "ATTACKATDAWN", "ATTACKATDAWNNOW"] {
+            let padded = c.pad(pt.as_bytes()).unwrap();
+            assert!(padded.starts_with(pt.as_bytes()));
+            assert_eq!(padded.len() % 6, 0);
+            assert_eq!(c.pad(pt.as_bytes()).unwrap(), padded);
+
+            let ct = c.encrypt_str(pt).unwrap();
+            assert_eq!(c.decrypt_str(&ct).unwrap(), pt);
+        }
+        // A whole block is added to an aligned message, ending with the 6th letter
+        assert_eq!(c.pad(b"ATTACK").unwrap()[6..].last(), Some(&b'F'));
+        assert_eq!(c.decrypt_str("ATTACKATDAWZ"), Err(CipherError::BadPadding));
+    }
+
+    #[test]
+    fn test_padded_playfair() {
+        let pf = PlayfairCipher::new("PLAYFAIREXAMPLE");
+        let c = Padded::new(PlayfairCipher::new("PLAYFAIREXAMPLE"), Padding::Fill('X')).unwrap();
+        let pt = "HIDETHEGOLDINTHETREESTUMP";
+        let ct = c.encrypt_str(pt).unwrap();
+        assert_eq!(ct, pf.encrypt_str(pt).unwrap());
+        assert_eq!(pf.decrypt_str(&ct).unwrap(), format!("{}X", pt));
+        assert_eq!(c.decrypt_str(&ct).unwrap(), pt);
+
+        assert_eq!(c.encrypt_str("BOX"), Err(CipherError::EndsWithFill { fill: 'X' }));
+        // Nothing to add: the text is taken as it is
+        assert_eq!(c.encrypt_str("BOXX").unwrap(), pf.encrypt_str("BOXX").unwrap());
+    }
+
+    #[test]
+    fn test_padded_playfair_nulls() {
+        // The nulls come from the square, which has no J
+        let c = Padded::new(PlayfairCipher::new("PLAYFAIREXAMPLE"), Padding::Nulls).unwrap();
+        let letters = Alphabet::latin().without("J").unwrap();
+        for seed in 0..1000 {
+            let mut rng = Rng::new(seed);
+            let len = 1 + rng.below(20);
+            let pt = rng.word(&letters, len);
+            let ct = c.encrypt_str(&pt).unwrap_or_else(|e| panic!("{}: {}", pt, e));
+            assert_eq!(c.decrypt_str(&ct).unwrap(), pt);
+        }
+    }
+
+    #[test]
+    fn test_padded_groups() {
+        let c = Padded::new(Solitaire::new_unkeyed(), Padding::Rectangle { width: 5, fill: 'X' }).unwrap();
+        let ct = c.encrypt_str("SOLITAIRE").unwrap();
+        assert_eq!(ct.len(), 10);
+        assert_eq!(c.decrypt_str(&ct).unwrap(), "SOLITAIRE");
+
+        // Stream ciphers have no block to fill
+        let c = Padded::new(CaesarCipher::new(3), Padding::Fill('X')).unwrap();
+        assert_eq!(c.encrypt_str("BOX").unwrap(), "ERA");
+        assert_eq!(c.decrypt_str("ERA").unwrap(), "BOX");
+    }
+
+    #[test]
+    fn test_padded_errors() {
+        let t = || Transposition::new("SUBWAY").unwrap();
+        assert_eq!(Padded::new(t(), Padding::Rectangle { width: 0, fill: 'X' }).err(), Some(CipherError::EmptyKey("width")));
+        let a = Alphabet::new("ABCDE").unwrap();
+        assert_eq!(
+            Padded::with_alphabet(t(), Padding::Nulls, &a).err(),
+            Some(CipherError::KeyTooShort { param: "alphabet", min: 6, got: 5 })
+        );
+    }
+
+    #[test]
+    fn test_padded_traced() {
+        let c = Padded::new(Transposition::new("SUBWAY").unwrap(), Padding::Fill('X')).unwrap();
+        let mut trace = Trace::new();
+        let ct = c.encrypt_traced(b"RETREAT", &mut trace).unwrap();
+        assert_eq!(trace.stages().count(), 2);
+        assert!(trace.to_string().starts_with("Padding: RETREAT -> RETREATXXXXX\n"));
+
+        trace.clear();
+        c.decrypt_traced(&ct, &mut trace).unwrap();
+        assert!(trace.to_string().ends_with("Padding: RETREATXXXXX -> RETREAT\n"));
+    }
+}
@@ -27,60 +27,118 @@
 //! let mut ciphertext = vec![0u8; plaintext.len()];
 //! cipher.encrypt(&mut ciphertext, plaintext);
 //! ```
-//! 
-use crate::Block;
-use crate::helpers;
+//!
+//! # Variants
+//!
+//! The square can merge another pair of letters, leave one out or hold the digits
+//! too ([`PlayfairSquare`]).  By default the plaintext must already have its doubled
+//! letters split, as above, and only gets a filler at the end if its length is odd;
+//! [`PlayfairCipher::split_doubles`] does the splitting, with fillers chosen by
+//! [`PlayfairCipher::with_fillers`], and [`PlayfairCipher::strip_fillers`] removes
+//! them after decryption.
+//!
+//! ```rust
+//! use old_crypto_rs::{BlockExt, PlayfairCipher, PlayfairSquare};
+//!
+//! let cipher = PlayfairCipher::with_square("PLAYFAIREXAMPLE", PlayfairSquare::Omit('Q'))
+//!     .unwrap()
+//!     .with_fillers('X', 'Z')
+//!     .unwrap()
+//!     .split_doubles(true)
+//!     .strip_fillers(true);
+//! let ct = cipher.encrypt_str("LANNONCE").unwrap();
+//! assert_eq!(ct, "AYOEOSKDMV");
+//! assert_eq!(cipher.decrypt_str(&ct).unwrap(), "LANNONCE");
+//! ```
+//!
+use crate::{Alphabet, Block, CipherError, Grid, Normalizer, Rekey, Rng};
+use crate::utf8::{self, Output};
+
+
+/// The letters of a Playfair square.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlayfairSquare {
+    /// The 25 letters without `from`, written `into` in the plaintext: J merged with
+    /// I by default.
+    Merge { from: char, into: char },
+    /// The 25 letters without this one, left out of the plaintext, e.g. Q.
+    Omit(char),
+    /// The 26 letters and the 10 digits in a 6×6 square.
+    Digits,
+}
 
-const ALPHABET: &str = "ABCDEFGHIKLMNOPQRSTUVWXYZ";
-const OP_ENCRYPT: u8 = 1;
-const OP_DECRYPT: u8 = 4;
-const CODE_WORD: &str = "01234";
+impl Default for PlayfairSquare {
+    fn default() -> Self {
+        PlayfairSquare::Merge { from: 'J', into: 'I' }
+    }
+}
 
-/// Playfair cipher implementation using a 5×5 keyed matrix.
+/// Playfair cipher implementation using a keyed square matrix, 5×5 by default.
 ///
-/// The `PlayfairCipher` struct holds the cipher key and maintains bidirectional tables
-/// between characters and their positions in the 5×5 Playfair matrix. This allows for
-/// efficient encryption and decryption operations.
+/// The `PlayfairCipher` struct holds the keyed alphabet filling the matrix row by row,
+/// which gives both the position of a character and the character at a position.
 ///
 /// # Fields
 ///
-/// * `key` - The condensed key string used to build the cipher matrix
-/// * `i2c` - Table from character (as u8) to its `Couple` position in the matrix
-/// * `c2i` - Table from matrix position to its character (as u8)
+/// * `alphabet` - The symbols of the matrix, in their original order
+/// * `square` - The keyed alphabet, i.e. the matrix read row by row
+/// * `side` - The number of rows and columns of the matrix
+/// * `merge` - The letter missing from the matrix and what the normalizer writes
+///   instead, nothing to leave it out
+/// * `double`, `odd` - The fillers splitting doubled letters and completing the last
+///   pair
+/// * `split`, `strip` - Whether doubled letters are split on encryption and the
+///   fillers removed on decryption
 /// 
 pub struct PlayfairCipher {
-    #[allow(dead_code)]
-    key: String,
-    i2c: [Couple; 256],
-    c2i: [u8; 25],
+    alphabet: Alphabet,
+    square: Alphabet,
+    side: usize,
+    merge: Option<(char, String)>,
+    double: char,
+    odd: char,
+    split: bool,
+    strip: bool,
 }
 
-/// Represents a coordinate pair (row, column) in the 5×5 Playfair matrix.
-///
-/// A `Couple` is used to represent either:
-/// - The position of a character in the Playfair matrix (as a coordinate)
-/// - A pair of characters to be encrypted/decrypted (as indices)
+/// Represents a coordinate pair (row, column) in the Playfair matrix.
 ///
 /// # Fields
 ///
-/// * `r` - Row index (0-4) or first character of a bigram
-/// * `c` - Column index (0-4) or second character of a bigram
+/// * `r` - Row index
+/// * `c` - Column index
 /// 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
 struct Couple {
-    r: u8,
-    c: u8,
+    r: usize,
+    c: usize,
 }
 
-const INVALID_COUPLE: Couple = Couple { r: 0xFF, c: 0xFF };
-
 impl PlayfairCipher {
-    fn lookup(&self, ch: u8) -> Couple {
-        let couple = self.i2c[ch as usize];
-        if couple.r == 0xFF {
-            panic!("invalid character");
+    /// Returns the position of `ch` in the matrix, `pos` being its offset in the input.
+    ///
+    fn lookup(&self, ch: char, pos: usize) -> Result<Couple, CipherError> {
+        let ind = self.square.index(ch).ok_or(CipherError::InvalidChar { ch, pos })?;
+        Ok(Couple { r: ind / self.side, c: ind % self.side })
+    }
+
+    /// Returns the character at position `at` in the matrix.
+    ///
+    fn at(&self, at: Couple) -> char {
+        self.square.symbols()[at.r * self.side + at.c]
+    }
+
+    /// Returns the character padding odd-length plaintext: 'X', or the last letter of
+    /// the alphabet if it has no 'X'.
+    ///
+    fn filler(&self) -> char {
+        let symbols = self.alphabet.symbols();
+        if self.alphabet.contains('X') {
+            'X'
+        } else {
+            symbols.iter().rev().copied().find(|c| c.is_alphabetic()).unwrap_or(symbols[symbols.len() - 1])
         }
-        couple
     }
 
     /// Transforms a pair of characters using the Playfair cipher rules.
@@ -90,44 +148,111 @@ impl PlayfairCipher {
     ///
     /// # Arguments
     ///
-    /// * `pt` - A `Couple` where `r` and `c` represent the two characters to transform
-    /// * `opt` - Operation mode: `OP_ENCRYPT` (1) for encryption, `OP_DECRYPT` (4) for decryption
+    /// * `pt` - The two characters to transform, with their offset in the input for
+    ///   error reporting
+    /// * `opt` - Shift along rows and columns: 1 for encryption, `side - 1` for decryption
     ///
     /// # Returns
     ///
-    /// A `Couple` containing the transformed character pair
+    /// The transformed character pair, or `CipherError::InvalidChar` if one of the
+    /// characters is not in the matrix
     ///
     /// # Transformation Rules
     ///
-    /// 1. **Same row**: Shift each character by `opt` positions to the right (modulo 5)
-    /// 2. **Same column**: Shift each character by `opt` positions down (modulo 5)
+    /// 1. **Same row**: Shift each character by `opt` positions to the right (modulo `side`)
+    /// 2. **Same column**: Shift each character by `opt` positions down (modulo `side`)
     /// 3. **Rectangle**: Swap the columns of the two characters
     /// 
-    fn transform(&self, pt: Couple, opt: u8) -> Couple {
-        let bg1 = self.lookup(pt.r);
-        let bg2 = self.lookup(pt.c);
-        if bg1.r == bg2.r {
-            let ct1 = Couple { r: bg1.r, c: (bg1.c + opt) % 5 };
-            let ct2 = Couple { r: bg2.r, c: (bg2.c + opt) % 5 };
-            return Couple {
-                r: self.c2i[(ct1.r as usize) * 5 + ct1.c as usize],
-                c: self.c2i[(ct2.r as usize) * 5 + ct2.c as usize],
-            };
+    fn transform(&self, pt: [(usize, char); 2], opt: usize) -> Result<[char; 2], CipherError> {
+        let side = self.side;
+        let bg1 = self.lookup(pt[0].1, pt[0].0)?;
+        let bg2 = self.lookup(pt[1].1, pt[1].0)?;
+        let (ct1, ct2) = if bg1.r == bg2.r {
+            (Couple { r: bg1.r, c: (bg1.c + opt) % side }, Couple { r: bg2.r, c: (bg2.c + opt) % side })
+        } else if bg1.c == bg2.c {
+            (Couple { r: (bg1.r + opt) % side, c: bg1.c }, Couple { r: (bg2.r + opt) % side, c: bg2.c })
+        } else {
+            (Couple { r: bg1.r, c: bg2.c }, Couple { r: bg2.r, c: bg1.c })
+        };
+        Ok([self.at(ct1), self.at(ct2)])
+    }
+
+    /// Returns the filler put between two `ch`: the one for doubled letters, or the
+    /// other one if `ch` is that filler, or 'Q' if both fillers are `ch`.
+    ///
+    fn splitter(&self, ch: char) -> char {
+        match ch {
+            ch if ch != self.double => self.double,
+            ch if ch != self.odd => self.odd,
+            _ => self.spare(),
         }
-        if bg1.c == bg2.c {
-            let ct1 = Couple { r: (bg1.r + opt) % 5, c: bg1.c };
-            let ct2 = Couple { r: (bg2.r + opt) % 5, c: bg2.c };
-            return Couple {
-                r: self.c2i[(ct1.r as usize) * 5 + ct1.c as usize],
-                c: self.c2i[(ct2.r as usize) * 5 + ct2.c as usize],
-            };
+    }
+
+    /// Returns the filler completing a last pair starting with `ch`.
+    ///
+    fn closer(&self, ch: char) -> char {
+        if self.split && ch == self.odd { self.splitter(ch) } else { self.odd }
+    }
+
+    /// Returns the filler splitting a doubled filler when both fillers are the same:
+    /// 'Q', or the last other letter of the alphabet if it has no 'Q'.
+    ///
+    fn spare(&self) -> char {
+        let symbols = self.alphabet.symbols();
+        if self.double != 'Q' && self.alphabet.contains('Q') {
+            'Q'
+        } else {
+            symbols.iter().rev().copied().find(|&c| c != self.double).unwrap_or(self.double)
         }
-        let ct1 = Couple { r: bg1.r, c: bg2.c };
-        let ct2 = Couple { r: bg2.r, c: bg1.c };
-        Couple {
-            r: self.c2i[(ct1.r as usize) * 5 + ct1.c as usize],
-            c: self.c2i[(ct2.r as usize) * 5 + ct2.c as usize],
+    }
+
+    /// Returns the characters of `text` with their offset, doubled letters split if
+    /// asked to and completed to an even number.
+    ///
+    fn prepare(&self, text: &str) -> Vec<(usize, char)> {
+        let mut chars: Vec<(usize, char)> = Vec::with_capacity(text.len() + 1);
+        for (i, ch) in text.char_indices() {
+            if self.split && chars.len() % 2 == 1 && chars.last().is_some_and(|&(_, c)| c == ch) && self.splitter(ch) != ch {
+                chars.push((i, self.splitter(ch)));
+            }
+            chars.push((i, ch));
         }
+        if let Some(&(_, last)) = chars.last().filter(|_| chars.len() % 2 == 1) {
+            chars.push((text.len(), self.closer(last)));
+        }
+        chars
+    }
+
+    /// Removes from a decrypted `text` the fillers between two identical letters and
+    /// the one completing the last pair.
+    ///
+    /// This is a guess: a plaintext with the same letters around a filler, or ending
+    /// with it, loses them too.
+    ///
+    fn strip(&self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let n = chars.len();
+        let mut out = String::with_capacity(text.len());
+        for (k, &c) in chars.iter().enumerate() {
+            let split = k % 2 == 1 && k + 1 < n && chars[k - 1] == chars[k + 1] && c == self.splitter(chars[k - 1]);
+            let closing = k == n - 1 && k % 2 == 1 && c == self.closer(chars[k - 1]);
+            if !split && !closing {
+                out.push(c);
+            }
+        }
+        out
+    }
+
+    /// Runs the pairs of `chars` through `transform` into `dst`.
+    ///
+    fn process(&self, dst: &mut [u8], chars: &[(usize, char)], opt: usize) -> Result<usize, CipherError> {
+        let mut out = Output::new(dst);
+        for pair in chars.chunks(2) {
+            let [a, b] = self.transform([pair[0], pair[1]], opt)?;
+            out.push(a);
+            out.push(b);
+        }
+        out.finish()
     }
 
     /// Creates a new Playfair cipher with the specified key.
@@ -154,27 +279,165 @@ impl PlayfairCipher {
     /// ```
     /// 
     pub fn new(key: &str) -> Self {
-        let condensed_key = helpers::condense(&format!("{}{}", key, ALPHABET));
-        let mut i2c = [INVALID_COUPLE; 256];
-        let mut c2i = [0u8; 25];
-        
-        let mut ind = 0;
-        let key_bytes = condensed_key.as_bytes();
-        for i in 0..CODE_WORD.len() {
-            for j in 0..CODE_WORD.len() {
-                let c = key_bytes[ind];
-                let couple = Couple { r: i as u8, c: j as u8 };
-                i2c[c as usize] = couple;
-                c2i[i * 5 + j] = c;
-                ind += 1;
+        Self::with_alphabet(key, &Alphabet::latin().without("J").unwrap()).unwrap()
+    }
+
+    /// Creates a Playfair cipher whose matrix holds `alphabet` instead of the 25 letters
+    /// without J, e.g. the 36 letters and digits for a 6×6 square.
+    ///
+    /// Key characters that are not in the alphabet are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::BadAlphabetLength` if the alphabet length is not a square
+    /// (of at least 2×2).
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use old_crypto_rs::{Alphabet, BlockExt, PlayfairCipher};
+    ///
+    /// let cipher = PlayfairCipher::with_alphabet("PLAYFAIR", &Alphabet::base36()).unwrap();
+    /// let ct = cipher.encrypt_str("MEETAT1030").unwrap();
+    /// assert_eq!(cipher.decrypt_str(&ct).unwrap(), "MEETAT1030");
+    ///
+    /// // 6×6 Cyrillic square: the 33 letters and three signs
+    /// let a = Alphabet::new("АБВГДЕЁЖЗИЙКЛМНОПРСТУФХЦЧШЩЪЫЬЭЮЯ.,-").unwrap();
+    /// let cipher = PlayfairCipher::with_alphabet("ШИФР", &a).unwrap();
+    /// let ct = cipher.encrypt_str("ПРИВЕТ").unwrap();
+    /// assert_eq!(cipher.decrypt_str(&ct).unwrap(), "ПРИВЕТ");
+    /// ```
+    ///
+    pub fn with_alphabet(key: &str, alphabet: &Alphabet) -> Result<Self, CipherError> {
+        let side = (1..).find(|n| n * n >= alphabet.len()).unwrap_or(0);
+        if side < 2 || side * side != alphabet.len() {
+            let expected = side.max(2) * side.max(2);
+            return Err(CipherError::BadAlphabetLength { param: "alphabet", expected, got: alphabet.len() });
+        }
+        let merge = (!alphabet.contains('J') && alphabet.contains('I')).then(|| ('J', "I".to_string()));
+
+        let mut c = PlayfairCipher {
+            alphabet: alphabet.clone(),
+            square: alphabet.keyed(key),
+            side,
+            merge,
+            double: 'X',
+            odd: 'X',
+            split: false,
+            strip: false,
+        };
+        c.odd = c.filler();
+        c.double = c.odd;
+        Ok(c)
+    }
+
+    /// Creates a Playfair cipher over one of the usual squares.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UnknownSymbol` for `square` if a letter of `Merge` or `Omit` is not
+    /// one of A-Z, or `into` is `from`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use old_crypto_rs::{Block, BlockExt, PlayfairCipher, PlayfairSquare};
+    ///
+    /// let cipher = PlayfairCipher::with_square("PLAYFAIR", PlayfairSquare::Merge { from: 'V', into: 'U' }).unwrap();
+    /// let ct = cipher.encrypt_str(&cipher.normalizer().normalize("Vive la reine")).unwrap();
+    /// assert_eq!(cipher.decrypt_str(&ct).unwrap(), "UIUELAREINEX");
+    /// ```
+    ///
+    pub fn with_square(key: &str, square: PlayfairSquare) -> Result<Self, CipherError> {
+        let latin = Alphabet::latin();
+        let letter = |ch: char| if latin.contains(ch) { Ok(ch) } else { Err(CipherError::UnknownSymbol { param: "square", symbol: ch }) };
+        let (alphabet, merge) = match square {
+            PlayfairSquare::Merge { from, into } => {
+                let alphabet = latin.without(&letter(from)?.to_string())?;
+                if !alphabet.contains(into) {
+                    return Err(CipherError::UnknownSymbol { param: "square", symbol: into });
+                }
+                (alphabet, Some((from, into.to_string())))
             }
+            PlayfairSquare::Omit(ch) => (latin.without(&letter(ch)?.to_string())?, Some((ch, String::new()))),
+            PlayfairSquare::Digits => (Alphabet::base36(), None),
+        };
+        let mut c = Self::with_alphabet(key, &alphabet)?;
+        c.merge = merge;
+        Ok(c)
+    }
+
+    /// Sets the filler put between doubled letters and the one completing an odd
+    /// plaintext, both 'X' by default (the last letter of the alphabet without 'X').
+    ///
+    /// Doubled fillers get the other one, and so does a plaintext ending with the odd
+    /// filler when doubled letters are split.  When both are the same letter, 'Q'
+    /// takes that place (see the default).
+    ///
+    /// # Errors
+    ///
+    /// Returns `UnknownSymbol` for `filler` if one of them is not in the matrix.
+    ///
+    pub fn with_fillers(mut self, double: char, odd: char) -> Result<Self, CipherError> {
+        if let Some(symbol) = [double, odd].into_iter().find(|&c| !self.alphabet.contains(c)) {
+            return Err(CipherError::UnknownSymbol { param: "filler", symbol });
         }
+        self.double = double;
+        self.odd = odd;
+        Ok(self)
+    }
+
+    /// Splits pairs of identical letters on encryption, which Playfair can not
+    /// encrypt: `LANNONCE` becomes `LA NX NO NC EX`.
+    ///
+    pub fn split_doubles(mut self, yes: bool) -> Self {
+        self.split = yes;
+        self
+    }
+
+    /// Removes the fillers on decryption: between two identical letters at the end
+    /// of a pair, and at the end of the text.  This is a guess, which also removes
+    /// such letters from a plaintext that had them.
+    ///
+    pub fn strip_fillers(mut self, yes: bool) -> Self {
+        self.strip = yes;
+        self
+    }
+
+    /// Creates a Playfair cipher whose square is a random arrangement of the 25
+    /// letters without J.
+    ///
+    pub fn random(rng: &mut Rng) -> Self {
+        Self::new(&rng.permutation(&Alphabet::latin().without("J").unwrap()))
+    }
+
+    /// Returns the keyed matrix, row by row.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use old_crypto_rs::PlayfairCipher;
+    ///
+    /// let cipher = PlayfairCipher::new("PLAYFAIREXAMPLE");
+    /// assert_eq!(cipher.matrix()[1], ['I', 'R', 'E', 'X', 'M']);
+    /// assert_eq!(
+    ///     cipher.grid().to_string(),
+    ///     "P L A Y F\nI R E X M\nB C D G H\nK N O Q S\nT U V W Z\n"
+    /// );
+    /// ```
+    ///
+    pub fn matrix(&self) -> Vec<Vec<char>> {
+        self.square.symbols().chunks(self.side).map(<[char]>::to_vec).collect()
+    }
 
-        PlayfairCipher {
-            key: condensed_key,
-            i2c,
-            c2i,
+    /// Returns the keyed matrix as a [`Grid`], without labels.
+    ///
+    pub fn grid(&self) -> Grid {
+        let mut g = Grid::new::<char>(&[]);
+        for row in self.matrix() {
+            g.push_row("", &row);
         }
+        g
     }
 }
 
@@ -184,22 +447,81 @@ impl Block for PlayfairCipher {
         2
     }
 
+    /// The symbols of the matrix, without J for the default one.
+    ///
+    fn alphabet(&self) -> Option<Alphabet> {
+        Some(self.alphabet.clone())
+    }
+
+    /// The default square has 25 letters, J is merged with I.
+    ///
+    fn normalizer(&self) -> Normalizer {
+        let n = self.alphabet.normalizer();
+        match &self.merge {
+            Some((from, into)) => n.substitute(*from, into),
+            None => n,
+        }
+    }
+
+    /// Odd-length plaintext gets an 'X' appended, so the output is rounded up to an even
+    /// number of characters, each as wide as the widest symbol.  Splitting doubled
+    /// letters adds one more for each of them.
+    fn encrypt_len(&self, src: &[u8]) -> usize {
+        let n = match std::str::from_utf8(src) {
+            Ok(text) if self.split => self.prepare(text).len(),
+            _ => {
+                let n = utf8::char_count(src);
+                n + n % 2
+            }
+        };
+        n * self.square.max_utf8_len()
+    }
+
+    /// Every character is replaced by a symbol, as wide as the widest one.
+    fn decrypt_len(&self, src: &[u8]) -> usize {
+        utf8::char_count(src) * self.square.max_utf8_len()
+    }
+
+    /// Splitting gives at most one filler per letter, otherwise only the last pair
+    /// may get one.
+    fn max_encrypt_len(&self, len: usize) -> usize {
+        let n = if self.split { 2 * len } else { len + 1 };
+        n * self.square.max_utf8_len()
+    }
+
+    fn max_decrypt_len(&self, len: usize) -> usize {
+        len * self.square.max_utf8_len()
+    }
+
+    /// Splitting doubled letters depends on the text.
+    fn encrypt_chars(&self, chars: usize) -> Option<usize> {
+        (!self.split).then_some(chars + chars % 2)
+    }
+
     /// Encrypts plaintext using the Playfair cipher.
     ///
     /// This method processes the input plaintext in pairs of characters (digraphs) and applies
     /// the Playfair transformation rules. If the plaintext has an odd length, an 'X' is
-    /// automatically appended as padding.
+    /// automatically appended as padding (the last letter of the alphabet if it has no 'X').
+    /// Decryption keeps it: wrap the cipher in a [`Padded`](crate::Padded) to have it
+    /// removed.
     ///
     /// # Arguments
     ///
     /// * `dst` - Destination buffer where the ciphertext will be written. Must be at least as
-    ///           large as the source length (rounded up to the nearest even number if odd).
-    /// * `src` - Source plaintext bytes to encrypt. Each byte should represent an uppercase
-    ///           letter from the Playfair alphabet.
+    ///   large as the source length (rounded up to the nearest even number if odd).
+    /// * `src` - Source plaintext to encrypt, as UTF-8. Each character should be a symbol
+    ///   of the Playfair alphabet.
     ///
     /// # Returns
     ///
-    /// The number of bytes written to the destination buffer (always even).
+    /// The number of bytes written to the destination buffer (always even for ASCII).
+    ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::InvalidUtf8` if `src` is not UTF-8, `CipherError::InvalidChar`
+    /// for a character that is not in the matrix (e.g. 'J' or a lowercase letter) and
+    /// `CipherError::BufferTooSmall` if `dst` can not hold the padded output.
     ///
     /// # Example
     ///
@@ -213,18 +535,9 @@ impl Block for PlayfairCipher {
     /// assert_eq!(written, 12); // 11 chars + 1 'X' padding = 12
     /// ```
     ///
-    fn encrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        let mut src_vec = src.to_vec();
-        if src_vec.len() % 2 == 1 {
-            src_vec.push(b'X');
-        }
-
-        for i in (0..src_vec.len()).step_by(2) {
-            let bg = self.transform(Couple { r: src_vec[i], c: src_vec[i+1] }, OP_ENCRYPT);
-            dst[i] = bg.r;
-            dst[i+1] = bg.c;
-        }
-        src_vec.len()
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        let chars = self.prepare(utf8::decode(src)?);
+        self.process(dst, &chars, 1)
     }
 
     /// Decrypts ciphertext using the Playfair cipher.
@@ -235,18 +548,19 @@ impl Block for PlayfairCipher {
     /// # Arguments
     ///
     /// * `dst` - Destination buffer where the plaintext will be written. Must be at least as
-    ///           large as the source length.
-    /// * `src` - Source ciphertext bytes to decrypt. Must have an even length, as Playfair
-    ///           operates on character pairs.
+    ///   large as the source length.
+    /// * `src` - Source ciphertext to decrypt. Must have an even number of characters, as
+    ///   Playfair operates on character pairs.
     ///
     /// # Returns
     ///
     /// The number of bytes written to the destination buffer (equal to source length).
     ///
-    /// # Panics
+    /// # Errors
     ///
-    /// Panics if the source ciphertext has an odd number of bytes, as this violates the
-    /// Playfair cipher's requirement to operate on digraphs.
+    /// Returns `CipherError::OddLength` if the source ciphertext has an odd number of characters,
+    /// as this violates the Playfair cipher's requirement to operate on digraphs, and
+    /// `CipherError::InvalidChar` or `CipherError::BufferTooSmall` as for encryption.
     ///
     /// # Example
     ///
@@ -260,23 +574,36 @@ impl Block for PlayfairCipher {
     /// assert_eq!(written, ciphertext.len());
     /// ```
     ///
-    fn decrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        if src.len() % 2 == 1 {
-            panic!("odd number of elements");
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        let chars: Vec<(usize, char)> = utf8::decode(src)?.char_indices().collect();
+        if chars.len() % 2 == 1 {
+            return Err(CipherError::OddLength { len: chars.len() });
         }
-
-        for i in (0..src.len()).step_by(2) {
-            let bg = self.transform(Couple { r: src[i], c: src[i+1] }, OP_DECRYPT);
-            dst[i] = bg.r;
-            dst[i+1] = bg.c;
+        let n = self.process(dst, &chars, self.side - 1)?;
+        if !self.strip {
+            return Ok(n);
         }
-        src.len()
+        let pt = self.strip(utf8::decode(&dst[..n])?);
+        dst[..pt.len()].copy_from_slice(pt.as_bytes());
+        Ok(pt.len())
+    }
+}
+
+/// The matrix is keyed again, over the same alphabet and with the same fillers.
+///
+impl Rekey for PlayfairCipher {
+    fn rekey(&mut self, key: &str) -> Result<(), CipherError> {
+        self.square.set_keyed(&self.alphabet, key);
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::BlockExt;
+
+    use rstest::rstest;
 
     #[test]
     fn test_new_cipher() {
@@ -289,6 +616,14 @@ mod tests {
         assert_eq!(c.block_size(), 2);
     }
 
+    #[test]
+    fn test_playfair_cipher_lengths() {
+        let c = PlayfairCipher::new("PLAYFAIREXAMPLE");
+        assert_eq!(c.encrypt_len(b"HID"), 4);
+        assert_eq!(c.encrypt_len(b"HIDE"), 4);
+        assert_eq!(c.decrypt_len(b"BMGE"), 4);
+    }
+
     #[test]
     fn test_playfair_cipher_encrypt() {
         let c = PlayfairCipher::new("PLAYFAIREXAMPLE");
@@ -327,4 +662,175 @@ mod tests {
         let mut dst = vec![0u8; 3];
         c.decrypt(&mut dst, ct);
     }
+
+    #[test]
+    fn test_playfair_cipher_try_errors() {
+        let c = PlayfairCipher::new("PLAYFAIREXAMPLE");
+        let mut dst = vec![0u8; 6];
+        assert_eq!(c.try_encrypt(&mut dst, b"HIJACK"), Err(CipherError::InvalidChar { ch: 'J', pos: 2 }));
+        assert_eq!(c.try_encrypt(&mut dst, b"HIDe"), Err(CipherError::InvalidChar { ch: 'e', pos: 3 }));
+        assert_eq!(c.try_encrypt(&mut dst[..2], b"HID"), Err(CipherError::BufferTooSmall { needed: 4, got: 2 }));
+        assert_eq!(c.try_decrypt(&mut dst, b"BMO"), Err(CipherError::OddLength { len: 3 }));
+        assert_eq!(c.try_decrypt(&mut dst, b"BMOD"), Ok(4));
+    }
+
+    #[test]
+    fn test_playfair_with_alphabet() {
+        let c = PlayfairCipher::with_alphabet("PLAYFAIR", &Alphabet::base36()).unwrap();
+        assert_eq!(c.side, 6);
+        assert_eq!(c.encrypt_str("HI10AK").unwrap(), "OP21CT");
+        assert_eq!(c.decrypt_str("OP21CT").unwrap(), "HI10AK");
+        assert_eq!(c.normalizer(), Normalizer::alphanumeric());
+    }
+
+    #[test]
+    fn test_playfair_with_alphabet_errors() {
+        assert_eq!(
+            PlayfairCipher::with_alphabet("KEY", &Alphabet::latin()).err(),
+            Some(CipherError::BadAlphabetLength { param: "alphabet", expected: 36, got: 26 })
+        );
+        assert_eq!(
+            PlayfairCipher::with_alphabet("KEY", &Alphabet::new("A").unwrap()).err(),
+            Some(CipherError::BadAlphabetLength { param: "alphabet", expected: 4, got: 1 })
+        );
+    }
+
+    #[test]
+    fn test_playfair_non_ascii() {
+        let a = Alphabet::new("АБВГДЕЁЖЗИЙКЛМНОПРСТУФХЦЧШЩЪЫЬЭЮЯ.,-").unwrap();
+        let c = PlayfairCipher::with_alphabet("ШИФР", &a).unwrap();
+        assert_eq!(c.side, 6);
+        assert_eq!(c.square.to_string(), "ШИФРАБВГДЕЁЖЗЙКЛМНОПСТУХЦЧЩЪЫЬЭЮЯ.,-");
+        assert_eq!(c.filler(), 'Я');
+
+        // Same row, same column and rectangle
+        assert_eq!(c.encrypt_str("ШФ").unwrap(), "ИР");
+        assert_eq!(c.encrypt_str("ШВ").unwrap(), "ВЗ");
+        assert_eq!(c.encrypt_str("ИД").unwrap(), "ФГ");
+
+        let pt = "ВСТРЕЧАВПОЛДЕНЬ";
+        let ct = c.encrypt_str(pt).unwrap();
+        assert!(c.encrypt_len(pt.as_bytes()) >= ct.len());
+        assert_eq!(c.decrypt_str(&ct).unwrap(), format!("{}Я", pt));
+        assert_eq!(
+            c.encrypt_str("ВСТРЕЧА J"),
+            Err(CipherError::InvalidChar { ch: ' ', pos: 14 })
+        );
+        assert_eq!(c.try_decrypt(&mut [0u8; 8], "ИРИ".as_bytes()), Err(CipherError::OddLength { len: 3 }));
+    }
+
+    #[test]
+    fn test_playfair_grid() {
+        let a = Alphabet::new("АБВГДЕЁЖЗИЙКЛМНОПРСТУФХЦЧШЩЪЫЬЭЮЯ.,-").unwrap();
+        let c = PlayfairCipher::with_alphabet("ШИФР", &a).unwrap();
+        let m = c.matrix();
+        assert_eq!(m.len(), 6);
+        assert_eq!(m[0], ['Ш', 'И', 'Ф', 'Р', 'А', 'Б']);
+        assert_eq!(m[5], ['Э', 'Ю', 'Я', '.', ',', '-']);
+        assert!(c.grid().to_string().ends_with("Э Ю Я . , -\n"));
+    }
+
+    #[test]
+    fn test_playfair_rekey() {
+        let mut c = PlayfairCipher::with_alphabet("ABC", &Alphabet::base36()).unwrap();
+        for key in ["PLAYFAIR", "MONARCHY1942", ""] {
+            c.rekey(key).unwrap();
+            let fresh = PlayfairCipher::with_alphabet(key, &Alphabet::base36()).unwrap();
+            assert_eq!(c.matrix(), fresh.matrix());
+            assert_eq!(c.encrypt_str("MEETAT1030").unwrap(), fresh.encrypt_str("MEETAT1030").unwrap());
+        }
+    }
+
+    #[test]
+    fn test_playfair_split_doubles() {
+        let c = PlayfairCipher::new("PLAYFAIREXAMPLE").split_doubles(true);
+        let prepared: String = c.prepare("LANNONCE").into_iter().map(|(_, ch)| ch).collect();
+        assert_eq!(prepared, "LANXNONCEX");
+        let ct = c.encrypt_str("LANNONCE").unwrap();
+        assert_eq!(c.encrypt_len(b"LANNONCE"), ct.len());
+        assert_eq!(c.decrypt_str(&ct).unwrap(), "LANXNONCEX");
+
+        // Already split, as in the usual example
+        let pt = "HIDETHEGOLDINTHETREXESTUMP";
+        assert_eq!(c.encrypt_str(pt).unwrap(), "BMODZBXDNABEKUDMUIXMMOUVIF");
+        assert_eq!(c.encrypt_str("HIDETHEGOLDINTHETREESTUMP").unwrap(), "BMODZBXDNABEKUDMUIXMMOUVIF");
+    }
+
+    #[rstest]
+    #[case("TAXXI", "TAXZXI")]
+    #[case("HEZ", "HEZX")]
+    #[case("BALLOON", "BALXLOON")]
+    #[case("BOOKKEEPER", "BOOKKEEPER")]
+    fn test_playfair_fillers(#[case] pt: &str, #[case] prepared: &str) {
+        let c = PlayfairCipher::new("MONARCHY").with_fillers('X', 'Z').unwrap().split_doubles(true).strip_fillers(true);
+        let p: String = c.prepare(pt).into_iter().map(|(_, ch)| ch).collect();
+        assert_eq!(p, prepared);
+        let ct = c.encrypt_str(pt).unwrap();
+        assert_eq!(ct.len(), prepared.len());
+        assert_eq!(c.decrypt_str(&ct).unwrap(), pt);
+    }
+
+    #[rstest]
+    #[case("XX", "XQXQ")]
+    #[case("TAXXI", "TAXQXI")]
+    #[case("SIX", "SIXQ")]
+    #[case("BALLOON", "BALXLOON")]
+    fn test_playfair_same_fillers(#[case] pt: &str, #[case] prepared: &str) {
+        // Both fillers are 'X' by default
+        let c = PlayfairCipher::new("MONARCHY").split_doubles(true).strip_fillers(true);
+        let p: String = c.prepare(pt).into_iter().map(|(_, ch)| ch).collect();
+        assert_eq!(p, prepared);
+        assert_eq!(c.decrypt_str(&c.encrypt_str(pt).unwrap()).unwrap(), pt);
+    }
+
+    #[test]
+    fn test_playfair_strip_guess() {
+        let c = PlayfairCipher::new("MONARCHY").strip_fillers(true);
+        // A real X between two identical letters, or at the end, is taken for a filler
+        assert_eq!(c.decrypt_str(&c.encrypt_str("AXA").unwrap()).unwrap(), "AA");
+        assert_eq!(c.decrypt_str(&c.encrypt_str("SIX").unwrap()).unwrap(), "SIX");
+    }
+
+    #[test]
+    fn test_playfair_squares() {
+        let c = PlayfairCipher::with_square("KEY", PlayfairSquare::Omit('Q')).unwrap();
+        assert!(!c.alphabet.contains('Q') && c.alphabet.contains('J'));
+        assert_eq!(c.normalizer().normalize("Quiet Jim"), "UIETJIM");
+
+        let c = PlayfairCipher::with_square("KEY", PlayfairSquare::default()).unwrap();
+        assert_eq!(c.matrix(), PlayfairCipher::new("KEY").matrix());
+        assert_eq!(c.normalizer(), PlayfairCipher::new("KEY").normalizer());
+
+        let c = PlayfairCipher::with_square("PLAYFAIR", PlayfairSquare::Digits).unwrap();
+        assert_eq!(c.side, 6);
+        assert_eq!(c.encrypt_str("HI10AK").unwrap(), "OP21CT");
+    }
+
+    #[rstest]
+    #[case(PlayfairSquare::Merge { from: 'J', into: 'J' }, 'J')]
+    #[case(PlayfairSquare::Merge { from: 'é', into: 'E' }, 'é')]
+    #[case(PlayfairSquare::Omit('1'), '1')]
+    fn test_playfair_square_errors(#[case] square: PlayfairSquare, #[case] symbol: char) {
+        assert_eq!(
+            PlayfairCipher::with_square("KEY", square).err(),
+            Some(CipherError::UnknownSymbol { param: "square", symbol })
+        );
+    }
+
+    #[test]
+    fn test_playfair_fillers_errors() {
+        assert_eq!(
+            PlayfairCipher::new("KEY").with_fillers('X', 'J').err(),
+            Some(CipherError::UnknownSymbol { param: "filler", symbol: 'J' })
+        );
+    }
+
+    #[test]
+    fn test_playfair_rekey_keeps_fillers() {
+        let mut c = PlayfairCipher::new("KEY").with_fillers('Q', 'Z').unwrap().split_doubles(true).strip_fillers(true);
+        c.rekey("MONARCHY").unwrap();
+        let fresh = PlayfairCipher::new("MONARCHY").with_fillers('Q', 'Z').unwrap().split_doubles(true);
+        assert_eq!(c.encrypt_str("BALLOON").unwrap(), fresh.encrypt_str("BALLOON").unwrap());
+        assert_eq!(c.decrypt_str(&c.encrypt_str("BALLOON").unwrap()).unwrap(), "BALLOON");
+    }
 }
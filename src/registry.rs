@@ -0,0 +1,853 @@
+//! Registry of every cipher available in the crate.
+//!
+//! Frontends (the TUI, the demo, scripts…) do not need to know the constructor of
+//! each cipher: they can list the registry, then build a `Box<dyn Block + Send + Sync>` from a
+//! name and a map of named parameters, or from a spec string like
+//! `adfgvx:key1=PORTABLE,key2=SUBWAY`.  Several specs joined with `+` build a
+//! [`Chain`], e.g. `playfair:key=ARABESQUE+irregular:key=SUBWAY`.
+//!
+//! Parameter names are the ones of the constructors, so the `CipherError` returned
+//! when a value is rejected names the right parameter.
+//!
+//! Each entry also describes its cipher (display name, era, how it works on the text,
+//! input and output symbols) and every parameter it takes: a label, a [`ParamKind`]
+//! with its constraints and an example value.  Frontends can build their forms and
+//! check values from it instead of knowing each cipher.
+//!
+//! Every cipher also takes an optional `padding` parameter ([`PADDING`]), e.g.
+//! `playfair:key=ARABESQUE,padding=fill X`, wrapping it in a [`Padded`] cipher which
+//! removes the padding on decryption.
+//!
+//! # Example
+//!
+//! ```
+//! use old_crypto_rs::{registry, BlockExt, CipherError};
+//!
+//! let c = registry::from_spec("adfgvx:key1=PORTABLE,key2=SUBWAY").unwrap();
+//! assert_eq!(c.encrypt_str("ATTACKATDAWN").unwrap(), "AFDFADAGAAAAVVVVGFGVGGGX");
+//!
+//! assert_eq!(
+//!     registry::from_spec("adfgvx:key1=PORTABLE").err(),
+//!     Some(CipherError::MissingParameter("key2")),
+//! );
+//! ```
+//!
+use crate::{
+    Alphabet, Block, Chain, CipherError, DigitEscape, ADFGVX, CaesarCipher, Chaocipher, IrregularTransposition, Nihilist,
+    NullCipher, Padded, Padding, PlayfairCipher, Solitaire, SquareCipher, StraddlingCheckerboard, Transposition,
+    VicCipher, Wheatstone,
+};
+#[cfg(feature = "sigaba")]
+use crate::Sigaba;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Named parameters given to a cipher constructor.
+pub type Params = BTreeMap<String, String>;
+
+/// Signature of the functions building a cipher from its parameters.
+type Builder = fn(&Params) -> Result<Box<dyn Block + Send + Sync>, CipherError>;
+
+/// One cipher of the registry.
+///
+pub struct CipherEntry {
+    /// Name used to look the cipher up (case-insensitive).
+    pub name: &'static str,
+    /// Name of the cipher for humans, e.g. `ADFGVX` or `Straddling checkerboard`.
+    pub display_name: &'static str,
+    /// When and by whom the cipher was used.
+    pub era: &'static str,
+    /// How the cipher works on the text: unit of substitution, keystream, whole
+    /// message…
+    pub block: &'static str,
+    /// Symbols accepted in the plaintext, with the default alphabet.
+    pub input: &'static str,
+    /// Symbols found in the ciphertext, with the default alphabet.
+    pub output: &'static str,
+    /// Accepted parameters, in the order of the constructor.
+    pub params: &'static [Param],
+    builder: Builder,
+}
+
+/// Kind of value a parameter takes, with its constraints.
+///
+/// Lengths are the ones of the default alphabet; they change with the `alphabet`
+/// parameter and are only checked when the cipher is built.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParamKind {
+    /// A whole number, possibly negative.
+    Integer,
+    /// Free text such as a keyword or a phrase, of `min` characters at least.
+    Text { min: usize },
+    /// Every symbol of the alphabet once, e.g. a Chaocipher wheel.
+    Permutation { len: usize },
+    /// Between `min` and `max` decimal digits.
+    Digits { min: usize, max: usize },
+    /// `len` distinct symbols, e.g. the row and column labels of a square.
+    Distinct { len: usize },
+    /// Exactly `len` symbols taken from `from`, e.g. SIGABA rotor positions.
+    Symbols { len: usize, from: &'static str },
+    /// A single symbol of the alphabet.
+    Symbol,
+    /// A replacement alphabet: distinct symbols, in order.
+    Alphabet,
+    /// A Solitaire deck: the card numbers 1 to 54 (53 and 54 are the jokers) in
+    /// order, separated by spaces.
+    Deck,
+    /// A [`Padding`] policy, in the form of its `Display`.
+    Padding,
+    /// A [`DigitEscape`] convention, in the form of its `Display`.
+    Escape,
+}
+
+impl ParamKind {
+    /// Checks the form of `value`, e.g. that a `Digits` value only holds digits.
+    ///
+    fn accepts(&self, value: &str) -> bool {
+        let len = value.chars().count();
+        let distinct = || {
+            let mut seen: Vec<char> = value.chars().collect();
+            seen.sort_unstable();
+            seen.dedup();
+            seen.len() == len
+        };
+        match *self {
+            ParamKind::Integer => value.parse::<i32>().is_ok(),
+            ParamKind::Text { min } => len >= min,
+            ParamKind::Permutation { .. } | ParamKind::Distinct { .. } | ParamKind::Alphabet => len > 0 && distinct(),
+            ParamKind::Digits { min, max } => (min..=max).contains(&len) && value.bytes().all(|b| b.is_ascii_digit()),
+            ParamKind::Symbols { len: n, from } => len == n && value.chars().all(|c| from.contains(c)),
+            ParamKind::Symbol => len == 1,
+            ParamKind::Deck => parse_deck(value).is_some(),
+            ParamKind::Padding => value.parse::<Padding>().is_ok(),
+            ParamKind::Escape => value.parse::<DigitEscape>().is_ok(),
+        }
+    }
+}
+
+/// Describes the constraint, e.g. `exactly 2 digits` or `26-letter permutation`.
+///
+impl fmt::Display for ParamKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            ParamKind::Integer => write!(f, "integer"),
+            ParamKind::Text { min: 0 } => write!(f, "any text"),
+            ParamKind::Text { min: 1 } => write!(f, "non-empty text"),
+            ParamKind::Text { min } => write!(f, "at least {} characters", min),
+            ParamKind::Permutation { len } => write!(f, "{}-letter permutation", len),
+            ParamKind::Digits { min, max } if min == max => write!(f, "exactly {} digits", min),
+            ParamKind::Digits { min, max: usize::MAX } => write!(f, "at least {} digits", min),
+            ParamKind::Digits { min, max } => write!(f, "{} to {} digits", min, max),
+            ParamKind::Distinct { len } => write!(f, "{} distinct symbols", len),
+            ParamKind::Symbols { len, from } => write!(f, "{} symbols out of {}", len, from),
+            ParamKind::Symbol => write!(f, "single symbol"),
+            ParamKind::Alphabet => write!(f, "distinct symbols"),
+            ParamKind::Deck => write!(f, "54 card numbers"),
+            ParamKind::Padding => write!(f, "none, fill X, nulls or rectangle N X"),
+            ParamKind::Escape => write!(f, "repeat, figures or board"),
+        }
+    }
+}
+
+/// One parameter of a cipher, enough for a frontend to build an input form.
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Param {
+    /// Name of the parameter in specs, key files and errors.
+    pub name: &'static str,
+    /// Short description for a form, e.g. `Transposition key`.
+    pub label: &'static str,
+    /// Kind of value, with its constraints.
+    pub kind: ParamKind,
+    /// Whether the parameter can be left out.
+    pub optional: bool,
+    /// A valid value.
+    pub example: &'static str,
+}
+
+impl Param {
+    const fn new(name: &'static str, label: &'static str, kind: ParamKind, example: &'static str) -> Self {
+        Param { name, label, kind, optional: false, example }
+    }
+
+    const fn optional(self) -> Self {
+        Param { optional: true, ..self }
+    }
+
+    /// Checks the form of `value` before building the cipher, e.g. as it is typed.
+    ///
+    /// What depends on other parameters, like the length of a permutation with a
+    /// custom alphabet or keys made of symbols of that alphabet, is left to the
+    /// constructor.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidParameter` if `value` does not fit the kind of the parameter.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use old_crypto_rs::registry;
+    ///
+    /// let chrs = registry::find("straddling").unwrap().param("chrs").unwrap();
+    /// assert_eq!(chrs.kind.to_string(), "1 to 3 digits");
+    /// assert!(chrs.validate("89").is_ok());
+    /// assert!(chrs.validate("8A").is_err());
+    /// ```
+    ///
+    pub fn validate(&self, value: &str) -> Result<(), CipherError> {
+        if self.kind.accepts(value) { Ok(()) } else { Err(invalid(self.name, value)) }
+    }
+}
+
+/// The optional `padding` parameter every cipher takes, wrapping it in [`Padded`].
+///
+pub const PADDING: Param = Param::new("padding", "Padding", ParamKind::Padding, "fill X").optional();
+
+/// The optional `alphabet` parameter most ciphers take.
+///
+const ALPHABET: Param =
+    Param::new("alphabet", "Alphabet", ParamKind::Alphabet, "ABCDEFGHIJKLMNOPQRSTUVWXYZ").optional();
+
+impl CipherEntry {
+    /// Returns the parameter called `name`.
+    ///
+    pub fn param(&self, name: &str) -> Option<&'static Param> {
+        self.params.iter().find(|p| p.name == name)
+    }
+
+    /// Returns the examples of the required parameters, which build the cipher.
+    ///
+    pub fn example(&self) -> Params {
+        self.params
+            .iter()
+            .filter(|p| !p.optional)
+            .map(|p| (p.name.to_string(), p.example.to_string()))
+            .collect()
+    }
+
+    /// Builds the cipher from its parameters.
+    ///
+    /// # Errors
+    ///
+    /// Returns `UnknownParameter` for a parameter the cipher does not take,
+    /// `MissingParameter` for a required one not given, and any error of the
+    /// constructor itself or of [`Padded`].
+    ///
+    pub fn build(&self, params: &Params) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+        if let Some(k) = params.keys().find(|k| k.as_str() != PADDING.name && self.param(k).is_none()) {
+            return Err(CipherError::UnknownParameter(k.clone()));
+        }
+        let c = (self.builder)(params)?;
+        let Some(padding) = params.get(PADDING.name) else {
+            return Ok(c);
+        };
+        Ok(Box::new(Padded::new(c, padding.parse()?)?))
+    }
+}
+
+const REGISTRY: &[CipherEntry] = &[
+    CipherEntry {
+        name: "null",
+        display_name: "Null cipher",
+        era: "none, for tests and chains",
+        block: "copies the text unchanged",
+        input: "anything",
+        output: "the input",
+        params: &[],
+        builder: build_null,
+    },
+    CipherEntry {
+        name: "caesar",
+        display_name: "Caesar cipher",
+        era: "Antiquity, Julius Caesar (1st century BC)",
+        block: "monoalphabetic substitution, letter by letter",
+        input: "A-Z",
+        output: "A-Z",
+        params: &[Param::new("key", "Shift", ParamKind::Integer, "3"), ALPHABET],
+        builder: build_caesar,
+    },
+    CipherEntry {
+        name: "playfair",
+        display_name: "Playfair cipher",
+        era: "1854, Charles Wheatstone; British army until WWI",
+        block: "digraphic substitution, two letters at a time",
+        input: "A-Z without J",
+        output: "A-Z without J",
+        params: &[Param::new("key", "Key", ParamKind::Text { min: 0 }, "PLAYFAIREXAMPLE"), ALPHABET],
+        builder: build_playfair,
+    },
+    CipherEntry {
+        name: "chaocipher",
+        display_name: "Chaocipher",
+        era: "1918, John F. Byrne",
+        block: "stream, two wheels permuted after each letter",
+        input: "A-Z",
+        output: "A-Z",
+        params: &[
+            Param::new("pkey", "Plain wheel", ParamKind::Permutation { len: 26 }, "PTLNBQDEOYSFAVZKGJRIHWXUMC"),
+            Param::new("ckey", "Cipher wheel", ParamKind::Permutation { len: 26 }, "HXUCZVAMDSLKPEFJRIGTWOBNYQ"),
+            ALPHABET,
+        ],
+        builder: build_chaocipher,
+    },
+    CipherEntry {
+        name: "square",
+        display_name: "Polybius square",
+        era: "Antiquity, Polybius (2nd century BC)",
+        block: "each symbol becomes its row and column labels",
+        input: "A-Z 0-9",
+        output: "the row and column labels",
+        params: &[
+            Param::new("key", "Key", ParamKind::Text { min: 0 }, "PORTABLE"),
+            Param::new("chrs", "Labels", ParamKind::Distinct { len: 6 }, "ADFGVX"),
+            ALPHABET,
+        ],
+        builder: build_square,
+    },
+    CipherEntry {
+        name: "transposition",
+        display_name: "Columnar transposition",
+        era: "19th century onwards",
+        block: "whole message, read off by columns",
+        input: "anything",
+        output: "the input, reordered",
+        params: &[Param::new("key", "Key", ParamKind::Text { min: 1 }, "SUBWAY")],
+        builder: build_transposition,
+    },
+    CipherEntry {
+        name: "irregular",
+        display_name: "Irregular columnar transposition",
+        era: "1950s, Soviet VIC cipher",
+        block: "whole message, disrupted rows read off by columns",
+        input: "anything",
+        output: "the input, reordered",
+        params: &[Param::new("key", "Key", ParamKind::Text { min: 2 }, "SUBWAY")],
+        builder: build_irregular,
+    },
+    CipherEntry {
+        name: "adfgvx",
+        display_name: "ADFGVX",
+        era: "1918, German army",
+        block: "Polybius square then columnar transposition of the whole message",
+        input: "A-Z 0-9",
+        output: "A D F G V X",
+        params: &[
+            Param::new("key1", "Square key", ParamKind::Text { min: 0 }, "PORTABLE"),
+            Param::new("key2", "Transposition key", ParamKind::Text { min: 1 }, "SUBWAY"),
+            ALPHABET,
+        ],
+        builder: build_adfgvx,
+    },
+    CipherEntry {
+        name: "straddling",
+        display_name: "Straddling checkerboard",
+        era: "20th century, Soviet and Warsaw Pact agents",
+        block: "each symbol becomes one or two digits",
+        input: "A-Z / -",
+        output: "0-9",
+        params: &[
+            Param::new("key", "Key", ParamKind::Text { min: 0 }, "ARABESQUE"),
+            Param::new("chrs", "Blank positions", ParamKind::Digits { min: 1, max: 3 }, "89"),
+            ALPHABET,
+            Param::new("escape", "Digits", ParamKind::Escape, "figures").optional(),
+        ],
+        builder: build_straddling,
+    },
+    CipherEntry {
+        name: "nihilist",
+        display_name: "Nihilist transposition",
+        era: "late 19th century, Russian Nihilists",
+        block: "straddling checkerboard then columnar transposition of the whole message",
+        input: "A-Z / -",
+        output: "0-9",
+        params: &[
+            Param::new("key1", "Checkerboard key", ParamKind::Text { min: 0 }, "ARABESQUE"),
+            Param::new("key2", "Transposition key", ParamKind::Text { min: 1 }, "SUBWAY"),
+            Param::new("chrs", "Blank positions", ParamKind::Digits { min: 1, max: 3 }, "37"),
+            ALPHABET,
+        ],
+        builder: build_nihilist,
+    },
+    CipherEntry {
+        name: "vic",
+        display_name: "VIC cipher",
+        era: "1950s, Soviet spy Reino Häyhänen",
+        block: "straddling checkerboard then two transpositions of the whole message",
+        input: "A-Z / -",
+        output: "0-9",
+        params: &[
+            Param::new("persn", "Personal number", ParamKind::Digits { min: 2, max: 2 }, "89"),
+            Param::new("ind", "Date", ParamKind::Digits { min: 5, max: usize::MAX }, "741776"),
+            Param::new("phrase", "Phrase", ParamKind::Text { min: 20 }, "IDREAMOFJEANNIEWITHT"),
+            Param::new("imsg", "Message indicator", ParamKind::Digits { min: 5, max: usize::MAX }, "77651"),
+        ],
+        builder: build_vic,
+    },
+    CipherEntry {
+        name: "wheatstone",
+        display_name: "Wheatstone cryptograph",
+        era: "1867, Charles Wheatstone",
+        block: "stream, the pointer moves with each letter",
+        input: "A-Z and + between words",
+        output: "A-Z",
+        params: &[
+            Param::new("start", "Start letter", ParamKind::Symbol, "M"),
+            Param::new("pkey", "Plain key", ParamKind::Text { min: 0 }, "CIPHER"),
+            Param::new("ckey", "Cipher key", ParamKind::Text { min: 0 }, "MACHINE"),
+            ALPHABET,
+        ],
+        builder: build_wheatstone,
+    },
+    CipherEntry {
+        name: "solitaire",
+        display_name: "Solitaire",
+        era: "1999, Bruce Schneier for Cryptonomicon",
+        block: "stream, keystream from a deck of cards",
+        input: "A-Z",
+        output: "A-Z",
+        params: &[
+            Param::new("passphrase", "Passphrase", ParamKind::Text { min: 0 }, "CRYPTONOMICON").optional(),
+            Param::new("deck", "Deck", ParamKind::Deck, UNKEYED_DECK).optional(),
+            ALPHABET,
+        ],
+        builder: build_solitaire,
+    },
+    #[cfg(feature = "sigaba")]
+    CipherEntry {
+        name: "sigaba",
+        display_name: "SIGABA",
+        era: "WWII to 1959, US army and navy",
+        block: "stream, rotor machine stepped after each letter",
+        input: "A-Z",
+        output: "A-Z",
+        params: &[
+            Param::new("cipher", "Cipher rotors", ROTORS, "01234"),
+            Param::new("control", "Control rotors", ROTORS, "56789"),
+            Param::new("index", "Index rotors", ParamKind::Symbols { len: 5, from: "01234" }, "01234"),
+            Param::new("cipher_pos", "Cipher positions", POSITIONS, "AAAAA").optional(),
+            Param::new("control_pos", "Control positions", POSITIONS, "AAAAA").optional(),
+            Param::new("index_pos", "Index positions", POSITIONS, "AAAAA").optional(),
+            Param::new("cipher_rev", "Reversed cipher rotors", REVERSED, "00000").optional(),
+            Param::new("control_rev", "Reversed control rotors", REVERSED, "00000").optional(),
+            Param::new("index_rev", "Reversed index rotors", REVERSED, "00000").optional(),
+        ],
+        builder: build_sigaba,
+    },
+];
+
+/// The deck of Solitaire in its unkeyed order.
+const UNKEYED_DECK: &str = "1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20 21 22 23 24 25 26 27 \
+    28 29 30 31 32 33 34 35 36 37 38 39 40 41 42 43 44 45 46 47 48 49 50 51 52 53 54";
+
+/// SIGABA rotor numbers, positions and orientations (`1` for reversed).
+#[cfg(feature = "sigaba")]
+const ROTORS: ParamKind = ParamKind::Symbols { len: 5, from: "0123456789" };
+#[cfg(feature = "sigaba")]
+const POSITIONS: ParamKind = ParamKind::Symbols { len: 5, from: "ABCDEFGHIJKLMNOPQRSTUVWXYZ" };
+#[cfg(feature = "sigaba")]
+const REVERSED: ParamKind = ParamKind::Symbols { len: 5, from: "01" };
+
+/// Returns every available cipher.
+///
+pub fn ciphers() -> &'static [CipherEntry] {
+    REGISTRY
+}
+
+/// Looks a cipher up by name, ignoring case.
+///
+pub fn find(name: &str) -> Option<&'static CipherEntry> {
+    REGISTRY.iter().find(|e| e.name.eq_ignore_ascii_case(name))
+}
+
+/// Builds the cipher called `name` from its parameters.
+///
+/// # Errors
+///
+/// Returns `UnknownCipher` if there is no such cipher, otherwise see [`CipherEntry::build`].
+///
+/// # Example
+///
+/// ```
+/// use old_crypto_rs::registry::{self, Params};
+///
+/// let mut params = Params::new();
+/// params.insert("key".to_string(), "3".to_string());
+/// let c = registry::build("caesar", &params).unwrap();
+/// assert_eq!(c.block_size(), 1);
+/// ```
+///
+pub fn build(name: &str, params: &Params) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+    find(name)
+        .ok_or_else(|| CipherError::UnknownCipher(name.to_string()))?
+        .build(params)
+}
+
+/// Splits a spec string into the cipher name and its parameters.
+///
+/// The syntax is `name` or `name:param=value,param=value…`; spaces around names and
+/// values are ignored.
+///
+/// # Errors
+///
+/// Returns `BadSpec` if a parameter is not of the `param=value` form, has an empty
+/// name or is given twice.
+///
+pub fn parse_spec(spec: &str) -> Result<(String, Params), CipherError> {
+    let (name, rest) = spec.split_once(':').unwrap_or((spec, ""));
+    let mut params = Params::new();
+    for item in rest.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        let (k, v) = item.split_once('=').ok_or_else(|| CipherError::BadSpec(item.to_string()))?;
+        let k = k.trim();
+        if k.is_empty() || params.insert(k.to_string(), v.trim().to_string()).is_some() {
+            return Err(CipherError::BadSpec(item.to_string()));
+        }
+    }
+    Ok((name.trim().to_string(), params))
+}
+
+/// Builds a cipher from a spec string such as `chaocipher:pkey=…,ckey=…`.
+///
+/// Specs separated by `+` are built in order into a [`Chain`].
+///
+/// # Errors
+///
+/// See [`parse_spec`] and [`build`].
+///
+/// # Example
+///
+/// ```
+/// use old_crypto_rs::{registry, BlockExt};
+///
+/// let c = registry::from_spec("square:key=PORTABLE,chrs=ADFGVX+transposition:key=SUBWAY").unwrap();
+/// assert_eq!(c.encrypt_str("ATTACKATDAWN").unwrap(), "AFDFADAGAAAAVVVVGFGVGGGX");
+/// ```
+///
+pub fn from_spec(spec: &str) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+    if !spec.contains('+') {
+        let (name, params) = parse_spec(spec)?;
+        return build(&name, &params);
+    }
+    let mut chain = Chain::new();
+    for part in spec.split('+') {
+        let (name, params) = parse_spec(part)?;
+        chain.push(build(&name, &params)?);
+    }
+    Ok(Box::new(chain))
+}
+
+/// Returns a required parameter.
+///
+fn get<'a>(params: &'a Params, name: &'static str) -> Result<&'a str, CipherError> {
+    params
+        .get(name)
+        .map(String::as_str)
+        .ok_or(CipherError::MissingParameter(name))
+}
+
+/// Returns the optional `alphabet` parameter.
+///
+fn alphabet(params: &Params) -> Result<Option<Alphabet>, CipherError> {
+    params.get("alphabet").map(|a| Alphabet::new(a)).transpose()
+}
+
+/// Builds the error for a value that can not be parsed.
+///
+fn invalid(param: &'static str, value: &str) -> CipherError {
+    CipherError::InvalidParameter { param, value: value.to_string() }
+}
+
+fn build_null(_: &Params) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+    Ok(Box::new(NullCipher::new()))
+}
+
+fn build_caesar(p: &Params) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+    let key = get(p, "key")?;
+    let shift = key.parse::<i32>().map_err(|_| invalid("key", key))?;
+    Ok(Box::new(match alphabet(p)? {
+        Some(a) => CaesarCipher::with_alphabet(shift, &a)?,
+        None => CaesarCipher::new(shift),
+    }))
+}
+
+fn build_playfair(p: &Params) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+    let key = get(p, "key")?;
+    Ok(Box::new(match alphabet(p)? {
+        Some(a) => PlayfairCipher::with_alphabet(key, &a)?,
+        None => PlayfairCipher::new(key),
+    }))
+}
+
+fn build_chaocipher(p: &Params) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+    let (pkey, ckey) = (get(p, "pkey")?, get(p, "ckey")?);
+    Ok(Box::new(match alphabet(p)? {
+        Some(a) => Chaocipher::with_alphabet(pkey, ckey, &a)?,
+        None => Chaocipher::new(pkey, ckey)?,
+    }))
+}
+
+fn build_square(p: &Params) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+    let (key, chrs) = (get(p, "key")?, get(p, "chrs")?);
+    Ok(Box::new(match alphabet(p)? {
+        Some(a) => SquareCipher::with_alphabet(key, chrs, &a)?,
+        None => SquareCipher::new(key, chrs)?,
+    }))
+}
+
+fn build_transposition(p: &Params) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+    Ok(Box::new(Transposition::new(get(p, "key")?)?))
+}
+
+fn build_irregular(p: &Params) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+    Ok(Box::new(IrregularTransposition::new(get(p, "key")?)?))
+}
+
+fn build_adfgvx(p: &Params) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+    let (key1, key2) = (get(p, "key1")?, get(p, "key2")?);
+    Ok(Box::new(match alphabet(p)? {
+        Some(a) => ADFGVX::with_alphabet(key1, key2, &a)?,
+        None => ADFGVX::new(key1, key2)?,
+    }))
+}
+
+fn build_straddling(p: &Params) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+    let (key, chrs) = (get(p, "key")?, get(p, "chrs")?);
+    let c = match alphabet(p)? {
+        Some(a) => StraddlingCheckerboard::with_alphabet(key, chrs, &a)?,
+        None => StraddlingCheckerboard::new(key, chrs)?,
+    };
+    Ok(Box::new(match p.get("escape") {
+        Some(e) => c.with_escape(e.parse()?)?,
+        None => c,
+    }))
+}
+
+fn build_nihilist(p: &Params) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+    let (key1, key2, chrs) = (get(p, "key1")?, get(p, "key2")?, get(p, "chrs")?);
+    Ok(Box::new(match alphabet(p)? {
+        Some(a) => Nihilist::with_alphabet(key1, key2, chrs, &a)?,
+        None => Nihilist::new(key1, key2, chrs)?,
+    }))
+}
+
+fn build_vic(p: &Params) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+    Ok(Box::new(VicCipher::new(
+        get(p, "persn")?,
+        get(p, "ind")?,
+        get(p, "phrase")?,
+        get(p, "imsg")?,
+    )?))
+}
+
+fn build_wheatstone(p: &Params) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+    let start = get(p, "start")?;
+    let &[ch] = start.as_bytes() else {
+        return Err(invalid("start", start));
+    };
+    let (pkey, ckey) = (get(p, "pkey")?, get(p, "ckey")?);
+    Ok(Box::new(match alphabet(p)? {
+        Some(a) => Wheatstone::with_alphabet(ch, pkey, ckey, &a)?,
+        None => Wheatstone::new(ch, pkey, ckey)?,
+    }))
+}
+
+/// The deck is either keyed by a passphrase or given as the 54 card numbers (53 and
+/// 54 are the jokers) separated by spaces.  Without both, it is left in its unkeyed
+/// order.
+///
+fn build_solitaire(p: &Params) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+    let s = match (p.get("passphrase"), p.get("deck")) {
+        (Some(_), Some(deck)) => return Err(invalid("deck", deck)),
+        (Some(pass), None) => Solitaire::new_with_passphrase(pass),
+        (None, Some(deck)) => Solitaire::new(parse_deck(deck).ok_or_else(|| invalid("deck", deck))?),
+        (None, None) => Solitaire::new_unkeyed(),
+    };
+    Ok(Box::new(match alphabet(p)? {
+        Some(a) => s.with_alphabet(&a)?,
+        None => s,
+    }))
+}
+
+/// Parses a deck order, which must hold every card from 1 to 54 once.
+///
+fn parse_deck(value: &str) -> Option<Vec<u8>> {
+    let deck: Vec<u8> = value.split_whitespace().map(|c| c.parse().ok()).collect::<Option<_>>()?;
+    let mut sorted = deck.clone();
+    sorted.sort_unstable();
+    sorted.into_iter().eq(1..=54).then_some(deck)
+}
+
+/// Parses exactly five symbols with `conv`, e.g. rotor numbers or positions.
+///
+#[cfg(feature = "sigaba")]
+fn five<T: Copy + Default>(
+    p: &Params,
+    param: &'static str,
+    default: Option<[T; 5]>,
+    conv: impl Fn(char) -> Option<T>,
+) -> Result<[T; 5], CipherError> {
+    let value = match (p.get(param), default) {
+        (Some(v), _) => v.as_str(),
+        (None, Some(d)) => return Ok(d),
+        (None, None) => return Err(CipherError::MissingParameter(param)),
+    };
+    let mut out = [T::default(); 5];
+    if value.chars().count() != 5 {
+        return Err(invalid(param, value));
+    }
+    for (o, ch) in out.iter_mut().zip(value.chars()) {
+        *o = conv(ch).ok_or_else(|| invalid(param, value))?;
+    }
+    Ok(out)
+}
+
+/// Rotors are given as five digits (`01234`), positions as five letters (`AAAAA`,
+/// the default) and orientations as five `0`/`1` (`00000`, the default, is all
+/// normal).
+///
+#[cfg(feature = "sigaba")]
+fn build_sigaba(p: &Params) -> Result<Box<dyn Block + Send + Sync>, CipherError> {
+    let rotor = |max: u32| move |ch: char| ch.to_digit(10).filter(|&d| d < max).map(|d| d as usize);
+    let pos = |ch: char| ch.is_ascii_uppercase().then(|| (ch as u8 - b'A') as usize);
+    let rev = |ch: char| match ch {
+        '0' => Some(false),
+        '1' => Some(true),
+        _ => None,
+    };
+
+    Ok(Box::new(Sigaba::new(
+        five(p, "cipher", None, rotor(10))?,
+        five(p, "cipher_pos", Some([0; 5]), pos)?,
+        five(p, "cipher_rev", Some([false; 5]), rev)?,
+        five(p, "control", None, rotor(10))?,
+        five(p, "control_pos", Some([0; 5]), pos)?,
+        five(p, "control_rev", Some([false; 5]), rev)?,
+        five(p, "index", None, rotor(5))?,
+        five(p, "index_pos", Some([0; 5]), pos)?,
+        five(p, "index_rev", Some([false; 5]), rev)?,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BlockExt;
+
+    use rstest::rstest;
+
+    #[test]
+    fn test_registry_names() {
+        for e in ciphers() {
+            assert_eq!(find(e.name).unwrap().name, e.name);
+            assert!(!e.display_name.is_empty() && !e.era.is_empty() && !e.block.is_empty());
+        }
+        assert_eq!(find("ADFGVX").unwrap().name, "adfgvx");
+        assert!(find("enigma").is_none());
+    }
+
+    #[test]
+    fn test_registry_examples() {
+        for e in ciphers() {
+            for p in e.params {
+                assert_eq!(p.validate(p.example), Ok(()), "{}.{}", e.name, p.name);
+                assert_eq!(e.param(p.name), Some(p));
+            }
+            let c = e.build(&e.example()).unwrap_or_else(|err| panic!("{}: {}", e.name, err));
+            let pt = c.normalizer().normalize("Attack at dawn");
+            assert_eq!(c.decrypt_str(&c.encrypt_str(&pt).unwrap()).unwrap(), pt, "{}", e.name);
+        }
+        assert_eq!(find("caesar").unwrap().param("shift"), None);
+    }
+
+    #[rstest]
+    #[case("caesar", "key", "integer", "-3", "three")]
+    #[case("irregular", "key", "at least 2 characters", "SU", "S")]
+    #[case("chaocipher", "pkey", "26-letter permutation", "PTLNBQDEOYSFAVZKGJRIHWXUMC", "PTLNBQDEOYSFAVZKGJRIHWXUMP")]
+    #[case("square", "chrs", "6 distinct symbols", "ADFGVX", "ADFGVA")]
+    #[case("transposition", "key", "non-empty text", "S", "")]
+    #[case("straddling", "chrs", "1 to 3 digits", "37", "3A")]
+    #[case("straddling", "escape", "repeat, figures or board", "figures", "shift")]
+    #[case("vic", "ind", "at least 5 digits", "741776", "7417")]
+    #[case("vic", "phrase", "at least 20 characters", "IDREAMOFJEANNIEWITHT", "IDREAM")]
+    #[case("wheatstone", "start", "single symbol", "M", "MA")]
+    #[case("solitaire", "deck", "54 card numbers", UNKEYED_DECK, "1 2 3")]
+    #[case("solitaire", "alphabet", "distinct symbols", "ABC", "ABCA")]
+    #[case("null", "padding", "none, fill X, nulls or rectangle N X", "rectangle 5 X", "rectangle X")]
+    fn test_param_validate(#[case] cipher: &str, #[case] param: &str, #[case] constraint: &str, #[case] ok: &str, #[case] bad: &str) {
+        let p = find(cipher).unwrap().param(param).unwrap_or(&PADDING);
+        assert_eq!(p.kind.to_string(), constraint);
+        assert_eq!(p.validate(ok), Ok(()));
+        assert_eq!(p.validate(bad), Err(invalid(p.name, bad)));
+    }
+
+    #[rstest]
+    #[case("null", "HELLO", "HELLO")]
+    #[case("caesar:key=3", "HELLO", "KHOOR")]
+    #[case("playfair:key=PLAYFAIREXAMPLE", "HIDETHEGOLDINTHETREXESTUMP", "BMODZBXDNABEKUDMUIXMMOUVIF")]
+    #[case("chaocipher:pkey=PTLNBQDEOYSFAVZKGJRIHWXUMC,ckey=HXUCZVAMDSLKPEFJRIGTWOBNYQ", "WELLDONE", "OAHQHCNY")]
+    #[case("adfgvx: key1 = PORTABLE , key2 = SUBWAY", "ATTACKATDAWN", "AFDFADAGAAAAVVVVGFGVGGGX")]
+    #[case("straddling:key=ARABESQUE,chrs=89", "ATTACKAT2AM", "0770808107972297088")]
+    #[case("straddling:key=ARABESQUE,chrs=89,escape=figures", "AT2215AM", "07972222115597088")]
+    #[case("wheatstone:start=M,pkey=CIPHER,ckey=MACHINE", "CHAR", "BYVL")]
+    #[case("solitaire", "AAAAA", "EXKYI")]
+    #[case("caesar:key=3,alphabet=ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789", "HAL9000", "KDOC333")]
+    #[case("square:key=POLYBIUS,chrs=12345,alphabet=ABCDEFGHIKLMNOPQRSTUVWXYZ", "HELLO", "3532131312")]
+    #[case("square:key=PORTABLE,chrs=ADFGVX + transposition:key=SUBWAY", "ATTACKATDAWN", "AFDFADAGAAAAVVVVGFGVGGGX")]
+    #[case("playfair:key=PLAYFAIREXAMPLE,padding=fill X", "HIDETHEGOLD", "BMODZBXDNAGE")]
+    #[case("transposition:key=SUBWAY,padding=rectangle 6 Q", "RETREAT", "EQTQRTEQRQAQ")]
+    fn test_from_spec(#[case] spec: &str, #[case] pt: &str, #[case] ct: &str) {
+        let c = from_spec(spec).unwrap();
+        assert_eq!(c.encrypt_str(pt).unwrap(), ct);
+        assert_eq!(c.decrypt_str(ct).unwrap(), pt);
+    }
+
+    #[rstest]
+    #[case("enigma", CipherError::UnknownCipher("enigma".to_string()))]
+    #[case("caesar", CipherError::MissingParameter("key"))]
+    #[case("caesar:key=three", CipherError::InvalidParameter { param: "key", value: "three".to_string() })]
+    #[case("caesar:key=3,shift=4", CipherError::UnknownParameter("shift".to_string()))]
+    #[case("caesar:key", CipherError::BadSpec("key".to_string()))]
+    #[case("caesar:key=3,key=4", CipherError::BadSpec("key=4".to_string()))]
+    #[case("caesar:key=3+", CipherError::UnknownCipher(String::new()))]
+    #[case("wheatstone:start=MA,pkey=CIPHER,ckey=MACHINE", CipherError::InvalidParameter { param: "start", value: "MA".to_string() })]
+    #[case("adfgvx:key1=PORTABLE,key2=", CipherError::EmptyKey("key2"))]
+    #[case("chaocipher:pkey=AB,ckey=CD", CipherError::BadAlphabetLength { param: "pkey", expected: 26, got: 2 })]
+    #[case("caesar:key=3,alphabet=ABCA", CipherError::DuplicateSymbol { param: "alphabet", symbol: 'A' })]
+    #[case("playfair:key=KEY,alphabet=ABCDE", CipherError::BadAlphabetLength { param: "alphabet", expected: 9, got: 5 })]
+    #[case("solitaire:deck=1 2 3", CipherError::InvalidParameter { param: "deck", value: "1 2 3".to_string() })]
+    #[case("solitaire:passphrase=KEY,deck=1", CipherError::InvalidParameter { param: "deck", value: "1".to_string() })]
+    #[case("caesar:key=3,padding=fill", CipherError::InvalidParameter { param: "padding", value: "fill".to_string() })]
+    #[case("transposition:key=SUBWAY,padding=nulls,alphabet=ABC", CipherError::UnknownParameter("alphabet".to_string()))]
+    fn test_from_spec_errors(#[case] spec: &str, #[case] err: CipherError) {
+        assert_eq!(from_spec(spec).err(), Some(err));
+    }
+
+    #[test]
+    fn test_from_spec_solitaire_deck() {
+        let deck: Vec<String> = Solitaire::new_with_passphrase("CRYPTONOMICON")
+            .deck()
+            .iter()
+            .map(u8::to_string)
+            .collect();
+        let c = from_spec(&format!("solitaire:deck={}", deck.join(" "))).unwrap();
+        let keyed = from_spec("solitaire:passphrase=CRYPTONOMICON").unwrap();
+        assert_eq!(c.encrypt_str("SOLITAIRE").unwrap(), keyed.encrypt_str("SOLITAIRE").unwrap());
+
+        let mut twice: Vec<u8> = (1..=54).collect();
+        twice[0] = 2;
+        assert_eq!(parse_deck(&twice.iter().map(u8::to_string).collect::<Vec<_>>().join(" ")), None);
+    }
+
+    #[cfg(feature = "sigaba")]
+    #[test]
+    fn test_from_spec_sigaba() {
+        let c = from_spec("sigaba:cipher=01234,control=01234,index=01234").unwrap();
+        let ct = c.encrypt_str("HELLOWORLD").unwrap();
+        assert_eq!(c.decrypt_str(&ct).unwrap(), "HELLOWORLD");
+        assert_eq!(
+            from_spec("sigaba:cipher=01234,control=01234,index=01239").err(),
+            Some(CipherError::InvalidParameter { param: "index", value: "01239".to_string() })
+        );
+    }
+}
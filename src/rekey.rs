@@ -0,0 +1,39 @@
+//! Changing the key of a cipher in place.
+//!
+//! Solvers trying keys by brute force or hill-climbing build a cipher for every
+//! candidate key, which means millions of allocations for the keyed alphabets and
+//! column orders.  [`Rekey`] changes the key of an existing cipher instead: its
+//! tables are rebuilt in the storage they already have, the rest of its setup
+//! (alphabet, coordinates, long digits…) is kept.
+//!
+//! # Example
+//!
+//! ```
+//! use old_crypto_rs::{BlockExt, PlayfairCipher, Rekey};
+//!
+//! let ct = PlayfairCipher::new("SECRET").encrypt_str("HIDETHEGOLD").unwrap();
+//!
+//! let mut c = PlayfairCipher::new("A");
+//! let found = ["PLAYFAIR", "MONARCHY", "SECRET"].into_iter().find(|&key| {
+//!     c.rekey(key).unwrap();
+//!     c.decrypt_str(&ct).unwrap().starts_with("HIDE")
+//! });
+//! assert_eq!(found, Some("SECRET"));
+//! ```
+//!
+use crate::CipherError;
+
+/// A cipher whose key can be changed without building a new one.
+///
+/// `K` is the type of the key, a string for most ciphers.
+///
+pub trait Rekey<K: ?Sized = str> {
+    /// Changes the key, the cipher then working as if it had been created with it.
+    ///
+    /// # Errors
+    ///
+    /// Same as the constructor of the cipher for its key.  The cipher is left
+    /// unchanged on error.
+    ///
+    fn rekey(&mut self, key: &K) -> Result<(), CipherError>;
+}
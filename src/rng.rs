@@ -0,0 +1,230 @@
+//! Random key generation.
+//!
+//! Every cipher has a `random` constructor drawing its key material from an [`Rng`].
+//! The generator is SplitMix64: small, fast and good enough to pick keys for
+//! exercises and training sets, but **not** a cryptographic generator.  The same seed
+//! always gives the same keys, on every platform.
+//!
+//! # Example
+//!
+//! ```
+//! use old_crypto_rs::{BlockExt, Chaocipher, Rng, Transposition};
+//!
+//! let mut rng = Rng::new(1944);
+//! let c = Chaocipher::random(&mut rng);
+//! let ct = c.encrypt_str("WELLDONEISBETTERTHANWELLSAID").unwrap();
+//!
+//! // Same seed, same key
+//! let mut rng = Rng::new(1944);
+//! assert_eq!(Chaocipher::random(&mut rng).encrypt_str("WELLDONEISBETTERTHANWELLSAID").unwrap(), ct);
+//!
+//! // Keys of a chosen length
+//! let t = Transposition::random(&mut rng, 7).unwrap();
+//! assert_eq!(t.encrypt_str("ATTACKATDAWN").unwrap().len(), 12);
+//! ```
+//!
+use crate::Alphabet;
+use std::hash::{BuildHasher, RandomState};
+
+/// A seedable pseudo-random generator (SplitMix64).
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Creates a generator from a seed, for reproducible keys.
+    ///
+    pub fn new(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    /// Creates a generator seeded from the per-process randomness of the standard
+    /// library, for keys that differ on every run.
+    ///
+    pub fn from_entropy() -> Self {
+        Self::new(RandomState::new().hash_one(std::time::SystemTime::now()))
+    }
+
+    /// Returns the next 64 random bits.
+    ///
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns a number in `0..n`, without modulo bias.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `n` is 0.
+    ///
+    pub fn below(&mut self, n: usize) -> usize {
+        assert!(n > 0, "empty range");
+        let n = n as u64;
+        // Reject the last, incomplete, run of n values
+        let zone = u64::MAX - u64::MAX % n;
+        loop {
+            let r = self.next_u64();
+            if r < zone {
+                return (r % n) as usize;
+            }
+        }
+    }
+
+    /// Returns `true` or `false` with the same probability.
+    ///
+    pub fn coin(&mut self) -> bool {
+        self.next_u64() >> 63 == 1
+    }
+
+    /// Shuffles `items` in place (Fisher-Yates).
+    ///
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+
+    /// Returns `len` symbols of `alphabet` drawn independently, e.g. a transposition
+    /// key or a key phrase.
+    ///
+    pub fn word(&mut self, alphabet: &Alphabet, len: usize) -> String {
+        (0..len).map(|_| alphabet.symbols()[self.below(alphabet.len())]).collect()
+    }
+
+    /// Returns the symbols of `alphabet` in random order, e.g. a Chaocipher wheel.
+    ///
+    pub fn permutation(&mut self, alphabet: &Alphabet) -> String {
+        let mut symbols = alphabet.symbols().to_vec();
+        self.shuffle(&mut symbols);
+        symbols.into_iter().collect()
+    }
+
+    /// Returns `len` random decimal digits.
+    ///
+    pub fn digits(&mut self, len: usize) -> String {
+        (0..len).map(|_| char::from(b'0' + self.below(10) as u8)).collect()
+    }
+
+    /// Returns `len` distinct decimal digits, e.g. the long digits of a straddling
+    /// checkerboard.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `len` is more than 10.
+    ///
+    pub fn distinct_digits(&mut self, len: usize) -> String {
+        assert!(len <= 10, "only 10 digits");
+        let mut digits = *b"0123456789";
+        self.shuffle(&mut digits);
+        digits[..len].iter().map(|&d| char::from(d)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::*;
+
+    #[test]
+    fn test_rng_reproducible() {
+        let (mut a, mut b) = (Rng::new(42), Rng::new(42));
+        for _ in 0..100 {
+            assert_eq!(a.next_u64(), b.next_u64());
+        }
+        assert_ne!(Rng::new(1).next_u64(), Rng::new(2).next_u64());
+    }
+
+    #[test]
+    fn test_rng_splitmix64() {
+        // Reference values of SplitMix64 for seed 0
+        let mut rng = Rng::new(0);
+        assert_eq!(rng.next_u64(), 0xe220_a839_7b1d_cdaf);
+        assert_eq!(rng.next_u64(), 0x6e78_9e6a_a1b9_65f4);
+    }
+
+    #[test]
+    fn test_rng_below() {
+        let mut rng = Rng::new(7);
+        let mut seen = [0usize; 6];
+        for _ in 0..6000 {
+            seen[rng.below(6)] += 1;
+        }
+        assert!(seen.iter().all(|&n| (800..1200).contains(&n)), "{:?}", seen);
+        assert_eq!(rng.below(1), 0);
+    }
+
+    #[test]
+    fn test_rng_permutation() {
+        let mut rng = Rng::new(7);
+        let a = Alphabet::latin();
+        let p = rng.permutation(&a);
+        assert_ne!(p, a.to_string());
+        assert_eq!(a.check_permutation("key", &p), Ok(()));
+
+        let w = rng.word(&a, 12);
+        assert_eq!(w.len(), 12);
+        assert_eq!(a.check_key("key", &w), Ok(()));
+
+        let d = rng.distinct_digits(10);
+        let mut sorted: Vec<char> = d.chars().collect();
+        sorted.sort_unstable();
+        assert_eq!(sorted.into_iter().collect::<String>(), "0123456789");
+        assert!(rng.digits(5).bytes().all(|b| b.is_ascii_digit()));
+    }
+
+    /// Builds a cipher with random keys.
+    type Random = fn(&mut Rng) -> Box<dyn Block + Send + Sync>;
+
+    #[test]
+    fn test_random_ciphers() {
+        let ciphers: [Random; 12] = [
+            |r| Box::new(CaesarCipher::random(r)),
+            |r| Box::new(Padded::new(PlayfairCipher::random(r), Padding::Fill('X')).unwrap()),
+            |r| Box::new(Chaocipher::random(r)),
+            |r| Box::new(SquareCipher::random(r, "ADFGVX").unwrap()),
+            |r| Box::new(Transposition::random(r, 7).unwrap()),
+            |r| Box::new(IrregularTransposition::random(r, 9).unwrap()),
+            |r| Box::new(ADFGVX::random(r, 6).unwrap()),
+            |r| Box::new(StraddlingCheckerboard::random(r)),
+            |r| Box::new(Nihilist::random(r, 5).unwrap()),
+            |r| Box::new(VicCipher::random(r)),
+            |r| Box::new(Wheatstone::random(r)),
+            |r| Box::new(Solitaire::random(r)),
+        ];
+        let pt = "WEAREDISCOVEREDSAVEYOURSELF";
+        for random in ciphers {
+            let ct = random(&mut Rng::new(1)).encrypt_str(pt).unwrap();
+            assert_eq!(random(&mut Rng::new(1)).encrypt_str(pt).unwrap(), ct);
+            assert_ne!(random(&mut Rng::new(2)).encrypt_str(pt).unwrap(), ct);
+            assert_eq!(random(&mut Rng::new(1)).decrypt_str(&ct).unwrap(), pt, "{}", ct);
+        }
+    }
+
+    #[cfg(feature = "sigaba")]
+    #[test]
+    fn test_random_sigaba() {
+        let c = Sigaba::random(&mut Rng::new(1));
+        let ct = c.encrypt_str("ATTACKATDAWN").unwrap();
+        assert_eq!(Sigaba::random(&mut Rng::new(1)).encrypt_str("ATTACKATDAWN").unwrap(), ct);
+        assert_ne!(Sigaba::random(&mut Rng::new(2)).start(), c.start());
+    }
+
+    #[test]
+    fn test_random_errors() {
+        let mut rng = Rng::new(1);
+        assert_eq!(Transposition::random(&mut rng, 0).err(), Some(CipherError::EmptyKey("key")));
+        assert_eq!(
+            IrregularTransposition::random(&mut rng, 1).err(),
+            Some(CipherError::KeyTooShort { param: "key", min: 2, got: 1 })
+        );
+        assert_eq!(ADFGVX::random(&mut rng, 0).err(), Some(CipherError::EmptyKey("key2")));
+        assert_eq!(Nihilist::random(&mut rng, 0).err(), Some(CipherError::EmptyKey("key2")));
+        assert_eq!(SquareCipher::random(&mut rng, "ADFGV").err(), Some(CipherError::BadAlphabetLength { param: "chrs", expected: 6, got: 5 }));
+    }
+}
@@ -11,38 +11,30 @@
 //! # References
 //! * [Wikipedia: SIGABA](https://en.wikipedia.org/wiki/SIGABA)
 //! * [The SIGABA (ECM Mark II) Cipher Machine](http://www.cryptomuseum.com/crypto/usa/sigaba/index.htm)
-use crate::Block;
-use std::cell::RefCell;
+use crate::{Block, CipherError, Normalizer, Rng, StreamCipher, Tracer};
+use crate::error::check_dst;
+use crate::trace;
+use std::fmt;
 
 /// Sigaba (ECM Mark II) Rotor wirings.
-/// Source: https://en.wikipedia.org/wiki/SIGABA#Rotors
+/// Source: M. Stamp and W. O. Chan, "SIGABA: Cryptanalysis of the Full Keyspace",
+/// Cryptologia 31(3), 2007, which lists the ten large rotors.
 const CIPHER_WIRINGS: [&[u8; 26]; 10] = [
-    b"WJKZREDSXOTYPUAGVHCBFMQLNI", // Rotor 0
-    b"DZWXPHSGBYRFOTKJVCNAEILMUQ", // Rotor 1
-    b"GZJDMYUXLWSIVREPHQBONTKAFC", // Rotor 2
-    b"BHFVMCXOTARZPUGDKSJNLYEWIQ", // Rotor 3
-    b"YQWJSCOZAKPXLVREBTUFDGNHMI", // Rotor 4
-    b"XOTZAYQUVREBKWSPLMNHIDGCJF", // Rotor 5
-    b"WJRAXLTZOYQUVREBKSPLMNHIDG", // Rotor 6
-    b"JRAXLTZOYQUVREBKSPLMNHIDGW", // Rotor 7
-    b"RAXLTZOYQUVREBKSPLMNHIDGWJ", // Rotor 8
-    b"AXLTZOYQUVREBKSPLMNHIDGWJR", // Rotor 9
+    b"YCHLQSUGBDIXNZKERPVJTAWFOM", // Rotor 0
+    b"INPXBWETGUYSAOCHVLDMQKZJFR", // Rotor 1
+    b"WNDRIOZPTAXHFJYQBMSVEKUCGL", // Rotor 2
+    b"TZGHOBKRVUXLQDMPNFWCJYEIAS", // Rotor 3
+    b"YWTAHRQJVLCEXUNGBIPZMSDFOK", // Rotor 4
+    b"QSLRBTEKOGAICFWYVMHJNXZUDP", // Rotor 5
+    b"CHJDQIGNBSAKVTUOXFWLEPRMZY", // Rotor 6
+    b"CDFAJXTIMNBEQHSUGRYLWZKVPO", // Rotor 7
+    b"XHFESZDNRBCGKQIJLTVMUOYAPW", // Rotor 8
+    b"EZJQXMOGYTCSFRIUPVNADLHWBK", // Rotor 9
 ];
 
 /// Rotor wirings for the control bank.
 /// In most Sigaba models, these were identical to the cipher bank wirings.
-const CONTROL_WIRINGS: [&[u8; 26]; 10] = [
-    b"WJKZREDSXOTYPUAGVHCBFMQLNI", // Same as cipher for now as per common Sigaba models
-    b"DZWXPHSGBYRFOTKJVCNAEILMUQ",
-    b"GZJDMYUXLWSIVREPHQBONTKAFC",
-    b"BHFVMCXOTARZPUGDKSJNLYEWIQ",
-    b"YQWJSCOZAKPXLVREBTUFDGNHMI",
-    b"XOTZAYQUVREBKWSPLMNHIDGCJF",
-    b"WJRAXLTZOYQUVREBKSPLMNHIDG",
-    b"JRAXLTZOYQUVREBKSPLMNHIDGW",
-    b"RAXLTZOYQUVREBKSPLMNHIDGWJ",
-    b"AXLTZOYQUVREBKSPLMNHIDGWJR",
-];
+const CONTROL_WIRINGS: [&[u8; 26]; 10] = CIPHER_WIRINGS;
 
 /// Rotor wirings for the index bank.
 /// These were smaller rotors with only 10 active positions (0-9).
@@ -55,7 +47,7 @@ const INDEX_WIRINGS: [&[u8; 26]; 5] = [
 ];
 
 /// Represents a single rotor in the SIGABA machine.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 struct Rotor {
     /// The wiring permutation of the rotor.
     wiring: [u8; 26],
@@ -75,12 +67,11 @@ impl Rotor {
         if !reversed {
             w.copy_from_slice(wiring);
         } else {
-            for i in 0..26 {
-                let val = wiring[i];
+            for (i, &val) in wiring.iter().enumerate() {
                 let input_offset = i as i8;
-                let output_offset = if val >= b'A' && val <= b'Z' {
+                let output_offset = if val.is_ascii_uppercase() {
                     (val - b'A') as i8
-                } else if val >= b'0' && val <= b'9' {
+                } else if val.is_ascii_digit() {
                     (val - b'0') as i8
                 } else {
                     0
@@ -90,9 +81,9 @@ impl Rotor {
                 let new_input_offset = output_offset;
                 let new_output_offset = (new_input_offset - diff + 26) % 26;
                 
-                if val >= b'A' && val <= b'Z' {
+                if val.is_ascii_uppercase() {
                     w[new_input_offset as usize] = ((new_output_offset as u8 + 26) % 26) + b'A';
-                } else if val >= b'0' && val <= b'9' {
+                } else if val.is_ascii_digit() {
                     w[new_input_offset as usize] = ((new_output_offset as u8 + 10) % 10) + b'0';
                 }
             }
@@ -100,9 +91,9 @@ impl Rotor {
         
         let mut inv = [0u8; 26];
         for (i, &val) in w.iter().enumerate() {
-            if val >= b'A' && val <= b'Z' {
+            if val.is_ascii_uppercase() {
                 inv[(val - b'A') as usize] = (i as u8) + b'A';
-            } else if val >= b'0' && val <= b'9' {
+            } else if val.is_ascii_digit() {
                 inv[(val - b'0') as usize] = (i as u8) + b'0';
             }
         }
@@ -117,11 +108,11 @@ impl Rotor {
 
     /// Performs a forward signal transformation through the rotor.
     fn forward(&self, input: u8) -> u8 {
-        let base = if input >= b'A' && input <= b'Z' { b'A' } else { b'0' };
+        let base = if input.is_ascii_uppercase() { b'A' } else { b'0' };
         let mod_val = if base == b'A' { 26 } else { 10 };
         let offset_in = (input - base + self.position as u8) % mod_val;
         let output = self.wiring[offset_in as usize];
-        let out_base = if output >= b'A' && output <= b'Z' { b'A' } else { b'0' };
+        let out_base = if output.is_ascii_uppercase() { b'A' } else { b'0' };
         let out_mod = if out_base == b'A' { 26 } else { 10 };
         let offset_out = ((output as i16 - out_base as i16 - self.position as i16) % out_mod as i16 + out_mod as i16) % out_mod as i16;
         (offset_out as u8) + out_base
@@ -129,11 +120,11 @@ impl Rotor {
 
     /// Performs a backward signal transformation through the rotor (used during decryption).
     fn backward(&self, input: u8) -> u8 {
-        let base = if input >= b'A' && input <= b'Z' { b'A' } else { b'0' };
+        let base = if input.is_ascii_uppercase() { b'A' } else { b'0' };
         let mod_val = if base == b'A' { 26 } else { 10 };
         let offset_in = (input - base + self.position as u8) % mod_val;
         let output = self.inverse[offset_in as usize];
-        let out_base = if output >= b'A' && output <= b'Z' { b'A' } else { b'0' };
+        let out_base = if output.is_ascii_uppercase() { b'A' } else { b'0' };
         let out_mod = if out_base == b'A' { 26 } else { 10 };
         let offset_out = ((output as i16 - out_base as i16 - self.position as i16) % out_mod as i16 + out_mod as i16) % out_mod as i16;
         (offset_out as u8) + out_base
@@ -145,9 +136,11 @@ impl Rotor {
     }
 }
 
-/// Internal state of the SIGABA machine, containing all rotor banks.
-#[derive(Clone)]
-struct SigabaState {
+/// State of the SIGABA machine, containing all rotor banks.
+///
+/// This is also the state used by the [`StreamCipher`] methods.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SigabaState {
     /// The bank of five cipher rotors.
     cipher_bank: [Rotor; 5],
     /// The bank of five control rotors.
@@ -156,12 +149,45 @@ struct SigabaState {
     index_bank: [Rotor; 5],
 }
 
+/// Prints the positions of the cipher, control and index rotors as letters.
+///
+impl fmt::Display for SigabaState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let letters = |pos: [usize; 5]| pos.map(|p| (b'A' + p as u8) as char).iter().collect::<String>();
+        write!(
+            f,
+            "{} {} {}",
+            letters(self.cipher_positions()),
+            letters(self.control_positions()),
+            letters(self.index_positions())
+        )
+    }
+}
+
+impl SigabaState {
+    /// Returns the current positions (0-25) of the cipher rotors.
+    ///
+    pub fn cipher_positions(&self) -> [usize; 5] {
+        self.cipher_bank.map(|r| r.position)
+    }
+
+    /// Returns the current positions (0-25) of the control rotors.
+    ///
+    pub fn control_positions(&self) -> [usize; 5] {
+        self.control_bank.map(|r| r.position)
+    }
+
+    /// Returns the current positions (0-25) of the index rotors.
+    ///
+    pub fn index_positions(&self) -> [usize; 5] {
+        self.index_bank.map(|r| r.position)
+    }
+}
+
 /// The SIGABA (ECM Mark II) cipher machine.
 pub struct Sigaba {
-    /// Initial state for resetting the machine.
+    /// Initial state of the machine, copied for each operation.
     initial_state: SigabaState,
-    /// Mutable current state of the machine.
-    state: RefCell<SigabaState>,
 }
 
 impl Sigaba {
@@ -177,6 +203,7 @@ impl Sigaba {
     /// * `index_indices` - Indices (0-4) of the rotors used in the index bank.
     /// * `index_pos` - Initial positions (0-25) of the index rotors.
     /// * `index_rev` - Whether each index rotor is reversed.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         cipher_indices: [usize; 5], cipher_pos: [usize; 5], cipher_rev: [bool; 5],
         control_indices: [usize; 5], control_pos: [usize; 5], control_rev: [bool; 5],
@@ -198,10 +225,28 @@ impl Sigaba {
             index_bank: create_bank(&index_indices, &index_pos, &index_rev, &INDEX_WIRINGS),
         };
 
-        Sigaba {
-            initial_state: initial_state.clone(),
-            state: RefCell::new(initial_state),
-        }
+        Sigaba { initial_state }
+    }
+
+    /// Creates a SIGABA machine with a random rotor selection: five of the ten
+    /// cipher/control rotors in each bank, the five index rotors in random order,
+    /// with random positions and orientations.
+    pub fn random(rng: &mut Rng) -> Self {
+        let mut rotors = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+        rng.shuffle(&mut rotors);
+        let mut index = [0, 1, 2, 3, 4];
+        rng.shuffle(&mut index);
+
+        let mut pos = || std::array::from_fn(|_| rng.below(26));
+        let (cipher_pos, control_pos, index_pos) = (pos(), pos(), pos());
+        let mut rev = || std::array::from_fn(|_| rng.coin());
+        let (cipher_rev, control_rev, index_rev) = (rev(), rev(), rev());
+
+        Self::new(
+            rotors[..5].try_into().unwrap(), cipher_pos, cipher_rev,
+            rotors[5..].try_into().unwrap(), control_pos, control_rev,
+            index, index_pos, index_rev,
+        )
     }
 
     /// Advanced the state of the machine by stepping the rotors.
@@ -281,9 +326,14 @@ impl Sigaba {
         val
     }
 
-    /// Resets the machine to its initial state.
-    fn reset(&self) {
-        *self.state.borrow_mut() = self.initial_state.clone();
+    /// Runs `src` through the machine from the given state.
+    fn process(state: &mut SigabaState, dst: &mut [u8], src: &[u8], decrypt: bool) -> Result<usize, CipherError> {
+        check_dst(dst, src.len())?;
+        for (i, &ch) in src.iter().enumerate() {
+            dst[i] = Self::transform(state, ch, decrypt);
+            Self::step_rotors(state);
+        }
+        Ok(src.len())
     }
 }
 
@@ -293,32 +343,75 @@ impl Block for Sigaba {
         1
     }
 
+    /// The cipher rotors have only the 26 letters.
+    ///
+    fn normalizer(&self) -> Normalizer {
+        Normalizer::letters()
+    }
+
     /// Encrypts the source buffer into the destination buffer.
-    /// Note: This implementation resets the machine state before encryption.
-    fn encrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        self.reset();
-        for (i, &ch) in src.iter().enumerate() {
-            let state = self.state.borrow();
-            dst[i] = Self::transform(&state, ch, false);
-            drop(state);
-            let mut state = self.state.borrow_mut();
-            Self::step_rotors(&mut state);
-        }
-        src.len()
+    /// Note: This implementation starts from the initial machine state.
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.encrypt_stream(&mut self.start(), dst, src)
     }
 
     /// Decrypts the source buffer into the destination buffer.
-    /// Note: This implementation resets the machine state before decryption.
-    fn decrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        self.reset();
-        for (i, &ch) in src.iter().enumerate() {
-            let state = self.state.borrow();
-            dst[i] = Self::transform(&state, ch, true);
-            drop(state);
-            let mut state = self.state.borrow_mut();
-            Self::step_rotors(&mut state);
+    /// Note: This implementation starts from the initial machine state.
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.decrypt_stream(&mut self.start(), dst, src)
+    }
+
+    /// Reports the state of the machine after every character.
+    ///
+    fn try_encrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        trace::trace_stream(self, dst, src, tracer, true)
+    }
+
+    /// Reports the state of the machine after every character.
+    ///
+    fn try_decrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        trace::trace_stream(self, dst, src, tracer, false)
+    }
+}
+
+impl StreamCipher for Sigaba {
+    type State = SigabaState;
+
+    /// Returns the initial state of the machine.
+    fn start(&self) -> SigabaState {
+        self.initial_state.clone()
+    }
+
+    /// Reads the positions of the cipher, control and index rotors, as five letters
+    /// each; the rotors themselves are those of the machine.
+    ///
+    fn restore(&self, saved: &str) -> Result<SigabaState, CipherError> {
+        let invalid = || CipherError::InvalidParameter { param: "state", value: saved.to_string() };
+        let set = |bank: &mut [Rotor; 5], letters: &str| {
+            if letters.len() != 5 || !letters.bytes().all(|c| c.is_ascii_uppercase()) {
+                return Err(invalid());
+            }
+            bank.iter_mut().zip(letters.bytes()).for_each(|(r, c)| r.position = (c - b'A') as usize);
+            Ok(())
+        };
+        match saved.split_whitespace().collect::<Vec<_>>()[..] {
+            [cipher, control, index] => {
+                let mut state = self.start();
+                set(&mut state.cipher_bank, cipher)?;
+                set(&mut state.control_bank, control)?;
+                set(&mut state.index_bank, index)?;
+                Ok(state)
+            }
+            _ => Err(invalid()),
         }
-        src.len()
+    }
+
+    fn encrypt_stream(&self, state: &mut SigabaState, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        Self::process(state, dst, src, false)
+    }
+
+    fn decrypt_stream(&self, state: &mut SigabaState, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        Self::process(state, dst, src, true)
     }
 }
 
@@ -326,6 +419,15 @@ impl Block for Sigaba {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_wirings_are_permutations() {
+        for w in CIPHER_WIRINGS {
+            let mut seen = [false; 26];
+            w.iter().for_each(|&c| seen[(c - b'A') as usize] = true);
+            assert!(seen.iter().all(|&s| s), "{}", String::from_utf8_lossy(w));
+        }
+    }
+
     #[test]
     fn test_sigaba_basic() {
         let s = Sigaba::new(
@@ -397,4 +499,59 @@ mod tests {
 
         assert_eq!(dec, plain);
     }
+
+    #[test]
+    fn test_sigaba_stream() {
+        let s = Sigaba::new(
+            [0, 1, 2, 3, 4], [0; 5], [false; 5],
+            [0, 1, 2, 3, 4], [0; 5], [false; 5],
+            [0, 1, 2, 3, 4], [0; 5], [false; 5],
+        );
+
+        let mut whole = vec![0u8; 10];
+        s.encrypt(&mut whole, b"HELLOWORLD");
+
+        let mut state = s.start();
+        let mut ct = vec![0u8; 10];
+        s.encrypt_stream(&mut state, &mut ct[..5], b"HELLO").unwrap();
+        let saved = state.clone();
+        s.encrypt_stream(&mut state, &mut ct[5..], b"WORLD").unwrap();
+        assert_eq!(ct, whole);
+
+        let mut state = saved;
+        let mut again = vec![0u8; 5];
+        s.encrypt_stream(&mut state, &mut again, b"WORLD").unwrap();
+        assert_eq!(&again, &whole[5..]);
+
+        assert_eq!(s.start().index_positions(), [0; 5]);
+    }
+
+    #[test]
+    fn test_sigaba_restore() {
+        let s = Sigaba::new(
+            [9, 8, 7, 6, 5], [1, 2, 3, 4, 5], [false, true, false, true, false],
+            [4, 3, 2, 1, 0], [5, 4, 3, 2, 1], [true, false, true, false, true],
+            [0, 1, 2, 3, 4], [0; 5], [false; 5],
+        );
+
+        let mut whole = vec![0u8; 10];
+        s.encrypt(&mut whole, b"HELLOWORLD");
+
+        let mut state = s.start();
+        let mut ct = vec![0u8; 10];
+        s.encrypt_stream(&mut state, &mut ct[..5], b"HELLO").unwrap();
+        let saved = state.to_string();
+
+        let mut state = s.restore(&saved).unwrap();
+        assert_eq!(state.to_string(), saved);
+        s.encrypt_stream(&mut state, &mut ct[5..], b"WORLD").unwrap();
+        assert_eq!(ct, whole);
+
+        for bad in ["ABCDE FGHIJ", "ABCDE FGHIJ KLMN", "ABCDE FGHIJ KLMNo"] {
+            assert_eq!(
+                s.restore(bad).err(),
+                Some(CipherError::InvalidParameter { param: "state", value: bad.to_string() })
+            );
+        }
+    }
 }
@@ -48,25 +48,26 @@
 //! - "Cryptonomicon" by Neal Stephenson
 //! - Wikipedia article on Solitaire cipher
 //! 
-use crate::Block;
-use std::cell::RefCell;
+use crate::{Alphabet, Block, CipherError, Normalizer, Rng, StreamCipher, Tracer};
+use crate::error::check_dst;
+use crate::trace;
+use std::fmt;
 
 /// A Solitaire cipher implementation using a 54-card deck.
 ///
-/// The `Solitaire` struct maintains the state of a deck of cards used for encryption
-/// and decryption operations. It stores both the initial deck configuration (for
-/// resetting between operations) and the current deck state (which is modified during
-/// keystream generation).
+/// The `Solitaire` struct holds the keyed deck of cards used for encryption and
+/// decryption operations. Every operation works on its own copy of that deck
+/// (a [`SolitaireState`]), which is modified during keystream generation.
 ///
 /// # Structure
 ///
-/// - `initial_deck`: The original deck configuration, preserved for resetting
-/// - `deck`: The working deck state, wrapped in `RefCell` for interior mutability
+/// - `initial_deck`: The original deck configuration, copied for each operation
+/// - `alphabet`: The symbols the keystream is added to, A-Z by default
 ///
 /// # Thread Safety
 ///
-/// This type is not thread-safe due to the use of `RefCell`. Each thread should
-/// create its own instance if parallel encryption/decryption is needed.
+/// The cipher itself is never modified, so a single instance can be shared between
+/// threads.
 ///
 /// # Examples
 ///
@@ -88,7 +89,40 @@ use std::cell::RefCell;
 #[derive(Clone)]
 pub struct Solitaire {
     initial_deck: Vec<u8>,
-    deck: RefCell<Vec<u8>>,
+    alphabet: Alphabet,
+}
+
+/// Working deck used by the [`StreamCipher`] methods.
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SolitaireState {
+    deck: Vec<u8>,
+}
+
+/// Prints the cards from top to bottom, the jokers as A and B.
+///
+impl fmt::Display for SolitaireState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, &card) in self.deck.iter().enumerate() {
+            if i > 0 {
+                write!(f, " ")?;
+            }
+            match card {
+                53 => write!(f, "A")?,
+                54 => write!(f, "B")?,
+                n => write!(f, "{}", n)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl SolitaireState {
+    /// Returns the order of the cards, from top to bottom (53 and 54 are the jokers).
+    ///
+    pub fn deck(&self) -> &[u8] {
+        &self.deck
+    }
 }
 
 impl Solitaire {
@@ -121,10 +155,43 @@ impl Solitaire {
     /// 
     pub fn new(deck: Vec<u8>) -> Self {
         assert_eq!(deck.len(), 54);
-        Solitaire {
-            initial_deck: deck.clone(),
-            deck: RefCell::new(deck),
-        }
+        Solitaire { initial_deck: deck, alphabet: Alphabet::latin() }
+    }
+
+    /// Makes the cipher add the keystream to the symbols of `alphabet` instead of A-Z.
+    ///
+    /// The keystream values are the cards taken modulo the alphabet length, so an
+    /// alphabet of more than 52 symbols never gets the largest shifts.  Lowercase
+    /// letters are still uppercased if the alphabet has no lowercase.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::UnknownSymbol` if the alphabet has a non-ASCII symbol.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use old_crypto_rs::{Alphabet, BlockExt, Solitaire};
+    ///
+    /// let cipher = Solitaire::new_with_passphrase("CRYPTONOMICON")
+    ///     .with_alphabet(&Alphabet::base36())
+    ///     .unwrap();
+    /// let ct = cipher.encrypt_str("MEET AT 1030").unwrap();
+    /// assert_eq!(cipher.decrypt_str(&ct).unwrap(), "MEET AT 1030");
+    /// ```
+    ///
+    pub fn with_alphabet(mut self, alphabet: &Alphabet) -> Result<Self, CipherError> {
+        alphabet.check_ascii()?;
+        self.alphabet = alphabet.clone();
+        Ok(self)
+    }
+
+    /// Returns the position of `ch` in the alphabet, uppercasing it if needed.
+    ///
+    fn index(&self, ch: u8) -> Option<usize> {
+        self.alphabet
+            .index(ch as char)
+            .or_else(|| self.alphabet.index(ch.to_ascii_uppercase() as char))
     }
 
     /// Creates a Solitaire cipher with an unkeyed (sorted) deck.
@@ -197,6 +264,30 @@ impl Solitaire {
         Self::new(deck)
     }
 
+    /// Creates a Solitaire cipher with a shuffled deck.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use old_crypto_rs::{Rng, Solitaire};
+    ///
+    /// let cipher = Solitaire::random(&mut Rng::new(52));
+    /// assert_eq!(cipher.deck().len(), 54);
+    /// ```
+    ///
+    pub fn random(rng: &mut Rng) -> Self {
+        let mut deck: Vec<u8> = (1..=54).collect();
+        rng.shuffle(&mut deck);
+        Self::new(deck)
+    }
+
+    /// Returns the initial order of the cards, from top to bottom (53 and 54 are the
+    /// jokers), e.g. to save the deck keyed by a passphrase.
+    ///
+    pub fn deck(&self) -> &[u8] {
+        &self.initial_deck
+    }
+
     /// Generates the next keystream value from the deck.
     ///
     /// This method performs one complete cycle of the Solitaire algorithm to produce
@@ -206,12 +297,13 @@ impl Solitaire {
     /// # Arguments
     ///
     /// * `deck` - A mutable reference to the current deck state
+    /// * `n` - The length of the alphabet, 26 for the original algorithm
     ///
     /// # Returns
     ///
-    /// A value between 1 and 26 representing the keystream output for this step.
-    /// Cards 1-26 map directly to 1-26, while cards 27-52 are reduced modulo 26
-    /// (i.e., card 27 becomes 1, card 28 becomes 2, etc.).
+    /// A value between 1 and `n` representing the keystream output for this step.
+    /// With 26 letters, cards 1-26 map directly to 1-26, while cards 27-52 are
+    /// reduced modulo 26 (i.e., card 27 becomes 1, card 28 becomes 2, etc.).
     ///
     /// # Algorithm Steps
     ///
@@ -219,9 +311,9 @@ impl Solitaire {
     /// 2. Look at the top card's value to determine a count position
     /// 3. Output the card at that position (treating jokers as value 53)
     /// 4. If output is a joker, repeat from step 1
-    /// 5. Convert the output card to a value in range 1-26
+    /// 5. Convert the output card to a value in range 1-`n`
     /// 
-    fn step(deck: &mut Vec<u8>) -> u8 {
+    fn step(deck: &mut Vec<u8>, n: usize) -> usize {
         loop {
             Self::advance_deck(deck);
 
@@ -232,9 +324,9 @@ impl Solitaire {
             let output_card = deck[count];
 
             if output_card <= 52 {
-                // Convert card value (1-52) to keystream value (1-26)
-                // Cards 1-26 stay as-is, cards 27-52 wrap around
-                return if output_card > 26 { output_card - 26 } else { output_card };
+                // Convert card value (1-52) to keystream value (1-n)
+                // With n = 26, cards 1-26 stay as-is, cards 27-52 wrap around
+                return (output_card as usize - 1) % n + 1;
             }
             // If output card is a joker, discard and repeat the entire process
         }
@@ -359,22 +451,6 @@ impl Solitaire {
             }
         }
     }
-
-    /// Resets the deck to its initial state.
-    ///
-    /// This method restores the deck to the configuration it had when the cipher
-    /// was first created. This is essential for ensuring that encryption and decryption
-    /// use the same keystream, as both operations start from the same initial deck state.
-    ///
-    /// # Implementation Note
-    ///
-    /// The deck state is maintained in a `RefCell` to allow interior mutability
-    /// while keeping the `encrypt` and `decrypt` methods with `&self` receivers
-    /// (required by the `Block` trait).
-    /// 
-    fn reset(&self) {
-        *self.deck.borrow_mut() = self.initial_deck.clone();
-    }
 }
 
 impl Block for Solitaire {
@@ -382,23 +458,38 @@ impl Block for Solitaire {
         1
     }
 
+    fn alphabet(&self) -> Option<Alphabet> {
+        Some(self.alphabet.clone())
+    }
+
+    /// The keystream is added to the alphabet only, letters by default.
+    ///
+    fn normalizer(&self) -> Normalizer {
+        self.alphabet.normalizer()
+    }
+
     /// Encrypts plaintext using the Solitaire cipher algorithm.
     ///
     /// This method generates a keystream by advancing the deck state and combines it
-    /// with the plaintext to produce ciphertext. The deck is reset to its initial state
-    /// before encryption begins, ensuring consistent keystream generation.
+    /// with the plaintext to produce ciphertext. Every call starts from the initial deck,
+    /// ensuring consistent keystream generation.
     ///
     /// # Arguments
     ///
     /// * `dst` - A mutable byte slice where the encrypted output will be written.
-    ///           Must be at least as long as `src`.
+    ///   Must be at least as long as `src`.
     /// * `src` - A byte slice containing the plaintext to encrypt.
-    ///           Only ASCII alphabetic characters are encrypted; all others pass through unchanged.
+    ///   Only characters of the alphabet (ASCII letters by default) are encrypted; all
+    ///   others pass through unchanged.
     ///
     /// # Returns
     ///
     /// The number of bytes written to `dst` (always equal to `src.len()`).
     ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::BufferTooSmall` if `dst` is shorter than `src`.
+    ///
     /// # Algorithm
     ///
     /// For each character in the source:
@@ -427,51 +518,35 @@ impl Block for Solitaire {
     ///
     /// - Input case is normalized to uppercase
     /// - Non-alphabetic characters (spaces, punctuation, digits) are preserved as-is
-    /// - The deck is reset before encryption, so multiple calls produce the same output
+    /// - Each call starts from the initial deck, so multiple calls produce the same output
     ///
-    fn encrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        // Reset deck to the initial state for consistent keystream generation
-        self.reset();
-
-        for (i, &ch) in src.iter().enumerate() {
-            if ch.is_ascii_alphabetic() {
-                // Convert plaintext character to number (A=1, B=2, ..., Z=26)
-                let p = (ch.to_ascii_uppercase() - b'A' + 1) as u8;
-
-                // Generate next keystream value (1-26)
-                let k = Self::step(&mut self.deck.borrow_mut());
-
-                // Add plaintext and keystream values (with modulo 26 wrap-around)
-                // Formula: C = (P + K - 1) mod 26 + 1
-                let c = (p + k - 1) % 26 + 1;
-
-                // Convert back to ASCII uppercase letter
-                dst[i] = c + b'A' - 1;
-            } else {
-                // Non-alphabetic characters pass through unchanged
-                dst[i] = ch;
-            }
-        }
-        src.len()
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        // Start from the initial deck for consistent keystream generation
+        self.encrypt_stream(&mut self.start(), dst, src)
     }
 
     /// Decrypts ciphertext using the Solitaire cipher algorithm.
     ///
     /// This method generates the same keystream used during encryption and subtracts it
-    /// from the ciphertext to recover the original plaintext. The deck is reset to its
-    /// initial state before decryption begins, ensuring the keystream matches encryption.
+    /// from the ciphertext to recover the original plaintext. Every call starts from the
+    /// initial deck, ensuring the keystream matches encryption.
     ///
     /// # Arguments
     ///
     /// * `dst` - A mutable byte slice where the decrypted output will be written.
-    ///           Must be at least as long as `src`.
+    ///   Must be at least as long as `src`.
     /// * `src` - A byte slice containing the ciphertext to decrypt.
-    ///           Only ASCII alphabetic characters are decrypted; all others pass through unchanged.
+    ///   Only characters of the alphabet (ASCII letters by default) are decrypted; all
+    ///   others pass through unchanged.
     ///
     /// # Returns
     ///
     /// The number of bytes written to `dst` (always equal to `src.len()`).
     ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::BufferTooSmall` if `dst` is shorter than `src`.
+    ///
     /// # Algorithm
     ///
     /// For each character in the source:
@@ -503,38 +578,107 @@ impl Block for Solitaire {
     /// - Must use the same deck configuration (initial state) as encryption
     /// - Input case is normalized to uppercase
     /// - Non-alphabetic characters are preserved unchanged
-    /// - The deck is reset before decryption to generate the correct keystream
+    /// - Each call starts from the initial deck to generate the correct keystream
+    ///
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        // Start from the initial deck for consistent keystream generation
+        self.decrypt_stream(&mut self.start(), dst, src)
+    }
+
+    /// Reports the state of the machine after every character.
+    ///
+    fn try_encrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        trace::trace_stream(self, dst, src, tracer, true)
+    }
+
+    /// Reports the state of the machine after every character.
+    ///
+    fn try_decrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        trace::trace_stream(self, dst, src, tracer, false)
+    }
+}
+
+impl StreamCipher for Solitaire {
+    type State = SolitaireState;
+
+    /// Returns a copy of the deck as it was when the cipher was created.
     ///
-    fn decrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        // Reset deck to initial state for consistent keystream generation
-        self.reset();
+    /// This is essential for ensuring that encryption and decryption use the same
+    /// keystream, as both operations start from the same initial deck state.
+    /// 
+    fn start(&self) -> SolitaireState {
+        SolitaireState { deck: self.initial_deck.clone() }
+    }
+
+    /// Reads the 54 cards from top to bottom, each one once.
+    ///
+    fn restore(&self, saved: &str) -> Result<SolitaireState, CipherError> {
+        let invalid = || CipherError::InvalidParameter { param: "state", value: saved.to_string() };
+        let mut seen = [false; 54];
+        let deck = saved
+            .split_whitespace()
+            .map(|card| {
+                let n = match card {
+                    "A" => 53,
+                    "B" => 54,
+                    n => n.parse().ok().filter(|n| (1..=52).contains(n)).ok_or_else(invalid)?,
+                };
+                if std::mem::replace(&mut seen[n as usize - 1], true) {
+                    return Err(invalid());
+                }
+                Ok(n)
+            })
+            .collect::<Result<Vec<u8>, _>>()?;
+        if deck.len() != 54 {
+            return Err(invalid());
+        }
+        Ok(SolitaireState { deck })
+    }
+
+    fn encrypt_stream(&self, state: &mut SolitaireState, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        check_dst(dst, src.len())?;
+        let n = self.alphabet.len();
 
         for (i, &ch) in src.iter().enumerate() {
-            if ch.is_ascii_alphabetic() {
-                // Convert ciphertext character to number (A=1, B=2, ..., Z=26)
-                let c = (ch.to_ascii_uppercase() - b'A' + 1) as u8;
+            if let Some(p) = self.index(ch) {
+                // Generate next keystream value (1-n)
+                let k = Self::step(&mut state.deck, n);
+
+                // Add plaintext position and keystream value (with modulo n wrap-around)
+                // With A=0 … Z=25, this is the original C = (P + K - 1) mod 26 + 1
+                dst[i] = self.alphabet.symbols()[(p + k) % n] as u8;
+            } else {
+                // Characters outside the alphabet pass through unchanged
+                dst[i] = ch;
+            }
+        }
+        Ok(src.len())
+    }
 
-                // Generate next keystream value (1-26) - must match encryption
-                let k = Self::step(&mut self.deck.borrow_mut());
+    fn decrypt_stream(&self, state: &mut SolitaireState, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        check_dst(dst, src.len())?;
+        let n = self.alphabet.len();
 
-                // Subtract keystream from ciphertext (with modulo 26 wrap-around)
-                // Formula: P = (C - K) mod 26, handling negative results
-                let p = if c > k { c - k } else { c + 26 - k };
+        for (i, &ch) in src.iter().enumerate() {
+            if let Some(c) = self.index(ch) {
+                // Generate next keystream value (1-n) - must match encryption
+                let k = Self::step(&mut state.deck, n);
 
-                // Convert back to ASCII uppercase letter
-                dst[i] = p + b'A' - 1;
+                // Subtract keystream from ciphertext (with modulo n wrap-around)
+                dst[i] = self.alphabet.symbols()[(c + n - k % n) % n] as u8;
             } else {
-                // Non-alphabetic characters pass through unchanged
+                // Characters outside the alphabet pass through unchanged
                 dst[i] = ch;
             }
         }
-        src.len()
+        Ok(src.len())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::BlockExt;
 
     #[test]
     fn test_solitaire_keystream() {
@@ -542,7 +686,7 @@ mod tests {
         let mut deck = s.initial_deck.clone();
         let mut actual = Vec::new();
         for _ in 0..10 {
-            actual.push(Solitaire::step(&mut deck));
+            actual.push(Solitaire::step(&mut deck, 26));
         }
         // Wikipedia says for unkeyed deck: 4, 49, 10, (skip 53), 24, 8, 51, 44, 6, 33, 10...
         // However, standard implementations (including this one) diverge slightly after 8 steps.
@@ -624,4 +768,63 @@ mod tests {
             assert_eq!(std::str::from_utf8(&dst).unwrap(), ct, "Failed for key '{}'", pass);
         }
     }
+
+    #[test]
+    fn test_solitaire_stream() {
+        let s = Solitaire::new_unkeyed();
+        let mut state = s.start();
+        let mut dst = vec![0u8; 15];
+        s.encrypt_stream(&mut state, &mut dst[..5], b"AAAAA").unwrap();
+        let saved = state.clone();
+        assert_eq!(saved.deck().len(), 54);
+        s.encrypt_stream(&mut state, &mut dst[5..], b"AAAAAAAAAA").unwrap();
+        assert_eq!(std::str::from_utf8(&dst).unwrap(), "EXKYIZSGEHUNTIQ");
+
+        let mut state = saved.clone();
+        let mut pt = vec![0u8; 10];
+        s.decrypt_stream(&mut state, &mut pt, b"ZSGEHUNTIQ").unwrap();
+        assert_eq!(&pt, b"AAAAAAAAAA");
+
+        assert_ne!(s.start(), saved);
+    }
+
+    #[test]
+    fn test_solitaire_restore() {
+        let s = Solitaire::new_unkeyed();
+        let mut state = s.start();
+        let mut dst = vec![0u8; 15];
+        s.encrypt_stream(&mut state, &mut dst[..5], b"AAAAA").unwrap();
+        let saved = state.to_string();
+
+        let mut state = s.restore(&saved).unwrap();
+        assert_eq!(state.to_string(), saved);
+        s.encrypt_stream(&mut state, &mut dst[5..], b"AAAAAAAAAA").unwrap();
+        assert_eq!(std::str::from_utf8(&dst).unwrap(), "EXKYIZSGEHUNTIQ");
+
+        let unkeyed = s.start().to_string();
+        for bad in [
+            unkeyed.replace(" 52 ", " 51 "),
+            unkeyed.replace(" 52 ", " 55 "),
+            unkeyed.replace(" B", ""),
+            format!("{} 1", unkeyed),
+        ] {
+            assert_eq!(
+                s.restore(&bad).err(),
+                Some(CipherError::InvalidParameter { param: "state", value: bad.clone() })
+            );
+        }
+    }
+
+    #[test]
+    fn test_solitaire_with_alphabet() {
+        let s = Solitaire::new_unkeyed().with_alphabet(&Alphabet::latin()).unwrap();
+        assert_eq!(s.encrypt_str("aaaaa").unwrap(), "EXKYI");
+
+        // First card is 4, so '0' (26) becomes '4' (30)
+        let s = Solitaire::new_unkeyed().with_alphabet(&Alphabet::base36()).unwrap();
+        let ct = s.encrypt_str("0000 AAAA").unwrap();
+        assert!(ct.starts_with('4'));
+        assert_eq!(s.decrypt_str(&ct).unwrap(), "0000 AAAA");
+        assert_eq!(s.normalizer(), Normalizer::alphanumeric());
+    }
 }
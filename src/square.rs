@@ -6,7 +6,7 @@
 //!
 //! # Algorithm
 //!
-//! 1. A key is combined with a base alphabet (BASE36) and condensed to remove duplicates
+//! 1. A key is combined with a base alphabet (`Alphabet::base36()` by default) and condensed to remove duplicates
 //! 2. The condensed alphabet is arranged in a square grid
 //! 3. Each character is encoded as coordinates (row, column) using the character set
 //! 4. Decryption reverses the process by looking up bigrams in the grid
@@ -22,22 +22,8 @@
 //! cipher.encrypt(&mut ciphertext, plaintext);
 //! ```
 //! 
-use crate::Block;
-use crate::helpers;
-
-/// Compact encoding entry for a single plaintext byte.
-///
-/// `len` is 0 for unmapped bytes, or 2 for the bigram length.
-/// `bytes` stores the bigram bytes for the code.
-#[derive(Copy, Clone, Debug, Default)]
-struct EncEntry {
-    len: u8,
-    bytes: [u8; 2],
-}
-
-/// Base alphabet used for creating the cipher square.
-/// Contains all uppercase letters A-Z followed by digits 0-9.
-pub const BASE36: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
+use crate::{Alphabet, Block, CipherError, Grid, Normalizer, Rekey, Rng};
+use crate::utf8::{self, Output};
 
 /// A Square Cipher that implements fractionating substitution.
 ///
@@ -50,22 +36,20 @@ pub const BASE36: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789";
 ///
 /// * `key` - The keyword used to initialize the cipher square
 /// * `chrs` - The character set used for bigram generation (e.g., "ADFGVX" or "012345")
-/// * `alpha` - The condensed alphabet used to populate the cipher square
-/// * `enc_table` - Fast encryption table from plaintext byte to bigram bytes
-/// * `dec_table` - Fast decryption table from bigram bytes to plaintext byte
+/// * `alphabet` - The symbols of the square, A-Z0-9 by default
+/// * `alpha` - The condensed alphabet used to populate the cipher square, row by row
 /// 
 pub struct SquareCipher {
     key: String,
-    chrs: String,
-    alpha: Vec<u8>,
-    enc_table: [EncEntry; 256],
-    dec_table: [u8; 256 * 256],
+    chrs: Alphabet,
+    alphabet: Alphabet,
+    alpha: Alphabet,
 }
 
 impl SquareCipher {
     /// Creates a new Square Cipher with the given key and character set.
     ///
-    /// The key is combined with BASE36 alphabet and condensed to remove duplicate characters.
+    /// The key is combined with the A-Z0-9 alphabet and condensed to remove duplicate characters.
     /// The character set determines which symbols will be used for the bigram encoding.
     ///
     /// # Arguments
@@ -76,7 +60,7 @@ impl SquareCipher {
     /// # Returns
     ///
     /// * `Ok(SquareCipher)` - Successfully created cipher
-    /// * `Err(String)` - Error message if key or chrs is empty
+    /// * `Err(CipherError)` - If the key or character set is invalid
     ///
     /// # Example
     ///
@@ -92,68 +76,96 @@ impl SquareCipher {
     ///
     /// # Errors
     ///
-    /// Returns an error if either `key` or `chrs` is an empty string.
+    /// Returns an error if:
+    /// - either `key` or `chrs` is an empty string (`EmptyKey`)
+    /// - `chrs` contains the same character twice (`DuplicateSymbol`)
+    /// - `chrs` is too short for the square to hold the whole alphabet (`BadAlphabetLength`)
     /// 
-    pub fn new(key: &str, chrs: &str) -> Result<Self, String> {
-        if key.is_empty() || chrs.is_empty() {
-            return Err("neither key nor chrs can be empty".to_string());
+    pub fn new(key: &str, chrs: &str) -> Result<Self, CipherError> {
+        Self::with_alphabet(key, chrs, &Alphabet::base36())
+    }
+
+    /// Creates a Square Cipher whose square holds `alphabet` instead of A-Z0-9.
+    ///
+    /// Key characters that are not in the alphabet are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`SquareCipher::new`], the size of the square depending on the alphabet.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use old_crypto_rs::{Alphabet, BlockExt, SquareCipher};
+    ///
+    /// // Classic 5x5 Polybius square
+    /// let a = Alphabet::latin().without("J").unwrap();
+    /// let cipher = SquareCipher::with_alphabet("POLYBIUS", "12345", &a).unwrap();
+    /// assert_eq!(cipher.encrypt_str("HELLO").unwrap(), "3532131312");
+    ///
+    /// // Greek square, the 24 letters in a 5x5 grid
+    /// let a = Alphabet::new("ΑΒΓΔΕΖΗΘΙΚΛΜΝΞΟΠΡΣΤΥΦΧΨΩ").unwrap();
+    /// let cipher = SquareCipher::with_alphabet("ΠΟΛΥΒΙΟΣ", "12345", &a).unwrap();
+    /// assert_eq!(cipher.encrypt_str("ΕΡΜΗΣ").unwrap(), "3144413322");
+    /// ```
+    ///
+    pub fn with_alphabet(key: &str, chrs: &str, alphabet: &Alphabet) -> Result<Self, CipherError> {
+        if key.is_empty() {
+            return Err(CipherError::EmptyKey("key"));
         }
+        let chrs = Alphabet::new(chrs).map_err(|e| e.for_param("chrs"))?;
+        let alpha = alphabet.keyed(key);
 
-        let alpha = helpers::condense(&format!("{}{}", key, BASE36)).as_bytes().to_vec();
+        // The square must have room for every symbol of the alphabet
+        let side = (1..).find(|n| n * n >= alpha.len()).unwrap_or(0);
+        if chrs.len() < side {
+            return Err(CipherError::BadAlphabetLength { param: "chrs", expected: side, got: chrs.len() });
+        }
 
-        let mut c = SquareCipher {
+        Ok(SquareCipher {
             key: key.to_string(),
-            chrs: chrs.to_string(),
+            chrs,
+            alphabet: alphabet.clone(),
             alpha,
-            enc_table: [EncEntry::default(); 256],
-            dec_table: [0; 256 * 256],
-        };
-        c.expand_key();
-        Ok(c)
+        })
+    }
+
+    /// Creates a Square Cipher whose square is a random arrangement of A-Z0-9, with
+    /// `chrs` as coordinates.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`SquareCipher::new`] for `chrs`.
+    ///
+    pub fn random(rng: &mut Rng, chrs: &str) -> Result<Self, CipherError> {
+        Self::new(&rng.permutation(&Alphabet::base36()), chrs)
     }
 
-    /// Expands the key into encryption and decryption lookup tables.
+    /// Returns the square as a [`Grid`], rows and columns labelled with the
+    /// coordinate symbols.  Unused cells of a square larger than the alphabet are
+    /// blank.
     ///
-    /// This method generates all possible bigrams from the character set and maps them
-    /// to/from the condensed alphabet. Each character in the alphabet is assigned
-    /// coordinates (i, j) which are encoded as a bigram using characters from `chrs`.
+    /// # Example
     ///
-    /// For example, with chrs="ADFGVX" and a 6x6 grid:
-    /// - Position (0,0) → "AA"
-    /// - Position (0,1) → "AD"
-    /// - Position (1,0) → "DA"
-    /// - etc.
-    /// 
-    fn expand_key(&mut self) {
-        let mut bigr = vec![0u8; 2];
-        let klen = self.chrs.len();
-        let chrs_bytes = self.chrs.as_bytes();
-
-        // Generate all bigrams by iterating through character set twice (i, j)
-        for i in 0..klen {
-            for j in 0..klen {
-                // Create bigram: first char from row i, second char from column j
-                bigr[0] = chrs_bytes[i];
-                bigr[1] = chrs_bytes[j];
-
-                // Calculate linear index in the square (row * width + column)
-                let ind = i * klen + j;
-
-                // Only map if we haven't exceeded the alphabet length
-                if ind < self.alpha.len() {
-                    // Forward mapping: alphabet character → bigram
-                    let pt = self.alpha[ind];
-                    let entry = EncEntry {
-                        len: 2,
-                        bytes: [bigr[0], bigr[1]],
-                    };
-                    self.enc_table[pt as usize] = entry;
-                    // Reverse mapping: bigram → alphabet character
-                    let idx = ((bigr[0] as usize) << 8) | (bigr[1] as usize);
-                    self.dec_table[idx] = pt;
-                }
-            }
+    /// ```
+    /// use old_crypto_rs::{Alphabet, SquareCipher};
+    ///
+    /// let a = Alphabet::latin().without("J").unwrap();
+    /// let cipher = SquareCipher::with_alphabet("POLYBIUS", "12345", &a).unwrap();
+    /// let g = cipher.grid();
+    /// assert_eq!(g.columns(), ["1", "2", "3", "4", "5"]);
+    /// assert_eq!(g.cell(1, 0), Some("I"));
+    /// ```
+    ///
+    pub fn grid(&self) -> Grid {
+        let chrs = self.chrs.symbols();
+        let mut g = Grid::new(chrs);
+        let mut cells = self.alpha.symbols().chunks(chrs.len());
+        for label in chrs {
+            let Some(row) = cells.next() else { break };
+            g.push_row(&label.to_string(), row);
         }
+        g
     }
 }
 
@@ -165,78 +177,145 @@ impl Block for SquareCipher {
     ///
     /// # Returns
     ///
-    /// The length of the cipher key in bytes.
+    /// The length of the cipher key in characters.
     /// 
     fn block_size(&self) -> usize {
-        self.key.len()
+        self.key.chars().count()
+    }
+
+    fn alphabet(&self) -> Option<Alphabet> {
+        Some(self.alphabet.clone())
+    }
+
+    /// The square holds the alphabet, the 26 letters and the 10 digits by default.
+    /// A square with I but no J, like the classic Polybius one, gets J written I.
+    ///
+    fn normalizer(&self) -> Normalizer {
+        let n = self.alphabet.normalizer();
+        if self.alphabet.contains('I') && !self.alphabet.contains('J') {
+            n.substitute('J', "I")
+        } else {
+            n
+        }
+    }
+
+    /// Every character becomes a bigram, so the output has twice as many characters
+    /// as the input, each as wide as the widest coordinate symbol.
+    /// 
+    fn encrypt_len(&self, src: &[u8]) -> usize {
+        utf8::char_count(src) * 2 * self.chrs.max_utf8_len()
+    }
+
+    /// Every bigram becomes one character, so the output has half as many characters
+    /// as the input, each as wide as the widest symbol of the square.
+    /// 
+    fn decrypt_len(&self, src: &[u8]) -> usize {
+        utf8::char_count(src) / 2 * self.alpha.max_utf8_len()
+    }
+
+    fn max_encrypt_len(&self, len: usize) -> usize {
+        len * 2 * self.chrs.max_utf8_len()
+    }
+
+    fn max_decrypt_len(&self, len: usize) -> usize {
+        len / 2 * self.alpha.max_utf8_len()
+    }
+
+    fn encrypt_chars(&self, chars: usize) -> Option<usize> {
+        Some(2 * chars)
     }
 
     /// Encrypts plaintext into ciphertext using the Square Cipher.
     ///
-    /// Each byte in the source is replaced by a two-character bigram, effectively
+    /// Each character in the source is replaced by a two-character bigram, effectively
     /// doubling the length of the output. The bigram represents the row and column
     /// coordinates of the character in the cipher square.
     ///
     /// # Arguments
     ///
-    /// * `dst` - Destination buffer for ciphertext (must be at least 2 * src.len())
-    /// * `src` - Source plaintext bytes to encrypt
+    /// * `dst` - Destination buffer for ciphertext (see `encrypt_len`)
+    /// * `src` - Source plaintext to encrypt, as UTF-8
     ///
     /// # Returns
     ///
-    /// The number of bytes written to dst (always 2 * src.len())
+    /// The number of bytes written to dst (2 * src.len() for ASCII coordinates)
     ///
-    /// # Note
+    /// # Errors
     ///
-    /// Characters not found in the encryption table are silently skipped.
+    /// Returns `CipherError::InvalidUtf8` if `src` is not UTF-8,
+    /// `CipherError::InvalidChar` for a character that is not in the square and
+    /// `CipherError::BufferTooSmall` if `dst` is too short.
     /// 
-    fn encrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        for (i, &ch) in src.iter().enumerate() {
-            let entry = self.enc_table[ch as usize];
-            if entry.len == 2 {
-                // Write the two-character bigram to destination
-                dst[i * 2] = entry.bytes[0];
-                dst[i * 2 + 1] = entry.bytes[1];
-            }
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        let width = self.chrs.len();
+        let mut out = Output::new(dst);
+        for (pos, ch) in utf8::decode(src)?.char_indices() {
+            let ind = self.alpha.index(ch).ok_or(CipherError::InvalidChar { ch, pos })?;
+            // Write the row then the column coordinates
+            out.push(self.chrs.symbols()[ind / width]);
+            out.push(self.chrs.symbols()[ind % width]);
         }
-        src.len() * 2
+        out.finish()
     }
 
     /// Decrypts ciphertext back into plaintext using the Square Cipher.
     ///
     /// Processes the source in pairs of characters (bigrams), looking up each
-    /// bigram in the decryption table to recover the original character.
+    /// bigram in the square to recover the original character.
     ///
     /// # Arguments
     ///
-    /// * `dst` - Destination buffer for plaintext (must be at least src.len() / 2)
-    /// * `src` - Source ciphertext bytes to decrypt (must have even length)
+    /// * `dst` - Destination buffer for plaintext (see `decrypt_len`)
+    /// * `src` - Source ciphertext to decrypt (must have an even number of characters)
     ///
     /// # Returns
     ///
-    /// The number of bytes written to dst (always src.len() / 2)
+    /// The number of bytes written to dst (src.len() / 2 for an ASCII square)
     ///
-    /// # Note
+    /// # Errors
     ///
-    /// Bigrams not found in the decryption table are silently skipped.
+    /// Returns `CipherError::InvalidUtf8` if `src` is not UTF-8,
+    /// `CipherError::OddLength` if `src` has an odd number of characters,
+    /// `CipherError::UnknownBigram` for a bigram that is not in the square and
+    /// `CipherError::BufferTooSmall` if `dst` is too short.
     /// 
-    fn decrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        let chars: Vec<(usize, char)> = utf8::decode(src)?.char_indices().collect();
+        if chars.len() % 2 == 1 {
+            return Err(CipherError::OddLength { len: chars.len() });
+        }
+        let mut out = Output::new(dst);
         // Process source in steps of 2 (each bigram)
-        for i in (0..src.len()).step_by(2) {
-            let idx = ((src[i] as usize) << 8) | (src[i + 1] as usize);
-            let pt = self.dec_table[idx];
-            if pt != 0 {
-                // Write the recovered character to destination
-                dst[i / 2] = pt;
-            }
+        for pair in chars.chunks(2) {
+            let ((pos, r), (_, c)) = (pair[0], pair[1]);
+            let pt = match (self.chrs.index(r), self.chrs.index(c)) {
+                (Some(i), Some(j)) => self.alpha.get(i * self.chrs.len() + j),
+                _ => None,
+            };
+            out.push(pt.ok_or(CipherError::UnknownBigram { bigram: [r, c], pos })?);
+        }
+        out.finish()
+    }
+}
+
+/// The square is keyed again, over the same alphabet and with the same coordinates.
+///
+impl Rekey for SquareCipher {
+    fn rekey(&mut self, key: &str) -> Result<(), CipherError> {
+        if key.is_empty() {
+            return Err(CipherError::EmptyKey("key"));
         }
-        src.len() / 2
+        self.key.clear();
+        self.key.push_str(key);
+        self.alpha.set_keyed(&self.alphabet, key);
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::BlockExt;
 
     #[test]
     fn test_expand_key() {
@@ -272,6 +351,18 @@ mod tests {
         assert!(c.is_err());
     }
 
+    #[test]
+    fn test_new_cipher_bad_chrs() {
+        assert_eq!(
+            SquareCipher::new("SUBWAY", "ADFGVA").err(),
+            Some(CipherError::DuplicateSymbol { param: "chrs", symbol: 'A' })
+        );
+        assert_eq!(
+            SquareCipher::new("SUBWAY", "ADFGV").err(),
+            Some(CipherError::BadAlphabetLength { param: "chrs", expected: 6, got: 5 })
+        );
+    }
+
     #[test]
     fn test_square_cipher_block_size() {
         let test_data = [
@@ -284,6 +375,13 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_square_cipher_lengths() {
+        let c = SquareCipher::new("PORTABLE", "ADFGVX").unwrap();
+        assert_eq!(c.encrypt_len(b"ATTACK"), 12);
+        assert_eq!(c.decrypt_len(b"AVAGAGAVDFFG"), 6);
+    }
+
     #[test]
     fn test_square_cipher_encrypt() {
         let test_data = [
@@ -315,4 +413,71 @@ mod tests {
             assert_eq!(String::from_utf8(dst).unwrap(), pt);
         }
     }
+
+    #[test]
+    fn test_square_cipher_try_errors() {
+        let c = SquareCipher::new("PORTABLE", "ADFGVX").unwrap();
+        let mut dst = vec![0u8; 4];
+        assert_eq!(c.try_encrypt(&mut dst, b"A-"), Err(CipherError::InvalidChar { ch: '-', pos: 1 }));
+        assert_eq!(c.try_encrypt(&mut dst, b"ATT"), Err(CipherError::BufferTooSmall { needed: 6, got: 4 }));
+        assert_eq!(c.try_decrypt(&mut dst, b"AVA"), Err(CipherError::OddLength { len: 3 }));
+        assert_eq!(
+            c.try_decrypt(&mut dst, b"AVAZ"),
+            Err(CipherError::UnknownBigram { bigram: ['A', 'Z'], pos: 2 })
+        );
+    }
+
+    #[test]
+    fn test_square_with_alphabet() {
+        let a = Alphabet::latin().without("J").unwrap();
+        let c = SquareCipher::with_alphabet("POLYBIUS", "12345", &a).unwrap();
+        assert_eq!(c.alpha.to_string(), "POLYBIUSACDEFGHKMNQRTVWXZ");
+        assert_eq!(c.normalizer(), Normalizer::letters().substitute('J', "I"));
+        assert_eq!(c.normalizer().normalize("Jolly"), "IOLLY");
+        assert_eq!(c.encrypt_to_vec(b"HELLO").unwrap(), b"3532131312");
+        assert_eq!(c.decrypt_to_vec(b"3532131312").unwrap(), b"HELLO");
+        assert_eq!(
+            SquareCipher::with_alphabet("POLYBIUS", "1234", &a).err(),
+            Some(CipherError::BadAlphabetLength { param: "chrs", expected: 5, got: 4 })
+        );
+    }
+
+    #[test]
+    fn test_square_greek() {
+        let a = Alphabet::new("ΑΒΓΔΕΖΗΘΙΚΛΜΝΞΟΠΡΣΤΥΦΧΨΩ").unwrap();
+        let c = SquareCipher::with_alphabet("ΠΟΛΥΒΙΟΣ", "12345", &a).unwrap();
+        assert_eq!(c.alpha.to_string(), "ΠΟΛΥΒΙΣΑΓΔΕΖΗΘΚΜΝΞΡΤΦΧΨΩ");
+        assert_eq!(c.normalizer().normalize("Ερμής, 3."), "ΕΡΜΗΣ");
+
+        let pt = "ΓΝΩΘΙΣΕΑΥΤΟΝ";
+        let ct = c.encrypt_str(pt).unwrap();
+        assert_eq!(c.encrypt_len(pt.as_bytes()), ct.len());
+        assert_eq!(c.decrypt_str(&ct).unwrap(), pt);
+        assert_eq!(
+            c.encrypt_str("ΓΝΩΘΙ ΣΕ"),
+            Err(CipherError::InvalidChar { ch: ' ', pos: 10 })
+        );
+        assert_eq!(c.try_encrypt(&mut [0u8; 4], b"\xce"), Err(CipherError::InvalidUtf8 { pos: 0 }));
+    }
+
+    #[test]
+    fn test_square_non_ascii_coordinates() {
+        let c = SquareCipher::with_alphabet("PORTABLE", "АБВГДЕ", &Alphabet::base36()).unwrap();
+        let ct = c.encrypt_str("ATTACK").unwrap();
+        assert_eq!(ct.chars().count(), 12);
+        assert_eq!(c.encrypt_len(b"ATTACK"), ct.len());
+        assert_eq!(c.decrypt_str(&ct).unwrap(), "ATTACK");
+    }
+
+    #[test]
+    fn test_square_rekey() {
+        let mut c = SquareCipher::new("ARABESQUE", "ADFGVX").unwrap();
+        c.rekey("PORTABLE").unwrap();
+        let fresh = SquareCipher::new("PORTABLE", "ADFGVX").unwrap();
+        assert_eq!(c.encrypt_str("ATTACKAT1200").unwrap(), fresh.encrypt_str("ATTACKAT1200").unwrap());
+        assert_eq!(c.block_size(), fresh.block_size());
+
+        assert_eq!(c.rekey(""), Err(CipherError::EmptyKey("key")));
+        assert_eq!(c.grid(), fresh.grid());
+    }
 }
@@ -7,16 +7,23 @@
 //!
 //! The cipher uses:
 //! - A keyword to shuffle the alphabet
-//! - Two "long" cipher digits that prefix two-digit codes
-//! - Eight "short" cipher digits for single-digit codes
+//! - One to three "long" cipher digits that prefix two-digit codes, the blanks of the
+//!   first row of the board, each one adding a row of ten cells
+//! - The other "short" cipher digits for single-digit codes
 //! - A frequency string to determine which letters get single-digit codes
 //!
-use crate::Block;
-use crate::helpers;
+//! A board with two blanks holds 8 + 20 symbols, enough for the Latin alphabet; three
+//! blanks make room for a larger one, or for the digits themselves.  How digits of the
+//! plaintext are written is chosen with [`DigitEscape`].
+//!
+use crate::{Alphabet, Block, CipherError, Digits, Grid, Normalizer, Rekey, Rng};
+use crate::utf8::{self, Output};
+use std::fmt;
+use std::str::FromStr;
 
-/// Compact encoding entry for a single plaintext byte.
+/// Compact encoding entry for a single plaintext character.
 ///
-/// `len` is 0 for unmapped bytes, or 1/2 for the number of output digits.
+/// `len` is 0 for unmapped characters, or 1/2 for the number of output digits.
 /// `bytes` stores the digit bytes for the code.
 #[derive(Copy, Clone, Debug, Default)]
 struct EncEntry {
@@ -31,6 +38,58 @@ pub const ALPHABET_TXT: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ/-";
 /// All cipher digits from 0 to 9 used in the checkerboard.
 const ALL_CIPHER: &[u8] = b"0123456789";
 
+/// How the digits of the plaintext are written.
+///
+/// The first two conventions use the '/' symbol of the board as a marker, and can
+/// not write the digits if it is not on the board.
+///
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DigitEscape {
+    /// Each digit is written twice between two markers: "2" becomes `/ 22 /`.
+    #[default]
+    Repeat,
+    /// The marker switches to figures and back, around a whole number: "20" becomes
+    /// `/ 22 00 /`.  Each digit is written twice, so that the marker ends the number;
+    /// it can not be a code made of the same digit twice.  A '/' of the plaintext
+    /// can not be written.
+    FigureShift,
+    /// Digits are symbols of the board, with a code of their own like the letters.
+    OnBoard,
+}
+
+/// The form used by the `escape` parameter of the [registry](crate::registry):
+/// `repeat`, `figures` or `board`.
+///
+impl fmt::Display for DigitEscape {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DigitEscape::Repeat => write!(f, "repeat"),
+            DigitEscape::FigureShift => write!(f, "figures"),
+            DigitEscape::OnBoard => write!(f, "board"),
+        }
+    }
+}
+
+impl FromStr for DigitEscape {
+    type Err = CipherError;
+
+    /// Parses the form written by `Display`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidParameter` for the `escape` parameter if `s` is not one of the
+    /// three forms.
+    ///
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "repeat" => Ok(DigitEscape::Repeat),
+            "figures" => Ok(DigitEscape::FigureShift),
+            "board" => Ok(DigitEscape::OnBoard),
+            _ => Err(CipherError::InvalidParameter { param: "escape", value: s.to_string() }),
+        }
+    }
+}
+
 /// A straddling checkerboard cipher implementation.
 ///
 /// This cipher maps plaintext characters to variable-length digit sequences.
@@ -53,18 +112,27 @@ const ALL_CIPHER: &[u8] = b"0123456789";
 pub struct StraddlingCheckerboard {
     /// The keyword used to shuffle the alphabet.
     key: String,
-    /// The two digits used as prefixes for two-digit codes (typically 2 bytes).
+    /// The letters getting a single digit, if they are on the board.
+    freq: String,
+    /// The digits used as prefixes for two-digit codes, one per row of the board.
     longc: Vec<u8>,
+    /// The alphabet on the board.
+    alphabet: Alphabet,
     /// The shuffled alphabet after applying the key.
-    full: String,
-    /// Fast encoding table indexed by plaintext byte.
-    enc_table: [EncEntry; 256],
+    full: Alphabet,
+    /// Encoding table, indexed like `full`.
+    codes: Vec<EncEntry>,
     /// Fast decoding table for single-digit codes.
-    dec1: [u8; 10],
+    dec1: [Option<char>; 10],
     /// Fast decoding table for two-digit codes.
-    dec2: [[u8; 10]; 10],
+    dec2: [[Option<char>; 10]; 10],
     /// Fast lookup for whether a digit is a long-code prefix.
     longc_mask: [bool; 10],
+    /// How digits are written.
+    escape: DigitEscape,
+    /// The rows of a board made by [`StraddlingCheckerboard::from_rows`], whose key
+    /// is the header.
+    rows: Option<Vec<String>>,
 }
 
 impl StraddlingCheckerboard {
@@ -76,17 +144,20 @@ impl StraddlingCheckerboard {
     /// # Arguments
     ///
     /// * `key` - The keyword used to shuffle the alphabet (must not be empty)
-    /// * `chrs` - A string of at least 2 digits that will be used as "long" cipher digits
+    /// * `chrs` - Distinct digits that will be used as "long" cipher digits, each one
+    ///   adding a row of 10 two-digit codes to the board: 2 for this alphabet
     ///
     /// # Returns
     ///
-    /// Returns `Ok(StraddlingCheckerboard)` on success, or `Err(String)` if validation fails.
+    /// Returns `Ok(StraddlingCheckerboard)` on success, or a `CipherError` if validation fails.
     ///
     /// # Errors
     ///
     /// Returns an error if:
-    /// - `key` is empty
-    /// - `chrs` contains fewer than 2 characters
+    /// - `key` is empty (`EmptyKey`)
+    /// - `chrs` contains fewer than 2 characters (`KeyTooShort`)
+    /// - `chrs` contains something other than digits (`InvalidDigit`) or a digit
+    ///   twice (`DuplicateSymbol`)
     ///
     /// # Examples
     ///
@@ -96,8 +167,34 @@ impl StraddlingCheckerboard {
     /// let cipher = StraddlingCheckerboard::new("ARABESQUE", "89").unwrap();
     /// ```
     ///
-    pub fn new(key: &str, chrs: &str) -> Result<Self, String> {
-        Self::new_with_freq(key, chrs, "ESANTIRU", ALPHABET_TXT)
+    pub fn new(key: &str, chrs: &str) -> Result<Self, CipherError> {
+        Self::with_alphabet(key, chrs, &Alphabet::new(ALPHABET_TXT)?)
+    }
+
+    /// Creates a new straddling checkerboard cipher over a custom alphabet, with
+    /// the default frequency.
+    ///
+    /// The '/' symbol, if present, is the digit escape marker.  The eight letters
+    /// getting a single digit are "ESANTIRU" if they are on the board; with another
+    /// script use [`new_with_freq`](Self::new_with_freq).
+    ///
+    /// # Errors
+    ///
+    /// Same as [`StraddlingCheckerboard::new`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use old_crypto_rs::{Alphabet, BlockExt, StraddlingCheckerboard};
+    ///
+    /// let a = Alphabet::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ.").unwrap();
+    /// let cipher = StraddlingCheckerboard::with_alphabet("ARABESQUE", "89", &a).unwrap();
+    /// let ct = cipher.encrypt_str("ATTACK.").unwrap();
+    /// assert_eq!(cipher.decrypt_str(&ct).unwrap(), "ATTACK.");
+    /// ```
+    ///
+    pub fn with_alphabet(key: &str, chrs: &str, alphabet: &Alphabet) -> Result<Self, CipherError> {
+        Self::new_with_freq(key, chrs, "ESANTIRU", alphabet)
     }
 
     /// Creates a new straddling checkerboard cipher with custom frequency and alphabet.
@@ -108,56 +205,293 @@ impl StraddlingCheckerboard {
     /// # Arguments
     ///
     /// * `key` - The keyword used to shuffle the alphabet (must not be empty)
-    /// * `chrs` - A string of at least 2 digits for "long" cipher digit prefixes
+    /// * `chrs` - Distinct digits for "long" cipher digit prefixes, enough for the
+    ///   board to hold the alphabet: 1 for up to 19 symbols (9 + 10 cells), 2 for up to
+    ///   28 and 3 for up to 37, e.g. a 33-letter alphabet
     /// * `freq_str` - Letters that should receive single-digit encodings
-    /// * `alphabet` - The alphabet to use for the checkerboard
+    /// * `alphabet` - The alphabet to use for the checkerboard (see [`ALPHABET_TXT`])
     ///
     /// # Returns
     ///
-    /// Returns `Ok(StraddlingCheckerboard)` on success, or `Err(String)` if validation fails.
+    /// Returns `Ok(StraddlingCheckerboard)` on success, or a `CipherError` if validation fails.
     ///
     /// # Errors
     ///
     /// Returns an error if:
-    /// - `key` is empty
-    /// - `chrs` contains fewer than 2 characters
+    /// - `key` is empty (`EmptyKey`)
+    /// - `chrs` contains something other than digits (`InvalidDigit`) or a digit
+    ///   twice (`DuplicateSymbol`)
+    /// - `chrs` has too few digits for the board to hold the alphabet (`KeyTooShort`),
+    ///   the letters of `freq_str` getting the single digits as long as there are some
+    ///   left, the other symbols a cell of a long row
+    /// - `alphabet` has more than 100 symbols (`BadAlphabetLength`)
+    ///
+    /// # Examples
+    ///
+    /// A Russian checkerboard: the 33 letters, the full stop and the digit marker in
+    /// 7 + 30 cells, ОЕАИНТС getting the single digits.
+    ///
+    /// ```
+    /// use old_crypto_rs::{Alphabet, BlockExt, StraddlingCheckerboard};
+    ///
+    /// let a = Alphabet::new("АБВГДЕЁЖЗИЙКЛМНОПРСТУФХЦЧШЩЪЫЬЭЮЯ./").unwrap();
+    /// let cipher = StraddlingCheckerboard::new_with_freq("СНЕГОПАД", "257", "ОЕАИНТС", &a).unwrap();
+    /// let ct = cipher.encrypt_str("ВСТРЕЧАВ10.").unwrap();
+    /// assert!(ct.bytes().all(|b| b.is_ascii_digit()));
+    /// assert_eq!(cipher.decrypt_str(&ct).unwrap(), "ВСТРЕЧАВ10.");
+    /// ```
     ///
-    pub fn new_with_freq(key: &str, chrs: &str, freq_str: &str, alphabet: &str) -> Result<Self, String> {
+    pub fn new_with_freq(key: &str, chrs: &str, freq_str: &str, alphabet: &Alphabet) -> Result<Self, CipherError> {
         if key.is_empty() {
-            return Err("key can not be empty".to_string());
+            return Err(CipherError::EmptyKey("key"));
         }
-        if chrs.len() < 2 {
-            return Err("longc must have at least 2 characters".to_string());
+        if let Some(ch) = chrs.chars().find(|c| !c.is_ascii_digit()) {
+            return Err(CipherError::InvalidDigit { param: "chrs", ch });
+        }
+        if let Some((i, _)) = chrs.char_indices().find(|&(i, c)| chrs[..i].contains(c)) {
+            return Err(CipherError::DuplicateSymbol { param: "chrs", symbol: chrs.as_bytes()[i] as char });
+        }
+        let min = Self::long_needed(alphabet, freq_str)
+            .ok_or(CipherError::BadAlphabetLength { param: "alphabet", expected: 100, got: alphabet.len() })?;
+        if chrs.len() < min {
+            return Err(CipherError::KeyTooShort { param: "chrs", min, got: chrs.len() });
         }
 
-        let longc = vec![chrs.as_bytes()[0], chrs.as_bytes()[1]];
-        let full = if key.is_empty() {
-            alphabet.to_string()
-        } else {
-            helpers::shuffle(key, alphabet)
-        };
-        // Remove digits from full if they were added by the key but not in the alphabet
-        let full_clean: String = full.chars().filter(|&c| alphabet.contains(c)).collect();
+        let longc = chrs.as_bytes().to_vec();
+        let full = alphabet.shuffled(key);
         let shortc = Self::extract(ALL_CIPHER, &longc);
 
         let mut c = StraddlingCheckerboard {
             key: key.to_string(),
-            full: full_clean,
+            freq: freq_str.to_string(),
+            alphabet: alphabet.clone(),
+            codes: vec![EncEntry::default(); full.len()],
+            full,
             longc,
-            enc_table: [EncEntry::default(); 256],
-            dec1: [0; 10],
-            dec2: [[0; 10]; 10],
+            dec1: [None; 10],
+            dec2: [[None; 10]; 10],
             longc_mask: [false; 10],
+            escape: DigitEscape::Repeat,
+            rows: None,
         };
         for &c_digit in &c.longc {
             if c_digit.is_ascii_digit() {
                 c.longc_mask[(c_digit - b'0') as usize] = true;
             }
         }
-        c.expand_key(shortc, freq_str.as_bytes());
+        c.expand_key(shortc, freq_str);
+        Ok(c)
+    }
+
+    /// Creates a checkerboard from its rows as drawn on paper, over the digits of
+    /// `header`.
+    ///
+    /// The first row has ten cells, a space marking each blank: the digits above the
+    /// blanks are the long digits, and label the next rows, in order.  This is how
+    /// the VIC procedure builds its board, the header being a permutation of the
+    /// digits derived from the key.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `header` is not a permutation of the ten digits (`BadAlphabetLength`,
+    ///   `UnknownSymbol` or `DuplicateSymbol`)
+    /// - the first row does not have ten cells, or a row has more than ten
+    ///   (`BadAlphabetLength`)
+    /// - there is no blank, or not one row per blank (`KeyTooShort`)
+    /// - a symbol is on the board twice (`DuplicateSymbol`)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use old_crypto_rs::{BlockExt, StraddlingCheckerboard};
+    ///
+    /// let cipher = StraddlingCheckerboard::from_rows(
+    ///     "5079281364",
+    ///     &["AT ONE SIR", "BCDFGHJKLM", "PQUVWXYZ./"],
+    /// ).unwrap();
+    /// assert_eq!(cipher.encrypt_str("ATTACK").unwrap(), "50057073");
+    /// ```
+    ///
+    pub fn from_rows(header: &str, rows: &[&str]) -> Result<Self, CipherError> {
+        Alphabet::new("0123456789")?.check_permutation("header", header)?;
+        let (top, rest) = rows.split_first().ok_or(CipherError::EmptyKey("rows"))?;
+        let cells = top.chars().count();
+        if cells != 10 {
+            return Err(CipherError::BadAlphabetLength { param: "rows", expected: 10, got: cells });
+        }
+        let longc: Vec<u8> = top.chars().zip(header.bytes()).filter(|&(c, _)| c == ' ').map(|(_, d)| d).collect();
+        if longc.is_empty() {
+            return Err(CipherError::KeyTooShort { param: "rows", min: 1, got: 0 });
+        }
+        if rest.len() != longc.len() {
+            return Err(CipherError::KeyTooShort { param: "rows", min: longc.len() + 1, got: rows.len() });
+        }
+        if let Some(row) = rest.iter().find(|r| r.chars().count() > 10) {
+            return Err(CipherError::BadAlphabetLength { param: "rows", expected: 10, got: row.chars().count() });
+        }
+        let symbols: String = rows.concat().chars().filter(|&c| c != ' ').collect();
+        let alphabet = Alphabet::new(&symbols).map_err(|e| e.for_param("rows"))?;
+
+        let mut c = StraddlingCheckerboard {
+            key: header.to_string(),
+            freq: top.chars().filter(|&c| c != ' ').collect(),
+            codes: vec![EncEntry::default(); alphabet.len()],
+            full: alphabet.clone(),
+            alphabet,
+            longc,
+            dec1: [None; 10],
+            dec2: [[None; 10]; 10],
+            longc_mask: [false; 10],
+            escape: DigitEscape::Repeat,
+            rows: Some(rows.iter().map(|r| r.to_string()).collect()),
+        };
+        c.fill_rows();
         Ok(c)
     }
 
+    /// Sets how the digits of the plaintext are written, [`DigitEscape::Repeat`] by
+    /// default.
+    ///
+    /// # Errors
+    ///
+    /// Returns `InvalidParameter` for the `escape` parameter if the board does not
+    /// allow it: `OnBoard` needs the ten digits on the board, `FigureShift` a marker
+    /// whose code is not the same digit twice.
+    ///
+    /// # Examples
+    ///
+    /// A board with three blanks, room for the digits:
+    ///
+    /// ```
+    /// use old_crypto_rs::{Alphabet, BlockExt, DigitEscape, StraddlingCheckerboard};
+    ///
+    /// let a = Alphabet::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789").unwrap();
+    /// let cipher = StraddlingCheckerboard::with_alphabet("ARABESQUE", "234", &a)
+    ///     .unwrap()
+    ///     .with_escape(DigitEscape::OnBoard)
+    ///     .unwrap();
+    /// let ct = cipher.encrypt_str("AT2215").unwrap();
+    /// assert_eq!(ct, "04623234736");
+    /// assert_eq!(cipher.decrypt_str(&ct).unwrap(), "AT2215");
+    /// ```
+    ///
+    pub fn with_escape(mut self, escape: DigitEscape) -> Result<Self, CipherError> {
+        self.check_escape(escape)?;
+        self.escape = escape;
+        Ok(self)
+    }
+
+    /// Checks that the board allows `escape`.
+    ///
+    fn check_escape(&self, escape: DigitEscape) -> Result<(), CipherError> {
+        let ok = match escape {
+            DigitEscape::Repeat => true,
+            DigitEscape::FigureShift => {
+                let m = self.code('/');
+                m.len != 2 || m.bytes[0] != m.bytes[1]
+            }
+            DigitEscape::OnBoard => ('0'..='9').all(|d| self.code(d).len != 0),
+        };
+        if ok { Ok(()) } else { Err(CipherError::InvalidParameter { param: "escape", value: escape.to_string() }) }
+    }
+
+    /// Writes the codes of a board made by [`StraddlingCheckerboard::from_rows`], the
+    /// key being the header.
+    ///
+    fn fill_rows(&mut self) {
+        let Some(rows) = self.rows.take() else { return };
+        let header = self.key.as_bytes();
+        let (top, rest) = rows.split_first().unwrap_or_else(|| unreachable!());
+        self.codes.fill(EncEntry::default());
+        self.dec1 = [None; 10];
+        self.dec2 = [[None; 10]; 10];
+        self.longc_mask = [false; 10];
+        self.longc.clear();
+        for (i, ch) in top.chars().enumerate() {
+            let d = header[i];
+            if ch == ' ' {
+                self.longc.push(d);
+                continue;
+            }
+            self.codes[self.full.index(ch).unwrap_or_default()] = EncEntry { len: 1, bytes: [d, 0] };
+            self.dec1[(d - b'0') as usize] = Some(ch);
+        }
+        for (&l, row) in self.longc.iter().zip(rest) {
+            self.longc_mask[(l - b'0') as usize] = true;
+            for (i, ch) in row.chars().enumerate().filter(|&(_, c)| c != ' ') {
+                let d = header[i];
+                self.codes[self.full.index(ch).unwrap_or_default()] = EncEntry { len: 2, bytes: [l, d] };
+                self.dec2[(l - b'0') as usize][(d - b'0') as usize] = Some(ch);
+            }
+        }
+        self.rows = Some(rows);
+    }
+
+    /// Returns the smallest number of long digits for a board holding `alphabet`, the
+    /// symbols of `freq` getting the single digits left.
+    ///
+    fn long_needed(alphabet: &Alphabet, freq: &str) -> Option<usize> {
+        let short = alphabet.symbols().iter().filter(|&&c| freq.contains(c)).count();
+        (1..=10).find(|&n| alphabet.len() - short.min(10 - n) <= 10 * n)
+    }
+
+    /// Creates a straddling checkerboard over the standard alphabet with a random
+    /// board and two random long digits.
+    ///
+    pub fn random(rng: &mut Rng) -> Self {
+        Self::new(&rng.permutation(&Alphabet::latin()), &rng.distinct_digits(2)).unwrap()
+    }
+
+    /// Returns the board as a [`Grid`]: columns are the digits 0-9, the first row
+    /// holds the single-digit letters with a blank under each long digit, the other
+    /// rows are labelled with their long digit.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use old_crypto_rs::StraddlingCheckerboard;
+    ///
+    /// let cipher = StraddlingCheckerboard::new("ARABESQUE", "89").unwrap();
+    /// assert_eq!(
+    ///     cipher.grid().to_string(),
+    ///     "  0 1 2 3 4 5 6 7 8 9
+    ///   A R E N S I U T
+    /// 8 C K V D L W B F M X
+    /// 9 G Y H O Z Q P / J -
+    /// "
+    /// );
+    /// ```
+    ///
+    pub fn grid(&self) -> Grid {
+        let cell = |ch: Option<char>| ch.map_or(String::new(), String::from);
+        let mut g = Grid::new(&(0..10).collect::<Vec<_>>());
+        g.push_row("", &self.dec1.map(cell));
+        for &d in &self.longc {
+            g.push_row(&(d as char).to_string(), &self.dec2[(d - b'0') as usize].map(cell));
+        }
+        g
+    }
+
+    /// Returns whether `ch` is a digit written with the marker.
+    ///
+    fn escaped(&self, ch: char) -> bool {
+        ch.is_ascii_digit() && self.escape != DigitEscape::OnBoard
+    }
+
+    /// Returns whether `ch` can not be written: with `FigureShift` the code of '/'
+    /// only shifts to figures and back.
+    ///
+    fn unwritable(&self, ch: char) -> bool {
+        ch == '/' && self.escape == DigitEscape::FigureShift
+    }
+
+    /// Returns the code of `ch`, of length 0 if it is not on the board.
+    ///
+    fn code(&self, ch: char) -> EncEntry {
+        self.full.index(ch).map_or(EncEntry::default(), |i| self.codes[i])
+    }
+
     /// Extracts elements from a set that are not in the exclusion list.
     ///
     /// Used to compute the "short" cipher digits by removing the "long" digits
@@ -179,7 +513,6 @@ impl StraddlingCheckerboard {
     /// Generates all two-digit combinations for a given prefix digit.
     ///
     /// Creates strings like "30", "31", ..., "39" for prefix '3'.
-    /// Special case: if prefix is '0', returns single digits "0" through "9".
     ///
     /// # Arguments
     ///
@@ -190,75 +523,57 @@ impl StraddlingCheckerboard {
     /// A vector of 10 strings representing all combinations with this prefix.
     ///
     fn times10(c: u8) -> Vec<String> {
-        let mut tmp = Vec::with_capacity(10);
-        if c == b'0' {
-            for &b in ALL_CIPHER {
-                tmp.push((b as char).to_string());
-            }
-        } else {
-            for &b in ALL_CIPHER {
-                let mut s = (c as char).to_string();
-                s.push(b as char);
-                tmp.push(s);
-            }
-        }
-        tmp
+        ALL_CIPHER.iter().map(|&b| format!("{}{}", c as char, b as char)).collect()
     }
 
-    /// Generates all two-digit combinations for both long cipher digits.
+    /// Generates all two-digit combinations for every long cipher digit.
     ///
-    /// Combines the results of `times10()` for both long cipher digits,
-    /// producing 20 total two-digit codes.
+    /// Combines the results of `times10()` for the long cipher digits,
+    /// producing 10 two-digit codes per digit.
     ///
     /// # Arguments
     ///
-    /// * `set` - A slice containing the two long cipher digits
+    /// * `set` - A slice containing the long cipher digits
     ///
     /// # Returns
     ///
-    /// A vector of 20 strings representing all two-digit codes.
+    /// A vector of strings representing all two-digit codes.
     ///
     fn set_times10(set: &[u8]) -> Vec<String> {
-        let mut longc = Vec::with_capacity(20);
-        longc.extend(Self::times10(set[0]));
-        longc.extend(Self::times10(set[1]));
-        longc
+        set.iter().flat_map(|&c| Self::times10(c)).collect()
     }
 
     /// Builds the encoding and decoding tables based on frequency analysis.
     ///
     /// Assigns single-digit codes to high-frequency letters and two-digit
-    /// codes to low-frequency letters. Populates the encode/decode tables.
+    /// codes to low-frequency letters, and to the high-frequency ones coming after
+    /// the single digits are used up. Populates the encode/decode tables.
     ///
     /// # Arguments
     ///
     /// * `shortc` - The digits available for single-digit encoding
     /// * `freq` - The high-frequency letters that should get single-digit codes
     ///
-    fn expand_key(&mut self, shortc: Vec<u8>, freq: &[u8]) {
+    fn expand_key(&mut self, shortc: Vec<u8>, freq: &str) {
         let longc = Self::set_times10(&self.longc);
 
         let mut i = 0;
         let mut j = 0;
-        for &ch in self.full.as_bytes() {
-            if freq.contains(&ch) {
-                if i < shortc.len() {
-                    let digit = shortc[i];
-                    self.enc_table[ch as usize] = EncEntry { len: 1, bytes: [digit, 0] };
-                    self.dec1[(digit - b'0') as usize] = ch;
-                    i += 1;
-                }
-            } else {
-                if j < longc.len() {
-                    let bytes = longc[j].as_bytes();
-                    if bytes.len() == 2 {
-                        let d0 = bytes[0];
-                        let d1 = bytes[1];
-                        self.enc_table[ch as usize] = EncEntry { len: 2, bytes: [d0, d1] };
-                        self.dec2[(d0 - b'0') as usize][(d1 - b'0') as usize] = ch;
-                    }
-                    j += 1;
+        for (ind, &ch) in self.full.symbols().iter().enumerate() {
+            if freq.contains(ch) && i < shortc.len() {
+                let digit = shortc[i];
+                self.codes[ind] = EncEntry { len: 1, bytes: [digit, 0] };
+                self.dec1[(digit - b'0') as usize] = Some(ch);
+                i += 1;
+            } else if j < longc.len() {
+                let bytes = longc[j].as_bytes();
+                if bytes.len() == 2 {
+                    let d0 = bytes[0];
+                    let d1 = bytes[1];
+                    self.codes[ind] = EncEntry { len: 2, bytes: [d0, d1] };
+                    self.dec2[(d0 - b'0') as usize][(d1 - b'0') as usize] = Some(ch);
                 }
+                j += 1;
             }
         }
     }
@@ -269,71 +584,155 @@ impl Block for StraddlingCheckerboard {
     ///
     /// # Returns
     ///
-    /// The length of the cipher key in bytes.
+    /// The length of the cipher key in characters.
     ///
     fn block_size(&self) -> usize {
-        self.key.len()
+        self.key.chars().count()
+    }
+
+    /// The symbols of the board but the '/' marker.
+    ///
+    fn alphabet(&self) -> Option<Alphabet> {
+        self.alphabet.without("/").ok()
+    }
+
+    /// Letters are on the board, digits are escaped by the checkerboard itself.
+    ///
+    fn normalizer(&self) -> Normalizer {
+        self.alphabet.normalizer().digits(Digits::Keep)
+    }
+
+    /// Returns the exact number of digits `src` is encoded into.
+    ///
+    /// Letters take one or two digits, each plaintext digit takes two copies of itself
+    /// surrounded by the '/' marker code (around the whole number with
+    /// `FigureShift`).  Characters not on the board, digits without a marker and '/'
+    /// with `FigureShift` make encryption fail and are counted as nothing.  If `src` is not UTF-8,
+    /// encryption fails and `src.len()` is returned.
+    ///
+    fn encrypt_len(&self, src: &[u8]) -> usize {
+        let marker = self.code('/').len as usize;
+        let Ok(src) = utf8::decode(src) else {
+            return src.len();
+        };
+        let mut figures = false;
+        let mut n = 0;
+        for ch in src.chars() {
+            let digit = self.escaped(ch);
+            if figures && !digit {
+                n += marker;
+                figures = false;
+            }
+            if !digit {
+                if !self.unwritable(ch) {
+                    n += self.code(ch).len as usize;
+                }
+            } else if marker != 0 {
+                if self.escape == DigitEscape::Repeat {
+                    n += 2 * marker + 2;
+                } else {
+                    n += if figures { 2 } else { marker + 2 };
+                    figures = true;
+                }
+            }
+        }
+        if figures { n + marker } else { n }
+    }
+
+    /// Every plaintext character takes at least one digit, so `src.len()` characters of
+    /// the widest symbol is an upper bound.
+    ///
+    fn decrypt_len(&self, src: &[u8]) -> usize {
+        src.len() * self.full.max_utf8_len()
+    }
+
+    /// A digit repeated between two markers is the longest code.
+    ///
+    fn max_encrypt_len(&self, len: usize) -> usize {
+        len * (2 * self.code('/').len as usize + 2)
+    }
+
+    fn max_decrypt_len(&self, len: usize) -> usize {
+        len * self.full.max_utf8_len()
+    }
+
+    /// Letters take one or two digits, so this depends on the text.
+    ///
+    fn encrypt_chars(&self, _chars: usize) -> Option<usize> {
+        None
     }
 
     /// Encrypts plaintext into digit ciphertext.
     ///
     /// Each plaintext letter is replaced with its corresponding digit code
-    /// (either 1 or 2 digits). Numeric digits in the plaintext are escaped
-    /// by surrounding them with the '/' marker code and duplicating the digit.
+    /// (either 1 or 2 digits). Numeric digits in the plaintext are written as
+    /// set by [`with_escape`](StraddlingCheckerboard::with_escape), by default
+    /// surrounding them with the '/' marker code and duplicating the digit.
     ///
     /// # Arguments
     ///
     /// * `dst` - Output buffer for the encrypted digit string
-    /// * `src` - Input plaintext bytes to encrypt
+    /// * `src` - Input plaintext to encrypt, as UTF-8
     ///
     /// # Returns
     ///
     /// The number of bytes written to `dst`.
     ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::InvalidUtf8` if `src` is not UTF-8,
+    /// `CipherError::InvalidChar` for a character that is not on the board, a digit
+    /// when the board has no '/' marker or a '/' with `FigureShift`, and `CipherError::BufferTooSmall` if `dst`
+    /// can not hold the whole output.
+    ///
     /// # Examples
     ///
     /// Encrypting "ATTACK" with key "ARABESQUE" and long digits "89" produces "07708081".
     ///
-    fn encrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        let mut offset = 0;
-        let marker = self.enc_table[b'/' as usize];
-        for &ch in src {
-            if ch.is_ascii_digit() {
-                if marker.len != 0 {
-                    dst[offset] = marker.bytes[0];
-                    if marker.len == 2 {
-                        dst[offset + 1] = marker.bytes[1];
-                    }
-                    offset += marker.len as usize;
-
-                    dst[offset] = ch;
-                    dst[offset + 1] = ch;
-                    offset += 2;
-
-                    dst[offset] = marker.bytes[0];
-                    if marker.len == 2 {
-                        dst[offset + 1] = marker.bytes[1];
-                    }
-                    offset += marker.len as usize;
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        let mut out = Output::new(dst);
+        let marker = self.code('/');
+        let marker = &marker.bytes[..marker.len as usize];
+        let mut emit = |bytes: &[u8]| bytes.iter().for_each(|&b| out.push(b as char));
+        let mut figures = false;
+        for (pos, ch) in utf8::decode(src)?.char_indices() {
+            let digit = self.escaped(ch);
+            if figures && !digit {
+                emit(marker);
+                figures = false;
+            }
+            if !digit {
+                let entry = self.code(ch);
+                if entry.len == 0 || self.unwritable(ch) {
+                    return Err(CipherError::InvalidChar { ch, pos });
                 }
+                emit(&entry.bytes[..entry.len as usize]);
+            } else if marker.is_empty() {
+                return Err(CipherError::InvalidChar { ch, pos });
+            } else if self.escape == DigitEscape::Repeat {
+                emit(marker);
+                emit(&[ch as u8, ch as u8]);
+                emit(marker);
             } else {
-                let entry = self.enc_table[ch as usize];
-                if entry.len != 0 {
-                    dst[offset] = entry.bytes[0];
-                    if entry.len == 2 {
-                        dst[offset + 1] = entry.bytes[1];
-                    }
-                    offset += entry.len as usize;
+                if !figures {
+                    emit(marker);
+                    figures = true;
                 }
+                emit(&[ch as u8, ch as u8]);
             }
         }
-        offset
+        if figures {
+            emit(marker);
+        }
+        out.finish()
     }
 
     /// Decrypts digit ciphertext back into plaintext.
     ///
     /// Processes the digit stream, recognizing both single-digit and two-digit
-    /// codes. Handles escaped numeric digits by detecting the '/' marker pattern.
+    /// codes. Handles escaped numeric digits by detecting the '/' marker pattern,
+    /// or the pairs of digits following it up to the next marker with
+    /// `FigureShift`.
     ///
     /// # Arguments
     ///
@@ -344,12 +743,16 @@ impl Block for StraddlingCheckerboard {
     ///
     /// The number of bytes written to `dst`.
     ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::BufferTooSmall` if `dst` can not hold the whole output.
+    ///
     /// # Examples
     ///
     /// Decrypting "07708081" with key "ARABESQUE" and long digits "89" produces "ATTACK".
     ///
-    fn decrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        let mut pt_offset = 0;
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        let mut out = Output::new(dst);
         let mut i = 0;
         while i < src.len() {
             let ch = src[i];
@@ -382,50 +785,99 @@ impl Block for StraddlingCheckerboard {
             }
             i += db_len;
 
-            if ptc == b'/' {
-                if i + 4 <= src.len() && src[i] == src[i + 1] {
-                    let row0 = src[i + 2];
-                    let row1 = src[i + 3];
-                    if row0.is_ascii_digit() && row1.is_ascii_digit() {
-                        let rd0 = (row0 - b'0') as usize;
-                        let rd1 = (row1 - b'0') as usize;
-                        let mut is_match = false;
-                        if db_len == 2 {
-                            is_match = row0 == src[i - 2] && row1 == src[i - 1];
-                        }
-
-                        if is_match || self.dec2[rd0][rd1] == b'/' {
-                            if pt_offset < dst.len() {
-                                dst[pt_offset] = src[i];
-                                pt_offset += 1;
-                            }
-                            i += 4;
-                            continue;
-                        }
-                    }
+            if ptc == Some('/') && self.escape == DigitEscape::FigureShift {
+                while i + 1 < src.len() && src[i].is_ascii_digit() && src[i] == src[i + 1] {
+                    out.push(src[i] as char);
+                    i += 2;
+                }
+                let marker = self.code('/');
+                if src[i..].starts_with(&marker.bytes[..marker.len as usize]) {
+                    i += marker.len as usize;
                 }
+                continue;
             }
-            if ptc != 0 {
-                if pt_offset < dst.len() {
-                    dst[pt_offset] = ptc;
-                    pt_offset += 1;
+            if ptc == Some('/') && self.escape == DigitEscape::Repeat && i + 4 <= src.len() && src[i] == src[i + 1] {
+                let row0 = src[i + 2];
+                let row1 = src[i + 3];
+                if row0.is_ascii_digit() && row1.is_ascii_digit() {
+                    let rd0 = (row0 - b'0') as usize;
+                    let rd1 = (row1 - b'0') as usize;
+                    let mut is_match = false;
+                    if db_len == 2 {
+                        is_match = row0 == src[i - 2] && row1 == src[i - 1];
+                    }
+
+                    if is_match || self.dec2[rd0][rd1] == Some('/') {
+                        out.push(src[i] as char);
+                        i += 4;
+                        continue;
+                    }
                 }
             }
+            if let Some(ptc) = ptc {
+                out.push(ptc);
+            }
+        }
+        out.finish()
+    }
+}
+
+/// The board is mixed again, with the same long digits, alphabet, frequent
+/// letters and way of writing digits.  A board made by
+/// [`StraddlingCheckerboard::from_rows`] keeps its rows instead and takes the key as
+/// its new header, the long digits being those above the blanks.  With
+/// `FigureShift`, a key giving the marker a code made of the same digit twice is
+/// rejected like in `with_escape`.
+///
+impl Rekey for StraddlingCheckerboard {
+    fn rekey(&mut self, key: &str) -> Result<(), CipherError> {
+        if key.is_empty() {
+            return Err(CipherError::EmptyKey("key"));
+        }
+        if self.rows.is_some() {
+            Alphabet::new("0123456789")?.check_permutation("header", key)?;
+        }
+        let old = std::mem::replace(&mut self.key, key.to_string());
+        self.reshuffle();
+        // The marker may now have a code figures can not be told from
+        if let Err(e) = self.check_escape(self.escape) {
+            self.key = old;
+            self.reshuffle();
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+impl StraddlingCheckerboard {
+    /// Rebuilds the board for the current key.
+    ///
+    fn reshuffle(&mut self) {
+        if self.rows.is_some() {
+            self.fill_rows();
+            return;
         }
-        pt_offset
+        self.full.set_shuffled(&self.alphabet, &self.key);
+        self.codes.fill(EncEntry::default());
+        self.dec1 = [None; 10];
+        self.dec2 = [[None; 10]; 10];
+        let freq = std::mem::take(&mut self.freq);
+        self.expand_key(Self::extract(ALL_CIPHER, &self.longc), &freq);
+        self.freq = freq;
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::BlockExt;
 
     use rstest::rstest;
 
     #[test]
     fn test_new_cipher() {
         let c = StraddlingCheckerboard::new("ARABESQUE", "89").unwrap();
-        assert_eq!(c.full, "ACKVRDLWBFMXEGNYSHOZQIP/UJT-");
+        assert_eq!(c.full.to_string(), "ACKVRDLWBFMXEGNYSHOZQIP/UJT-");
         assert_eq!(c.longc, b"89");
     }
 
@@ -433,15 +885,117 @@ mod tests {
     fn test_new_cipher_bad_keys() {
         assert!(StraddlingCheckerboard::new("ARABESQUE", "").is_err());
         assert!(StraddlingCheckerboard::new("", "89").is_err());
+        assert_eq!(
+            StraddlingCheckerboard::new("ARABESQUE", "8").err(),
+            Some(CipherError::KeyTooShort { param: "chrs", min: 2, got: 1 })
+        );
+        assert_eq!(
+            StraddlingCheckerboard::new("ARABESQUE", "8A").err(),
+            Some(CipherError::InvalidDigit { param: "chrs", ch: 'A' })
+        );
+        assert_eq!(
+            StraddlingCheckerboard::new("ARABESQUE", "898").err(),
+            Some(CipherError::DuplicateSymbol { param: "chrs", symbol: '8' })
+        );
+        let a = Alphabet::new(&('Ā'..='Ť').collect::<String>()).unwrap();
+        assert_eq!(
+            StraddlingCheckerboard::with_alphabet("ĀĂ", "0123456789", &a).err(),
+            Some(CipherError::BadAlphabetLength { param: "alphabet", expected: 100, got: 101 })
+        );
+    }
+
+    #[rstest]
+    #[case("ABCDEFGHIJKLMNOPQRS", "ABCDEFGHI", 1)]
+    #[case("ABCDEFGHIJKLMNOPQRS", "ESANTIRU", 2)]
+    #[case("ABCDEFGHIJKLMNOPQRST", "ABCDEFGHI", 2)]
+    #[case("ABCDEFGHIJKLMNOPQRSTUVWXYZ/-", "ESANTIRU", 2)]
+    #[case("ABCDEFGHIJKLMNOPQRSTUVWXYZ/-", "", 3)]
+    #[case("ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789", "ESANTIRU", 3)]
+    #[case("ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789./", "ESANTIRU", 4)]
+    fn test_long_needed(#[case] alphabet: &str, #[case] freq: &str, #[case] n: usize) {
+        let a = Alphabet::new(alphabet).unwrap();
+        assert_eq!(StraddlingCheckerboard::long_needed(&a, freq), Some(n));
+        let chrs = &"0123456789"[..n];
+        assert!(StraddlingCheckerboard::new_with_freq("KEY", chrs, freq, &a).is_ok());
+        assert_eq!(
+            StraddlingCheckerboard::new_with_freq("KEY", &chrs[1..], freq, &a).err(),
+            Some(CipherError::KeyTooShort { param: "chrs", min: n, got: n - 1 })
+        );
+    }
+
+    #[test]
+    fn test_one_blank() {
+        let a = Alphabet::new("ABCDEFGHIKLMNOPRSTU").unwrap();
+        let c = StraddlingCheckerboard::new_with_freq("SECRET", "4", "ETAOINSRH", &a).unwrap();
+        assert_eq!(
+            c.grid().to_string(),
+            "  0 1 2 3 4 5 6 7 8 9
+  S A H N   E I O R T
+4 B C D K P F L U G M
+"
+        );
+        let ct = c.encrypt_str("ATTACK").unwrap();
+        assert_eq!(ct, "19914143");
+        assert_eq!(c.decrypt_str(&ct).unwrap(), "ATTACK");
+    }
+
+    #[test]
+    fn test_freq_overflow() {
+        // Seven short digits for the eight frequent letters: the last one gets a long code
+        let c = StraddlingCheckerboard::new("ARABESQUE", "789").unwrap();
+        let t = c.code('T');
+        assert_eq!(t.len, 2);
+        assert_eq!(c.decrypt_str(&c.encrypt_str("ATTACK").unwrap()).unwrap(), "ATTACK");
+    }
+
+    #[rstest]
+    #[case(DigitEscape::Repeat, "ATTACKAT2AM", Ok("0770808107972297088"))]
+    #[case(DigitEscape::FigureShift, "AT2215AM", Ok("07972222115597088"))]
+    #[case(DigitEscape::FigureShift, "9TO5", Ok("979997793975597"))]
+    #[case(DigitEscape::FigureShift, "A/1", Err(CipherError::InvalidChar { ch: '/', pos: 1 }))]
+    #[case(DigitEscape::FigureShift, "AT2/", Err(CipherError::InvalidChar { ch: '/', pos: 3 }))]
+    fn test_escape(#[case] escape: DigitEscape, #[case] pt: &str, #[case] ct: Result<&str, CipherError>) {
+        let c = StraddlingCheckerboard::new("ARABESQUE", "89").unwrap().with_escape(escape).unwrap();
+        assert_eq!(c.encrypt_str(pt), ct.clone().map(String::from));
+        if let Ok(ct) = ct {
+            assert_eq!(c.encrypt_len(pt.as_bytes()), ct.len());
+            assert_eq!(c.decrypt_str(ct).unwrap(), pt);
+        }
+    }
+
+    #[test]
+    fn test_escape_on_board() {
+        let a = Alphabet::new("ABCDEFGHIJKLMNOPQRSTUVWXYZ0123456789").unwrap();
+        let c = StraddlingCheckerboard::with_alphabet("ARABESQUE", "234", &a).unwrap();
+        // No marker on the board: digits can not be written
+        assert_eq!(c.encrypt_str("A1"), Err(CipherError::InvalidChar { ch: '1', pos: 1 }));
+        let c = c.with_escape(DigitEscape::OnBoard).unwrap();
+        assert_eq!(c.encrypt_str("A1").unwrap(), "047");
+        assert_eq!(c.decrypt_str("047").unwrap(), "A1");
+
+        assert_eq!(
+            StraddlingCheckerboard::new("ARABESQUE", "89").unwrap().with_escape(DigitEscape::OnBoard).err(),
+            Some(CipherError::InvalidParameter { param: "escape", value: "board".to_string() })
+        );
+    }
+
+    #[rstest]
+    #[case("repeat", DigitEscape::Repeat)]
+    #[case("figures", DigitEscape::FigureShift)]
+    #[case("board", DigitEscape::OnBoard)]
+    fn test_escape_parse(#[case] s: &str, #[case] escape: DigitEscape) {
+        assert_eq!(s.parse::<DigitEscape>(), Ok(escape));
+        assert_eq!(escape.to_string(), s);
+        assert!("double".parse::<DigitEscape>().is_err());
     }
 
     #[test]
     fn test_expand_key() {
         let c = StraddlingCheckerboard::new("ARABESQUE", "89").unwrap();
-        let v = c.enc_table[b'V' as usize];
-        let k = c.enc_table[b'K' as usize];
-        let a = c.enc_table[b'A' as usize];
-        let e = c.enc_table[b'E' as usize];
+        let v = c.code('V');
+        let k = c.code('K');
+        let a = c.code('A');
+        let e = c.code('E');
         assert_eq!(v.len, 2);
         assert_eq!(v.bytes, *b"82");
         assert_eq!(k.len, 2);
@@ -450,13 +1004,15 @@ mod tests {
         assert_eq!(a.bytes[0], b'0');
         assert_eq!(e.len, 1);
         assert_eq!(e.bytes[0], b'2');
-        assert_eq!(c.dec2[8][2], b'V');
-        assert_eq!(c.dec1[0], b'A');
+        assert_eq!(c.dec2[8][2], Some('V'));
+        assert_eq!(c.dec1[0], Some('A'));
+        assert_eq!(c.code('é').len, 0);
     }
 
     #[rstest]
     #[case(b'3', vec!["30", "31", "32", "33", "34", "35", "36", "37", "38", "39"])]
     #[case(b'1', vec!["10", "11", "12", "13", "14", "15", "16", "17", "18", "19"])]
+    #[case(b'0', vec!["00", "01", "02", "03", "04", "05", "06", "07", "08", "09"])]
     fn test_times10(#[case] c: u8, #[case] expected: Vec<&str>) {
         assert_eq!(StraddlingCheckerboard::times10(c), expected);
     }
@@ -498,4 +1054,145 @@ mod tests {
         let spt = String::from_utf8_lossy(&dst).trim_matches('\0').to_string();
         assert_eq!(spt, pt);
     }
+
+    #[rstest]
+    #[case("ARABESQUE", "89", "ATTACKAT2AM", "0770808107972297088")]
+    #[case("ARABESQUE", "36", "ATTACKAT2AM", "0990303109672267038")]
+    #[case("PORTABLE", "89", "RETRIBUTION", "1721693526840")]
+    fn test_straddling_lengths(#[case] key: &str, #[case] chrs: &str, #[case] pt: &str, #[case] ct: &str) {
+        let c = StraddlingCheckerboard::new(key, chrs).unwrap();
+        assert_eq!(c.encrypt_len(pt.as_bytes()), ct.len());
+        assert!(c.decrypt_len(ct.as_bytes()) >= pt.len());
+    }
+
+    #[test]
+    fn test_straddling_short_buffer() {
+        let c = StraddlingCheckerboard::new("ARABESQUE", "89").unwrap();
+        let mut dst = vec![0u8; 4];
+        assert_eq!(
+            c.try_encrypt(&mut dst, b"ATTACK"),
+            Err(CipherError::BufferTooSmall { needed: 8, got: 4 })
+        );
+        assert_eq!(
+            c.try_decrypt(&mut dst, b"07708081"),
+            Err(CipherError::BufferTooSmall { needed: 6, got: 4 })
+        );
+        assert_eq!(c.try_encrypt(&mut dst, b"AT2"), Err(CipherError::BufferTooSmall { needed: 8, got: 4 }));
+    }
+
+    #[test]
+    fn test_straddling_greek() {
+        let a = Alphabet::new("ΑΒΓΔΕΖΗΘΙΚΛΜΝΞΟΠΡΣΤΥΦΧΨΩ./").unwrap();
+        let c = StraddlingCheckerboard::new_with_freq("ΑΘΗΝΑ", "25", "ΑΟΙΕΤΣΝΗ", &a).unwrap();
+        assert_eq!(c.full.len(), 26);
+
+        // Every letter of the 24 is on the board
+        for (ind, &ch) in c.full.symbols().iter().enumerate() {
+            let e = c.codes[ind];
+            assert!(e.len == 1 || e.len == 2, "{} has no code", ch);
+            assert_eq!(e.len == 1, "ΑΟΙΕΤΣΝΗ".contains(ch));
+        }
+        let pt = "ΨΥΧΗΖΩΗ";
+        let ct = c.encrypt_str(pt).unwrap();
+        assert_eq!(c.encrypt_len(pt.as_bytes()), ct.len());
+        assert_eq!(c.decrypt_str(&ct).unwrap(), pt);
+        assert_eq!(c.encrypt_str("ΨΥΧΗ,ΖΩΗ"), Err(CipherError::InvalidChar { ch: ',', pos: 8 }));
+        assert_eq!(c.try_encrypt(&mut [0u8; 8], b"\xce"), Err(CipherError::InvalidUtf8 { pos: 0 }));
+        assert_eq!(
+            StraddlingCheckerboard::new_with_freq("ΑΘΗΝΑ", "2Β", "ΑΟΙΕΤΣΝΗ", &a).err(),
+            Some(CipherError::InvalidDigit { param: "chrs", ch: 'Β' })
+        );
+    }
+
+    #[test]
+    fn test_straddling_cyrillic() {
+        let a = Alphabet::new("АБВГДЕЁЖЗИЙКЛМНОПРСТУФХЦЧШЩЪЫЬЭЮЯ./").unwrap();
+        let c = StraddlingCheckerboard::new_with_freq("СНЕГОПАД", "257", "ОЕАИНТС", &a).unwrap();
+        assert_eq!(c.full.len(), 35);
+
+        // Every letter of the 33 is on the board
+        for (ind, &ch) in c.full.symbols().iter().enumerate() {
+            let e = c.codes[ind];
+            assert!(e.len == 1 || e.len == 2, "{} has no code", ch);
+            assert_eq!(e.len == 1, "ОЕАИНТС".contains(ch));
+        }
+        let pt = "ЭХЁЖИК";
+        let ct = c.encrypt_str(pt).unwrap();
+        assert_eq!(c.encrypt_len(pt.as_bytes()), ct.len());
+        assert_eq!(c.decrypt_str(&ct).unwrap(), pt);
+        assert_eq!(c.encrypt_str("ЭХ,ЁЖИК"), Err(CipherError::InvalidChar { ch: ',', pos: 4 }));
+        assert_eq!(c.try_encrypt(&mut [0u8; 8], b"\xd0"), Err(CipherError::InvalidUtf8 { pos: 0 }));
+        assert_eq!(
+            StraddlingCheckerboard::new_with_freq("СНЕГОПАД", "25Б", "ОЕАИНТС", &a).err(),
+            Some(CipherError::InvalidDigit { param: "chrs", ch: 'Б' })
+        );
+    }
+
+    #[test]
+    fn test_straddling_rekey() {
+        let a = Alphabet::new("ΑΒΓΔΕΖΗΘΙΚΛΜΝΞΟΠΡΣΤΥΦΧΨΩ./").unwrap();
+        let mut c = StraddlingCheckerboard::new_with_freq("ΣΠΑΡΤΗ", "25", "ΑΟΙΕΤΣΝΗ", &a).unwrap();
+        c.rekey("ΑΘΗΝΑ").unwrap();
+        let fresh = StraddlingCheckerboard::new_with_freq("ΑΘΗΝΑ", "25", "ΑΟΙΕΤΣΝΗ", &a).unwrap();
+        assert_eq!(c.grid(), fresh.grid());
+        assert_eq!(c.encrypt_str("ΣΤΙΣ10.").unwrap(), fresh.encrypt_str("ΣΤΙΣ10.").unwrap());
+
+        assert_eq!(c.rekey(""), Err(CipherError::EmptyKey("key")));
+        assert_eq!(c.grid(), fresh.grid());
+    }
+
+    #[test]
+    fn test_from_rows() {
+        let c = StraddlingCheckerboard::from_rows("5079281364", &["AT ONE SIR", "BCDFGHJKLM", "PQUVWXYZ./"]).unwrap();
+        assert_eq!(c.longc, b"71");
+        assert_eq!(
+            c.grid().to_string(),
+            "  0 1 2 3 4 5 6 7 8 9
+  T   N S R A I   E O
+7 C J G K M B L D H F
+1 Q Y W Z / P . U X V
+"
+        );
+        let ct = c.encrypt_str("MEETAT10.").unwrap();
+        assert_eq!(c.decrypt_str(&ct).unwrap(), "MEETAT10.");
+    }
+
+    #[rstest]
+    #[case("507928136", &["AT ONE SIR", "BCDFGHJKLM", "PQUVWXYZ./"], CipherError::BadAlphabetLength { param: "header", expected: 10, got: 9 })]
+    #[case("5079281364", &["AT ONE SI", "BCDFGHJKLM", "PQUVWXYZ./"], CipherError::BadAlphabetLength { param: "rows", expected: 10, got: 9 })]
+    #[case("5079281364", &["ATRONEQSIX", "BCDFGHJKLM"], CipherError::KeyTooShort { param: "rows", min: 1, got: 0 })]
+    #[case("5079281364", &["AT ONE SIR", "BCDFGHJKLM"], CipherError::KeyTooShort { param: "rows", min: 3, got: 2 })]
+    #[case("5079281364", &["AT ONE SIR", "BCDFGHJKLM", "PQUVWXYZ.A"], CipherError::DuplicateSymbol { param: "rows", symbol: 'A' })]
+    fn test_from_rows_errors(#[case] header: &str, #[case] rows: &[&str], #[case] err: CipherError) {
+        assert_eq!(StraddlingCheckerboard::from_rows(header, rows).err(), Some(err));
+    }
+
+    #[test]
+    fn test_escape_rekey() {
+        // Key "AB" puts the marker under its own long digit
+        let c = StraddlingCheckerboard::new("AB", "89").unwrap();
+        assert_eq!(c.code('/').bytes[0], c.code('/').bytes[1]);
+        assert!(c.with_escape(DigitEscape::FigureShift).is_err());
+
+        let mut c = StraddlingCheckerboard::new("ARABESQUE", "89").unwrap().with_escape(DigitEscape::FigureShift).unwrap();
+        let grid = c.grid();
+        assert_eq!(c.rekey("AB"), Err(CipherError::InvalidParameter { param: "escape", value: "figures".to_string() }));
+        assert_eq!(c.grid(), grid);
+        assert_eq!(c.encrypt_str("AT2215AM").unwrap(), "07972222115597088");
+    }
+
+    #[test]
+    fn test_from_rows_rekey() {
+        // The rows stay, only the header changes
+        let rows = ["AT ONE SIR", "BCDFGHJKLM", "PQUVWXYZ./"];
+        let mut c = StraddlingCheckerboard::from_rows("5079281364", &rows).unwrap();
+        c.rekey("0123456789").unwrap();
+        let d = StraddlingCheckerboard::from_rows("0123456789", &rows).unwrap();
+        assert_eq!(c.grid(), d.grid());
+        assert_eq!(c.encrypt_str("ATTACK").unwrap(), "01102127");
+        assert_eq!(c.decrypt_str("01102127").unwrap(), "ATTACK");
+
+        assert!(matches!(c.rekey("ARABESQUE"), Err(CipherError::BadAlphabetLength { .. })));
+        assert_eq!(c.grid(), d.grid());
+    }
 }
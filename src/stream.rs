@@ -0,0 +1,83 @@
+//! Stream mode for the ciphers keeping an internal state.
+//!
+//! Machines like the Chaocipher, the Wheatstone cryptograph, Solitaire or SIGABA
+//! evolve after every character.  Their [`Block`] implementation starts over from
+//! the key for every call, which is fine for a whole message but not when the text
+//! arrives in chunks.  [`StreamCipher`] lets the caller own that state instead: the
+//! keyed cipher stays immutable (and can be shared between threads) while each
+//! message carries its own state, which can be cloned to resume later.  A state
+//! printed with `Display` can be given back to [`StreamCipher::restore`], so the
+//! message can also be resumed by another process.
+//!
+//! # Example
+//!
+//! ```
+//! use old_crypto_rs::{Chaocipher, StreamCipher};
+//!
+//! let c = Chaocipher::new(
+//!     "PTLNBQDEOYSFAVZKGJRIHWXUMC",
+//!     "HXUCZVAMDSLKPEFJRIGTWOBNYQ",
+//! ).unwrap();
+//!
+//! let mut state = c.start();
+//! let mut ct = [0u8; 8];
+//! c.encrypt_stream(&mut state, &mut ct[..4], b"WELL").unwrap();
+//! let saved = state.clone();
+//! c.encrypt_stream(&mut state, &mut ct[4..], b"DONE").unwrap();
+//! assert_eq!(&ct, b"OAHQHCNY");
+//!
+//! // Going back to the saved state gives the same second chunk
+//! let mut state = saved;
+//! let mut again = [0u8; 4];
+//! c.encrypt_stream(&mut state, &mut again, b"DONE").unwrap();
+//! assert_eq!(&again, b"HCNY");
+//!
+//! // The state can also be saved as text
+//! let saved = state.to_string();
+//! let mut state = c.restore(&saved).unwrap();
+//! c.encrypt_stream(&mut state, &mut again, b"GOOD").unwrap();
+//! ```
+//!
+use crate::{Block, CipherError};
+use std::fmt;
+
+/// A cipher whose state carries over from one call to the next.
+///
+/// `start` returns the state derived from the key, `encrypt_stream`/`decrypt_stream`
+/// continue from the state they are given and update it.  The `Block` methods are
+/// equivalent to a single stream call on a fresh state.  `restore` reads back a
+/// state printed with `Display`.
+///
+pub trait StreamCipher: Block {
+    /// Per-message state, cloned to take a snapshot or printed to save it.
+    type State: Clone + fmt::Display;
+
+    /// Returns the initial state for a new message.
+    ///
+    fn start(&self) -> Self::State;
+
+    /// Rebuilds a state from the text printed by its `Display`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for the `state` parameter: `InvalidParameter` if `saved` is
+    /// not of the printed form, or does not fit the key of the cipher.
+    ///
+    fn restore(&self, saved: &str) -> Result<Self::State, CipherError>;
+
+    /// Encrypts `src` into `dst`, continuing from `state`.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Block::try_encrypt`].  `state` is left untouched on error.
+    ///
+    fn encrypt_stream(&self, state: &mut Self::State, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError>;
+
+    /// Decrypts `src` into `dst`, continuing from `state`.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Block::try_decrypt`].  `state` is left untouched on error.
+    ///
+    fn decrypt_stream(&self, state: &mut Self::State, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError>;
+}
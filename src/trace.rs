@@ -0,0 +1,284 @@
+//! Tracing the stages of a cipher.
+//!
+//! When a message does not decrypt, the question is which stage broke it: the
+//! checkerboard, one of the transpositions, the square…  The `*_traced` methods of
+//! [`Block`] report every stage of a cipher to a [`Tracer`]: its name, input and
+//! output (or error).  Product ciphers (ADFGVX, Nihilist, VIC, [`Chain`]) report
+//! each of their stages, machines (Chaocipher, Wheatstone, Solitaire, SIGABA) also
+//! report their state after every character.  [`Trace`] is a tracer collecting it
+//! all, which prints as a worksheet.
+//!
+//! # Example
+//!
+//! ```
+//! use old_crypto_rs::{BlockExt, Trace, TraceEntry, ADFGVX};
+//!
+//! let c = ADFGVX::new("PORTABLE", "SUBWAY").unwrap();
+//! let mut trace = Trace::new();
+//! let ct = c.encrypt_traced(b"ATTACKATDAWN", &mut trace).unwrap();
+//! assert_eq!(ct, b"AFDFADAGAAAAVVVVGFGVGGGX");
+//! assert_eq!(
+//!     trace.to_string(),
+//!     "SquareCipher: ATTACKATDAWN -> AVAGAGAVDFFGAVAGDGAVGVFX
+//! Transposition: AVAGAGAVDFFGAVAGDGAVGVFX -> AFDFADAGAAAAVVVVGFGVGGGX
+//! "
+//! );
+//!
+//! // Which stage failed?
+//! let mut trace = Trace::new();
+//! assert!(c.decrypt_traced(b"AFDFADAGAAAAVVVVGFGVGGZ", &mut trace).is_err());
+//! let Some(TraceEntry::Stage { name, output, .. }) = trace.entries().last() else { panic!() };
+//! assert_eq!(name, "SquareCipher");
+//! assert!(output.is_err());
+//! ```
+//!
+//! [`Block`]: crate::Block
+//! [`Chain`]: crate::Chain
+//!
+use crate::{CipherError, StreamCipher};
+use crate::error::check_dst;
+use std::fmt;
+
+/// Receives the stages of a traced operation.
+///
+pub trait Tracer {
+    /// Called once a stage is done, with its output or the error it returned.
+    ///
+    fn stage(&mut self, name: &str, input: &[u8], output: Result<&[u8], &CipherError>);
+
+    /// Called by machines after each character, `pos` being its offset in the input of
+    /// the stage and `state` the state of the machine once it went through.
+    ///
+    /// The default ignores it.
+    ///
+    fn step(&mut self, name: &str, pos: usize, input: u8, output: u8, state: &dyn fmt::Display) {
+        let _ = (name, pos, input, output, state);
+    }
+}
+
+/// A tracer discarding everything, for the untraced methods sharing the code of the
+/// traced ones.
+///
+pub(crate) struct NoTrace;
+
+impl Tracer for NoTrace {
+    fn stage(&mut self, _: &str, _: &[u8], _: Result<&[u8], &CipherError>) {}
+}
+
+/// One line of a [`Trace`].
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TraceEntry {
+    /// A stage of the cipher, with its input and output or error.
+    Stage {
+        name: String,
+        input: String,
+        output: Result<String, CipherError>,
+    },
+    /// A character going through a machine and the state it left it in.
+    Step {
+        name: String,
+        pos: usize,
+        input: char,
+        output: char,
+        state: String,
+    },
+}
+
+/// A [`Tracer`] keeping every stage and step, in order.
+///
+/// Texts are kept as strings, invalid UTF-8 being replaced.
+///
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Trace {
+    entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    /// Creates an empty trace.
+    ///
+    pub fn new() -> Self {
+        Trace { entries: Vec::new() }
+    }
+
+    /// Returns what was recorded, in order.
+    ///
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.entries
+    }
+
+    /// Returns the stages only, without the machine steps.
+    ///
+    pub fn stages(&self) -> impl Iterator<Item = &TraceEntry> {
+        self.entries.iter().filter(|e| matches!(e, TraceEntry::Stage { .. }))
+    }
+
+    /// Forgets everything, to reuse the trace.
+    ///
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Tracer for Trace {
+    fn stage(&mut self, name: &str, input: &[u8], output: Result<&[u8], &CipherError>) {
+        self.entries.push(TraceEntry::Stage {
+            name: name.to_string(),
+            input: String::from_utf8_lossy(input).into_owned(),
+            output: output.map(|o| String::from_utf8_lossy(o).into_owned()).map_err(Clone::clone),
+        });
+    }
+
+    fn step(&mut self, name: &str, pos: usize, input: u8, output: u8, state: &dyn fmt::Display) {
+        self.entries.push(TraceEntry::Step {
+            name: name.to_string(),
+            pos,
+            input: input as char,
+            output: output as char,
+            state: state.to_string(),
+        });
+    }
+}
+
+/// One line per entry, steps indented under the stage they belong to.
+///
+impl fmt::Display for Trace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for e in &self.entries {
+            match e {
+                TraceEntry::Stage { name, input, output: Ok(output) } => writeln!(f, "{}: {} -> {}", name, input, output)?,
+                TraceEntry::Stage { name, input, output: Err(e) } => writeln!(f, "{}: {} -> error: {}", name, input, e)?,
+                TraceEntry::Step { pos, input, output, state, .. } => {
+                    writeln!(f, "  {:>3} {} -> {}  {}", pos, input, output, state)?
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Returns the name of a cipher type as shown in traces, without its path and
+/// generic parameters.
+///
+pub(crate) fn stage_name<T: ?Sized>() -> &'static str {
+    let name = std::any::type_name::<T>();
+    let name = name.split('<').next().unwrap_or(name);
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+/// Reports `output` as the result of the stage `name`.
+///
+pub(crate) fn report(
+    tracer: &mut dyn Tracer,
+    name: &str,
+    src: &[u8],
+    dst: &[u8],
+    output: Result<usize, CipherError>,
+) -> Result<usize, CipherError> {
+    tracer.stage(name, src, output.as_ref().map(|&n| &dst[..n]));
+    output
+}
+
+/// Runs a machine one character at a time to report its state after each of them.
+///
+/// The whole text goes through once first, so that errors keep the position they
+/// have in the untraced methods.
+///
+pub(crate) fn trace_stream<C>(
+    c: &C,
+    dst: &mut [u8],
+    src: &[u8],
+    tracer: &mut dyn Tracer,
+    encrypt: bool,
+) -> Result<usize, CipherError>
+where
+    C: StreamCipher,
+    C::State: fmt::Display,
+{
+    let name = stage_name::<C>();
+    let run = |state: &mut C::State, dst: &mut [u8], src: &[u8]| {
+        if encrypt {
+            c.encrypt_stream(state, dst, src)
+        } else {
+            c.decrypt_stream(state, dst, src)
+        }
+    };
+    if let Err(e) = check_dst(dst, src.len()).and_then(|_| run(&mut c.start(), dst, src)) {
+        return report(tracer, name, src, dst, Err(e));
+    }
+
+    let mut state = c.start();
+    for i in 0..src.len() {
+        run(&mut state, &mut dst[i..=i], &src[i..=i])?;
+        tracer.step(name, i, src[i], dst[i], &state);
+    }
+    report(tracer, name, src, dst, Ok(src.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Block, BlockExt, CaesarCipher, Chaocipher, Chain, Transposition};
+
+    #[test]
+    fn test_stage_name() {
+        assert_eq!(stage_name::<CaesarCipher>(), "CaesarCipher");
+        assert_eq!(stage_name::<Vec<CaesarCipher>>(), "Vec");
+    }
+
+    #[test]
+    fn test_trace_default() {
+        let c = CaesarCipher::new(3);
+        let mut trace = Trace::new();
+        assert_eq!(c.encrypt_traced(b"HELLO", &mut trace).unwrap(), b"KHOOR");
+        assert_eq!(
+            trace.entries(),
+            [TraceEntry::Stage { name: "CaesarCipher".to_string(), input: "HELLO".to_string(), output: Ok("KHOOR".to_string()) }]
+        );
+
+        trace.clear();
+        let t = Transposition::new("SUBWAY").unwrap();
+        assert_eq!(t.try_encrypt_traced(&mut [0u8; 2], b"HELLO", &mut trace), Err(CipherError::BufferTooSmall { needed: 5, got: 2 }));
+        assert_eq!(trace.to_string(), "Transposition: HELLO -> error: destination buffer too small: need 5, got 2\n");
+    }
+
+    #[test]
+    fn test_trace_machine() {
+        let c = Chaocipher::new("PTLNBQDEOYSFAVZKGJRIHWXUMC", "HXUCZVAMDSLKPEFJRIGTWOBNYQ").unwrap();
+        let mut trace = Trace::new();
+        assert_eq!(c.encrypt_traced(b"WELLDONE", &mut trace).unwrap(), b"OAHQHCNY");
+        assert_eq!(trace.entries().len(), 9);
+        assert_eq!(trace.stages().count(), 1);
+        let TraceEntry::Step { pos, input, output, state, .. } = &trace.entries()[1] else { panic!() };
+        assert_eq!((*pos, *input, *output), (1, 'E', 'A'));
+        let mut s = c.start();
+        c.encrypt_stream(&mut s, &mut [0u8; 2], b"WE").unwrap();
+        assert_eq!(*state, s.to_string());
+
+        trace.clear();
+        assert_eq!(
+            c.encrypt_traced(b"WELL DONE", &mut trace),
+            Err(CipherError::InvalidChar { ch: ' ', pos: 4 })
+        );
+        assert_eq!(trace.entries().len(), 1);
+    }
+
+    #[test]
+    fn test_trace_chain() {
+        let c = Chain::new()
+            .then(CaesarCipher::new(3))
+            .then(Chain::new().then(Transposition::new("SUBWAY").unwrap()));
+        let mut trace = Trace::new();
+        let ct = c.encrypt_traced(b"ATTACKATDAWN", &mut trace).unwrap();
+        assert_eq!(ct, c.encrypt_to_vec(b"ATTACKATDAWN").unwrap());
+        assert_eq!(trace.to_string(), "CaesarCipher: ATTACKATDAWN -> DWWDFNDWGDZQ\nTransposition: DWWDFNDWGDZQ -> FZWGDDWWDDNQ\n");
+
+        trace.clear();
+        assert_eq!(c.decrypt_traced(&ct, &mut trace).unwrap(), b"ATTACKATDAWN");
+        let names: Vec<_> = trace.stages().map(|e| match e {
+            TraceEntry::Stage { name, .. } => name.as_str(),
+            _ => "",
+        }).collect();
+        assert_eq!(names, ["Transposition", "CaesarCipher"]);
+    }
+}
@@ -15,28 +15,102 @@
 //! cipher.encrypt(&mut ciphertext, plaintext);
 //! ```
 //!
-use crate::Block;
+use crate::{Alphabet, Block, CipherError, Grid, Normalizer, Rekey, Rng};
+use crate::error::check_dst;
 use crate::helpers;
 
+/// Lays out the characters of `key` over their column numbers, starting at 1.
+///
+fn key_grid(key: &str, tkey: &[u8]) -> Grid {
+    let mut g = Grid::new(&key.chars().collect::<Vec<_>>());
+    g.push_row("", &tkey.iter().map(|&n| n + 1).collect::<Vec<_>>());
+    g
+}
+
+/// Fills `tkey` with the ranks of the characters of `key`, as [`helpers::to_numeric`]
+/// does, reusing its storage.
+///
+fn numeric_into(key: &str, tkey: &mut Vec<u8>) {
+    tkey.clear();
+    for (i, c) in key.chars().enumerate() {
+        tkey.push(key.chars().enumerate().filter(|&(j, d)| (d, j) < (c, i)).count() as u8);
+    }
+}
+
+/// Returns the column of rank `rank`.
+///
+fn rank_pos(tkey: &[u8], rank: usize) -> usize {
+    tkey.iter().position(|&x| x as usize == rank).unwrap()
+}
+
+/// Checks that `key` can place the two triangular areas of an irregular
+/// transposition.
+///
+fn check_irregular_key(key: &str) -> Result<(), CipherError> {
+    if key.is_empty() {
+        return Err(CipherError::EmptyKey("key"));
+    }
+    let len = key.chars().count();
+    if len < 2 {
+        return Err(CipherError::KeyTooShort { param: "key", min: 2, got: len });
+    }
+    Ok(())
+}
+
 /// A columnar transposition cipher.
 #[derive(Debug)]
 pub struct Transposition {
-    #[allow(dead_code)]
     key: String,
     tkey: Vec<u8>,
 }
 
 impl Transposition {
     /// Creates a new regular columnar transposition cipher.
-    pub fn new(key: &str) -> Result<Self, String> {
+    ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::EmptyKey` if the key is empty.
+    pub fn new(key: &str) -> Result<Self, CipherError> {
         if key.is_empty() {
-            return Err("key can not be empty".to_string());
+            return Err(CipherError::EmptyKey("key"));
         }
         Ok(Transposition {
             key: key.to_string(),
             tkey: helpers::to_numeric(key),
         })
     }
+
+    /// Creates a transposition whose key is `len` random letters.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::EmptyKey` if `len` is 0.
+    pub fn random(rng: &mut Rng, len: usize) -> Result<Self, CipherError> {
+        Self::new(&rng.word(&Alphabet::latin(), len))
+    }
+
+    /// Returns the rank of each column in the reading order, starting at 0, as
+    /// computed by [`helpers::to_numeric`].
+    ///
+    pub fn order(&self) -> &[u8] {
+        &self.tkey
+    }
+
+    /// Returns the key over its column numbers, starting at 1 as on paper.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use old_crypto_rs::Transposition;
+    ///
+    /// let cipher = Transposition::new("ZEBRAS").unwrap();
+    /// assert_eq!(cipher.order(), [5, 2, 1, 3, 0, 4]);
+    /// assert_eq!(cipher.grid().to_string(), "Z E B R A S\n6 3 2 4 1 5\n");
+    /// ```
+    ///
+    pub fn grid(&self) -> Grid {
+        key_grid(&self.key, &self.tkey)
+    }
 }
 
 impl Block for Transposition {
@@ -44,6 +118,13 @@ impl Block for Transposition {
         self.tkey.len()
     }
 
+    /// Any byte can be moved around, but the text is expected to be made of letters and
+    /// digits, as it usually is the output of a substitution.
+    ///
+    fn normalizer(&self) -> Normalizer {
+        Normalizer::alphanumeric()
+    }
+
     /// Encrypts the source data using columnar transposition.
     ///
     /// The encryption process works as follows:
@@ -65,6 +146,10 @@ impl Block for Transposition {
     ///
     /// Returns the number of bytes written to `dst` (equal to the length of `src`).
     ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::BufferTooSmall` if `dst` is shorter than `src`.
+    ///
     /// # Examples
     ///
     /// ```
@@ -77,7 +162,8 @@ impl Block for Transposition {
     /// assert_eq!(n, plaintext.len());
     /// ```
     ///
-    fn encrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        check_dst(dst, src.len())?;
         let klen = self.tkey.len();
         let mut offset = 0;
         for i in 0..klen {
@@ -89,7 +175,7 @@ impl Block for Transposition {
                 curr += klen;
             }
         }
-        src.len()
+        Ok(src.len())
     }
 
     /// Decrypts the source data using columnar transposition.
@@ -118,6 +204,10 @@ impl Block for Transposition {
     /// Returns the number of bytes written to `dst` (equal to the length of `src`),
     /// or 0 if the key length is 0 or the source is empty.
     ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::BufferTooSmall` if `dst` is shorter than `src`.
+    ///
     /// # Examples
     ///
     /// ```
@@ -130,10 +220,11 @@ impl Block for Transposition {
     /// assert_eq!(n, ciphertext.len());
     /// ```
     ///
-    fn decrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        check_dst(dst, src.len())?;
         let klen = self.tkey.len();
         if klen == 0 || src.is_empty() {
-            return 0;
+            return Ok(0);
         }
 
         let scol = src.len() / klen;
@@ -151,7 +242,19 @@ impl Block for Transposition {
             }
             current += how_many;
         }
-        src.len()
+        Ok(src.len())
+    }
+}
+
+impl Rekey for Transposition {
+    fn rekey(&mut self, key: &str) -> Result<(), CipherError> {
+        if key.is_empty() {
+            return Err(CipherError::EmptyKey("key"));
+        }
+        self.key.clear();
+        self.key.push_str(key);
+        numeric_into(key, &mut self.tkey);
+        Ok(())
     }
 }
 
@@ -161,9 +264,10 @@ impl Block for Transposition {
 /// pattern, creating triangular areas that are filled after the regular areas. It's specifically
 /// designed for use in the VIC cipher.
 ///
-/// The cipher uses two special positions (rank 0 and rank 1) in the key to define triangular
-/// areas. These areas are filled separately from the rest of the grid, creating a more complex
-/// transposition pattern.
+/// The grid has as many cells as the text, its last row being short.  The first triangular
+/// area starts on the top row under the column numbered 1 and runs to the end of the row; each
+/// row below starts it one column further right, until a row where it is empty.  The next area
+/// starts on the following row under the column numbered 2, and so on down the grid.
 ///
 /// # Examples
 ///
@@ -177,10 +281,8 @@ impl Block for Transposition {
 /// ```
 #[derive(Debug)]
 pub struct IrregularTransposition {
-    #[allow(dead_code)]
     key: String,
     tkey: Vec<u8>,
-    rank_pos: [usize; 2],
     tkey_order: Vec<usize>,
 }
 
@@ -188,8 +290,8 @@ impl IrregularTransposition {
     /// Creates a new irregular transposition cipher.
     ///
     /// The key is converted to a numeric representation where each character's position
-    /// in alphabetical order determines its rank. The positions of ranks 0 and 1 define
-    /// the triangular areas used in the irregular transposition.
+    /// in alphabetical order determines its rank, the triangular areas starting under the
+    /// columns of ranks 0, 1 and so on.
     ///
     /// # Arguments
     ///
@@ -197,12 +299,13 @@ impl IrregularTransposition {
     ///
     /// # Returns
     ///
-    /// Returns `Ok(IrregularTransposition)` if the key is valid, or an error message
+    /// Returns `Ok(IrregularTransposition)` if the key is valid, or a `CipherError`
     /// if the key is empty.
     ///
     /// # Errors
     ///
-    /// Returns an error if the key is empty.
+    /// Returns `CipherError::EmptyKey` if the key is empty, or `CipherError::KeyTooShort`
+    /// if it has fewer than the two characters needed to place the triangular areas.
     ///
     /// # Examples
     ///
@@ -211,48 +314,73 @@ impl IrregularTransposition {
     ///
     /// let cipher = IrregularTransposition::new("SUBWAY").unwrap();
     /// ```
-    pub fn new(key: &str) -> Result<Self, String> {
-        if key.is_empty() {
-            return Err("key can not be empty".to_string());
-        }
+    pub fn new(key: &str) -> Result<Self, CipherError> {
+        check_irregular_key(key)?;
         let tkey = helpers::to_numeric(key);
-        let pos0 = tkey.iter().position(|&x| x == 0).unwrap();
-        let pos1 = tkey.iter().position(|&x| x == 1).unwrap();
-        
-        let klen = tkey.len();
-        let mut tkey_order = vec![0; klen];
-        for i in 0..klen {
-            tkey_order[i] = tkey.iter().position(|&x| x == i as u8).unwrap();
-        }
+        let tkey_order: Vec<usize> = (0..tkey.len()).map(|i| rank_pos(&tkey, i)).collect();
 
-        Ok(IrregularTransposition {
-            key: key.to_string(),
-            tkey,
-            rank_pos: [pos0, pos1],
-            tkey_order,
-        })
+        Ok(IrregularTransposition { key: key.to_string(), tkey, tkey_order })
     }
 
-    /// Computes which cells are "irregular" (triangular areas) for a given message length.
+    /// Creates an irregular transposition whose key is `len` random letters.
     ///
-    /// The triangular areas start at the positions of ranks 0 and 1 in the key, and expand
-    /// to the right as row numbers increase. A cell at position (r, c) is in a triangular
-    /// area if it's at or beyond `rank_pos[0] + r` or `rank_pos[1] + r`, and within the
-    /// grid width.
+    /// # Errors
     ///
-    /// # Arguments
+    /// Same as [`IrregularTransposition::new`], for a `len` below 2.
     ///
-    /// * `r` - The row index (0-based)
-    /// * `c` - The column index (0-based)
+    pub fn random(rng: &mut Rng, len: usize) -> Result<Self, CipherError> {
+        Self::new(&rng.word(&Alphabet::latin(), len))
+    }
+
+    /// Returns the rank of each column in the reading order, starting at 0.
     ///
-    /// # Returns
+    pub fn order(&self) -> &[u8] {
+        &self.tkey
+    }
+
+    /// Returns the key over its column numbers, starting at 1 as on paper.  The
+    /// triangular areas start under the columns numbered 1, 2 and so on.
+    ///
+    pub fn grid(&self) -> Grid {
+        key_grid(&self.key, &self.tkey)
+    }
+
+    /// Returns whether the cell at row `r`, column `c` is in a triangular area.
+    ///
+    /// An area starting under column `start` covers the columns from `start + i` to the
+    /// end of its `i`-th row, down to the row where it is empty; the next area starts
+    /// on the row after it, under the column read next.
     ///
-    /// Returns `true` if the cell is in a triangular area, `false` otherwise.
-    #[inline]
     fn is_in_triangular_area(&self, r: usize, c: usize) -> bool {
-        // The triangle starts at (row 0, start_col)
-        // It expands to the right: at row `i`, it covers columns `start_col + i` to `klen - 1`
-        (c >= self.rank_pos[0] + r || c >= self.rank_pos[1] + r) && c < self.tkey.len()
+        let klen = self.tkey.len();
+        let mut top = 0;
+        for &start in self.tkey_order.iter().cycle() {
+            let height = klen - start + 1;
+            if r < top + height {
+                return c >= start + r - top;
+            }
+            top += height;
+        }
+        false
+    }
+
+    /// Returns the cells of a grid holding `len` characters in the order they are
+    /// written: row by row outside the triangular areas, then row by row inside them.
+    ///
+    fn fill_order(&self, len: usize) -> Vec<usize> {
+        let klen = self.tkey.len();
+        let (mut regular, mut areas): (Vec<usize>, Vec<usize>) =
+            (0..len).partition(|&idx| !self.is_in_triangular_area(idx / klen, idx % klen));
+        regular.append(&mut areas);
+        regular
+    }
+
+    /// Returns the cells of a grid holding `len` characters in the order they are
+    /// read: column by column in the order of the key.
+    ///
+    fn read_order(&self, len: usize) -> impl Iterator<Item = usize> + '_ {
+        let klen = self.tkey.len();
+        self.tkey_order.iter().flat_map(move |&col| (col..len).step_by(klen))
     }
 }
 
@@ -261,21 +389,26 @@ impl Block for IrregularTransposition {
         self.tkey.len()
     }
 
+    /// Any byte can be moved around, but the text is expected to be made of letters and
+    /// digits, as it usually is the output of a substitution.
+    ///
+    fn normalizer(&self) -> Normalizer {
+        Normalizer::alphanumeric()
+    }
+
     /// Encrypts the source data using irregular transposition.
     ///
     /// The encryption process works in three phases:
     ///
-    /// 1. **Fill non-triangular areas**: The plaintext is written into a grid row by row,
-    ///    filling only the cells that are NOT in the triangular areas (defined by ranks 0 and 1).
-    ///    This creates the "regular" part of the transposition.
+    /// 1. **Fill non-triangular areas**: The plaintext is written into a grid with as many
+    ///    cells as it has bytes, row by row, filling only the cells that are NOT in the
+    ///    triangular areas. This creates the "regular" part of the transposition.
     ///
     /// 2. **Fill triangular areas**: After the regular areas are filled, the remaining plaintext
-    ///    continues filling the triangular areas row by row. These are the cells at positions
-    ///    (r, c) where `c >= rank_pos[0] + r` or `c >= rank_pos[1] + r`.
+    ///    continues filling the triangular areas row by row.
     ///
     /// 3. **Read column by column**: The ciphertext is generated by reading the grid column by
-    ///    column in the order specified by `tkey_order` (alphabetical order of the key). Only
-    ///    active cells (those that were filled) are read.
+    ///    column in the order specified by `tkey_order` (alphabetical order of the key).
     ///
     /// # Arguments
     ///
@@ -286,6 +419,10 @@ impl Block for IrregularTransposition {
     ///
     /// Returns the number of bytes written to `dst` (equal to the length of `src`).
     ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::BufferTooSmall` if `dst` is shorter than `src`.
+    ///
     /// # Examples
     ///
     /// ```
@@ -298,74 +435,31 @@ impl Block for IrregularTransposition {
     /// assert_eq!(n, plaintext.len());
     /// ```
     /// 
-    fn encrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        let klen = self.tkey.len();
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        check_dst(dst, src.len())?;
         let len = src.len();
-        if klen == 0 || len == 0 {
-            return 0;
-        }
-        let rows = (len + klen - 1) / klen;
-
-        let mut grid = vec![0u8; rows * klen];
-        let mut active = vec![0u8; (rows * klen + 7) / 8];
-        let mut src_idx = 0;
-
-        // Phase 1: Fill non-triangular areas row by row
-        for r in 0..rows {
-            let row_off = r * klen;
-            for c in 0..klen {
-                if !self.is_in_triangular_area(r, c) && src_idx < len {
-                    let idx = row_off + c;
-                    grid[idx] = src[src_idx];
-                    active[idx >> 3] |= 1 << (idx & 7);
-                    src_idx += 1;
-                }
-            }
-        }
-
-        // Phase 2: Fill triangular areas row by row
-        for r in 0..rows {
-            let row_off = r * klen;
-            for c in 0..klen {
-                if self.is_in_triangular_area(r, c) && src_idx < len {
-                    let idx = row_off + c;
-                    grid[idx] = src[src_idx];
-                    active[idx >> 3] |= 1 << (idx & 7);
-                    src_idx += 1;
-                }
-            }
+        let mut grid = vec![0u8; len];
+        for (&idx, &b) in self.fill_order(len).iter().zip(src) {
+            grid[idx] = b;
         }
-
-        // Read out column by column according to tkey
-        let mut dst_idx = 0;
-        for &col in &self.tkey_order {
-            for r in 0..rows {
-                let idx = r * klen + col;
-                if (active[idx >> 3] & (1 << (idx & 7))) != 0 {
-                    dst[dst_idx] = grid[idx];
-                    dst_idx += 1;
-                }
-            }
+        for (d, idx) in dst.iter_mut().zip(self.read_order(len)) {
+            *d = grid[idx];
         }
-
-        len
+        Ok(len)
     }
 
     /// Decrypts the source data using irregular transposition.
     ///
     /// The decryption process reverses the encryption:
     ///
-    /// 1. **Determine active cells**: First, we determine which cells in the grid are active
-    ///    by simulating the filling process (non-triangular areas first, then triangular areas).
-    ///    This tells us which cells contain actual ciphertext data.
+    /// 1. **Fill grid from columns**: The ciphertext is written column by column in the order
+    ///    specified by `tkey_order`, into a grid with as many cells as it has bytes. This
+    ///    reverses the column-wise reading done during encryption.
     ///
-    /// 2. **Fill grid from columns**: The ciphertext is read column by column in the order
-    ///    specified by `tkey_order`, filling the grid's active cells. This reverses the
-    ///    column-wise reading done during encryption.
-    ///
-    /// 3. **Read in two phases**: The plaintext is recovered by reading the grid in two phases:
+    /// 2. **Read in two phases**: The plaintext is recovered by reading the grid in two phases:
     ///    - First phase: Read row by row from non-triangular areas
     ///    - Second phase: Read row by row from triangular areas
+    ///
     ///    This reverses the two-phase filling done during encryption.
     ///
     /// # Arguments
@@ -377,6 +471,10 @@ impl Block for IrregularTransposition {
     ///
     /// Returns the number of bytes written to `dst` (equal to the length of `src`).
     ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::BufferTooSmall` if `dst` is shorter than `src`.
+    ///
     /// # Examples
     ///
     /// ```
@@ -389,81 +487,36 @@ impl Block for IrregularTransposition {
     /// assert_eq!(n, ciphertext.len());
     /// ```
     /// 
-    fn decrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        let klen = self.tkey.len();
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        check_dst(dst, src.len())?;
         let len = src.len();
-        if klen == 0 || len == 0 {
-            return 0;
-        }
-        let rows = (len + klen - 1) / klen;
-
-        // Determine active cells
-        let mut active = vec![0u8; (rows * klen + 7) / 8];
-        let mut count = 0;
-        for r in 0..rows {
-            let row_off = r * klen;
-            for c in 0..klen {
-                if !self.is_in_triangular_area(r, c) && count < len {
-                    let idx = row_off + c;
-                    active[idx >> 3] |= 1 << (idx & 7);
-                    count += 1;
-                }
-            }
-        }
-        for r in 0..rows {
-            let row_off = r * klen;
-            for c in 0..klen {
-                if self.is_in_triangular_area(r, c) && count < len {
-                    let idx = row_off + c;
-                    active[idx >> 3] |= 1 << (idx & 7);
-                    count += 1;
-                }
-            }
-        }
-
-        // Fill grid from src column by column
-        let mut grid = vec![0u8; rows * klen];
-        let mut src_idx = 0;
-        for &col in &self.tkey_order {
-            for r in 0..rows {
-                let idx = r * klen + col;
-                if (active[idx >> 3] & (1 << (idx & 7))) != 0 {
-                    grid[idx] = src[src_idx];
-                    src_idx += 1;
-                }
-            }
-        }
-
-        // Read out row by row, first non-mask then mask
-        let mut dst_idx = 0;
-        for r in 0..rows {
-            let row_off = r * klen;
-            for c in 0..klen {
-                let idx = row_off + c;
-                if ((active[idx >> 3] & (1 << (idx & 7))) != 0) && !self.is_in_triangular_area(r, c) {
-                    dst[dst_idx] = grid[idx];
-                    dst_idx += 1;
-                }
-            }
+        let mut grid = vec![0u8; len];
+        for (idx, &b) in self.read_order(len).zip(src) {
+            grid[idx] = b;
         }
-        for r in 0..rows {
-            let row_off = r * klen;
-            for c in 0..klen {
-                let idx = row_off + c;
-                if ((active[idx >> 3] & (1 << (idx & 7))) != 0) && self.is_in_triangular_area(r, c) {
-                    dst[dst_idx] = grid[idx];
-                    dst_idx += 1;
-                }
-            }
+        for (d, &idx) in dst.iter_mut().zip(&self.fill_order(len)) {
+            *d = grid[idx];
         }
+        Ok(len)
+    }
+}
 
-        len
+impl Rekey for IrregularTransposition {
+    fn rekey(&mut self, key: &str) -> Result<(), CipherError> {
+        check_irregular_key(key)?;
+        self.key.clear();
+        self.key.push_str(key);
+        numeric_into(key, &mut self.tkey);
+        self.tkey_order.clear();
+        self.tkey_order.extend((0..self.tkey.len()).map(|i| rank_pos(&self.tkey, i)));
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::BlockExt;
 
     use rstest::rstest;
 
@@ -478,6 +531,11 @@ mod tests {
     fn test_new_cipher_empty() {
         let c = Transposition::new("");
         assert!(c.is_err());
+        assert_eq!(IrregularTransposition::new("").err(), Some(CipherError::EmptyKey("key")));
+        assert_eq!(
+            IrregularTransposition::new("A").err(),
+            Some(CipherError::KeyTooShort { param: "key", min: 2, got: 1 })
+        );
     }
 
     #[test]
@@ -510,28 +568,79 @@ mod tests {
         assert_eq!(String::from_utf8(dst).unwrap(), pt);
     }
 
+    #[test]
+    fn test_transposition_short_buffer() {
+        let c = Transposition::new("SUBWAY").unwrap();
+        let mut dst = vec![0u8; 4];
+        assert_eq!(c.try_encrypt(&mut dst, b"ATTACK"), Err(CipherError::BufferTooSmall { needed: 6, got: 4 }));
+        let c = IrregularTransposition::new("SUBWAY").unwrap();
+        assert_eq!(c.try_decrypt(&mut dst, b"ATTACK"), Err(CipherError::BufferTooSmall { needed: 6, got: 4 }));
+    }
+
     #[test]
     fn test_irregular_transposition_mask() {
-        let key = "94735236270398134";
-        let c = IrregularTransposition::new(key).unwrap();
-
-        // Row 0:
-        // Rank 0 is at pos 10.
-        // Rank 1 is at pos 14.
-        for col in 10..17 {
-            assert!(c.is_in_triangular_area(0, col), "Row 0 col {} should be true", col);
-        }
-        for col in 0..10 {
-            assert!(!c.is_in_triangular_area(0, col), "Row 0 col {} should be false", col);
-        }
+        // Key R of the quadibloc example: columns numbered 1 and 2 are 3 and 2
+        let c = IrregularTransposition::new("HJCBFEJFGEDIFIG").unwrap();
+        let row = |r: usize| (0..15).filter(|&col| c.is_in_triangular_area(r, col)).collect::<Vec<_>>();
+
+        // The first area narrows from column 3 to the last one, then a row is left empty
+        assert_eq!(row(0), (3..15).collect::<Vec<_>>());
+        assert_eq!(row(1), (4..15).collect::<Vec<_>>());
+        assert_eq!(row(11), [14]);
+        assert!(row(12).is_empty());
+
+        // The second area starts on the next row under column 2
+        assert_eq!(row(13), (2..15).collect::<Vec<_>>());
+        assert_eq!(row(25), [14]);
+        assert!(row(26).is_empty());
+        assert_eq!(row(27).first(), Some(&10));
+    }
 
-        // Row 1: col 11 to 16 are true for the first triangle.
-        for col in 11..17 {
-            assert!(c.is_in_triangular_area(1, col), "Row 1 col {} should be true", col);
-        }
-        assert!(!c.is_in_triangular_area(1, 10));
+    #[test]
+    fn test_irregular_transposition() {
+        // W E A R E A    areas: row 0 from column 4, row 1 column 5,
+        // E D I S C T    row 2 empty, row 3 from column 2
+        // O V E R E D
+        // F L O N C E
+        // E
+        let c = IrregularTransposition::new("SUBWAY").unwrap();
+        let ct = c.encrypt_to_vec(b"WEAREDISCOVEREDFLEEATONCE").unwrap();
+        assert_eq!(ct, b"ECECAIEOWEOFEEDVLRSRNATDE");
+        assert_eq!(c.decrypt_to_vec(&ct).unwrap(), b"WEAREDISCOVEREDFLEEATONCE");
+    }
 
-        // Row 0: rank 1 is at pos 14.
-        assert!(c.is_in_triangular_area(0, 14));
+    #[test]
+    fn test_irregular_transposition_grid() {
+        let c = IrregularTransposition::new("SUBWAY").unwrap();
+        assert_eq!(c.order(), [2, 3, 1, 4, 0, 5]);
+        assert_eq!(c.grid().to_string(), "S U B W A Y\n3 4 2 5 1 6\n");
+    }
+
+    #[rstest]
+    #[case("SUBWAY")]
+    #[case("ZEBRAS")]
+    #[case("ΚΛΕΙΔΙ")]
+    #[case("AAAAA")]
+    fn test_transposition_rekey(#[case] key: &str) {
+        let mut c = Transposition::new("ABCDEFGHIJKL").unwrap();
+        c.rekey(key).unwrap();
+        assert_eq!(c.order(), helpers::to_numeric(key));
+        assert_eq!(c.key, key);
+
+        let mut c = IrregularTransposition::new("ABCDEFGHIJKL").unwrap();
+        c.rekey(key).unwrap();
+        let fresh = IrregularTransposition::new(key).unwrap();
+        assert_eq!((&c.tkey, &c.tkey_order), (&fresh.tkey, &fresh.tkey_order));
+    }
+
+    #[test]
+    fn test_transposition_rekey_errors() {
+        let mut c = Transposition::new("SUBWAY").unwrap();
+        assert_eq!(c.rekey(""), Err(CipherError::EmptyKey("key")));
+        assert_eq!(c.order(), [2, 3, 1, 4, 0, 5]);
+
+        let mut c = IrregularTransposition::new("SUBWAY").unwrap();
+        assert_eq!(c.rekey("A"), Err(CipherError::KeyTooShort { param: "key", min: 2, got: 1 }));
+        assert_eq!(c.key, "SUBWAY");
     }
 }
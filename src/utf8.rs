@@ -0,0 +1,90 @@
+//! UTF-8 plumbing for the ciphers working on characters.
+//!
+//! `Block` works on bytes, so the substitutions accepting any [`Alphabet`] decode
+//! their input first and encode their output one character at a time.
+//!
+//! [`Alphabet`]: crate::Alphabet
+//!
+use crate::CipherError;
+use crate::error::check_dst;
+
+/// Returns `src` as a string.
+///
+/// # Errors
+///
+/// Returns `CipherError::InvalidUtf8` with the offset of the first invalid byte.
+///
+pub(crate) fn decode(src: &[u8]) -> Result<&str, CipherError> {
+    std::str::from_utf8(src).map_err(|e| CipherError::InvalidUtf8 { pos: e.valid_up_to() })
+}
+
+/// Returns the number of characters of `src`, or its length if it is not UTF-8, so
+/// that the `*_len` estimates stay an upper bound.
+///
+pub(crate) fn char_count(src: &[u8]) -> usize {
+    decode(src).map_or(src.len(), |s| s.chars().count())
+}
+
+/// Writes characters into a destination buffer.
+///
+/// What does not fit is still counted, so that [`Output::finish`] can report the
+/// length actually needed.
+///
+pub(crate) struct Output<'a> {
+    dst: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> Output<'a> {
+    pub(crate) fn new(dst: &'a mut [u8]) -> Self {
+        Output { dst, len: 0 }
+    }
+
+    /// Appends `ch`, encoded as UTF-8.
+    ///
+    pub(crate) fn push(&mut self, ch: char) {
+        let end = self.len + ch.len_utf8();
+        if let Some(buf) = self.dst.get_mut(self.len..end) {
+            ch.encode_utf8(buf);
+        }
+        self.len = end;
+    }
+
+    /// Returns the number of bytes written.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::BufferTooSmall` if some of the output did not fit.
+    ///
+    pub(crate) fn finish(self) -> Result<usize, CipherError> {
+        check_dst(self.dst, self.len)?;
+        Ok(self.len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode() {
+        assert_eq!(decode("ЖУК".as_bytes()), Ok("ЖУК"));
+        assert_eq!(decode(b"AB\xffC"), Err(CipherError::InvalidUtf8 { pos: 2 }));
+        assert_eq!(char_count("ΑΒΓ".as_bytes()), 3);
+        assert_eq!(char_count(b"AB\xff"), 3);
+    }
+
+    #[test]
+    fn test_output() {
+        let mut dst = [0u8; 4];
+        let mut out = Output::new(&mut dst);
+        out.push('A');
+        out.push('Ж');
+        assert_eq!(out.finish(), Ok(3));
+        assert_eq!(&dst[..3], "AЖ".as_bytes());
+
+        let mut out = Output::new(&mut dst);
+        "ЖЖЖ".chars().for_each(|c| out.push(c));
+        assert_eq!(out.finish(), Err(CipherError::BufferTooSmall { needed: 6, got: 4 }));
+    }
+}
@@ -5,10 +5,32 @@
 //!
 //! Full description & test vectors: <http://www.quadibloc.com/crypto/pp1324.htm>
 //!
-use crate::Block;
+//! [`VicCipher::new`] takes the key material as is.  [`VicMessage`] follows the whole
+//! procedure of Häyhänen's messages: the widths of both transpositions come from his
+//! personal number, the checkerboard is drawn from the key, and the message keygroup
+//! goes into the ciphertext at a position given by the date, where the recipient
+//! finds it.
+//!
+//! # Example
+//!
+//! ```
+//! use old_crypto_rs::{BlockExt, VicMessage};
+//!
+//! let m = VicMessage::new(6, "391945", "IDREAMOFJEANNIEWITHT", "77651").unwrap();
+//! let ct = m.encrypt_str("ATTACKATDAWN").unwrap();
+//! assert_eq!(ct.len() % 5, 0);
+//!
+//! // The recipient only knows the personal number, date and phrase
+//! let r = VicMessage::new(6, "391945", "IDREAMOFJEANNIEWITHT", "00000").unwrap();
+//! assert!(r.decrypt_str(&ct).unwrap().starts_with("ATTACKATDAWN"));
+//! ```
+//!
+use crate::{Alphabet, Block, BlockExt, CipherError, Normalizer, Rng, Tracer};
 use crate::transposition::{Transposition, IrregularTransposition};
 use crate::straddling::{StraddlingCheckerboard, ALPHABET_TXT};
 use crate::helpers;
+use crate::error::check_dst;
+use crate::trace::{self, NoTrace};
 
 /// VIC cipher implementation combining straddling checkerboard and transposition ciphers.
 ///
@@ -45,10 +67,17 @@ impl VicCipher {
     ///
     /// # Returns
     ///
-    /// Returns `Ok(VicCipher)` if the cipher was successfully constructed, or `Err(String)`
+    /// Returns `Ok(VicCipher)` if the cipher was successfully constructed, or a `CipherError`
     /// if any of the key material is invalid or if the transposition or straddling checkerboard
     /// construction fails.
     ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `ind` or `imsg` has fewer than 5 digits (`KeyTooShort`) or contains a non-digit (`InvalidDigit`)
+    /// - `phrase` has fewer than 20 characters (`KeyTooShort`)
+    /// - `persn` is not valid as the checkerboard long digits (reported as `persn`)
+    ///
     /// # Examples
     ///
     /// ```
@@ -61,7 +90,17 @@ impl VicCipher {
     /// ).unwrap();
     /// ```
     ///
-    pub fn new(persn: &str, ind: &str, phrase: &str, imsg: &str) -> Result<Self, String> {
+    pub fn new(persn: &str, ind: &str, phrase: &str, imsg: &str) -> Result<Self, CipherError> {
+        check_digits("ind", ind, 5)?;
+        check_digits("imsg", imsg, 5)?;
+        if phrase.len() < 20 {
+            return Err(CipherError::KeyTooShort { param: "phrase", min: 20, got: phrase.len() });
+        }
+        if !phrase.is_ascii() {
+            let symbol = phrase.chars().find(|c| !c.is_ascii()).unwrap_or_default();
+            return Err(CipherError::UnknownSymbol { param: "phrase", symbol });
+        }
+
         let imsg_int = str2int(imsg);
         let ikey5 = str2int(&ind[..5]);
 
@@ -75,7 +114,8 @@ impl VicCipher {
 
         // Straddling Checkerboard using 'sckey' (converted to letters) and 'persn'
         let sc_key_str: String = expanded.sckey.iter().map(|&v| (b'0' + v) as char).collect();
-        let sc = StraddlingCheckerboard::new_with_freq(&sc_key_str, persn, "ATONESIR", ALPHABET_TXT)?;
+        let sc = StraddlingCheckerboard::new_with_freq(&sc_key_str, persn, "ATONESIR", &Alphabet::new(ALPHABET_TXT)?)
+            .map_err(|e| e.for_param("persn"))?;
 
         Ok(VicCipher {
             firsttp,
@@ -83,6 +123,17 @@ impl VicCipher {
             sc,
         })
     }
+
+    /// Creates a VIC cipher from random key material: a personal number of two distinct
+    /// digits, a 6-digit indicator, a 20-letter phrase and a 5-digit message number.
+    ///
+    pub fn random(rng: &mut Rng) -> Self {
+        let persn = rng.distinct_digits(2);
+        let ind = rng.digits(6);
+        let phrase = rng.word(&Alphabet::latin(), 20);
+        let imsg = rng.digits(5);
+        Self::new(&persn, &ind, &phrase, &imsg).unwrap()
+    }
 }
 
 /// Intermediate structure holding expanded key material.
@@ -116,8 +167,8 @@ struct ExpandedKey {
 /// Returns an `ExpandedKey` structure containing all derived key material.
 ///
 fn expand_key(phrase: &str, imsg: &[u8], ikey5: &[u8]) -> ExpandedKey {
-    let ph1: Vec<u8> = helpers::to_numeric(&phrase[..10]).into_iter().map(|x| (x as u8 + 1) % 10).collect();
-    let ph2: Vec<u8> = helpers::to_numeric(&phrase[10..20]).into_iter().map(|x| (x as u8 + 1) % 10).collect();
+    let ph1: Vec<u8> = helpers::to_numeric(&phrase[..10]).into_iter().map(|x| (x + 1) % 10).collect();
+    let ph2: Vec<u8> = helpers::to_numeric(&phrase[10..20]).into_iter().map(|x| (x + 1) % 10).collect();
 
     let mut first = submod10(imsg, ikey5);
     first = chainadd_extend(&first, 5);
@@ -144,6 +195,195 @@ fn expand_key(phrase: &str, imsg: &[u8], ikey5: &[u8]) -> ExpandedKey {
 }
 
 
+/// The checkerboard of the VIC messages: the letters of "AT ONE SIR" on the top row,
+/// its spaces being the blanks, then the other letters, the full stop and the digit
+/// marker.
+///
+pub const VIC_BOARD: [&str; 3] = ["AT ONE SIR", "BCDFGHJKLM", "PQUVWXYZ./"];
+
+/// The key derivation of a VIC message, line by line as on the agent's worksheet.
+///
+/// Lines are lettered A to S, without I and O.  Digits are numbers from 0 to 9,
+/// sequenced lines number their digits or letters from 1 in order, 10 being
+/// written 0.  It prints as the worksheet.
+///
+/// # Example
+///
+/// ```
+/// use old_crypto_rs::VicMessage;
+///
+/// let m = VicMessage::new(6, "391945", "IDREAMOFJEANNIEWITHT", "77651").unwrap();
+/// let w = m.worksheet();
+/// assert_eq!(w.c, [4, 8, 5, 6, 7]);
+/// assert_eq!(w.h, [9, 9, 2, 3, 8, 9, 6, 0, 9, 7]);
+/// assert_eq!(w.to_string().lines().next(), Some("A  7 7 6 5 1"));
+/// ```
+///
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct VicWorksheet {
+    /// The message keygroup
+    pub a: Vec<u8>,
+    /// The first five digits of the date
+    pub b: Vec<u8>,
+    /// `a - b`, digit by digit without carry
+    pub c: Vec<u8>,
+    /// The first 20 letters of the phrase
+    pub d: Vec<char>,
+    /// Both halves of the phrase, sequenced
+    pub e: [Vec<u8>; 2],
+    /// `c` extended to ten digits by chain addition
+    pub f: Vec<u8>,
+    /// `e[0] + f`, digit by digit without carry
+    pub g: Vec<u8>,
+    /// `g` encoded through `e[1]`: each digit is replaced by the one of `e[1]` under
+    /// its place in 1234567890
+    pub h: Vec<u8>,
+    /// `h` sequenced, the order in which the columns of the block are read
+    pub j: Vec<u8>,
+    /// Lines K, L, M, N and P: `h` extended by chain addition, ten digits each
+    pub block: [Vec<u8>; 5],
+    /// The key of the first transposition, as wide as the personal number plus the
+    /// next to last digit of line P that differs from the last one
+    pub q: Vec<u8>,
+    /// The key of the second transposition, as wide as the personal number plus the
+    /// last digit of line P
+    pub r: Vec<u8>,
+    /// Line P sequenced, the digits heading the columns of the checkerboard
+    pub s: Vec<u8>,
+}
+
+impl VicWorksheet {
+    /// Runs the key derivation of a message, to be checked against one done by hand.
+    ///
+    /// # Arguments
+    ///
+    /// * `persn` - The personal number of the agent, from 1 to 16
+    /// * `date` - The date as six digits, e.g. "391945" for 3 September 1945
+    /// * `phrase` - The key phrase, of which the first 20 letters are used
+    /// * `keygroup` - The five digits of the message keygroup
+    ///
+    /// # Errors
+    ///
+    /// Same as [`VicMessage::new`].
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use old_crypto_rs::VicWorksheet;
+    ///
+    /// let w = VicWorksheet::new(6, "391945", "I dream of Jeannie with the light brown hair", "77651").unwrap();
+    /// assert_eq!(w.b, [3, 9, 1, 9, 4]);
+    /// assert_eq!(w.q, [5, 6, 4, 4, 7, 1]);
+    /// assert_eq!(w.s, [3, 9, 6, 1, 4, 2, 7, 5, 0, 8]);
+    /// assert!(w.to_string().contains("\nS  3 9 6 1 4 2 7 5 0 8\n"));
+    /// ```
+    ///
+    pub fn new(persn: usize, date: &str, phrase: &str, keygroup: &str) -> Result<Self, CipherError> {
+        if !(1..=16).contains(&persn) {
+            return Err(CipherError::InvalidParameter { param: "persn", value: persn.to_string() });
+        }
+        check_exact_digits("date", date, 6)?;
+        check_exact_digits("keygroup", keygroup, 5)?;
+        let phrase: Vec<char> = phrase.chars().filter(|c| c.is_alphabetic()).flat_map(char::to_uppercase).take(20).collect();
+        if phrase.len() < 20 {
+            return Err(CipherError::KeyTooShort { param: "phrase", min: 20, got: phrase.len() });
+        }
+        Ok(Self::derive(persn, &str2int(date), &phrase, &str2int(keygroup)))
+    }
+
+    /// Runs the key derivation.  `date` has six digits, `phrase` 20 letters.
+    ///
+    fn derive(persn: usize, date: &[u8], phrase: &[char], keygroup: &[u8]) -> Self {
+        let half = |r: std::ops::Range<usize>| -> Vec<u8> {
+            let s: String = phrase[r].iter().collect();
+            helpers::to_numeric(&s).into_iter().map(|x| (x + 1) % 10).collect()
+        };
+        let c = submod10(keygroup, &date[..5]);
+        let e = [half(0..10), half(10..20)];
+        let f = chainadd_extend(&c, 5);
+        let mut g = f.clone();
+        addmod10_inplace(&mut g, &e[0]);
+        let h = first_encode(&g, &e[1]);
+        let j = sequence(&h);
+        let digits = chainadd_extend(&h, 50);
+        let block: [Vec<u8>; 5] = std::array::from_fn(|i| digits[10 * (i + 1)..10 * (i + 2)].to_vec());
+
+        // The personal number plus the last two unequal digits of line P
+        let p = &block[4];
+        let last = p[9];
+        let before = p[..9].iter().rev().copied().find(|&d| d != last).unwrap_or(last);
+        let widths = [persn + before as usize, persn + last as usize];
+
+        // Lines K to P read by columns, in the order of line J
+        let mut columns = Vec::with_capacity(50);
+        for n in (1..=10).map(|n| n % 10) {
+            let col = j.iter().position(|&d| d == n).unwrap_or_default();
+            columns.extend(block.iter().map(|row| row[col]));
+        }
+        let q = columns[..widths[0]].to_vec();
+        let r = columns[widths[0]..widths[0] + widths[1]].to_vec();
+        let s = sequence(p);
+
+        VicWorksheet { a: keygroup.to_vec(), b: date[..5].to_vec(), c, d: phrase.to_vec(), e, f, g, h, j, block, q, r, s }
+    }
+}
+
+/// Writes `items` separated by spaces.
+///
+fn spaced<T: std::fmt::Display>(items: &[T]) -> String {
+    items.iter().map(T::to_string).collect::<Vec<_>>().join(" ")
+}
+
+/// One line per letter, the two halves of D, E and F side by side, the second
+/// half of F being the 1234567890 row H is encoded through.
+///
+impl std::fmt::Display for VicWorksheet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "A  {}", spaced(&self.a))?;
+        writeln!(f, "B  {}", spaced(&self.b))?;
+        writeln!(f, "C  {}", spaced(&self.c))?;
+        writeln!(f, "D  {}   {}", spaced(&self.d[..10]), spaced(&self.d[10..]))?;
+        writeln!(f, "E  {}   {}", spaced(&self.e[0]), spaced(&self.e[1]))?;
+        writeln!(f, "F  {}   1 2 3 4 5 6 7 8 9 0", spaced(&self.f))?;
+        writeln!(f, "G  {}", spaced(&self.g))?;
+        writeln!(f, "H  {}", spaced(&self.h))?;
+        writeln!(f, "J  {}", spaced(&self.j))?;
+        for (letter, row) in ["K", "L", "M", "N", "P"].iter().zip(&self.block) {
+            writeln!(f, "{}  {}", letter, spaced(row))?;
+        }
+        writeln!(f, "Q  {}", spaced(&self.q))?;
+        writeln!(f, "R  {}", spaced(&self.r))?;
+        writeln!(f, "S  {}", spaced(&self.s))
+    }
+}
+
+/// Numbers the digits of `a` from 1 in increasing order, 0 coming after 9 and ties
+/// going from left to right, 10 being written 0.
+///
+fn sequence(a: &[u8]) -> Vec<u8> {
+    let rank = |i: usize| ((a[i] + 9) % 10, i);
+    (0..a.len()).map(|i| ((0..a.len()).filter(|&j| rank(j) < rank(i)).count() + 1) as u8 % 10).collect()
+}
+
+/// Writes digits as a transposition key: letters in the order of the VIC
+/// sequencing, where 0 comes after 9.
+///
+fn digit_key(a: &[u8]) -> String {
+    a.iter().map(|&d| (b'A' + (d + 9) % 10) as char).collect()
+}
+
+/// Checks that a key is made of at least `min` decimal digits.
+///
+fn check_digits(param: &'static str, s: &str, min: usize) -> Result<(), CipherError> {
+    if let Some(ch) = s.chars().find(|c| !c.is_ascii_digit()) {
+        return Err(CipherError::InvalidDigit { param, ch });
+    }
+    if s.len() < min {
+        return Err(CipherError::KeyTooShort { param, min, got: s.len() });
+    }
+    Ok(())
+}
+
 /// Converts a string of digits to a vector of integers.
 ///
 /// # Arguments
@@ -209,7 +449,7 @@ fn chainadd_inplace(a: &mut [u8]) {
 /// * `a` - Initial slice
 /// * `n` - Number of elements to add
 ///
-fn chainadd_extend(a: &[u8], n: usize) -> Vec<u8> {
+pub(crate) fn chainadd_extend(a: &[u8], n: usize) -> Vec<u8> {
     let mut res = Vec::with_capacity(a.len() + n);
     res.extend_from_slice(a);
     for i in 0..n {
@@ -256,11 +496,292 @@ fn first_encode(a: &[u8], b: &[u8]) -> Vec<u8> {
     a.iter().map(|&v| b[((v as i32 + 9) % 10) as usize]).collect()
 }
 
+impl VicCipher {
+    /// Builds the cipher of a message from its worksheet.
+    ///
+    fn from_worksheet(keys: &VicWorksheet, rows: &[&str]) -> Result<Self, CipherError> {
+        let header: String = keys.s.iter().map(|&d| (b'0' + d) as char).collect();
+        Ok(VicCipher {
+            firsttp: Transposition::new(&digit_key(&keys.q)).map_err(|e| e.for_param("persn"))?,
+            secondtp: IrregularTransposition::new(&digit_key(&keys.r)).map_err(|e| e.for_param("persn"))?,
+            sc: StraddlingCheckerboard::from_rows(&header, rows)?,
+        })
+    }
+}
+
+/// A VIC message, following the whole historical procedure.
+///
+/// The keys of a message come from the agent's personal number, the date, the
+/// phrase and a keygroup chosen at random for each message:
+///
+/// - lines A to S of the [worksheet](VicWorksheet) are derived from them as
+///   described on the quadibloc page, line S giving the order of the columns of the
+///   checkerboard;
+/// - the widths of the transpositions are the personal number plus each of the last
+///   two unequal digits of line P, their keys the digits of lines K to P read by
+///   columns in the order of line J;
+/// - the checkerboard output is completed to whole groups of five with the code of
+///   the first letter of the board, which decryption leaves at the end;
+/// - the keygroup is inserted as the n-th group from the end of the ciphertext, n
+///   being the last digit of the date (0 counting as 10), or first if the message is
+///   shorter than that.
+///
+/// Decryption takes the keygroup out of the ciphertext, so it does not depend on
+/// the one given to [`VicMessage::new`].  Spaces between groups are ignored.
+///
+#[derive(Debug)]
+pub struct VicMessage {
+    persn: usize,
+    date: Vec<u8>,
+    phrase: Vec<char>,
+    rows: Vec<String>,
+    /// Keys of the message encrypted
+    cipher: VicCipher,
+    keygroup: Vec<u8>,
+}
+
+impl VicMessage {
+    /// Creates a message with the [`VIC_BOARD`] checkerboard.
+    ///
+    /// # Arguments
+    ///
+    /// * `persn` - The personal number of the agent, from 1 to 16
+    /// * `date` - The date as six digits, e.g. "391945" for 3 September 1945
+    /// * `phrase` - The key phrase, of which the first 20 letters are used
+    /// * `keygroup` - The five random digits of the message keygroup
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - `persn` is not between 1 and 16 (`InvalidParameter`)
+    /// - `date` or `keygroup` has a non-digit (`InvalidDigit`), fewer digits than
+    ///   needed (`KeyTooShort`) or more (`InvalidParameter`)
+    /// - `phrase` has fewer than 20 letters (`KeyTooShort`)
+    ///
+    pub fn new(persn: usize, date: &str, phrase: &str, keygroup: &str) -> Result<Self, CipherError> {
+        let keys = VicWorksheet::new(persn, date, phrase, keygroup)?;
+        let cipher = VicCipher::from_worksheet(&keys, &VIC_BOARD)?;
+        Ok(VicMessage { persn, date: str2int(date), phrase: keys.d, rows: VIC_BOARD.map(String::from).to_vec(), cipher, keygroup: keys.a })
+    }
+
+    /// Uses another checkerboard, given as for [`StraddlingCheckerboard::from_rows`],
+    /// e.g. a Russian one.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`StraddlingCheckerboard::from_rows`], with the header of the key.
+    ///
+    pub fn with_board(self, rows: &[&str]) -> Result<Self, CipherError> {
+        let cipher = VicCipher::from_worksheet(&self.worksheet(), rows)?;
+        Ok(VicMessage { cipher, rows: rows.iter().map(|r| r.to_string()).collect(), ..self })
+    }
+
+    /// Returns the cipher of the message, for the keygroup given to
+    /// [`VicMessage::new`].
+    ///
+    pub fn cipher(&self) -> &VicCipher {
+        &self.cipher
+    }
+
+    /// Returns the key derivation of the message, for the keygroup given to
+    /// [`VicMessage::new`].
+    ///
+    pub fn worksheet(&self) -> VicWorksheet {
+        VicWorksheet::derive(self.persn, &self.date, &self.phrase, &self.keygroup)
+    }
+
+    /// Returns the rows of the checkerboard.
+    ///
+    fn rows_ref(&self) -> Vec<&str> {
+        self.rows.iter().map(String::as_str).collect()
+    }
+
+    /// Returns the index of the keygroup among the `groups` groups of a message.
+    ///
+    fn keygroup_pos(&self, groups: usize) -> usize {
+        let n = match self.date[5] {
+            0 => 10,
+            n => n as usize,
+        };
+        groups.saturating_sub(n)
+    }
+
+    /// Returns the checkerboard code of the letter completing the last group.
+    ///
+    fn null(&self) -> Vec<u8> {
+        let first = self.rows[0].chars().find(|&c| c != ' ').unwrap_or_default();
+        self.cipher.sc.encrypt_to_vec(first.encode_utf8(&mut [0; 4]).as_bytes()).unwrap_or_default()
+    }
+
+    /// Runs the checkerboard and pads its output to whole groups.
+    ///
+    fn encode(&self, src: &[u8], tracer: &mut dyn Tracer) -> Result<Vec<u8>, CipherError> {
+        let mut buf = vec![0u8; self.cipher.sc.encrypt_len(src)];
+        let n = self.cipher.sc.try_encrypt_traced(&mut buf, src, tracer)?;
+        buf.truncate(n);
+        let null = self.null();
+        while !buf.len().is_multiple_of(5) {
+            buf.extend_from_slice(&null);
+        }
+        Ok(buf)
+    }
+}
+
+/// Checks that a key is made of exactly `len` decimal digits.
+///
+fn check_exact_digits(param: &'static str, s: &str, len: usize) -> Result<(), CipherError> {
+    check_digits(param, s, len)?;
+    if s.len() > len {
+        return Err(CipherError::InvalidParameter { param, value: s.to_string() });
+    }
+    Ok(())
+}
+
+impl Block for VicMessage {
+    fn block_size(&self) -> usize {
+        5
+    }
+
+    fn alphabet(&self) -> Option<Alphabet> {
+        self.cipher.sc.alphabet()
+    }
+
+    fn normalizer(&self) -> Normalizer {
+        Normalizer::alphanumeric()
+    }
+
+    /// The checkerboard output completed to whole groups, plus the keygroup.
+    ///
+    fn encrypt_len(&self, src: &[u8]) -> usize {
+        self.cipher.sc.encrypt_len(src) + 9
+    }
+
+    /// The ciphertext without the keygroup.
+    ///
+    fn decrypt_len(&self, src: &[u8]) -> usize {
+        self.cipher.sc.decrypt_len(src)
+    }
+
+    fn max_encrypt_len(&self, len: usize) -> usize {
+        self.cipher.sc.max_encrypt_len(len) + 9
+    }
+
+    fn max_decrypt_len(&self, len: usize) -> usize {
+        self.cipher.sc.max_decrypt_len(len)
+    }
+
+    fn encrypt_chars(&self, _chars: usize) -> Option<usize> {
+        None
+    }
+
+    /// The last group is completed with nulls and the keygroup goes in once.
+    ///
+    fn whole_message(&self) -> bool {
+        true
+    }
+
+    /// Encrypts `src` and inserts the keygroup.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::InvalidChar` for a character the checkerboard can not
+    /// encode and `CipherError::BufferTooSmall` if `dst` can not hold the whole output.
+    ///
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.try_encrypt_traced(dst, src, &mut NoTrace)
+    }
+
+    /// Takes the keygroup out of `src`, derives the keys of the message from it and
+    /// decrypts the rest.  The nulls completing the last group are kept.
+    ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::InvalidChar` for a character which is neither a digit
+    /// nor a space, `CipherError::KeyTooShort` for a message shorter than the
+    /// keygroup, `CipherError::InvalidParameter` if it is not in whole groups of
+    /// five and `CipherError::BufferTooSmall` if `dst` can not hold the whole
+    /// output.
+    ///
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.try_decrypt_traced(dst, src, &mut NoTrace)
+    }
+
+    /// Reports the checkerboard, both transpositions and the keygroup insertion.
+    ///
+    fn try_encrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        let digits = self.encode(src, tracer)?;
+        let mut buf = vec![0u8; digits.len()];
+        let n = self.cipher.firsttp.try_encrypt_traced(&mut buf, &digits, tracer)?;
+        let mut ct = vec![0u8; n];
+        let n = self.cipher.secondtp.try_encrypt_traced(&mut ct, &buf[..n], tracer)?;
+        ct.truncate(n);
+
+        let at = 5 * self.keygroup_pos(n / 5 + 1);
+        let keygroup: Vec<u8> = self.keygroup.iter().map(|&d| b'0' + d).collect();
+        ct.splice(at..at, keygroup);
+        let res = check_dst(dst, ct.len()).map(|_| {
+            dst[..ct.len()].copy_from_slice(&ct);
+            ct.len()
+        });
+        trace::report(tracer, "Keygroup", &buf[..n], dst, res)
+    }
+
+    /// Reports the keygroup extraction, both transpositions and the checkerboard.
+    ///
+    fn try_decrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        let mut ct = Vec::with_capacity(src.len());
+        for (pos, &b) in src.iter().enumerate() {
+            match b {
+                b'0'..=b'9' => ct.push(b),
+                b' ' => {}
+                _ => return Err(CipherError::InvalidChar { ch: b as char, pos }),
+            }
+        }
+        if ct.len() < 5 {
+            return Err(CipherError::KeyTooShort { param: "src", min: 5, got: ct.len() });
+        }
+        if !ct.len().is_multiple_of(5) {
+            return Err(CipherError::InvalidParameter { param: "src", value: String::from_utf8_lossy(src).into_owned() });
+        }
+        let at = 5 * self.keygroup_pos(ct.len() / 5);
+        let keygroup: Vec<u8> = ct.drain(at..at + 5).map(|b| b - b'0').collect();
+        tracer.stage("Keygroup", src, Ok(&ct));
+
+        let cipher = VicCipher::from_worksheet(&VicWorksheet::derive(self.persn, &self.date, &self.phrase, &keygroup), &self.rows_ref())?;
+        cipher.try_decrypt_traced(dst, &ct, tracer)
+    }
+}
+
 impl Block for VicCipher {
     fn block_size(&self) -> usize {
         1
     }
 
+    fn alphabet(&self) -> Option<Alphabet> {
+        self.sc.alphabet()
+    }
+
+    /// The checkerboard takes letters and digits.
+    ///
+    fn normalizer(&self) -> Normalizer {
+        Normalizer::alphanumeric()
+    }
+
+    /// The output has as many digits as the checkerboard produces, both transpositions
+    /// keep the length.
+    ///
+    fn encrypt_len(&self, src: &[u8]) -> usize {
+        self.sc.encrypt_len(src)
+    }
+
+    fn max_encrypt_len(&self, len: usize) -> usize {
+        self.sc.max_encrypt_len(len)
+    }
+
+    fn encrypt_chars(&self, chars: usize) -> Option<usize> {
+        self.sc.encrypt_chars(chars)
+    }
+
     /// Encrypts plaintext using the VIC cipher.
     ///
     /// The encryption process consists of three steps:
@@ -277,19 +798,30 @@ impl Block for VicCipher {
     ///
     /// Returns the number of bytes written to the destination buffer.
     ///
-    fn encrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
+    /// # Errors
+    ///
+    /// Returns `CipherError::InvalidChar` for a character the checkerboard can not
+    /// encode and `CipherError::BufferTooSmall` if `dst` can not hold the whole output.
+    ///
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.try_encrypt_traced(dst, src, &mut NoTrace)
+    }
+
+    /// Reports the checkerboard, then both transpositions.
+    ///
+    fn try_encrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
         // VIC Encipherment:
         // 1. Straddling Checkerboard
         // 2. First Transposition (regular)
         // 3. Second Transposition (irregular)
 
-        let mut buf_sc = vec![0u8; src.len() * 3]; // Straddling can expand
-        let sc_len = self.sc.encrypt(&mut buf_sc, src);
+        let mut buf_sc = vec![0u8; self.sc.encrypt_len(src)]; // Straddling can expand
+        let sc_len = self.sc.try_encrypt_traced(&mut buf_sc, src, tracer)?;
 
         let mut buf_tp1 = vec![0u8; sc_len];
-        let tp1_len = self.firsttp.encrypt(&mut buf_tp1, &buf_sc[..sc_len]);
+        let tp1_len = self.firsttp.try_encrypt_traced(&mut buf_tp1, &buf_sc[..sc_len], tracer)?;
 
-        self.secondtp.encrypt(dst, &buf_tp1[..tp1_len])
+        self.secondtp.try_encrypt_traced(dst, &buf_tp1[..tp1_len], tracer)
     }
 
     /// Decrypts ciphertext using the VIC cipher.
@@ -308,19 +840,29 @@ impl Block for VicCipher {
     ///
     /// Returns the number of bytes written to the destination buffer.
     ///
-    fn decrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
+    /// # Errors
+    ///
+    /// Returns `CipherError::BufferTooSmall` if `dst` can not hold the whole output.
+    ///
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.try_decrypt_traced(dst, src, &mut NoTrace)
+    }
+
+    /// Reports both transpositions, then the checkerboard.
+    ///
+    fn try_decrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
         // VIC Decipherment (Reverse of Encipherment):
         // 1. Second Transposition (irregular)
         // 2. First Transposition (regular)
         // 3. Straddling Checkerboard
 
         let mut buf_tp2 = vec![0u8; src.len()];
-        let tp2_len = self.secondtp.decrypt(&mut buf_tp2, src);
+        let tp2_len = self.secondtp.try_decrypt_traced(&mut buf_tp2, src, tracer)?;
 
         let mut buf_tp1 = vec![0u8; tp2_len];
-        let tp1_len = self.firsttp.decrypt(&mut buf_tp1, &buf_tp2[..tp2_len]);
+        let tp1_len = self.firsttp.try_decrypt_traced(&mut buf_tp1, &buf_tp2[..tp2_len], tracer)?;
 
-        self.sc.decrypt(dst, &buf_tp1[..tp1_len])
+        self.sc.try_decrypt_traced(dst, &buf_tp1[..tp1_len], tracer)
     }
 }
 
@@ -334,11 +876,20 @@ mod tests {
         let _c = VicCipher::new("89", "741776", "IDREAMOFJEANNIEWITHT", "77651").unwrap();
     }
 
+    #[rstest]
+    #[case("89", "7417", "IDREAMOFJEANNIEWITHT", "77651", CipherError::KeyTooShort { param: "ind", min: 5, got: 4 })]
+    #[case("89", "741776", "IDREAMOFJEANNIEWITHT", "7765X", CipherError::InvalidDigit { param: "imsg", ch: 'X' })]
+    #[case("89", "741776", "IDREAMOFJEANNIE", "77651", CipherError::KeyTooShort { param: "phrase", min: 20, got: 15 })]
+    #[case("8", "741776", "IDREAMOFJEANNIEWITHT", "77651", CipherError::KeyTooShort { param: "persn", min: 2, got: 1 })]
+    fn test_new_cipher_errors(#[case] persn: &str, #[case] ind: &str, #[case] phrase: &str, #[case] imsg: &str, #[case] err: CipherError) {
+        assert_eq!(VicCipher::new(persn, ind, phrase, imsg).err(), Some(err));
+    }
+
     #[rstest]
     #[case("IDREAMOFJE", vec![6, 2, 0, 3, 1, 8, 9, 5, 7, 4])]
     #[case("ANNIEWITHT", vec![1, 6, 7, 4, 2, 0, 5, 8, 3, 9])]
     fn test_to_numeric_one(#[case] s: &str, #[case] r: Vec<u8>) {
-        let res: Vec<u8> = helpers::to_numeric(s).into_iter().map(|x| (x as u8 + 1) % 10).collect();
+        let res: Vec<u8> = helpers::to_numeric(s).into_iter().map(|x| (x + 1) % 10).collect();
         assert_eq!(res, r);
     }
 
@@ -534,4 +1085,153 @@ mod tests {
         assert_eq!(sckey, vec![0, 7, 9, 4, 1, 6, 3, 5, 8, 2]);
     }
 
+    #[test]
+    fn test_vic_traced() {
+        use crate::{BlockExt, Trace, TraceEntry};
+
+        let c = VicCipher::new("89", "741776", "IDREAMOFJEANNIEWITHT", "77651").unwrap();
+        let mut trace = Trace::new();
+        let ct = c.encrypt_traced(b"ATTACKATDAWN", &mut trace).unwrap();
+        assert_eq!(ct, c.encrypt_to_vec(b"ATTACKATDAWN").unwrap());
+        let names: Vec<&str> = trace.stages().map(|e| match e {
+            TraceEntry::Stage { name, .. } => name.as_str(),
+            _ => "",
+        }).collect();
+        assert_eq!(names, ["StraddlingCheckerboard", "Transposition", "IrregularTransposition"]);
+        let Some(TraceEntry::Stage { output: Ok(last), .. }) = trace.entries().last() else { panic!() };
+        assert_eq!(last.as_bytes(), ct);
+    }
+
+    #[test]
+    fn test_vic_message_keys() {
+        let keys = VicWorksheet::new(6, "391945", "IDREAMOFJEANNIEWITHT", "77651").unwrap();
+        assert_eq!(keys.q, str2int("564471"));
+        assert_eq!(keys.r, str2int("803265067549697"));
+        assert_eq!(keys.s, str2int("3961427508"));
+        assert_eq!(sequence(&str2int("9923896097")), str2int("6712583094"));
+        assert_eq!(digit_key(&str2int("1290")), "ABIJ");
+    }
+
+    #[test]
+    fn test_vic_message() {
+        let m = VicMessage::new(6, "391945", "I dream of Jeannie with the light brown hair", "77651").unwrap();
+        let ct = m.encrypt_to_vec(b"ATTACKATDAWN").unwrap();
+        assert_eq!(ct.len() % 5, 0);
+
+        // The keygroup is the fifth group from the end, or the first of a short message
+        let groups: Vec<&[u8]> = ct.chunks(5).collect();
+        assert_eq!(groups[groups.len().saturating_sub(5)], b"77651");
+        let pt = m.decrypt_to_vec(&ct).unwrap();
+        assert!(pt.starts_with(b"ATTACKATDAWN") && pt.len() < 12 + 5, "{}", String::from_utf8_lossy(&pt));
+
+        let long = "WEAREREADYTOSTARTTHEOPERATIONATTHEAGREEDPLACEONTHETENTHOFMARCH";
+        let ct = m.encrypt_str(long).unwrap();
+        let groups: Vec<&str> = ct.as_bytes().chunks(5).map(|g| std::str::from_utf8(g).unwrap()).collect();
+        assert_eq!(groups[groups.len() - 5], "77651");
+
+        // Another keygroup gives other keys, and the recipient does not need it
+        let other = VicMessage::new(6, "391945", "IDREAMOFJEANNIEWITHT", "12345").unwrap();
+        let ct2 = other.encrypt_str(long).unwrap();
+        assert_ne!(ct2.replace("12345", ""), ct.replace("77651", ""));
+        assert!(m.decrypt_str(&groups.join(" ")).unwrap().starts_with(long));
+        assert!(m.decrypt_str(&ct2).unwrap().starts_with(long));
+    }
+
+    #[test]
+    fn test_vic_hollow_nickel() {
+        // The opening of the Hollow Nickel message, with the keys of the quadibloc example
+        let pt = "WEAREPLEASEDTOHEAROFYOURSAFEARRIVAL.WECONFIRMTHERECEIPTOFYOURLETTERTOTHEADDRESSVREPEATVANDTHEREADINGOFLETTERNUMBER1.";
+        // Checked against a separate model of the procedure, not against the message
+        // printed on the quadibloc page
+        let groups = "67826 66366 81168 26130 68323 19882 02123 84716 76724 82199 76462 27802 92390 \
+                      49296 89987 50245 86068 88311 62960 26349 18783 61210 79166 72177 36612 26097 \
+                      67801 14637 77651 16622 53227 72635 76367";
+        let m = VicMessage::new(6, "391945", "IDREAMOFJEANNIEWITHT", "77651").unwrap();
+
+        // Personal number 6 plus the digits 0 and 9 ending line P
+        let w = m.worksheet();
+        assert_eq!((w.q.len(), w.r.len()), (6, 15));
+
+        // The keygroup is the fifth group from the end, the date ending in 5
+        let ct = m.encrypt_str(pt).unwrap();
+        assert_eq!(ct, groups.replace(' ', ""));
+        assert_eq!(&ct[ct.len() - 25..ct.len() - 20], "77651");
+
+        // The recipient finds the keygroup in the message
+        let r = VicMessage::new(6, "391945", "IDREAMOFJEANNIEWITHT", "00000").unwrap();
+        let dec = r.decrypt_str(groups).unwrap();
+        assert!(dec.starts_with(pt) && dec[pt.len()..].chars().all(|c| c == 'A'), "{}", dec);
+    }
+
+    #[test]
+    fn test_vic_message_board() {
+        let board = ["ES TONIA R", "BCDFGHJKLM", "PQUVWXYZ./"];
+        let m = VicMessage::new(13, "391945", "IDREAMOFJEANNIEWITHT", "20818")
+            .unwrap()
+            .with_board(&board)
+            .unwrap();
+        let ct = m.encrypt_str("WEAREPLEASEDTOHEAROFYOURSAFEARRIVAL.").unwrap();
+        assert!(ct.bytes().all(|b| b.is_ascii_digit()));
+        assert!(m.decrypt_str(&ct).unwrap().starts_with("WEAREPLEASEDTOHEAROFYOURSAFEARRIVAL."));
+    }
+
+    #[test]
+    fn test_vic_message_russian_board() {
+        let board = ["ОЕА ИНТ С ", "БВГДЖЗЙКЛМ", "ПРУФХЦЧШЩЪ", "ЫЬЭЮЯЁ./"];
+        let m = VicMessage::new(13, "391945", "ТОЛЬКО СЛЫШНО НА УЛИЦЕ ГДЕ-ТО", "20818")
+            .unwrap()
+            .with_board(&board)
+            .unwrap();
+        let ct = m.encrypt_str("ПОЗДРАВЛЯЮСБЛАГОПОЛУЧНЫМПРИБЫТИЕМ.").unwrap();
+        assert!(ct.bytes().all(|b| b.is_ascii_digit()));
+        assert!(m.decrypt_str(&ct).unwrap().starts_with("ПОЗДРАВЛЯЮСБЛАГОПОЛУЧНЫМПРИБЫТИЕМ."));
+    }
+
+    #[rstest]
+    #[case(0, "391945", "IDREAMOFJEANNIEWITHT", "77651", CipherError::InvalidParameter { param: "persn", value: "0".to_string() })]
+    #[case(6, "39194", "IDREAMOFJEANNIEWITHT", "77651", CipherError::KeyTooShort { param: "date", min: 6, got: 5 })]
+    #[case(6, "391945", "IDREAMOFJEANNIEWITHT", "776510", CipherError::InvalidParameter { param: "keygroup", value: "776510".to_string() })]
+    #[case(6, "391945", "IDREAM OF JEANNIE", "77651", CipherError::KeyTooShort { param: "phrase", min: 20, got: 15 })]
+    fn test_vic_message_errors(#[case] persn: usize, #[case] date: &str, #[case] phrase: &str, #[case] keygroup: &str, #[case] err: CipherError) {
+        assert_eq!(VicWorksheet::new(persn, date, phrase, keygroup).err(), Some(err.clone()));
+        assert_eq!(VicMessage::new(persn, date, phrase, keygroup).err(), Some(err));
+    }
+
+    #[test]
+    fn test_vic_message_decrypt_errors() {
+        let m = VicMessage::new(6, "391945", "IDREAMOFJEANNIEWITHT", "77651").unwrap();
+        assert_eq!(m.decrypt_str("1234"), Err(CipherError::KeyTooShort { param: "src", min: 5, got: 4 }));
+        assert_eq!(m.decrypt_str("12345 6789"), Err(CipherError::InvalidParameter { param: "src", value: "12345 6789".to_string() }));
+        assert_eq!(m.decrypt_str("12345-67890"), Err(CipherError::InvalidChar { ch: '-', pos: 5 }));
+    }
+
+    #[test]
+    fn test_vic_worksheet() {
+        let m = VicMessage::new(6, "391945", "I dream of Jeannie with the light brown hair", "77651").unwrap();
+        let w = m.worksheet();
+        assert_eq!(w.e, [str2int("6203189574"), str2int("1674205839")]);
+        assert_eq!(w.block[4], str2int("3072527509"));
+        assert_eq!((w.q.len(), w.r.len()), (6, 15));
+        assert_eq!(
+            w.to_string(),
+            "A  7 7 6 5 1
+B  3 9 1 9 4
+C  4 8 5 6 7
+D  I D R E A M O F J E   A N N I E W I T H T
+E  6 2 0 3 1 8 9 5 7 4   1 6 7 4 2 0 5 8 3 9
+F  4 8 5 6 7 2 3 1 3 9   1 2 3 4 5 6 7 8 9 0
+G  0 0 5 9 8 0 2 6 0 3
+H  9 9 2 3 8 9 6 0 9 7
+J  6 7 1 2 5 8 3 0 9 4
+K  8 1 5 1 7 5 6 9 6 5
+L  9 6 6 8 2 1 5 5 1 4
+M  5 2 4 0 3 6 0 6 5 9
+N  7 6 4 3 9 6 6 1 4 6
+P  3 0 7 2 5 2 7 5 0 9
+Q  5 6 4 4 7 1
+R  8 0 3 2 6 5 0 6 7 5 4 9 6 9 7
+S  3 9 6 1 4 2 7 5 0 8
+"
+        );
+    }
 }
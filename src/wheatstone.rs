@@ -17,45 +17,66 @@
 //! cipher.encrypt(&mut ciphertext, plaintext);
 //! ```
 
-use crate::Block;
-use crate::helpers;
-use std::cell::RefCell;
-
-const ALPHABET: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZ";
-const LEN_PL: usize = ALPHABET.len() + 1;
-const LEN_CT: usize = ALPHABET.len();
+use crate::{Alphabet, Block, CipherError, Normalizer, Rng, StreamCipher, Tracer};
+use crate::error::check_dst;
+use crate::trace;
+use std::fmt;
 
 /// Wheatstone cipher machine implementation.
 ///
-/// This struct represents a Wheatstone cipher with two keyed alphabets:
-/// - A plaintext wheel (27 characters, including '+' as a separator)
-/// - A ciphertext wheel (26 characters, standard alphabet)
+/// This struct represents a Wheatstone cipher with two keyed alphabets, the 26 letters
+/// by default:
+/// - A plaintext wheel (the alphabet plus '+' as a separator)
+/// - A ciphertext wheel (the alphabet)
 ///
 /// The cipher maintains internal state to track the current positions
 /// of both wheels during encryption and decryption operations.
 pub struct Wheatstone {
-    /// Plaintext wheel alphabet (27 characters)
+    /// Symbols of the ciphertext wheel
+    alphabet: Alphabet,
+    /// Plaintext wheel alphabet (27 characters by default)
     aplw: Vec<u8>,
-    /// Ciphertext wheel alphabet (26 characters)
+    /// Ciphertext wheel alphabet (26 characters by default)
     actw: Vec<u8>,
     /// Starting character position on the ciphertext wheel
     start: u8,
-    /// Internal mutable state for wheel positions
-    state: RefCell<WheatstoneState>,
 }
 
 /// Internal state for tracking wheel positions during encryption/decryption.
 ///
 /// The Wheatstone cipher needs to maintain state between character operations
 /// as each encryption/decryption affects the position of the wheels for the
-/// next character.
-struct WheatstoneState {
+/// next character.  It is also the state used by the [`StreamCipher`] methods.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WheatstoneState {
     /// Current position on the plaintext wheel (0-26)
     curpos: usize,
     /// Current position on the ciphertext wheel (0-25)
     ctpos: usize,
 }
 
+/// Prints the positions on the plaintext and ciphertext wheels.
+///
+impl fmt::Display for WheatstoneState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "plain {} cipher {}", self.curpos, self.ctpos)
+    }
+}
+
+impl WheatstoneState {
+    /// Returns the current position on the plaintext wheel (0-26).
+    ///
+    pub fn plain_pos(&self) -> usize {
+        self.curpos
+    }
+
+    /// Returns the current position on the ciphertext wheel (0-25).
+    ///
+    pub fn cipher_pos(&self) -> usize {
+        self.ctpos
+    }
+}
+
 impl Wheatstone {
     /// Creates a new Wheatstone cipher with the provided configuration.
     ///
@@ -67,8 +88,15 @@ impl Wheatstone {
     ///
     /// # Returns
     ///
-    /// Returns `Ok(Wheatstone)` if the cipher is successfully created, or
-    /// `Err(String)` if either key is empty.
+    /// Returns `Ok(Wheatstone)` if the cipher is successfully created, or a
+    /// `CipherError` otherwise.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if:
+    /// - either key is empty (`EmptyKey`)
+    /// - a key contains something other than A-Z (`UnknownSymbol`)
+    /// - `start` is not a letter of the ciphertext wheel (`UnknownSymbol`)
     ///
     /// # Example
     ///
@@ -78,31 +106,69 @@ impl Wheatstone {
     /// let cipher = Wheatstone::new(b'M', "CIPHER", "MACHINE").unwrap();
     /// ```
     /// 
-    pub fn new(start: u8, pkey: &str, ckey: &str) -> Result<Self, String> {
-        if pkey.is_empty() || ckey.is_empty() {
-            return Err("keys can not be empty".to_string());
+    pub fn new(start: u8, pkey: &str, ckey: &str) -> Result<Self, CipherError> {
+        Self::with_alphabet(start, pkey, ckey, &Alphabet::latin())
+    }
+
+    /// Creates a Wheatstone cipher whose wheels hold `alphabet` instead of A-Z.
+    ///
+    /// The plaintext wheel gets the '+' separator on top of the alphabet.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`Wheatstone::new`] with `alphabet` instead of A-Z, plus
+    /// `UnknownSymbol` if the alphabet has a non-ASCII symbol and
+    /// `DuplicateSymbol` if it has the '+' separator.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use old_crypto_rs::{Alphabet, BlockExt, Wheatstone};
+    ///
+    /// let cipher = Wheatstone::with_alphabet(b'M', "CIPHER", "MACHINE", &Alphabet::base36()).unwrap();
+    /// let ct = cipher.encrypt_str("AT+1030").unwrap();
+    /// assert_eq!(cipher.decrypt_str(&ct).unwrap(), "AT+1030");
+    /// ```
+    ///
+    pub fn with_alphabet(start: u8, pkey: &str, ckey: &str, alphabet: &Alphabet) -> Result<Self, CipherError> {
+        alphabet.check_ascii()?;
+        if alphabet.contains('+') {
+            return Err(CipherError::DuplicateSymbol { param: "alphabet", symbol: '+' });
+        }
+        for (param, key) in [("pkey", pkey), ("ckey", ckey)] {
+            if key.is_empty() {
+                return Err(CipherError::EmptyKey(param));
+            }
+            alphabet.check_key(param, key)?;
+        }
+        if !alphabet.contains(start as char) {
+            return Err(CipherError::UnknownSymbol { param: "start", symbol: start as char });
         }
 
         // Transform with key
-        let pkey_shuffled = format!("+{}", helpers::shuffle(pkey, ALPHABET));
-        let ckey_shuffled = helpers::shuffle(ckey, ALPHABET);
+        let pkey_shuffled = format!("+{}", alphabet.shuffled(pkey));
+        let ckey_shuffled = alphabet.shuffled(ckey).to_string();
 
         let aplw = pkey_shuffled.as_bytes().to_vec();
         let actw = ckey_shuffled.as_bytes().to_vec();
 
-        let ctpos = actw.iter().position(|&x| x == start).unwrap_or(0);
-
         Ok(Wheatstone {
+            alphabet: alphabet.clone(),
             aplw,
             actw,
             start,
-            state: RefCell::new(WheatstoneState {
-                curpos: 0,
-                ctpos,
-            }),
         })
     }
 
+    /// Creates a Wheatstone cipher with random wheels over A-Z and a random starting
+    /// letter.
+    ///
+    pub fn random(rng: &mut Rng) -> Self {
+        let a = Alphabet::latin();
+        let start = a.symbols()[rng.below(a.len())] as u8;
+        Self::new(start, &rng.permutation(&a), &rng.permutation(&a)).unwrap()
+    }
+
     /// Encodes a single character using the Wheatstone cipher mechanism.
     ///
     /// This method finds the character on the plaintext wheel, calculates
@@ -111,22 +177,22 @@ impl Wheatstone {
     ///
     /// # Arguments
     ///
+    /// * `state` - The current wheel positions, advanced by the character
     /// * `ch` - The plaintext character to encode (as a byte)
     ///
     /// # Returns
     ///
     /// The encoded ciphertext character (as a byte)
     /// 
-    fn encode(&self, ch: u8) -> u8 {
-        let mut state = self.state.borrow_mut();
+    fn encode(&self, state: &mut WheatstoneState, ch: u8) -> u8 {
         let a = self.aplw.iter().position(|&x| x == ch).unwrap_or(0);
         let off = if a <= state.curpos {
-            (a + LEN_PL) - state.curpos
+            (a + self.aplw.len()) - state.curpos
         } else {
             a - state.curpos
         };
         state.curpos = a;
-        state.ctpos = (state.ctpos + off) % LEN_CT;
+        state.ctpos = (state.ctpos + off) % self.actw.len();
         self.actw[state.ctpos]
     }
 
@@ -138,39 +204,24 @@ impl Wheatstone {
     ///
     /// # Arguments
     ///
+    /// * `state` - The current wheel positions, advanced by the character
     /// * `ch` - The ciphertext character to decode (as a byte)
     ///
     /// # Returns
     ///
     /// The decoded plaintext character (as a byte)
     /// 
-    fn decode(&self, ch: u8) -> u8 {
-        let mut state = self.state.borrow_mut();
+    fn decode(&self, state: &mut WheatstoneState, ch: u8) -> u8 {
         let a = self.actw.iter().position(|&x| x == ch).unwrap_or(0);
         let off = if a <= state.ctpos {
-            (a + LEN_CT) - state.ctpos
+            (a + self.actw.len()) - state.ctpos
         } else {
             a - state.ctpos
         };
         state.ctpos = a;
-        state.curpos = (state.curpos + off) % LEN_PL;
+        state.curpos = (state.curpos + off) % self.aplw.len();
         self.aplw[state.curpos]
     }
-
-    /// Resets the cipher state to the initial configuration.
-    ///
-    /// This method resets both wheel positions to their starting values:
-    /// - Plaintext wheel position to 0
-    /// - Ciphertext wheel position to the location of the start character
-    ///
-    /// This is called automatically before each encrypt/decrypt operation
-    /// to ensure consistent results.
-    /// 
-    fn reset(&self) {
-        let mut state = self.state.borrow_mut();
-        state.curpos = 0;
-        state.ctpos = self.actw.iter().position(|&x| x == self.start).unwrap_or(0);
-    }
 }
 
 impl Block for Wheatstone {
@@ -178,11 +229,18 @@ impl Block for Wheatstone {
         1
     }
 
+    /// The plaintext wheel has the alphabet and '+' as word separator, doubled
+    /// letters are split with a 'Q', or an 'X' for a doubled 'Q'.
+    ///
+    fn normalizer(&self) -> Normalizer {
+        self.alphabet.normalizer().substitute(' ', "+").split_doubles(Some('Q'))
+    }
+
     /// Encrypts plaintext using the Wheatstone cipher.
     ///
     /// This method encrypts the entire source buffer character by character,
-    /// writing the ciphertext to the destination buffer. The cipher state is
-    /// automatically reset before encryption to ensure consistent results.
+    /// writing the ciphertext to the destination buffer. Every call starts
+    /// from the initial wheel positions to ensure consistent results.
     ///
     /// # Arguments
     ///
@@ -193,6 +251,11 @@ impl Block for Wheatstone {
     ///
     /// The number of bytes encrypted (equal to `src.len()`)
     ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::InvalidChar` for a character that is not on the plaintext
+    /// wheel (A-Z and '+') and `CipherError::BufferTooSmall` if `dst` is shorter than `src`.
+    ///
     /// # Example
     ///
     /// ```no_run
@@ -205,19 +268,15 @@ impl Block for Wheatstone {
     /// assert_eq!(len, plaintext.len());
     /// ```
     ///
-    fn encrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        self.reset();
-        for (i, &ch) in src.iter().enumerate() {
-            dst[i] = self.encode(ch);
-        }
-        src.len()
+    fn try_encrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.encrypt_stream(&mut self.start(), dst, src)
     }
 
     /// Decrypts ciphertext using the Wheatstone cipher.
     ///
     /// This method decrypts the entire source buffer character by character,
-    /// writing the plaintext to the destination buffer. The cipher state is
-    /// automatically reset before decryption to ensure consistent results.
+    /// writing the plaintext to the destination buffer. Every call starts
+    /// from the initial wheel positions to ensure consistent results.
     ///
     /// # Arguments
     ///
@@ -228,6 +287,11 @@ impl Block for Wheatstone {
     ///
     /// The number of bytes decrypted (equal to `src.len()`)
     ///
+    /// # Errors
+    ///
+    /// Returns `CipherError::InvalidChar` for a character that is not on the ciphertext
+    /// wheel (A-Z) and `CipherError::BufferTooSmall` if `dst` is shorter than `src`.
+    ///
     /// # Example
     ///
     /// ```no_run
@@ -240,18 +304,81 @@ impl Block for Wheatstone {
     /// assert_eq!(len, ciphertext.len());
     /// ```
     ///
-    fn decrypt(&self, dst: &mut [u8], src: &[u8]) -> usize {
-        self.reset();
+    fn try_decrypt(&self, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        self.decrypt_stream(&mut self.start(), dst, src)
+    }
+
+    /// Reports the state of the machine after every character.
+    ///
+    fn try_encrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        trace::trace_stream(self, dst, src, tracer, true)
+    }
+
+    /// Reports the state of the machine after every character.
+    ///
+    fn try_decrypt_traced(&self, dst: &mut [u8], src: &[u8], tracer: &mut dyn Tracer) -> Result<usize, CipherError> {
+        trace::trace_stream(self, dst, src, tracer, false)
+    }
+}
+
+impl StreamCipher for Wheatstone {
+    type State = WheatstoneState;
+
+    /// Returns the initial wheel positions:
+    /// - Plaintext wheel position to 0
+    /// - Ciphertext wheel position to the location of the start character
+    ///
+    /// This is used by each `Block` encrypt/decrypt operation to ensure
+    /// consistent results.
+    /// 
+    fn start(&self) -> WheatstoneState {
+        WheatstoneState {
+            curpos: 0,
+            ctpos: self.actw.iter().position(|&x| x == self.start).unwrap_or(0),
+        }
+    }
+
+    /// Reads both positions, which must be on their wheel.
+    ///
+    fn restore(&self, saved: &str) -> Result<WheatstoneState, CipherError> {
+        let invalid = || CipherError::InvalidParameter { param: "state", value: saved.to_string() };
+        let pos = |p: &str, len: usize| p.parse().ok().filter(|&p| p < len).ok_or_else(invalid);
+        match saved.split_whitespace().collect::<Vec<_>>()[..] {
+            ["plain", curpos, "cipher", ctpos] => Ok(WheatstoneState {
+                curpos: pos(curpos, self.aplw.len())?,
+                ctpos: pos(ctpos, self.actw.len())?,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+
+    fn encrypt_stream(&self, state: &mut WheatstoneState, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        check_dst(dst, src.len())?;
+        if let Some(pos) = src.iter().position(|ch| !self.aplw.contains(ch)) {
+            return Err(CipherError::InvalidChar { ch: src[pos] as char, pos });
+        }
         for (i, &ch) in src.iter().enumerate() {
-            dst[i] = self.decode(ch);
+            dst[i] = self.encode(state, ch);
         }
-        src.len()
+        Ok(src.len())
+    }
+
+    fn decrypt_stream(&self, state: &mut WheatstoneState, dst: &mut [u8], src: &[u8]) -> Result<usize, CipherError> {
+        check_dst(dst, src.len())?;
+        if let Some(pos) = src.iter().position(|ch| !self.actw.contains(ch)) {
+            return Err(CipherError::InvalidChar { ch: src[pos] as char, pos });
+        }
+        for (i, &ch) in src.iter().enumerate() {
+            dst[i] = self.decode(state, ch);
+        }
+        Ok(src.len())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{helpers, BlockExt};
 
     const PLAIN_TXT: &str = "CHARLES+WHEATSTONE+HAD+A+REMARKABLY+FERTILE+MIND";
     const CIPHER_TXT: &str = "BYVLQKWAMNLCYXIOUBFLHTXGHFPBJHZZLUEZFHIVBVRTFVRQ";
@@ -267,54 +394,85 @@ mod tests {
         assert_eq!(c.actw.len(), 26);
     }
 
+    #[test]
+    fn test_new_wheatstone_cipher_errors() {
+        assert_eq!(Wheatstone::new(b'M', "", KEY2).err(), Some(CipherError::EmptyKey("pkey")));
+        assert_eq!(Wheatstone::new(b'M', KEY1, "").err(), Some(CipherError::EmptyKey("ckey")));
+        assert_eq!(
+            Wheatstone::new(b'M', "cipher", KEY2).err(),
+            Some(CipherError::UnknownSymbol { param: "pkey", symbol: 'c' })
+        );
+        assert_eq!(
+            Wheatstone::new(b'+', KEY1, KEY2).err(),
+            Some(CipherError::UnknownSymbol { param: "start", symbol: '+' })
+        );
+    }
+
+    #[test]
+    fn test_wheatstone_with_alphabet() {
+        let c = Wheatstone::with_alphabet(b'M', KEY1, KEY2, &Alphabet::base36()).unwrap();
+        assert_eq!(c.aplw.len(), 37);
+        assert_eq!(c.actw.len(), 36);
+        let ct = c.encrypt_str("WEST+AT+1030").unwrap();
+        assert_eq!(c.decrypt_str(&ct).unwrap(), "WEST+AT+1030");
+
+        let a = Alphabet::new("ABC+").unwrap();
+        assert_eq!(
+            Wheatstone::with_alphabet(b'A', "AB", "BA", &a).err(),
+            Some(CipherError::DuplicateSymbol { param: "alphabet", symbol: '+' })
+        );
+    }
+
     #[test]
     fn test_wheatstone_encode() {
         let c = Wheatstone::new(b'M', KEY1, KEY2).unwrap();
-        assert_eq!(c.state.borrow().curpos, 0);
-        assert_eq!(c.state.borrow().ctpos, 0);
+        let mut st = c.start();
+        assert_eq!(st.curpos, 0);
+        assert_eq!(st.ctpos, 0);
 
         // Round 1
-        assert_eq!(c.encode(b'C'), b'B');
-        assert_eq!(c.state.borrow().curpos, 1);
-        assert_eq!(c.state.borrow().ctpos, 1);
+        assert_eq!(c.encode(&mut st, b'C'), b'B');
+        assert_eq!(st.curpos, 1);
+        assert_eq!(st.ctpos, 1);
 
         // Round 2
-        assert_eq!(c.encode(b'H'), b'Y');
-        assert_eq!(c.state.borrow().curpos, 15);
-        assert_eq!(c.state.borrow().ctpos, 15);
+        assert_eq!(c.encode(&mut st, b'H'), b'Y');
+        assert_eq!(st.curpos, 15);
+        assert_eq!(st.ctpos, 15);
 
         // Round 3
-        assert_eq!(c.encode(b'A'), b'V');
-        assert_eq!(c.state.borrow().curpos, 2);
-        assert_eq!(c.state.borrow().ctpos, 3);
+        assert_eq!(c.encode(&mut st, b'A'), b'V');
+        assert_eq!(st.curpos, 2);
+        assert_eq!(st.ctpos, 3);
 
         // Round 4
-        assert_eq!(c.encode(b'R'), b'L');
-        assert_eq!(c.state.borrow().curpos, 23);
-        assert_eq!(c.state.borrow().ctpos, 24);
+        assert_eq!(c.encode(&mut st, b'R'), b'L');
+        assert_eq!(st.curpos, 23);
+        assert_eq!(st.ctpos, 24);
     }
 
     #[test]
     fn test_wheatstone_decode() {
         let c = Wheatstone::new(b'M', KEY1, KEY2).unwrap();
-        assert_eq!(c.state.borrow().curpos, 0);
-        assert_eq!(c.state.borrow().ctpos, 0);
+        let mut st = c.start();
+        assert_eq!(st.curpos, 0);
+        assert_eq!(st.ctpos, 0);
 
-        assert_eq!(c.decode(b'B'), b'C');
-        assert_eq!(c.state.borrow().curpos, 1);
-        assert_eq!(c.state.borrow().ctpos, 1);
+        assert_eq!(c.decode(&mut st, b'B'), b'C');
+        assert_eq!(st.curpos, 1);
+        assert_eq!(st.ctpos, 1);
 
-        assert_eq!(c.decode(b'Y'), b'H');
-        assert_eq!(c.state.borrow().curpos, 15);
-        assert_eq!(c.state.borrow().ctpos, 15);
+        assert_eq!(c.decode(&mut st, b'Y'), b'H');
+        assert_eq!(st.curpos, 15);
+        assert_eq!(st.ctpos, 15);
 
-        assert_eq!(c.decode(b'V'), b'A');
-        assert_eq!(c.state.borrow().curpos, 2);
-        assert_eq!(c.state.borrow().ctpos, 3);
+        assert_eq!(c.decode(&mut st, b'V'), b'A');
+        assert_eq!(st.curpos, 2);
+        assert_eq!(st.ctpos, 3);
 
-        assert_eq!(c.decode(b'L'), b'R');
-        assert_eq!(c.state.borrow().curpos, 23);
-        assert_eq!(c.state.borrow().ctpos, 24);
+        assert_eq!(c.decode(&mut st, b'L'), b'R');
+        assert_eq!(st.curpos, 23);
+        assert_eq!(st.ctpos, 24);
     }
 
     #[test]
@@ -354,6 +512,67 @@ mod tests {
         assert_eq!(dst, PLAIN_TXT.as_bytes());
     }
 
+    #[test]
+    fn test_wheatstone_stream() {
+        let c = Wheatstone::new(b'M', KEY1, KEY2).unwrap();
+        let plain = helpers::fix_double(LPLAIN_TXT, 'Q');
+        let src = plain.as_bytes();
+        let mut dst = vec![0u8; src.len()];
+        let mut state = c.start();
+        for (i, chunk) in src.chunks(10).enumerate() {
+            c.encrypt_stream(&mut state, &mut dst[i * 10..], chunk).unwrap();
+        }
+        assert_eq!(dst, LCIPHER_TXT.as_bytes());
+
+        let mut state = c.start();
+        let mut pt = vec![0u8; 4];
+        c.decrypt_stream(&mut state, &mut pt, b"BYVL").unwrap();
+        assert_eq!((state.plain_pos(), state.cipher_pos()), (23, 24));
+        let saved = state;
+        assert!(c.decrypt_stream(&mut state, &mut pt, b"QKW+").is_err());
+        assert_eq!(state, saved);
+        c.decrypt_stream(&mut state, &mut pt, b"QKWA").unwrap();
+        assert_eq!(&pt, b"LES+");
+    }
+
+    #[test]
+    fn test_wheatstone_restore() {
+        let c = Wheatstone::new(b'M', KEY1, KEY2).unwrap();
+        let mut state = c.start();
+        let mut pt = vec![0u8; 4];
+        c.decrypt_stream(&mut state, &mut pt, b"BYVL").unwrap();
+        assert_eq!(state.to_string(), "plain 23 cipher 24");
+
+        let mut state = c.restore("plain 23 cipher 24").unwrap();
+        c.decrypt_stream(&mut state, &mut pt, b"QKWA").unwrap();
+        assert_eq!(&pt, b"LES+");
+
+        for bad in ["plain 27 cipher 0", "plain 0 cipher 26", "plain 0", "plain -1 cipher 0"] {
+            assert_eq!(
+                c.restore(bad).err(),
+                Some(CipherError::InvalidParameter { param: "state", value: bad.to_string() })
+            );
+        }
+    }
+
+    #[test]
+    fn test_wheatstone_try_errors() {
+        let c = Wheatstone::new(b'M', KEY1, KEY2).unwrap();
+        let mut dst = vec![0u8; 8];
+        assert_eq!(c.try_encrypt(&mut dst, b"CHAR LES"), Err(CipherError::InvalidChar { ch: ' ', pos: 4 }));
+        assert_eq!(c.try_decrypt(&mut dst, b"BYV+"), Err(CipherError::InvalidChar { ch: '+', pos: 3 }));
+        assert_eq!(c.try_encrypt(&mut dst[..2], b"CHA"), Err(CipherError::BufferTooSmall { needed: 3, got: 2 }));
+    }
+
+    #[test]
+    fn test_wheatstone_normalizer_qq() {
+        let c = Wheatstone::new(b'M', KEY1, KEY2).unwrap();
+        let pt = c.normalizer().normalize("Bell of Qqaa");
+        assert_eq!(pt, "BELQL+OF+QXQAQA");
+        let ct = c.encrypt_str(&pt).unwrap();
+        assert_eq!(c.decrypt_str(&ct).unwrap(), pt);
+    }
+
     #[test]
     fn test_wheatstone_decrypt_long() {
         let c = Wheatstone::new(b'M', KEY1, KEY2).unwrap();